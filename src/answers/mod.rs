@@ -0,0 +1,67 @@
+// Learned answers: curated question→answer pairs, embedded by question and given in place of a
+// generated answer when a later question is close enough, so teams can correct answers over time
+use anyhow::Result;
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::types::LearnedAnswer;
+
+/// Save an answer for a question in the current project, returning its id
+pub async fn learn(app: &ChunkyMonkeyApp, question: &str, answer: &str) -> Result<u32> {
+    let question = question.trim();
+    let answer = answer.trim();
+    if question.is_empty() || answer.is_empty() {
+        anyhow::bail!("Both a question and an answer are needed");
+    }
+
+    let (vectors, model_version) = app.embedding_model.embed_texts_versioned(&[question.to_string()]).await?;
+    if model_version.starts_with("simple/") {
+        // A fallback embedding would match unrelated questions later
+        anyhow::bail!("Ollama is needed to embed the question; is it running?");
+    }
+    let vector = vectors.into_iter().next().unwrap_or_default();
+    app.db.add_learned_answer(app.project.as_deref(), question, answer, &vector, &model_version)
+}
+
+/// Compose an answer from the chunks recorded as relevant to `question` with `feedback`
+pub fn answer_from_feedback(app: &ChunkyMonkeyApp, question: &str) -> Result<String> {
+    let question = question.trim();
+    let mut parts = Vec::new();
+    for (query, chunk_id, relevant) in app.db.get_feedback(app.project.as_deref())? {
+        if !relevant || query.trim() != question {
+            continue;
+        }
+        let Some(chunk) = app.db.get_chunk(chunk_id)? else {
+            continue;
+        };
+        let source = app.db.get_document(chunk.document_id)?
+            .map(|document| document.file_path)
+            .unwrap_or_default();
+        parts.push(format!("{}\n\n(Source: {})", chunk.text.trim(), source));
+    }
+
+    if parts.is_empty() {
+        anyhow::bail!("No chunks were recorded as relevant to \"{}\" (see `feedback`)", question);
+    }
+    Ok(parts.join("\n\n---\n\n"))
+}
+
+/// The saved answer whose question is most similar to the embedded question, if it reaches
+/// `rag.learned_answer_threshold`. Only answers embedded by the current model are compared:
+/// another model's vectors score meaningless similarities even at the same dimension.
+pub async fn best_match(app: &ChunkyMonkeyApp, question_vector: &[f32]) -> Result<Option<(LearnedAnswer, f32)>> {
+    let threshold = app.config.rag.learned_answer_threshold;
+    let current = app.embedding_model.version().await;
+    // Without the model's digest (the server is unreachable), the model name is all there is to go on
+    let same_model = |version: &str| match crate::embeddings::is_resolved_version(&current) {
+        true => version == current,
+        false => version.split('@').next() == Some(current.as_str()),
+    };
+    let best = app.db.get_learned_answers(app.project.as_deref())?
+        .into_iter()
+        .filter(|(_, vector, version)| vector.len() == question_vector.len() && same_model(version))
+        .map(|(answer, vector, _)| {
+            let similarity = app.config.search.metric.similarity(question_vector, &vector);
+            (answer, similarity)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    Ok(best.filter(|(_, similarity)| *similarity >= threshold))
+}
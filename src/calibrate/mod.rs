@@ -0,0 +1,164 @@
+// Calibrating the relevance threshold to the embedding model: models spread similarity scores very
+// differently (one scores unrelated text at 0.2, another at 0.6), so a fixed cutoff either lets
+// noise through or drops good matches
+use anyhow::Result;
+use serde::Serialize;
+use crate::core::app::ChunkyMonkeyApp;
+
+/// Chunks sampled when `calibrate` is not told how many
+pub const DEFAULT_SAMPLES: usize = 50;
+/// Fewest usable chunks a calibration runs with
+const MIN_SAMPLES: usize = 5;
+/// Words of a chunk's held-out sentence taken as the query the rest of the chunk should match
+const QUERY_WORDS: usize = 8;
+/// Letters and digits a query needs to be worth matching; shorter ones are mostly markup
+const MIN_QUERY_CHARS: usize = 16;
+/// Fraction of unrelated pairs the suggested threshold keeps out
+const BACKGROUND_PERCENTILE: f32 = 0.95;
+/// Buckets of the histogram between the lowest and highest score
+const HISTOGRAM_BUCKETS: usize = 10;
+
+/// Similarity at a few percentiles of a score distribution
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Distribution {
+    pub min: f32,
+    pub p10: f32,
+    pub p50: f32,
+    pub p90: f32,
+    pub p95: f32,
+    pub max: f32,
+}
+
+impl Distribution {
+    fn of(scores: &mut [f32]) -> Self {
+        scores.sort_by(f32::total_cmp);
+        Self {
+            min: scores[0],
+            p10: percentile(scores, 0.10),
+            p50: percentile(scores, 0.50),
+            p90: percentile(scores, 0.90),
+            p95: percentile(scores, BACKGROUND_PERCENTILE),
+            max: scores[scores.len() - 1],
+        }
+    }
+}
+
+/// Outcome of `calibrate`
+#[derive(Debug, Serialize)]
+pub struct CalibrationReport {
+    pub model: String,
+    pub samples: usize,
+    /// Scores of each query against the rest of the chunk it was taken from
+    pub matching: Distribution,
+    /// Scores of each query against chunks of other documents
+    pub background: Distribution,
+    /// Each bucket's lower bound with its count of matching and of background pairs
+    pub histogram: Vec<(f32, usize, usize)>,
+    /// Whether matching and background scores overlap too much to separate cleanly
+    pub overlapping: bool,
+    pub previous: Option<f32>,
+    pub threshold: f32,
+    pub applied: bool,
+}
+
+/// Sample chunks of the current project, hold out one sentence of each as a query, and compare how
+/// those queries score against the rest of their own chunk and against chunks of other documents.
+/// The query text is left out of the chunk it is matched to, so matches score as a question worded
+/// apart from its answer would rather than as text against itself. The suggested threshold sits
+/// between the two distributions; it is saved for the configured embedding model (unless
+/// `dry_run`) and used as the relevance threshold from then on.
+pub async fn calibrate(app: &mut ChunkyMonkeyApp, samples: usize, seed: Option<u64>, dry_run: bool) -> Result<CalibrationReport> {
+    let model = app.config.ollama.model.clone();
+    let metric = app.config.search.metric;
+
+    // Chunks of partitions with their own embedding model are scored on another scale
+    let mut pairs = Vec::new();
+    for (chunk, path, _) in app.db.sample_chunks(app.project.as_deref(), samples, seed)? {
+        if app.config.embedding_model_for(&path).is_some() {
+            continue;
+        }
+        if app.db.get_embedding(chunk.id)?.is_none_or(|embedding| embedding.vector.is_empty()) {
+            continue;
+        }
+        if let Some((query, rest)) = held_out(&chunk.text) {
+            let vector = app.embedding_model.embed_text(&rest).await?;
+            pairs.push((path, query, vector));
+        }
+    }
+    let documents = pairs.iter().map(|(path, _, _)| path).collect::<std::collections::HashSet<_>>().len();
+    if pairs.len() < MIN_SAMPLES || documents < 2 {
+        anyhow::bail!("Need at least {} embedded chunks from 2 or more documents to calibrate (found {} from {}); index more first",
+            MIN_SAMPLES, pairs.len(), documents);
+    }
+
+    let mut matching = Vec::new();
+    let mut background = Vec::new();
+    for (path, query, vector) in &pairs {
+        let query_vector = app.embedding_model.embed_text(query).await?;
+        matching.push(metric.similarity(&query_vector, vector));
+        for (other_path, _, other) in &pairs {
+            if other_path != path {
+                background.push(metric.similarity(&query_vector, other));
+            }
+        }
+    }
+
+    let histogram = histogram(&matching, &background);
+    let matching = Distribution::of(&mut matching);
+    let background = Distribution::of(&mut background);
+    // Midway between the weakest matches (all but the bottom tenth) and the strongest unrelated
+    // pairs. With no gap, keeping out most unrelated text matters more than every weak match.
+    let overlapping = matching.p10 <= background.p95;
+    let threshold = if overlapping { background.p95 } else { (background.p95 + matching.p10) / 2.0 };
+
+    let report = CalibrationReport {
+        previous: app.db.get_similarity_threshold(&model)?,
+        model,
+        samples: pairs.len(),
+        matching,
+        background,
+        histogram,
+        overlapping,
+        threshold,
+        applied: !dry_run,
+    };
+    if !dry_run {
+        app.db.save_similarity_threshold(&report.model, threshold, report.samples)?;
+        app.rag_engine.set_relevance_threshold(threshold);
+    }
+    Ok(report)
+}
+
+/// The last sentence of `text` long enough to be a query, cut to `QUERY_WORDS` words, and the text
+/// without it; None when no sentence but the first is long enough or nothing would be left
+fn held_out(text: &str) -> Option<(String, String)> {
+    let alphanumeric = |s: &str| s.chars().filter(|c| c.is_alphanumeric()).count();
+    let sentences: Vec<&str> = text.split_inclusive(['.', '!', '?', '\n']).collect();
+    let held = (1..sentences.len()).rev().find(|&i| alphanumeric(sentences[i]) >= MIN_QUERY_CHARS)?;
+    let query = sentences[held].split_whitespace().take(QUERY_WORDS).collect::<Vec<_>>().join(" ");
+    let rest: String = sentences.iter().enumerate().filter(|&(i, _)| i != held).map(|(_, s)| *s).collect();
+    (alphanumeric(&query) >= MIN_QUERY_CHARS && alphanumeric(&rest) >= MIN_QUERY_CHARS).then_some((query, rest))
+}
+
+fn histogram(matching: &[f32], background: &[f32]) -> Vec<(f32, usize, usize)> {
+    let all = || matching.iter().chain(background);
+    let low = all().copied().fold(f32::INFINITY, f32::min);
+    let high = all().copied().fold(f32::NEG_INFINITY, f32::max);
+    // Every score the same (a model that cannot tell texts apart) is one bucket
+    let buckets = if high > low { HISTOGRAM_BUCKETS } else { 1 };
+    let width = ((high - low) / buckets as f32).max(f32::EPSILON);
+    let bucket = |score: f32| (((score - low) / width) as usize).min(buckets - 1);
+    let mut rows: Vec<(f32, usize, usize)> = (0..buckets).map(|i| (low + i as f32 * width, 0, 0)).collect();
+    for &score in matching {
+        rows[bucket(score)].1 += 1;
+    }
+    for &score in background {
+        rows[bucket(score)].2 += 1;
+    }
+    rows
+}
+
+/// Score at `fraction` of the way through sorted `scores`
+fn percentile(scores: &[f32], fraction: f32) -> f32 {
+    scores[((scores.len() - 1) as f32 * fraction).round() as usize]
+}
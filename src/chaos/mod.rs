@@ -0,0 +1,122 @@
+// Dev-only fault injection: artificial latency and failures in the embedding, LLM and vector store clients
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChaosConfig {
+    pub enabled: bool,
+    /// Probability (0.0 to 1.0) that a call fails before reaching the service
+    pub failure_rate: f64,
+    /// Fixed latency added to every call
+    pub latency_ms: u64,
+    /// Extra random latency of up to this many milliseconds
+    pub latency_jitter_ms: u64,
+    /// Clients to disrupt: "embedding", "llm", "pinecone", "qdrant" (empty means all)
+    pub targets: Vec<String>,
+    /// Seed for reproducible failure sequences
+    pub seed: Option<u64>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            failure_rate: 0.0,
+            latency_ms: 0,
+            latency_jitter_ms: 0,
+            targets: Vec::new(),
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChaosTarget {
+    Embedding,
+    Llm,
+    Pinecone,
+    Qdrant,
+}
+
+impl ChaosTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChaosTarget::Embedding => "embedding",
+            ChaosTarget::Llm => "llm",
+            ChaosTarget::Pinecone => "pinecone",
+            ChaosTarget::Qdrant => "qdrant",
+        }
+    }
+}
+
+/// Shared injector handed to each client; a disabled injector is a no-op
+#[derive(Clone, Default)]
+pub struct ChaosInjector {
+    config: Option<Arc<ChaosConfig>>,
+    state: Arc<AtomicU64>,
+}
+
+impl ChaosInjector {
+    pub fn new(config: ChaosConfig) -> Self {
+        if !config.enabled {
+            return Self::default();
+        }
+
+        let seed = config.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0)
+        });
+
+        Self {
+            config: Some(Arc::new(config)),
+            state: Arc::new(AtomicU64::new(seed)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Uniform value in [0, 1) from a splitmix64 sequence
+    fn next_unit(&self) -> f64 {
+        let mut z = self.state.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Delay and possibly fail a call to `target`, as configured
+    pub async fn inject(&self, target: ChaosTarget) -> Result<()> {
+        let config = match self.config {
+            Some(ref config) => config,
+            None => return Ok(()),
+        };
+
+        if !config.targets.is_empty() && !config.targets.iter().any(|t| t.eq_ignore_ascii_case(target.as_str())) {
+            return Ok(());
+        }
+
+        let jitter = if config.latency_jitter_ms > 0 {
+            (self.next_unit() * config.latency_jitter_ms as f64) as u64
+        } else {
+            0
+        };
+        let latency = config.latency_ms + jitter;
+        if latency > 0 {
+            tokio::time::sleep(Duration::from_millis(latency)).await;
+        }
+
+        if config.failure_rate > 0.0 && self.next_unit() < config.failure_rate {
+            anyhow::bail!("Chaos: injected {} failure", target.as_str());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+// Code chunking: split between top-level definitions so functions and types stay whole where
+// they fit, with their doc comments and attributes
+use crate::core::config::ChunkingConfig;
+use super::recursive::{pack, split_pieces};
+use super::{Chunker, Span};
+
+/// Leading words of a top-level definition across common languages
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn", "pub", "impl", "struct", "enum", "trait", "mod", "union", "macro_rules",
+    "def", "class", "async", "function", "export", "interface", "type", "func",
+    "const", "static", "package", "namespace", "module",
+];
+
+/// Extensions of source files, chunked as code when setting up a repository as a project
+pub const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "mjs", "cjs", "jsx", "ts", "tsx", "go", "java", "kt", "kts", "c", "h",
+    "cc", "cpp", "cxx", "hpp", "cs", "rb", "php", "swift", "scala", "sh", "bash", "zsh",
+];
+
+/// Separators for definitions too large for one chunk: blank lines, then lines, then words
+const BLOCK_SEPARATORS: &[&str] = &["\n\n", "\n", " "];
+
+/// Splits source files at unindented definitions, packing small ones together and splitting
+/// oversized ones at blank lines and then lines
+pub struct CodeChunker;
+
+impl Chunker for CodeChunker {
+    fn split(&self, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+        let max_size = config.max_chunk_size.max(1);
+        let boundaries = definition_boundaries(text);
+
+        let mut pieces = Vec::new();
+        for block in boundaries.windows(2) {
+            split_pieces(text, block[0], block[1], BLOCK_SEPARATORS, config.unit, max_size, &mut pieces);
+        }
+        pack(text, &pieces, config, max_chunks)
+    }
+}
+
+/// Byte offsets where blocks start, from 0 to the end of `text`: each unindented definition,
+/// moved up over the comment and attribute lines directly above it
+fn definition_boundaries(text: &str) -> Vec<usize> {
+    let mut lines: Vec<(usize, &str)> = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push((offset, line));
+        offset += line.len();
+    }
+
+    let mut boundaries = vec![0];
+    for (i, &(_, line)) in lines.iter().enumerate() {
+        if !is_definition(line) {
+            continue;
+        }
+        let mut first = i;
+        while first > 0 && is_annotation(lines[first - 1].1) {
+            first -= 1;
+        }
+        let boundary = lines[first].0;
+        if boundary > *boundaries.last().unwrap_or(&0) {
+            boundaries.push(boundary);
+        }
+    }
+    boundaries.push(text.len());
+    boundaries
+}
+
+/// An unindented line starting with a definition keyword ("pub fn", "class", "def")
+fn is_definition(line: &str) -> bool {
+    if line.starts_with(char::is_whitespace) {
+        return false;
+    }
+    let word = line.split(|c: char| !(c.is_alphanumeric() || c == '_')).next().unwrap_or("");
+    DEFINITION_KEYWORDS.contains(&word)
+}
+
+/// A comment, doc comment, attribute or decorator line, which belongs to the definition below it
+fn is_annotation(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() {
+        return false;
+    }
+    let indented = trimmed.len() < line.len();
+    ["//", "/*", "#", "@", "--"].iter().any(|marker| !indented && trimmed.starts_with(marker))
+        || trimmed.starts_with('*')
+}
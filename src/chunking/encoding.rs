@@ -0,0 +1,87 @@
+// Reading text files saved in legacy encodings: the encoding is detected from the bytes and the
+// text transcoded to UTF-8, so Windows-1252, Shift-JIS, GBK or cp1251 files index like any other
+use anyhow::Result;
+use encoding_rs::{Encoding, UTF_8};
+use crate::core::types::Chunk;
+
+/// Bytes examined to detect the encoding of a file that is read in windows
+pub const SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Share of control characters above which bytes are taken for a binary file
+const MAX_CONTROL_SHARE: f32 = 0.05;
+
+/// The encoding of `bytes`, or None for UTF-8. `bytes` may be the start of a longer file, cut
+/// anywhere. Fails for binary content, which no encoding makes into text.
+pub fn detect(bytes: &[u8]) -> Result<Option<&'static Encoding>> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Ok((encoding != UTF_8).then_some(encoding));
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(_) => return Ok(None),
+        // A character cut off at the end of a sample
+        Err(e) if e.error_len().is_none() && bytes.len() - e.valid_up_to() < 4 => return Ok(None),
+        Err(_) => {}
+    }
+
+    let controls = bytes.iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    if bytes.contains(&0) || controls as f32 > bytes.len() as f32 * MAX_CONTROL_SHARE {
+        anyhow::bail!("not a text file (binary content)");
+    }
+
+    // chardetng weighs the byte patterns of each legacy encoding, as browsers do for unlabeled pages
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(trim_partial(bytes), true);
+    Ok(Some(detector.guess(None, false)))
+}
+
+/// Text of a whole file and, when it was not UTF-8, the encoding it was transcoded from
+pub fn decode(bytes: &[u8]) -> Result<(String, Option<&'static Encoding>)> {
+    match detect(bytes)? {
+        None => Ok((String::from_utf8(bytes.to_vec())?, None)),
+        Some(encoding) => {
+            let (text, _) = encoding.decode_with_bom_removal(bytes);
+            Ok((text.into_owned(), Some(encoding)))
+        }
+    }
+}
+
+/// Move the byte ranges of `chunks` of the text `decode` made of `bytes`, transcoded from
+/// `encoding`, to the bytes of the file, where the same characters take more or fewer bytes
+pub fn map_to_source(bytes: &[u8], encoding: &'static Encoding, chunks: &mut [Chunk]) {
+    let mut offsets: Vec<usize> = chunks.iter()
+        .filter_map(|chunk| chunk.byte_range)
+        .flat_map(|(start, end)| [start, end])
+        .collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+
+    // Feed the decoder a byte at a time: a character starts where the text stands before its first byte
+    let mut sources = Vec::with_capacity(offsets.len());
+    let mut decoder = encoding.new_decoder_with_bom_removal();
+    let mut buffer = vec![0; decoder.max_utf8_buffer_length(1).unwrap_or(16).max(16)];
+    let mut decoded = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        while sources.len() < offsets.len() && offsets[sources.len()] <= decoded {
+            sources.push(i);
+        }
+        let (_, _, written, _) = decoder.decode_to_utf8(std::slice::from_ref(byte), &mut buffer, false);
+        decoded += written;
+    }
+    sources.resize(offsets.len(), bytes.len());
+
+    let source = |offset: usize| offsets.binary_search(&offset).map_or(bytes.len(), |i| sources[i]);
+    for chunk in chunks {
+        chunk.byte_range = chunk.byte_range.map(|(start, end)| (source(start), source(end)));
+    }
+}
+
+/// Drop a trailing lead byte whose character the sample cut off, which would fail strict decoding
+fn trim_partial(bytes: &[u8]) -> &[u8] {
+    if bytes.len() >= SAMPLE_SIZE && bytes.last().is_some_and(|&b| b >= 0x80) {
+        &bytes[..bytes.len() - 1]
+    } else {
+        bytes
+    }
+}
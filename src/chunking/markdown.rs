@@ -0,0 +1,124 @@
+// Markdown chunking: sections start at headings, and no chunk spans two sections
+use crate::core::config::ChunkingConfig;
+use super::table::{markdown_tables, table_spans};
+use super::{section_ranges, Chunker, Span};
+
+/// Splits markdown at its headings, then into windows within each section. Each chunk records
+/// its heading path ("# Install > ## Linux"). With `table_aware`, tables are chunked apart from
+/// the prose around them, in groups of rows under their header.
+pub struct MarkdownChunker;
+
+impl Chunker for MarkdownChunker {
+    fn split(&self, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+        split_under(text, config, max_chunks, &mut Vec::new())
+    }
+}
+
+/// `MarkdownChunker::split` of text that follows the open `headings` (level and "## Title"),
+/// which become the path of text before its first heading. Leaves the headings open at its end.
+pub(super) fn split_under(text: &str, config: &ChunkingConfig, max_chunks: usize, headings: &mut Vec<(usize, String)>) -> Vec<Span> {
+    let tables = if config.table_aware { markdown_tables(text) } else { Vec::new() };
+
+    let mut spans = Vec::new();
+    for (start, end, heading_path) in markdown_sections(text, headings) {
+        // Prose between the tables of this section, then each table's row groups
+        let mut cursor = start;
+        for table in tables.iter().filter(|table| table.start >= start && table.start < end) {
+            prose_spans(text, cursor, table.start, &heading_path, config, max_chunks, &mut spans);
+            for mut span in table_spans(text, table, config, max_chunks.saturating_sub(spans.len())) {
+                span.section = heading_path.clone();
+                spans.push(span);
+            }
+            cursor = table.end().min(end);
+        }
+        prose_spans(text, cursor, end, &heading_path, config, max_chunks, &mut spans);
+        if spans.len() >= max_chunks {
+            break;
+        }
+    }
+    spans
+}
+
+/// Append the windows of `text[start..end]` to `spans`, up to `max_chunks` in all
+fn prose_spans(text: &str, start: usize, end: usize, heading_path: &Option<String>, config: &ChunkingConfig, max_chunks: usize, spans: &mut Vec<Span>) {
+    if spans.len() >= max_chunks || start >= end {
+        return;
+    }
+    for (chunk_start, chunk_end) in section_ranges(&text[start..end], config, max_chunks - spans.len()) {
+        spans.push(Span {
+            start: start + chunk_start,
+            end: start + chunk_end,
+            section: heading_path.clone(),
+            header: None,
+        });
+    }
+}
+
+/// Level and title of an ATX heading line ("## Linux" gives (2, "Linux"))
+pub(super) fn heading(line: &str) -> Option<(usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = line[indent..].trim_end();
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    // Closing hashes are decoration: "## Linux ##"
+    let title = rest.trim().trim_end_matches('#').trim_end();
+    Some((level, title))
+}
+
+/// Byte ranges of the markdown sections of `text`, each starting at its heading line, with the
+/// path of headings leading to it. Text before the first heading has the path of the `headings`
+/// already open, if any; headings inside fenced code blocks are ignored and sections holding
+/// nothing but their heading are dropped.
+fn markdown_sections(text: &str, headings: &mut Vec<(usize, String)>) -> Vec<(usize, usize, Option<String>)> {
+    let heading_path = |headings: &[(usize, String)]| {
+        (!headings.is_empty()).then(|| headings.iter().map(|(_, heading)| heading.as_str()).collect::<Vec<_>>().join(" > "))
+    };
+    let mut sections = Vec::new();
+    let mut current: (usize, Option<String>) = (0, heading_path(headings));
+    let mut fence: Option<&str> = None;
+
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+
+        let Some((level, title)) = heading(line) else {
+            continue;
+        };
+        sections.push((current.0, line_start, current.1.take()));
+
+        headings.retain(|(open_level, _)| *open_level < level);
+        headings.push((level, format!("{} {}", "#".repeat(level), title)));
+        current = (line_start, heading_path(headings));
+    }
+    sections.push((current.0, text.len(), current.1));
+
+    sections.retain(|&(start, end, _)| {
+        let body = &text[start..end];
+        let body = match body.split_once('\n') {
+            Some((first, rest)) if heading(first).is_some() => rest,
+            _ if heading(body).is_some() => "",
+            _ => body,
+        };
+        !body.trim().is_empty()
+    });
+    sections
+}
@@ -0,0 +1,476 @@
+// Splitting document text into overlapping chunks, sized in characters or approximate tokens
+use crate::core::config::{ChunkStrategy, ChunkUnit, ChunkingConfig};
+use crate::core::types::Chunk;
+
+mod code;
+pub mod encoding;
+mod markdown;
+mod recursive;
+pub mod stream;
+mod table;
+
+pub use code::CODE_EXTENSIONS;
+
+/// Characters per subword token when estimating token counts, roughly what BPE tokenizers give for English
+const CHARS_PER_TOKEN: usize = 4;
+
+/// How far back from the size limit to look for a word boundary, in characters
+const BOUNDARY_WINDOW: usize = 100;
+
+/// Longest first line kept in a document title, in characters
+const TITLE_LINE_CHARS: usize = 200;
+
+/// Extensions of files chunked along their headings by default when `respect_section_boundaries` is set
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdx"];
+
+/// Byte range of one chunk within the text handed to a `Chunker`, with the heading path of the
+/// markdown section it came from and, for table rows, the range of the header repeated before it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub section: Option<String>,
+    pub header: Option<(usize, usize)>,
+}
+
+impl Span {
+    fn new(start: usize, end: usize) -> Self {
+        Self { start, end, section: None, header: None }
+    }
+
+    /// This span of a text starting `offset` bytes into a longer one, as a span of the longer text
+    fn shifted(self, offset: usize) -> Self {
+        Self {
+            start: self.start + offset,
+            end: self.end + offset,
+            header: self.header.map(|(start, end)| (start + offset, end + offset)),
+            ..self
+        }
+    }
+}
+
+/// A way of splitting text into chunks, sized per `ChunkingConfig`
+pub trait Chunker {
+    /// Byte ranges of at most `max_chunks` chunks of `text`, in order
+    fn split(&self, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Span>;
+}
+
+/// Fixed-size windows over the whole text
+pub struct FixedChunker;
+
+impl Chunker for FixedChunker {
+    fn split(&self, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+        section_ranges(text, config, max_chunks)
+            .into_iter()
+            .map(|(start, end)| Span::new(start, end))
+            .collect()
+    }
+}
+
+/// The chunker implementing `strategy`
+pub fn chunker(strategy: ChunkStrategy) -> Box<dyn Chunker> {
+    match strategy {
+        ChunkStrategy::Fixed => Box::new(FixedChunker),
+        ChunkStrategy::Recursive => Box::new(recursive::RecursiveChunker),
+        ChunkStrategy::Markdown => Box::new(markdown::MarkdownChunker),
+        ChunkStrategy::Code => Box::new(code::CodeChunker),
+        ChunkStrategy::Table => Box::new(table::TableChunker),
+    }
+}
+
+/// Strategy for the file at `path`: its extension in `chunking.strategies`, then "*", then
+/// tables for CSV/TSV files (with `table_aware`), markdown sections for markdown files (with
+/// `respect_section_boundaries`) or fixed windows
+pub fn strategy_for(path: &str, config: &ChunkingConfig) -> ChunkStrategy {
+    let extension = std::path::Path::new(path).extension().and_then(|ext| ext.to_str());
+    let configured = configured_strategy(path, config);
+
+    let is_table = extension.is_some_and(|ext| table::TABLE_EXTENSIONS.iter().any(|t| ext.eq_ignore_ascii_case(t)));
+    configured.unwrap_or(if config.table_aware && is_table {
+        ChunkStrategy::Table
+    } else if config.respect_section_boundaries && is_markdown(path) {
+        ChunkStrategy::Markdown
+    } else {
+        ChunkStrategy::Fixed
+    })
+}
+
+/// The strategy `chunking.strategies` (or an index manifest root) sets for the file at `path`
+pub fn configured_strategy(path: &str, config: &ChunkingConfig) -> Option<ChunkStrategy> {
+    let extension = std::path::Path::new(path).extension().and_then(|ext| ext.to_str());
+    extension
+        .and_then(|ext| config.strategies.iter().find(|(key, _)| key.trim_start_matches('.').eq_ignore_ascii_case(ext)))
+        .or_else(|| config.strategies.iter().find(|(key, _)| key.as_str() == "*"))
+        .map(|(_, strategy)| *strategy)
+}
+
+/// Split the text of the file at `path` into at most `max_chunks` chunks with the strategy
+/// configured for it. Chunk ids and indexes are positional; byte ranges point into `text`.
+pub fn chunk_text(path: &str, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Chunk> {
+    chunk_file(path, strategy_for(path, config), text, config, max_chunks)
+}
+
+/// Split the text of the file at `path` with the given strategy. Markdown front matter is left
+/// out, since its fields are kept as the document's metadata; chunk ranges still count from the
+/// start of `text`.
+pub fn chunk_file(path: &str, strategy: ChunkStrategy, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Chunk> {
+    let body = body_start(path, text);
+    let spans = chunker(strategy).split(&text[body..], config, max_chunks);
+    chunks_of(text, spans.into_iter().map(|span| span.shifted(body)).collect(), "")
+}
+
+/// Where the text of the file at `path` starts after its front matter, when it is markdown
+fn body_start(path: &str, text: &str) -> usize {
+    if is_markdown(path) {
+        crate::front_matter::body_start(text)
+    } else {
+        0
+    }
+}
+
+/// Split `text` into at most `max_chunks` chunks with the given strategy
+pub fn chunk_with(strategy: ChunkStrategy, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Chunk> {
+    chunks_of(text, chunker(strategy).split(text, config, max_chunks), "")
+}
+
+/// Chunks of `text` at `spans`, each text starting with `prefix` and its span's header
+fn chunks_of(text: &str, spans: Vec<Span>, prefix: &str) -> Vec<Chunk> {
+    let line_starts = line_starts(text);
+    spans
+        .into_iter()
+        .enumerate()
+        .map(|(chunk_index, span)| Chunk {
+            id: chunk_index as u32,
+            document_id: 0, // Will be set by database
+            text: match span.header {
+                Some((start, end)) => format!("{}{}{}", prefix, &text[start..end], &text[span.start..span.end]),
+                None => format!("{}{}", prefix, &text[span.start..span.end]),
+            },
+            chunk_index,
+            byte_range: Some((span.start, span.end)),
+            line_range: Some(line_range(&line_starts, span.start, span.end)),
+            section: span.section,
+            parent_id: None,
+            language: None,
+        })
+        .collect()
+}
+
+/// Summary of a CSV/TSV file's columns when it is chunked as a table, as a chunk to store after
+/// its rows at `chunk_index`. The text is generated rather than taken from the file, so the chunk
+/// has no byte or line range.
+pub fn schema_chunk(path: &str, text: &str, strategy: ChunkStrategy, chunk_index: usize) -> Option<Chunk> {
+    let extension = std::path::Path::new(path).extension().and_then(|ext| ext.to_str())?;
+    if strategy != ChunkStrategy::Table || !table::TABLE_EXTENSIONS.iter().any(|t| extension.eq_ignore_ascii_case(t)) {
+        return None;
+    }
+    Some(Chunk {
+        id: chunk_index as u32,
+        document_id: 0, // Will be set by database
+        text: table::schema_summary(path, text)?,
+        chunk_index,
+        byte_range: None,
+        line_range: None,
+        section: Some("Schema".to_string()),
+        parent_id: None,
+        language: None,
+    })
+}
+
+/// Split each parent chunk into children of `child_chunk_size`, overlapping and merging in the
+/// same proportions as the parents. A parent holding a table (chunked with `strategy`) is split
+/// into groups of its rows that each repeat its header. Each child's `parent_id` is its parent's
+/// index in `parents`; a parent with no range in `text` (a schema summary) is its own only child.
+pub fn child_chunks(text: &str, parents: &[Chunk], strategy: ChunkStrategy, config: &ChunkingConfig) -> Vec<Chunk> {
+    let child_size = config.child_chunk_size.max(1);
+    let scale = |size: usize| size * child_size / config.max_chunk_size.max(1);
+    let child_config = ChunkingConfig {
+        max_chunk_size: child_size,
+        min_chunk_size: scale(config.min_chunk_size),
+        overlap_size: scale(config.overlap_size),
+        ..config.clone()
+    };
+
+    let line_starts = line_starts(text);
+    let mut children: Vec<Chunk> = Vec::new();
+    for (parent_index, parent) in parents.iter().enumerate() {
+        let Some((start, end)) = parent.byte_range else {
+            let chunk_index = children.len();
+            children.push(Chunk { id: chunk_index as u32, chunk_index, parent_id: Some(parent_index as u32), ..parent.clone() });
+            continue;
+        };
+        // A parent's text is its range, after the header repeated before a table's later rows
+        let repeated = parent.text.strip_suffix(&text[start..end]).map(str::len);
+        let table = repeated.and_then(|_| table::table_in(&parent.text, strategy == ChunkStrategy::Table));
+        let pieces: Vec<(String, usize, usize)> = match (table, repeated) {
+            (Some(table), Some(repeated)) => table::table_spans(&parent.text, &table, &child_config, usize::MAX)
+                .into_iter()
+                .map(|span| {
+                    let rows = &parent.text[span.start..span.end];
+                    let text = match span.header {
+                        Some((header_start, header_end)) => format!("{}{}", &parent.text[header_start..header_end], rows),
+                        None => rows.to_string(),
+                    };
+                    (text, span.start.saturating_sub(repeated), span.end - repeated)
+                })
+                .collect(),
+            _ => section_ranges(&text[start..end], &child_config, usize::MAX)
+                .into_iter()
+                .map(|(child_start, child_end)| (text[start + child_start..start + child_end].to_string(), child_start, child_end))
+                .collect(),
+        };
+        for (child_text, child_start, child_end) in pieces {
+            let chunk_index = children.len();
+            children.push(Chunk {
+                id: chunk_index as u32,
+                document_id: 0, // Will be set by database
+                text: child_text,
+                chunk_index,
+                byte_range: Some((start + child_start, start + child_end)),
+                line_range: Some(line_range(&line_starts, start + child_start, start + child_end)),
+                section: parent.section.clone(),
+                parent_id: Some(parent_index as u32),
+                language: None,
+            });
+        }
+    }
+    children
+}
+
+/// Byte offsets at which the lines of `text` start
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0).chain(text.match_indices('\n').map(|(i, _)| i + 1)).collect()
+}
+
+/// First and last line (from 1) touched by `start..end`, given `line_starts`
+fn line_range(line_starts: &[usize], start: usize, end: usize) -> (usize, usize) {
+    let line_of = |offset: usize| line_starts.partition_point(|&line_start| line_start <= offset);
+    (line_of(start), line_of(end.saturating_sub(1).max(start)))
+}
+
+/// Chunk ranges within one section (or a whole document), relative to `text`
+fn section_ranges(text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<(usize, usize)> {
+    let max_size = config.max_chunk_size.max(1);
+    let ranges = match config.unit {
+        ChunkUnit::Chars => char_ranges(text, max_size, chunk_overlap(config), max_chunks),
+        ChunkUnit::Tokens => token_ranges(text, max_size, chunk_overlap(config), max_chunks),
+    };
+    merge_short_tail(text, ranges, config)
+}
+
+/// `overlap_size`, but at most half a chunk: overlap beyond that would index most text several
+/// times over
+fn chunk_overlap(config: &ChunkingConfig) -> usize {
+    config.overlap_size.min(config.max_chunk_size.max(1) / 2)
+}
+
+pub fn is_markdown(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| MARKDOWN_EXTENSIONS.iter().any(|md| ext.eq_ignore_ascii_case(md)))
+}
+
+/// Whether a chunk is too short or holds too few letters and digits to be worth indexing
+pub fn is_low_quality(text: &str, min_chars: usize, min_alphanumeric: usize) -> bool {
+    let text = text.trim();
+    text.chars().count() < min_chars || text.chars().filter(|c| c.is_alphanumeric()).count() < min_alphanumeric
+}
+
+/// Signs that a chunk's text was decoded wrongly: replacement characters, UTF-8 read as Latin-1
+/// ("Ã©" for "é") and control characters other than whitespace
+pub fn encoding_issues(text: &str) -> Vec<&'static str> {
+    let mut issues = Vec::new();
+    if text.contains('\u{FFFD}') {
+        issues.push("replacement characters");
+    }
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if matches!(c, 'Ã' | 'Â' | 'â') && chars.peek().is_some_and(|next| ('\u{80}'..='\u{BF}').contains(next) || "€™œ©".contains(*next)) {
+            issues.push("mojibake (UTF-8 read as Latin-1)");
+            break;
+        }
+    }
+    if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        issues.push("control characters");
+    }
+    issues
+}
+
+/// Short text naming a document, embedded alongside its chunks: the file name, plus the first
+/// markdown heading or, failing that, the first line of text
+pub fn document_title(path: &str, text: &str) -> String {
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path);
+    let first_line = text.lines().find_map(|line| markdown::heading(line).map(|(_, title)| title))
+        .filter(|_| is_markdown(path))
+        .or_else(|| text.lines().map(str::trim).find(|line| !line.is_empty()));
+
+    match first_line {
+        Some(line) => format!("{}: {}", file_name, line.chars().take(TITLE_LINE_CHARS).collect::<String>()),
+        None => file_name.to_string(),
+    }
+}
+
+/// Approximate token count: each punctuation mark is one token, words one per `CHARS_PER_TOKEN` characters
+pub fn estimate_tokens(text: &str) -> usize {
+    token_units(text).iter().map(|unit| unit.2).sum()
+}
+
+/// The longest start of `text` within `max_tokens` approximate tokens, cut back to the end of a
+/// sentence when one falls in its second half
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> &str {
+    let mut used = 0;
+    let mut end = 0;
+    for (_, unit_end, tokens) in token_units(text) {
+        if used + tokens > max_tokens {
+            break;
+        }
+        used += tokens;
+        end = unit_end;
+    }
+    let prefix = &text[..end];
+    match prefix.rfind(['.', '!', '?', '\n']) {
+        Some(sentence_end) if sentence_end >= end / 2 => &prefix[..=sentence_end],
+        _ => prefix,
+    }
+}
+
+/// Size of a chunk in the configured unit
+fn measure(text: &str, unit: ChunkUnit) -> usize {
+    match unit {
+        ChunkUnit::Chars => text.chars().count(),
+        ChunkUnit::Tokens => estimate_tokens(text),
+    }
+}
+
+/// Byte range of `text[start..end]` with surrounding whitespace trimmed, or None when it is blank
+fn trimmed_range(text: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    let raw = &text[start..end];
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let trimmed_start = start + (raw.len() - raw.trim_start().len());
+    Some((trimmed_start, trimmed_start + trimmed.len()))
+}
+
+/// Fixed-size character windows, ending at the last space or newline before the limit when there is one
+fn char_ranges(text: &str, chunk_size: usize, overlap: usize, max_chunks: usize) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+
+    // Work in character indices for proper UTF-8 handling, keeping each character's byte offset
+    let chars: Vec<char> = text.chars().collect();
+    let byte_offsets: Vec<usize> = text.char_indices().map(|(i, _)| i).chain(std::iter::once(text.len())).collect();
+    let text_len = chars.len();
+
+    let mut start_char = 0;
+    while start_char < text_len && ranges.len() < max_chunks {
+        let end_char = (start_char + chunk_size).min(text_len);
+
+        // Find word boundary for end
+        let mut actual_end_char = end_char;
+        if actual_end_char < text_len {
+            let search_start = end_char.saturating_sub(BOUNDARY_WINDOW).max(start_char);
+            let search_range = &chars[search_start..end_char];
+
+            if let Some(last_space_idx) = search_range.iter().rposition(|&c| c == ' ') {
+                actual_end_char = search_start + last_space_idx;
+            } else if let Some(last_newline_idx) = search_range.iter().rposition(|&c| c == '\n') {
+                actual_end_char = search_start + last_newline_idx;
+            }
+        }
+
+        // Ensure we don't go backwards
+        if actual_end_char <= start_char {
+            actual_end_char = start_char + 1;
+        }
+
+        if let Some(range) = trimmed_range(text, byte_offsets[start_char], byte_offsets[actual_end_char]) {
+            ranges.push(range);
+        }
+
+        let next_start = if actual_end_char == end_char { end_char } else { actual_end_char + 1 };
+        if next_start >= text_len {
+            break;
+        }
+        start_char = next_start.saturating_sub(overlap).max(start_char + 1);
+    }
+
+    ranges
+}
+
+/// Word and punctuation units as (byte start, byte end, token cost)
+fn token_units(text: &str) -> Vec<(usize, usize, usize)> {
+    let mut units = Vec::new();
+    let mut word: Option<(usize, usize)> = None; // (byte start, chars)
+
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() || c == '_' {
+            word = Some(word.map_or((i, 1), |(start, chars)| (start, chars + 1)));
+            continue;
+        }
+        if let Some((start, chars)) = word.take() {
+            units.push((start, i, chars.div_ceil(CHARS_PER_TOKEN)));
+        }
+        if !c.is_whitespace() {
+            units.push((i, i + c.len_utf8(), 1));
+        }
+    }
+    if let Some((start, chars)) = word {
+        units.push((start, text.len(), chars.div_ceil(CHARS_PER_TOKEN)));
+    }
+
+    units
+}
+
+/// Windows of whole words holding at most `max_tokens` approximate tokens (a single longer word
+/// gets a chunk to itself), overlapping by up to `overlap` tokens
+fn token_ranges(text: &str, max_tokens: usize, overlap: usize, max_chunks: usize) -> Vec<(usize, usize)> {
+    let units = token_units(text);
+    let mut ranges = Vec::new();
+
+    let mut start = 0;
+    while start < units.len() && ranges.len() < max_chunks {
+        let mut end = start;
+        let mut tokens = 0;
+        while end < units.len() && (end == start || tokens + units[end].2 <= max_tokens) {
+            tokens += units[end].2;
+            end += 1;
+        }
+        ranges.push((units[start].0, units[end - 1].1));
+
+        if end == units.len() {
+            break;
+        }
+
+        // Step back over whole units for the overlap, always moving forward overall
+        let mut next = end;
+        let mut overlapped = 0;
+        while next > start + 1 && overlapped + units[next - 1].2 <= overlap {
+            next -= 1;
+            overlapped += units[next].2;
+        }
+        start = next;
+    }
+
+    ranges
+}
+
+/// Fold a final chunk below `min_chunk_size` into its predecessor so documents don't end in a
+/// fragment with too little context to embed well
+fn merge_short_tail(text: &str, mut ranges: Vec<(usize, usize)>, config: &ChunkingConfig) -> Vec<(usize, usize)> {
+    if ranges.len() < 2 {
+        return ranges;
+    }
+    let (tail_start, tail_end) = ranges[ranges.len() - 1];
+    if tail_end == text.trim_end().len() && measure(&text[tail_start..tail_end], config.unit) < config.min_chunk_size {
+        ranges.pop();
+        if let Some(last) = ranges.last_mut() {
+            last.1 = tail_end;
+        }
+    }
+    ranges
+}
@@ -0,0 +1,102 @@
+// Recursive chunking: split at the coarsest separator that makes pieces small enough, then pack
+// neighbouring pieces back together up to the size limit
+use crate::core::config::{ChunkUnit, ChunkingConfig};
+use super::{char_ranges, measure, merge_short_tail, token_ranges, trimmed_range, Chunker, Span};
+
+/// Separators tried in order: paragraphs, lines, sentences, words
+const SEPARATORS: &[&str] = &["\n\n", "\n", ". ", " "];
+
+/// Splits at paragraph breaks, falling back to lines, sentences and words for pieces that are
+/// still too large, so chunks end at the most natural boundary that fits
+pub struct RecursiveChunker;
+
+impl Chunker for RecursiveChunker {
+    fn split(&self, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+        let max_size = config.max_chunk_size.max(1);
+        let mut pieces = Vec::new();
+        split_pieces(text, 0, text.len(), SEPARATORS, config.unit, max_size, &mut pieces);
+        pack(text, &pieces, config, max_chunks)
+    }
+}
+
+/// Append to `pieces` consecutive ranges covering `text[start..end]`, each within `max_size`
+/// where a separator allows; text with no separator left is cut into fixed windows
+pub(super) fn split_pieces(
+    text: &str,
+    start: usize,
+    end: usize,
+    separators: &[&str],
+    unit: ChunkUnit,
+    max_size: usize,
+    pieces: &mut Vec<(usize, usize)>,
+) {
+    let piece = &text[start..end];
+    if measure(piece, unit) <= max_size {
+        pieces.push((start, end));
+        return;
+    }
+
+    let Some((separator, finer)) = separators.split_first() else {
+        let windows = match unit {
+            ChunkUnit::Chars => char_ranges(piece, max_size, 0, usize::MAX),
+            ChunkUnit::Tokens => token_ranges(piece, max_size, 0, usize::MAX),
+        };
+        // Windows are trimmed; widen them back so the pieces stay contiguous
+        let mut piece_start = start;
+        for (i, (_, window_end)) in windows.iter().enumerate() {
+            let piece_end = if i + 1 == windows.len() { end } else { start + window_end };
+            pieces.push((piece_start, piece_end));
+            piece_start = piece_end;
+        }
+        return;
+    };
+
+    let mut part_start = start;
+    for part in piece.split_inclusive(separator) {
+        let part_end = part_start + part.len();
+        split_pieces(text, part_start, part_end, finer, unit, max_size, pieces);
+        part_start = part_end;
+    }
+}
+
+/// Pack consecutive pieces into chunks of at most `max_chunk_size`, each repeating up to
+/// `overlap_size` of whole pieces from the end of the one before
+pub(super) fn pack(text: &str, pieces: &[(usize, usize)], config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+    let max_size = config.max_chunk_size.max(1);
+    let overlap = super::chunk_overlap(config);
+    let sizes: Vec<usize> = pieces.iter().map(|&(start, end)| measure(&text[start..end], config.unit)).collect();
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < pieces.len() && ranges.len() < max_chunks {
+        let mut end = start;
+        let mut size = 0;
+        while end < pieces.len() && (end == start || size + sizes[end] <= max_size) {
+            size += sizes[end];
+            end += 1;
+        }
+        if let Some(range) = trimmed_range(text, pieces[start].0, pieces[end - 1].1) {
+            ranges.push(range);
+        }
+
+        if end == pieces.len() {
+            break;
+        }
+
+        // Step back over whole pieces for the overlap, leaving room for the next piece so every
+        // chunk reaches further than the one before
+        let overlap = overlap.min(max_size.saturating_sub(sizes[end]));
+        let mut next = end;
+        let mut overlapped = 0;
+        while next > start + 1 && overlapped + sizes[next - 1] <= overlap {
+            next -= 1;
+            overlapped += sizes[next];
+        }
+        start = next;
+    }
+
+    merge_short_tail(text, ranges, config)
+        .into_iter()
+        .map(|(start, end)| Span::new(start, end))
+        .collect()
+}
@@ -0,0 +1,138 @@
+// Reading a large file as a sequence of bounded text windows, so it can be chunked and indexed
+// without holding all of it in memory
+use anyhow::Result;
+use std::io::Read;
+use crate::core::config::{ChunkStrategy, ChunkingConfig};
+use crate::core::types::Chunk;
+use super::{encoding, markdown, table};
+
+/// One window of a file: its text and where it starts in the file
+pub struct TextWindow {
+    pub text: String,
+    /// Byte offset of the window in the file
+    pub byte_offset: usize,
+    /// Lines of the file before the window
+    pub line_offset: usize,
+}
+
+/// What a window takes over from the windows before it, so its chunks keep the structure of the
+/// whole file: the header line of a CSV/TSV file and the markdown headings still open
+#[derive(Debug, Default)]
+pub struct Carried {
+    header: Option<String>,
+    headings: Vec<(usize, String)>,
+}
+
+impl TextWindow {
+    /// Split this window into at most `max_chunks` chunks as `chunk_text` splits a whole file:
+    /// table rows repeat the header line of the first window, markdown before the window's first
+    /// heading keeps the heading path of the windows before it, and the first window leaves out
+    /// markdown front matter
+    pub fn chunk(&self, path: &str, config: &ChunkingConfig, max_chunks: usize, carried: &mut Carried) -> Vec<Chunk> {
+        match super::strategy_for(path, config) {
+            ChunkStrategy::Table => match &carried.header {
+                Some(header) => super::chunks_of(&self.text, table::continued_table_spans(&self.text, header, config, max_chunks), header),
+                None => {
+                    carried.header = table::header_line(&self.text).map(str::to_string);
+                    super::chunk_with(ChunkStrategy::Table, &self.text, config, max_chunks)
+                }
+            },
+            ChunkStrategy::Markdown => {
+                let body = if self.byte_offset == 0 { super::body_start(path, &self.text) } else { 0 };
+                let spans = markdown::split_under(&self.text[body..], config, max_chunks, &mut carried.headings);
+                super::chunks_of(&self.text, spans.into_iter().map(|span| span.shifted(body)).collect(), "")
+            }
+            strategy => super::chunk_with(strategy, &self.text, config, max_chunks),
+        }
+    }
+
+    /// Move chunks of this window's text to their place in the file: byte and line ranges from
+    /// the start of the file, chunk indexes after the `index_offset` chunks of earlier windows
+    pub fn place(&self, chunks: &mut [Chunk], index_offset: usize) {
+        for chunk in chunks {
+            chunk.byte_range = chunk.byte_range.map(|(start, end)| (start + self.byte_offset, end + self.byte_offset));
+            chunk.line_range = chunk.line_range.map(|(first, last)| (first + self.line_offset, last + self.line_offset));
+            chunk.chunk_index += index_offset;
+        }
+    }
+}
+
+/// Successive windows of a UTF-8 file, each at most `window_size` bytes and ending at a line
+/// break where the window holds one (otherwise at a character boundary)
+pub struct TextWindows<R> {
+    reader: R,
+    window_size: usize,
+    /// Bytes read past the end of the previous window
+    carry: Vec<u8>,
+    byte_offset: usize,
+    line_offset: usize,
+    done: bool,
+}
+
+impl TextWindows<Box<dyn Read + Send>> {
+    /// Windows of the file at `path`, transcoded to UTF-8 when its start shows another encoding
+    /// (see `encoding::detect`), with the name of that encoding
+    pub fn open(path: &std::path::Path, window_size: usize) -> Result<(Self, Option<&'static str>)> {
+        let mut sample = Vec::with_capacity(encoding::SAMPLE_SIZE);
+        std::fs::File::open(path)?.take(encoding::SAMPLE_SIZE as u64).read_to_end(&mut sample)?;
+        let detected = encoding::detect(&sample)?;
+
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let reader: Box<dyn Read + Send> = match detected {
+            None => Box::new(file),
+            Some(detected) => Box::new(encoding_rs_io::DecodeReaderBytesBuilder::new().encoding(Some(detected)).build(file)),
+        };
+        Ok((Self::new(reader, window_size), detected.map(|detected| detected.name())))
+    }
+}
+
+impl<R: Read> TextWindows<R> {
+    pub fn new(reader: R, window_size: usize) -> Self {
+        Self {
+            reader,
+            window_size: window_size.max(4),
+            carry: Vec::new(),
+            byte_offset: 0,
+            line_offset: 0,
+            done: false,
+        }
+    }
+
+    /// The next window, or None at the end of the file
+    pub fn next_window(&mut self) -> Result<Option<TextWindow>> {
+        let mut buffer = std::mem::take(&mut self.carry);
+        if !self.done && buffer.len() < self.window_size {
+            let wanted = self.window_size - buffer.len();
+            let read = (&mut self.reader).take(wanted as u64).read_to_end(&mut buffer)?;
+            self.done = read < wanted;
+        }
+        if buffer.is_empty() {
+            return Ok(None);
+        }
+
+        // Cut after the last line break, or failing that the last whole character
+        let cut = if self.done {
+            buffer.len()
+        } else if let Some(newline) = buffer.iter().rposition(|&b| b == b'\n') {
+            newline + 1
+        } else {
+            match std::str::from_utf8(&buffer) {
+                Ok(_) => buffer.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(e) => return Err(e.into()),
+            }
+        };
+        self.carry = buffer.split_off(cut);
+        let text = String::from_utf8(buffer)
+            .map_err(|e| anyhow::anyhow!("stream did not contain valid UTF-8: {}", e))?;
+
+        let window = TextWindow {
+            byte_offset: self.byte_offset,
+            line_offset: self.line_offset,
+            text,
+        };
+        self.byte_offset += window.text.len();
+        self.line_offset += window.text.matches('\n').count();
+        Ok(Some(window))
+    }
+}
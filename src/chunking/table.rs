@@ -0,0 +1,333 @@
+// Table chunking: rows are packed into groups that each repeat the table's header, so every
+// chunk of a CSV file or markdown table still says what its columns are
+use std::collections::HashMap;
+use crate::core::config::ChunkingConfig;
+use super::{measure, section_ranges, trimmed_range, Chunker, Span};
+
+/// Extensions of delimited files chunked as tables by default when `table_aware` is set
+pub(super) const TABLE_EXTENSIONS: &[&str] = &["csv", "tsv"];
+
+/// Splits a CSV or TSV file into groups of records under its header line
+pub struct TableChunker;
+
+impl Chunker for TableChunker {
+    fn split(&self, text: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+        let records = csv_records(text);
+        if records.len() < 2 {
+            return section_ranges(text, config, max_chunks)
+                .into_iter()
+                .map(|(start, end)| Span::new(start, end))
+                .collect();
+        }
+        let table = Table { start: records[0].0, header_end: records[0].1, rows: records[1..].to_vec() };
+        table_spans(text, &table, config, max_chunks)
+    }
+}
+
+/// A table: where it starts, where its header (and markdown delimiter row) ends, and the byte
+/// ranges of its body rows
+pub(super) struct Table {
+    pub start: usize,
+    pub header_end: usize,
+    pub rows: Vec<(usize, usize)>,
+}
+
+impl Table {
+    pub fn end(&self) -> usize {
+        self.rows.last().map_or(self.header_end, |row| row.1)
+    }
+}
+
+/// Chunks of a table: groups of body rows, the first starting at the header and the others
+/// repeating it
+pub(super) fn table_spans(text: &str, table: &Table, config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+    if table.rows.is_empty() {
+        return trimmed_range(text, table.start, table.header_end)
+            .filter(|_| max_chunks > 0)
+            .map(|(start, end)| vec![Span::new(start, end)])
+            .unwrap_or_default();
+    }
+    let header_size = measure(&text[table.start..table.header_end], config.unit);
+    let mut spans = row_groups(text, Some((table.start, table.header_end)), header_size, &table.rows, config, max_chunks);
+    if let Some(first) = spans.first_mut() {
+        first.start = table.start;
+        first.header = None;
+    }
+    spans
+}
+
+/// Chunks of `text`, records of a CSV/TSV file that follow its `header` line, which is not in
+/// `text`: groups of records sized to fit the header put before each of them
+pub(super) fn continued_table_spans(text: &str, header: &str, config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+    row_groups(text, None, measure(header, config.unit), &csv_records(text), config, max_chunks)
+}
+
+/// The header line of a CSV/TSV file starting with `text`
+pub(super) fn header_line(text: &str) -> Option<&str> {
+    csv_records(text).first().map(|&(start, end)| &text[start..end])
+}
+
+/// The table a chunk's `text` holds: CSV/TSV records under their header line (`csv`), or a
+/// markdown table with nothing around it
+pub(super) fn table_in(text: &str, csv: bool) -> Option<Table> {
+    if csv {
+        let records = csv_records(text);
+        return (records.len() >= 2).then(|| Table { start: records[0].0, header_end: records[0].1, rows: records[1..].to_vec() });
+    }
+    let mut tables = markdown_tables(text);
+    match tables.as_slice() {
+        [table] if !table.rows.is_empty() && text[..table.start].trim().is_empty() && text[table.end()..].trim().is_empty() => tables.pop(),
+        _ => None,
+    }
+}
+
+/// Pack consecutive `rows` into groups that fit within `max_chunk_size` together with a header
+/// of `header_size`, each carrying the header's range (if in `text`) to be repeated before it.
+/// A row too long for a group of its own is split into pieces that each repeat the header.
+fn row_groups(text: &str, header: Option<(usize, usize)>, header_size: usize, rows: &[(usize, usize)], config: &ChunkingConfig, max_chunks: usize) -> Vec<Span> {
+    let budget = config.max_chunk_size.max(1).saturating_sub(header_size).max(1);
+
+    let mut spans = Vec::new();
+    let mut start = 0;
+    while start < rows.len() && spans.len() < max_chunks {
+        let (row_start, row_end) = rows[start];
+        if measure(&text[row_start..row_end], config.unit) > budget {
+            let row_config = ChunkingConfig {
+                max_chunk_size: budget,
+                min_chunk_size: config.min_chunk_size * budget / config.max_chunk_size.max(1),
+                ..config.clone()
+            };
+            for (piece_start, piece_end) in section_ranges(&text[row_start..row_end], &row_config, max_chunks - spans.len()) {
+                spans.push(Span { start: row_start + piece_start, end: row_start + piece_end, section: None, header });
+            }
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        let mut size = 0;
+        while end < rows.len() {
+            let row_size = measure(&text[rows[end].0..rows[end].1], config.unit);
+            if end > start && size + row_size > budget {
+                break;
+            }
+            size += row_size;
+            end += 1;
+        }
+        if let Some((group_start, group_end)) = trimmed_range(text, rows[start].0, rows[end - 1].1) {
+            spans.push(Span { start: group_start, end: group_end, section: None, header });
+        }
+        start = end;
+    }
+    spans
+}
+
+/// Byte ranges of the records of delimited text, each ending after its line break. Quoted fields
+/// may span lines, so a record only ends at a line break outside quotes.
+fn csv_records(text: &str) -> Vec<(usize, usize)> {
+    let mut records = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '\n' if !quoted => {
+                if !text[start..i].trim().is_empty() {
+                    records.push((start, i + 1));
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !text[start..].trim().is_empty() {
+        records.push((start, text.len()));
+    }
+    records
+}
+
+/// Tables in markdown `text`: a pipe row followed by a delimiter row ("|---|:--:|"), then the
+/// pipe rows up to the next line without one. Tables inside fenced code blocks are ignored.
+pub(super) fn markdown_tables(text: &str) -> Vec<Table> {
+    let mut lines: Vec<(usize, &str)> = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push((offset, line));
+        offset += line.len();
+    }
+
+    let mut tables = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let trimmed = lines[i].1.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            i += 1;
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            i += 1;
+            continue;
+        }
+
+        if i + 1 < lines.len() && is_pipe_row(lines[i].1) && is_delimiter_row(lines[i + 1].1) {
+            let (start, _) = lines[i];
+            let header_end = lines[i + 1].0 + lines[i + 1].1.len();
+            let mut rows = Vec::new();
+            i += 2;
+            while i < lines.len() && is_pipe_row(lines[i].1) {
+                rows.push((lines[i].0, lines[i].0 + lines[i].1.len()));
+                i += 1;
+            }
+            tables.push(Table { start, header_end, rows });
+            continue;
+        }
+        i += 1;
+    }
+    tables
+}
+
+fn is_pipe_row(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && line.contains('|')
+}
+
+/// A delimiter row like "| --- | :-: |" or "---|---"
+fn is_delimiter_row(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-') && line.contains('|') && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+/// Distinct values of a column listed in full in its schema line; columns with more list their
+/// most common values
+const LISTED_VALUES: usize = 10;
+
+/// Most common values shown for a column with many distinct ones
+const COMMON_VALUES: usize = 3;
+
+/// Longest value quoted in a schema summary, in characters
+const VALUE_CHARS: usize = 40;
+
+/// A description of a delimited file's columns, for questions about the data as a whole ("which
+/// columns are there", "what regions appear") that no group of rows answers: the row count, then
+/// per column its type, its range or its values, and how many rows leave it empty.
+pub(super) fn schema_summary(path: &str, text: &str) -> Option<String> {
+    let records = csv_records(text);
+    let (header, rows) = records.split_first()?;
+    let delimiter = delimiter_for(path, &text[header.0..header.1]);
+    let columns = split_fields(&text[header.0..header.1], delimiter);
+    if columns.len() < 2 || rows.is_empty() {
+        return None;
+    }
+
+    let mut values: Vec<Vec<String>> = vec![Vec::new(); columns.len()];
+    for &(start, end) in rows {
+        for (column, field) in split_fields(&text[start..end], delimiter).into_iter().enumerate().take(columns.len()) {
+            values[column].push(field);
+        }
+    }
+
+    let name = std::path::Path::new(path).file_name().map_or(path.to_string(), |name| name.to_string_lossy().to_string());
+    let mut summary = format!("Schema of {}: {} rows, {} columns\n", name, rows.len(), columns.len());
+    for (column, values) in columns.iter().zip(&values) {
+        summary.push_str(&format!("- {}\n", describe_column(column, values, rows.len())));
+    }
+    Some(summary.trim_end().to_string())
+}
+
+fn describe_column(name: &str, values: &[String], row_count: usize) -> String {
+    let present: Vec<&str> = values.iter().map(|value| value.trim()).filter(|value| !value.is_empty()).collect();
+    let empty = row_count - present.len();
+    let empty_note = if empty > 0 { format!(", {} empty", empty) } else { String::new() };
+    if present.is_empty() {
+        return format!("{} (empty)", name);
+    }
+
+    // Thousands separators allowed; "inf" and "NaN" are words here
+    let numbers: Vec<f64> = present.iter()
+        .filter(|value| value.chars().any(|c| c.is_ascii_digit()))
+        .filter_map(|value| value.replace(',', "").parse().ok())
+        .collect();
+    if numbers.len() == present.len() {
+        let kind = if present.iter().all(|value| value.replace(',', "").parse::<i64>().is_ok()) { "integer" } else { "number" };
+        let (min, max) = numbers.iter().fold((f64::MAX, f64::MIN), |(min, max), &n| (min.min(n), max.max(n)));
+        return format!("{} ({}): {} to {}{}", name, kind, min, max, empty_note);
+    }
+    if present.iter().all(|value| is_date(value)) {
+        let min = present.iter().min().unwrap();
+        let max = present.iter().max().unwrap();
+        return format!("{} (date): {} to {}{}", name, min, max, empty_note);
+    }
+
+    // Distinct values, most common first and in order of appearance among equals
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for &value in &present {
+        let position = *positions.entry(value).or_insert_with(|| {
+            counts.push((value, 0));
+            counts.len() - 1
+        });
+        counts[position].1 += 1;
+    }
+    let kind = if present.iter().all(|value| is_boolean(value)) { "boolean" } else { "text" };
+    if counts.len() <= LISTED_VALUES {
+        let listed: Vec<String> = counts.iter().map(|(value, _)| shorten(value)).collect();
+        let plural = if counts.len() == 1 { "" } else { "s" };
+        return format!("{} ({}): {} value{}: {}{}", name, kind, counts.len(), plural, listed.join(", "), empty_note);
+    }
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    let common: Vec<String> = counts.iter().take(COMMON_VALUES).map(|(value, count)| format!("{} ({})", shorten(value), count)).collect();
+    format!("{} ({}): {} distinct values, most common: {}{}", name, kind, counts.len(), common.join(", "), empty_note)
+}
+
+/// Tab for TSV files; otherwise whichever of comma, semicolon, tab and pipe the header uses most
+fn delimiter_for(path: &str, header: &str) -> char {
+    if path.to_lowercase().ends_with(".tsv") {
+        return '\t';
+    }
+    [',', ';', '\t', '|'].into_iter()
+        .max_by_key(|&delimiter| split_fields(header, delimiter).len())
+        .unwrap_or(',')
+}
+
+/// Fields of one record, with quotes removed and doubled quotes unescaped
+fn split_fields(record: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.trim_end_matches(['\r', '\n']).chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// "2024-05-30", optionally followed by a time
+fn is_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, b)| if i == 4 || i == 7 { *b == b'-' || *b == b'/' } else { b.is_ascii_digit() })
+}
+
+fn is_boolean(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "true" | "false" | "yes" | "no" | "y" | "n")
+}
+
+fn shorten(value: &str) -> String {
+    match value.char_indices().nth(VALUE_CHARS) {
+        Some((cut, _)) => format!("{}…", &value[..cut]),
+        None => value.to_string(),
+    }
+}
@@ -0,0 +1,84 @@
+// Quoted citations: the LLM backs claims with exact quotes from numbered context chunks, and each
+// quote is checked against the text of the chunk it names before it is shown
+use regex::Regex;
+use crate::core::types::{Citation, SearchResult};
+
+/// Marker line the LLM puts before its citations
+const CITATIONS_MARKER: &str = "CITATIONS:";
+
+/// Instructions appended to answer prompts when quoted citations are enabled
+pub const PROMPT_INSTRUCTIONS: &str = "After the answer, add a line reading exactly \"CITATIONS:\" followed by one line per \
+    supporting quote in the form [chunk <id>] \"<exact words copied from that chunk>\". Copy quotes verbatim; \
+    use the chunk ids shown in the context headers.";
+
+/// Split an LLM answer into the answer text and its claimed (chunk id, quote) citations
+pub fn split_citations(answer: &str) -> (String, Vec<(u32, String)>) {
+    let Some(marker) = answer.rfind(CITATIONS_MARKER) else {
+        return (answer.to_string(), Vec::new());
+    };
+
+    let line = Regex::new(r#"\[\s*chunk\s+(?:id\s+)?(\d+)\s*\]\s*[:\-]?\s*["“](.+?)["”]\s*$"#).unwrap();
+    let claims = answer[marker + CITATIONS_MARKER.len()..]
+        .lines()
+        .filter_map(|text| {
+            let captures = line.captures(text.trim())?;
+            Some((captures[1].parse().ok()?, captures[2].trim().to_string()))
+        })
+        .collect();
+
+    (answer[..marker].trim_end().to_string(), claims)
+}
+
+/// How much of a generated answer is answer text: everything before the citations marker. While
+/// the answer is still coming (not `complete`), a trailing part that may be the start of the
+/// marker is held back too.
+pub fn answer_prefix_len(answer: &str, complete: bool) -> usize {
+    if let Some(marker) = answer.find(CITATIONS_MARKER) {
+        return marker;
+    }
+    let held = (1..CITATIONS_MARKER.len()).rev()
+        .find(|&len| !complete && answer.ends_with(&CITATIONS_MARKER[..len]))
+        .unwrap_or(0);
+    answer.len() - held
+}
+
+/// Keep the claims whose quote occurs in the cited source's text, locating the exact span.
+/// Whitespace differences are tolerated; anything else (paraphrase, wrong chunk) is dropped.
+pub fn verify(claims: &[(u32, String)], sources: &[SearchResult]) -> (Vec<Citation>, usize) {
+    let mut citations: Vec<Citation> = Vec::new();
+    let mut rejected = 0;
+    for (chunk_id, quote) in claims {
+        let span = sources.iter()
+            .find(|source| source.chunk_id == *chunk_id)
+            .and_then(|source| find_quote(&source.chunk_text, quote).map(|span| (source, span)));
+        match span {
+            Some((source, (start, end))) => {
+                if citations.iter().any(|c| c.chunk_id == *chunk_id && c.span == (start, end)) {
+                    continue;
+                }
+                citations.push(Citation {
+                    chunk_id: *chunk_id,
+                    document_path: source.document_path.clone(),
+                    quote: source.chunk_text[start..end].to_string(),
+                    span: (start, end),
+                });
+            }
+            None => rejected += 1,
+        }
+    }
+    (citations, rejected)
+}
+
+/// Byte range of `quote` in `text`, matching runs of whitespace loosely
+fn find_quote(text: &str, quote: &str) -> Option<(usize, usize)> {
+    let quote = quote.trim();
+    if quote.is_empty() {
+        return None;
+    }
+    if let Some(start) = text.find(quote) {
+        return Some((start, start + quote.len()));
+    }
+
+    let pattern = quote.split_whitespace().map(regex::escape).collect::<Vec<_>>().join(r"\s+");
+    Regex::new(&pattern).ok()?.find(text).map(|m| (m.start(), m.end()))
+}
@@ -0,0 +1,180 @@
+// Question autocomplete for interactive mode: past questions plus a prefix trie over corpus terms
+use anyhow::Result;
+use colored::*;
+use console::{Key, Term};
+use std::collections::{BTreeMap, HashMap};
+use crate::core::app::ChunkyMonkeyApp;
+
+const MIN_TERM_LEN: usize = 4;
+const MAX_SUGGESTIONS: usize = 5;
+
+#[derive(Default)]
+struct TrieNode {
+    children: BTreeMap<char, TrieNode>,
+    /// Occurrences of the word ending at this node
+    count: u32,
+}
+
+/// Prefix trie of corpus terms weighted by frequency
+#[derive(Default)]
+pub struct Trie {
+    root: TrieNode,
+}
+
+impl Trie {
+    pub fn insert(&mut self, word: &str, count: u32) {
+        let mut node = &mut self.root;
+        for c in word.chars() {
+            node = node.children.entry(c).or_default();
+        }
+        node.count += count;
+    }
+
+    /// The most frequent words starting with `prefix` (excluding `prefix` itself)
+    pub fn complete(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let mut node = &self.root;
+        for c in prefix.chars() {
+            match node.children.get(&c) {
+                Some(next) => node = next,
+                None => return Vec::new(),
+            }
+        }
+
+        let mut words = Vec::new();
+        let mut stack = vec![(node, prefix.to_string())];
+        while let Some((node, word)) = stack.pop() {
+            if node.count > 0 && word != prefix {
+                words.push((node.count, word.clone()));
+            }
+            for (c, child) in &node.children {
+                stack.push((child, format!("{}{}", word, c)));
+            }
+        }
+
+        words.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        words.into_iter().take(limit).map(|(_, word)| word).collect()
+    }
+}
+
+/// Suggests completions for a partially typed question
+pub struct QuestionCompleter {
+    /// Past questions with the times they were asked, most frequent (then most recent) first
+    history: Vec<(String, u32)>,
+    vocabulary: Trie,
+}
+
+impl QuestionCompleter {
+    pub fn from_app(app: &ChunkyMonkeyApp) -> Result<Self> {
+        let history = app.db.get_query_history()?;
+
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for text in app.db.get_all_chunk_texts()? {
+            for word in text.split(|c: char| !c.is_alphanumeric()) {
+                if word.chars().count() >= MIN_TERM_LEN && !word.chars().all(|c| c.is_numeric()) {
+                    *counts.entry(word.to_lowercase()).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut vocabulary = Trie::default();
+        for (word, count) in counts {
+            vocabulary.insert(&word, count);
+        }
+
+        Ok(Self { history, vocabulary })
+    }
+
+    /// Count a question just asked, moving it ahead of those asked as often
+    pub fn add_question(&mut self, question: &str) {
+        let count = match self.history.iter().position(|(asked, _)| asked == question) {
+            Some(i) => self.history.remove(i).1 + 1,
+            None => 1,
+        };
+        let place = self.history.iter().position(|(_, asked)| *asked <= count).unwrap_or(self.history.len());
+        self.history.insert(place, (question.to_string(), count));
+    }
+
+    pub fn suggest(&self, input: &str) -> Vec<String> {
+        if input.trim().is_empty() {
+            return Vec::new();
+        }
+
+        let lowered = input.to_lowercase();
+        let mut suggestions: Vec<String> = self
+            .history
+            .iter()
+            .filter(|(q, _)| q.len() > input.len() && q.to_lowercase().starts_with(&lowered))
+            .take(MAX_SUGGESTIONS)
+            .map(|(q, _)| q.clone())
+            .collect();
+
+        // Complete the word being typed from corpus terms
+        let partial = input.rsplit(|c: char| !c.is_alphanumeric()).next().unwrap_or("");
+        if partial.chars().count() >= 2 {
+            let prefix = partial.to_lowercase();
+            for word in self.vocabulary.complete(&prefix, MAX_SUGGESTIONS) {
+                if suggestions.len() >= MAX_SUGGESTIONS {
+                    break;
+                }
+                // Keep what the user typed and append the rest of the term
+                let completion = format!("{}{}", input, &word[prefix.len()..]);
+                if !suggestions.contains(&completion) {
+                    suggestions.push(completion);
+                }
+            }
+        }
+
+        suggestions
+    }
+}
+
+/// Read a line, showing the best suggestion as dimmed ghost text.
+/// Tab accepts it, Up/Down cycle through alternatives. Falls back to plain input off a terminal.
+pub fn read_line_with_completion(term: &Term, prompt: &str, completer: &QuestionCompleter) -> Result<String> {
+    if !term.is_term() {
+        term.write_str(prompt)?;
+        return Ok(term.read_line()?);
+    }
+
+    let mut input = String::new();
+    let mut selected = 0;
+
+    loop {
+        let suggestions = completer.suggest(&input);
+        if selected >= suggestions.len() {
+            selected = 0;
+        }
+        let ghost = suggestions.get(selected).and_then(|s| s.get(input.len()..)).unwrap_or("");
+
+        term.clear_line()?;
+        term.write_str(&format!("{}{}{}", prompt, input, ghost.dimmed()))?;
+        if !ghost.is_empty() {
+            term.move_cursor_left(ghost.chars().count())?;
+        }
+
+        match term.read_key()? {
+            Key::Enter => {
+                term.clear_line()?;
+                term.write_line(&format!("{}{}", prompt, input))?;
+                return Ok(input);
+            }
+            Key::Tab => {
+                if let Some(suggestion) = suggestions.get(selected) {
+                    input = suggestion.clone();
+                    selected = 0;
+                }
+            }
+            Key::ArrowDown if !suggestions.is_empty() => selected = (selected + 1) % suggestions.len(),
+            Key::ArrowUp if !suggestions.is_empty() => selected = (selected + suggestions.len() - 1) % suggestions.len(),
+            Key::Backspace => {
+                input.pop();
+                selected = 0;
+            }
+            Key::Char(c) if !c.is_control() => {
+                input.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
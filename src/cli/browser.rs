@@ -0,0 +1,155 @@
+// Interactive corpus browser: projects → documents → chunks, with inline delete and re-chunk
+use anyhow::Result;
+use colored::*;
+use console::Term;
+use crate::core::app::ChunkyMonkeyApp;
+
+const PREVIEW_CHARS: usize = 70;
+
+fn preview(text: &str) -> String {
+    let flat: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() > PREVIEW_CHARS {
+        format!("{}...", flat.chars().take(PREVIEW_CHARS).collect::<String>())
+    } else {
+        flat
+    }
+}
+
+fn project_label(project: &Option<String>) -> String {
+    project.clone().unwrap_or_else(|| "default".to_string())
+}
+
+fn prompt(message: &str) -> Result<String> {
+    let term = Term::stdout();
+    term.write_str(&format!("\n{} ", message))?;
+    Ok(term.read_line()?.trim().to_string())
+}
+
+fn confirm(message: &str) -> Result<bool> {
+    let answer = prompt(&format!("{} (y/N):", message))?;
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Parse "<command> <n>" where n is a 1-based position in a list of `len` items
+fn parse_item(input: &str, command: &str, len: usize) -> Option<usize> {
+    let rest = input.strip_prefix(command)?.trim();
+    let n: usize = rest.parse().ok()?;
+    (1..=len).contains(&n).then(|| n - 1)
+}
+
+pub async fn run_corpus_browser(app: &mut ChunkyMonkeyApp) -> Result<()> {
+    loop {
+        let projects = app.db.get_projects()?;
+
+        println!("\n{}", "🗂️  Corpus Browser - Projects".bright_purple().bold());
+        println!("{}", "─".repeat(50));
+
+        if projects.is_empty() {
+            println!("Nothing has been indexed yet.");
+            return Ok(());
+        }
+
+        for (i, (project, documents, chunks)) in projects.iter().enumerate() {
+            let current = if *project == app.project { " (current)".bright_green().to_string() } else { String::new() };
+            println!("   {}. 📁 {}{} - {} documents, {} chunks",
+                i + 1, project_label(project).white().bold(), current, documents, chunks);
+        }
+
+        let input = prompt("Select a project number, or 'b' to go back:")?;
+        if input.is_empty() || input == "b" || input == "q" {
+            return Ok(());
+        }
+
+        match input.parse::<usize>().ok().filter(|n| (1..=projects.len()).contains(n)) {
+            Some(n) => {
+                let project = projects[n - 1].0.clone();
+                // Deletes and re-chunks go through the app, which is scoped to one project at a time
+                if project != app.project {
+                    app.set_project(project.clone())?;
+                    println!("🔀 Switched to project {}", project_label(&project).bright_cyan());
+                }
+                browse_documents(app).await?;
+            }
+            None => println!("❌ Invalid choice. Please try again."),
+        }
+    }
+}
+
+async fn browse_documents(app: &mut ChunkyMonkeyApp) -> Result<()> {
+    loop {
+        let documents = app.db.get_documents_by_project(app.project.as_deref())?;
+
+        println!("\n{} {}", "📄 Documents in".bright_purple().bold(), project_label(&app.project).bright_cyan().bold());
+        println!("{}", "─".repeat(50));
+
+        if documents.is_empty() {
+            println!("No documents in this project.");
+            return Ok(());
+        }
+
+        for (i, document) in documents.iter().enumerate() {
+            println!("   {}. {} ({} chunks, {} bytes)",
+                i + 1, document.file_path.bright_green(), document.chunk_count, document.size);
+        }
+
+        println!("\n💡 {}: <n> open, d <n> delete, r <n> re-chunk, b back", "Commands".bright_purple());
+        let input = prompt("Command:")?;
+
+        if input.is_empty() || input == "b" || input == "q" {
+            return Ok(());
+        } else if let Some(i) = parse_item(&input, "d", documents.len()) {
+            let document = &documents[i];
+            if confirm(&format!("Delete {} and its {} chunks?", document.file_path, document.chunk_count))? {
+                app.delete_document(document.id).await?;
+                println!("🗑️  Deleted {}", document.file_path);
+            }
+        } else if let Some(i) = parse_item(&input, "r", documents.len()) {
+            let document = &documents[i];
+            match app.rechunk_document(document.id).await {
+                Ok(_) => println!("♻️  Re-chunked {}", document.file_path),
+                Err(e) => println!("❌ {}", format!("Failed to re-chunk {}: {}", document.file_path, e).bright_red()),
+            }
+        } else if let Some(n) = input.parse::<usize>().ok().filter(|n| (1..=documents.len()).contains(n)) {
+            browse_chunks(app, documents[n - 1].id, &documents[n - 1].file_path).await?;
+        } else {
+            println!("❌ Invalid choice. Please try again.");
+        }
+    }
+}
+
+async fn browse_chunks(app: &mut ChunkyMonkeyApp, document_id: u32, file_path: &str) -> Result<()> {
+    loop {
+        let chunks = app.db.get_chunks_by_document(document_id)?;
+
+        println!("\n{} {}", "🧩 Chunks of".bright_purple().bold(), file_path.bright_green().bold());
+        println!("{}", "─".repeat(50));
+
+        if chunks.is_empty() {
+            println!("This document has no chunks.");
+            return Ok(());
+        }
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            println!("   {}. [#{}] {}", i + 1, chunk.chunk_index, preview(&chunk.text).dimmed());
+        }
+
+        println!("\n💡 {}: <n> view, d <n> delete, b back", "Commands".bright_purple());
+        let input = prompt("Command:")?;
+
+        if input.is_empty() || input == "b" || input == "q" {
+            return Ok(());
+        } else if let Some(i) = parse_item(&input, "d", chunks.len()) {
+            if confirm(&format!("Delete chunk #{}?", chunks[i].chunk_index))? {
+                app.delete_chunk(chunks[i].id).await?;
+                println!("🗑️  Deleted chunk #{}", chunks[i].chunk_index);
+            }
+        } else if let Some(n) = input.parse::<usize>().ok().filter(|n| (1..=chunks.len()).contains(n)) {
+            let chunk = &chunks[n - 1];
+            println!("\n{} (id {}, {} chars)", format!("Chunk #{}", chunk.chunk_index).bright_cyan().bold(), chunk.id, chunk.text.chars().count());
+            println!("{}", "─".repeat(50));
+            println!("{}", chunk.text);
+        } else {
+            println!("❌ Invalid choice. Please try again.");
+        }
+    }
+}
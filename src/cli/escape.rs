@@ -0,0 +1,174 @@
+// Cancelling a running search or answer from the interactive menu by pressing Esc
+use crate::core::cancel::CancellationToken;
+
+/// Watches the terminal for a lone Esc key or Ctrl-C while an operation runs and cancels `token`
+/// when it sees one, so Ctrl-C stops the operation rather than the program. Escape sequences
+/// (arrow keys and the like) are ignored. The terminal is put back the
+/// way it was when the watcher is dropped, or when the process exits, panics or is terminated
+/// while it watches.
+pub struct EscapeWatcher {
+    #[cfg(unix)]
+    _inner: Option<unix::Watcher>,
+}
+
+impl EscapeWatcher {
+    /// Start watching; does nothing when stdin is not a terminal
+    pub fn start(token: &CancellationToken) -> Self {
+        #[cfg(unix)]
+        {
+            Self { _inner: unix::Watcher::start(token.clone()) }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = token;
+            Self {}
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Once, OnceLock};
+    use std::thread::JoinHandle;
+    use crate::core::cancel::CancellationToken;
+
+    const ESC: u8 = 0x1b;
+    /// Ctrl-C, read as a key while signals from the terminal are off
+    const ETX: u8 = 0x03;
+    /// How long to wait for the rest of an escape sequence before taking ESC as a key press
+    const SEQUENCE_WAIT_MS: i32 = 30;
+    /// How often the watcher checks whether it should stop
+    const POLL_MS: i32 = 100;
+
+    /// Terminal settings from before the first watcher, put back if the process ends while one runs
+    static ORIGINAL: OnceLock<libc::termios> = OnceLock::new();
+    /// Whether a watcher has the terminal changed
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    pub struct Watcher {
+        saved: libc::termios,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Watcher {
+        pub fn start(token: CancellationToken) -> Option<Self> {
+            let fd = libc::STDIN_FILENO;
+            if unsafe { libc::isatty(fd) } != 1 {
+                return None;
+            }
+            let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+            if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+                return None;
+            }
+            // Read key presses one at a time, without echoing them and with Ctrl-C as a key
+            let mut raw = saved;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            ORIGINAL.get_or_init(|| saved);
+            install_restore_hooks();
+            if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+                return None;
+            }
+            ACTIVE.store(true, Ordering::SeqCst);
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread = {
+                let stop = stop.clone();
+                std::thread::spawn(move || watch(fd, &stop, &token))
+            };
+            Some(Self { saved, stop, thread: Some(thread) })
+        }
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+            ACTIVE.store(false, Ordering::SeqCst);
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+            }
+        }
+    }
+
+    /// Put the terminal back on exit, on a panic and on the signals that end the process, none of
+    /// which run the watcher's `Drop`. With terminal signals off, Ctrl-C and Ctrl-\ send none.
+    fn install_restore_hooks() {
+        static INSTALLED: Once = Once::new();
+        INSTALLED.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                restore_terminal();
+                previous(info);
+            }));
+            unsafe {
+                libc::atexit(restore_at_exit);
+                for signal in [libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT] {
+                    libc::signal(signal, restore_and_reraise as extern "C" fn(libc::c_int) as libc::sighandler_t);
+                }
+            }
+        });
+    }
+
+    /// Only calls tcsetattr, which is safe in a signal handler
+    fn restore_terminal() {
+        if ACTIVE.swap(false, Ordering::SeqCst) {
+            if let Some(original) = ORIGINAL.get() {
+                unsafe {
+                    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+                }
+            }
+        }
+    }
+
+    extern "C" fn restore_at_exit() {
+        restore_terminal();
+    }
+
+    /// Restore the terminal, then end the process the way the signal would have
+    extern "C" fn restore_and_reraise(signal: libc::c_int) {
+        restore_terminal();
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+
+    fn watch(fd: i32, stop: &AtomicBool, token: &CancellationToken) {
+        while !stop.load(Ordering::Relaxed) && !token.is_cancelled() {
+            if !readable(fd, POLL_MS) {
+                continue;
+            }
+            let Some(byte) = read_byte(fd) else { continue };
+            if byte == ETX {
+                token.cancel();
+                continue;
+            }
+            if byte != ESC {
+                continue;
+            }
+            if readable(fd, SEQUENCE_WAIT_MS) {
+                // The start of an escape sequence: swallow the rest of it
+                while readable(fd, 0) && read_byte(fd).is_some() {}
+                continue;
+            }
+            token.cancel();
+        }
+    }
+
+    fn readable(fd: i32, timeout_ms: i32) -> bool {
+        let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        unsafe { libc::poll(&mut pollfd, 1, timeout_ms) > 0 }
+    }
+
+    fn read_byte(fd: i32) -> Option<u8> {
+        let mut byte = 0u8;
+        let read = unsafe { libc::read(fd, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+        (read == 1).then_some(byte)
+    }
+}
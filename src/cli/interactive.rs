@@ -4,7 +4,9 @@ use console::Term;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::time::{Duration, Instant};
 use std::thread;
+use crate::cli::escape::EscapeWatcher;
 use crate::core::app::ChunkyMonkeyApp;
+use crate::core::cancel::{self, CancellationToken};
 use crate::core::types::*;
 
 // Preloader struct for managing interactive loading states
@@ -189,15 +191,21 @@ pub async fn run_interactive(app: &mut ChunkyMonkeyApp) -> Result<()> {
                     document_count: 0,
                     chunk_count: 0,
                     database_size_mb: 0.0,
+                    tags: Vec::new(),
+                    failed_files: Vec::new(),
                 };
             }
             "7" => {
-                handle_settings();
+                crate::cli::browser::run_corpus_browser(app).await?;
+                stats = app.get_stats().await?;
             }
             "8" => {
+                handle_settings();
+            }
+            "9" => {
                 demonstrate_rotating_dots();
             }
-            "9" | "q" | "quit" | "exit" => {
+            "10" | "q" | "quit" | "exit" => {
                 show_exit_message();
                 break;
             }
@@ -275,9 +283,10 @@ async fn show_main_menu(stats: &DatabaseStats) -> Result<()> {
     println!("   4. 📊 {}         - See database info", "View Statistics".white());
     println!("   5. 🤖 {}         - See RAG system status", "RAG Pipeline Stats".white());
     println!("   6. 🧹 {}         - Remove all data", "Clear Database".white());
-    println!("   7. ⚙️  {}             - Configure ChunkyMonkey", "Settings".white());
-    println!("   8. 🎪 {}         - Demo rotating dots", "Demo Preloader".white());
-    println!("   9. ❌ {}                  - Close ChunkyMonkey", "Exit".white());
+    println!("   7. 🗂️  {}        - Audit projects, documents and chunks", "Browse Corpus".white());
+    println!("   8. ⚙️  {}             - Configure ChunkyMonkey", "Settings".white());
+    println!("   9. 🎪 {}         - Demo rotating dots", "Demo Preloader".white());
+    println!("  10. ❌ {}                  - Close ChunkyMonkey", "Exit".white());
     
     println!("\n💡 {}: Type 'q', 'quit', or 'exit' to leave", "Tip".bright_purple());
     Ok(())
@@ -303,11 +312,11 @@ async fn handle_first_time_indexing(app: &mut ChunkyMonkeyApp) -> Result<()> {
     if confirm_indexing(&directory_path, &file_patterns)? {
         println!("\n🚀 Starting indexing process...");
         
-        // Use the new summary function
-        let (successful, total) = handle_indexing_with_summary(app, &directory_path, &file_patterns).await?;
-        
-        // Show a friendly summary
-        show_indexing_summary(successful, total);
+        let summary = handle_indexing_with_summary(app, &directory_path, &file_patterns).await?;
+        show_indexing_summary(&summary);
+        if summary.indexed > 0 {
+            crate::ui::show_question_suggestions(app).await;
+        }
     } else {
         println!("❌ Indexing cancelled. You'll need to index documents to use ChunkyMonkey.");
     }
@@ -325,11 +334,8 @@ async fn handle_index_directory(app: &mut ChunkyMonkeyApp) -> Result<()> {
     if confirm_indexing(&directory_path, &file_patterns)? {
         println!("\n🚀 Starting indexing process...");
         
-        // Use the new summary function
-        let (successful, total) = handle_indexing_with_summary(app, &directory_path, &file_patterns).await?;
-        
-        // Show a friendly summary
-        show_indexing_summary(successful, total);
+        let summary = handle_indexing_with_summary(app, &directory_path, &file_patterns).await?;
+        show_indexing_summary(&summary);
     } else {
         println!("❌ Indexing cancelled.");
     }
@@ -367,7 +373,7 @@ fn confirm_indexing(directory: &str, patterns: &str) -> Result<bool> {
     Ok(response.trim().to_lowercase() == "y")
 }
 
-async fn handle_search_flow(app: &ChunkyMonkeyApp) -> Result<()> {
+async fn handle_search_flow(app: &mut ChunkyMonkeyApp) -> Result<()> {
     println!("\n{}", "🔍 Semantic Search".bright_purple().bold());
     println!("{}", "─".repeat(50));
     
@@ -390,7 +396,7 @@ async fn handle_search_flow(app: &ChunkyMonkeyApp) -> Result<()> {
         let limit = get_search_limit()?;
         let threshold = get_search_threshold()?;
         
-        println!("\n🔍 Searching...");
+        println!("\n🔍 Searching... (Esc or Ctrl-C to cancel)");
         
         // Create interactive preloader for search
         let preloader = InteractivePreloader::new("Searching documents");
@@ -399,8 +405,13 @@ async fn handle_search_flow(app: &ChunkyMonkeyApp) -> Result<()> {
         // Show engaging messages while searching
         show_engaging_message();
         
-        // Start the search process
-        let result = app.search(query, limit, threshold).await;
+        // Start the search process; Esc or Ctrl-C cancels it
+        let cancel = CancellationToken::new();
+        app.set_cancellation(cancel.clone());
+        let result = {
+            let _escape = EscapeWatcher::start(&cancel);
+            app.search(query, limit, threshold).await
+        };
         
         // Update preloader during search
         for i in 0..5 {
@@ -413,7 +424,10 @@ async fn handle_search_flow(app: &ChunkyMonkeyApp) -> Result<()> {
         match result {
             Ok(results) => {
                 preloader.finish_with_success();
-                display_search_results(&results);
+                display_search_results(&results, &crate::ui::Highlighter::new(query, &app.config.search.keyword_language));
+            }
+            Err(e) if cancel::is_cancelled(&e) => {
+                preloader.finish_with_message("⏹️ Cancelled");
             }
             Err(e) => {
                 preloader.finish_with_error(&e.to_string());
@@ -447,7 +461,7 @@ fn get_search_threshold() -> Result<f32> {
     Ok(threshold.max(0.0).min(1.0))
 }
 
-fn display_search_results(results: &[SearchResult]) {
+fn display_search_results(results: &[SearchResult], highlighter: &crate::ui::Highlighter) {
     if results.is_empty() {
         println!("❌ No results found");
         return;
@@ -458,14 +472,21 @@ fn display_search_results(results: &[SearchResult]) {
     for (i, result) in results.iter().enumerate() {
         println!("{}. 📄 {} (Similarity: {:.3})", 
             (i + 1).to_string().bright_yellow(), 
-            result.document_path.bright_green(), 
+            result.location().bright_green(), 
             result.similarity.to_string().bright_green()
         );
         
+        if result.metadata_only {
+            println!("   {}", "(metadata only - chunk text not available locally)".dimmed());
+            println!();
+            continue;
+        }
+        
         // Show a cleaner preview of the content
-        let preview = result.chunk_text.chars().take(80).collect::<String>();
+        let preview = highlighter.highlight_start(&result.chunk_text, 80);
         if !preview.is_empty() {
-            println!("   {}", preview.bright_white());
+            // Not colored as a whole: the highlights' color resets would end it at the first match
+            println!("   {}", preview);
         }
         
         if result.chunk_text.len() > 80 {
@@ -475,15 +496,21 @@ fn display_search_results(results: &[SearchResult]) {
     }
 }
 
-async fn handle_ask_flow(app: &ChunkyMonkeyApp) -> Result<()> {
+async fn handle_ask_flow(app: &mut ChunkyMonkeyApp) -> Result<()> {
     println!("\n{}", "❓ RAG Question Answering".bright_yellow().bold());
     println!("{}", "─".repeat(50));
     
     let term = Term::stdout();
+    let mut completer = crate::cli::autocomplete::QuestionCompleter::from_app(app)?;
+    println!("💡 {}: Tab accepts a suggestion, ↑/↓ cycle through them, Esc or Ctrl-C stops a running answer", "Tip".bright_purple());
     
     loop {
-        term.write_str("\n🤔 Enter your question (or 'back' to return): ")?;
-        let question = term.read_line()?;
+        println!();
+        let question = crate::cli::autocomplete::read_line_with_completion(
+            &term,
+            "🤔 Enter your question (or 'back' to return): ",
+            &completer,
+        )?;
         let question = question.trim();
         
         if question.to_lowercase() == "back" {
@@ -495,35 +522,28 @@ async fn handle_ask_flow(app: &ChunkyMonkeyApp) -> Result<()> {
             continue;
         }
         
-        println!("\n🧠 Processing your question...");
+        println!("\n🧠 Processing your question... (Esc or Ctrl-C to stop)");
         
-        // Create interactive preloader for RAG processing
-        let preloader = InteractivePreloader::new("Processing question with RAG");
-        let mut runtime = RuntimeDisplay::new();
-        
-        // Show engaging messages while processing
-        show_engaging_message();
-        
-        // Start the RAG process
-        let result = app.ask_question(question, None).await;
-        
-        // Update preloader during RAG processing
-        for i in 0..6 {
-            preloader.update_message(&format!("Processing question... Step {}", i + 1));
-            preloader.tick();
-            runtime.update_if_needed();
-            thread::sleep(Duration::from_millis(400));
+        // Remember the question for autocomplete
+        if let Err(e) = app.db.record_query(question) {
+            show_error(&format!("Failed to save question history: {}", e));
         }
+        completer.add_question(question);
+        
+        // Start the RAG process, printing the answer as it is generated; Esc or Ctrl-C stops it
+        let cancel = CancellationToken::new();
+        app.set_cancellation(cancel.clone());
+        app.set_answer_echo(Some(STREAMED_ANSWER_HEADING));
+        let result = {
+            let _escape = EscapeWatcher::start(&cancel);
+            app.ask_question(question, None).await
+        };
+        app.set_answer_echo(None);
         
         match result {
-            Ok(answer) => {
-                preloader.finish_with_success();
-                display_rag_answer(&answer);
-            }
-            Err(e) => {
-                preloader.finish_with_error(&e.to_string());
-                show_error(&format!("Question answering failed: {}", e));
-            }
+            Ok(answer) => display_rag_answer(&answer),
+            Err(e) if cancel::is_cancelled(&e) => println!("{}", "⏹️  Stopped".yellow()),
+            Err(e) => show_error(&format!("Question answering failed: {}", e)),
         }
         
         term.write_str("\n🔄 Press Enter to ask another question, or type 'back' to return: ")?;
@@ -536,13 +556,44 @@ async fn handle_ask_flow(app: &ChunkyMonkeyApp) -> Result<()> {
     Ok(())
 }
 
+/// Heading of an answer printed as it is generated
+const STREAMED_ANSWER_HEADING: &str = "\n💡 Answer:";
+
 fn display_rag_answer(answer: &RAGAnswer) {
-    println!("\n{}", "✨ Answer Generated Successfully!".bright_green().bold());
-    println!("{}", "─".repeat(50));
+    // A streamed answer is on screen already, save for what validation added
+    if answer.printed > 0 {
+        let rest = answer.answer[answer.printed..].trim_start();
+        if !rest.is_empty() {
+            println!("\n{}", rest.bright_white());
+        }
+    } else {
+        println!("\n{}", "✨ Answer Generated Successfully!".bright_green().bold());
+        println!("{}", "─".repeat(50));
+        
+        println!("❓ Question: {}", answer.question.bright_green());
+        match answer.learned_from {
+            Some(ref learned) => println!("\n📌 Saved answer #{}:", learned.id),
+            None => println!("{}", STREAMED_ANSWER_HEADING),
+        }
+        println!("{}", answer.answer.bright_white());
+    }
     
-    println!("❓ Question: {}", answer.question.bright_green());
-    println!("\n💡 Answer:");
-    println!("{}", answer.answer.bright_white());
+    for misuse in &answer.term_misuses {
+        println!("{}", format!("⚠️  Terminology: \"{}\" is discouraged; the glossary term is \"{}\"", misuse.used, misuse.preferred).yellow());
+    }
+    if !answer.glossary_terms.is_empty() {
+        println!("\n📖 Glossary:");
+        for term in &answer.glossary_terms {
+            println!("   {}: {}", term.term.bold(), term.definition);
+        }
+    }
+    
+    if !answer.citations.is_empty() {
+        println!("\n📎 Citations:");
+        for (i, citation) in answer.citations.iter().enumerate() {
+            println!("[{}] {}: \"{}\"", i + 1, citation.document_path.bright_green(), citation.quote.italic());
+        }
+    }
 }
 
 async fn handle_show_stats(app: &ChunkyMonkeyApp) -> Result<()> {
@@ -592,7 +643,10 @@ async fn handle_clear_database(app: &mut ChunkyMonkeyApp) -> Result<()> {
     println!("\n{}", "🧹 Clear Database".bright_purple().bold());
     println!("{}", "─".repeat(50));
     
-    println!("⚠️  This action will permanently delete ALL indexed documents and data!");
+    match app.project {
+        Some(ref project) => println!("⚠️  This action will permanently delete ALL documents in project '{}'!", project),
+        None => println!("⚠️  This action will permanently delete ALL indexed documents and data!"),
+    }
     println!("This action cannot be undone.\n");
     
     let term = Term::stdout();
@@ -620,7 +674,7 @@ fn handle_settings() {
     println!("\n{}", "⚙️  Settings".bright_white().bold());
     println!("{}", "─".repeat(50));
     println!("Settings configuration is not yet implemented.");
-    println!("Configuration is currently handled through config.toml file.");
+    println!("Configuration is currently handled through config.toml file (create one with `chunkymonkey init`).");
 }
 
 fn show_error(message: &str) {
@@ -790,97 +844,61 @@ fn demonstrate_full_preloader_experience() -> Result<()> {
 }
 
 // Function to show a user-friendly indexing summary
-fn show_indexing_summary(successful_files: usize, total_files: usize) {
+fn show_indexing_summary(summary: &crate::search::progress::IndexSummary) {
     println!("\n🎉 Indexing Summary");
     println!("{}", "─".repeat(30));
-    println!("✅ Successfully processed: {} files", successful_files.to_string().bright_green());
-    println!("💡 Your documents are now searchable!");
-}
-
-// Function to handle indexing with graceful error handling
-async fn handle_indexing_with_graceful_errors(
-    app: &mut ChunkyMonkeyApp, 
-    directory_path: &str, 
-    file_patterns: &str
-) -> Result<()> {
-    let preloader = InteractivePreloader::new("Indexing documents");
-    let mut runtime = RuntimeDisplay::new();
-    
-    // Show engaging messages while processing
-    show_engaging_message();
-    
-    // Start the indexing process
-    let indexer = crate::search::Indexer::new();
-    let result = indexer.index_directory(directory_path, Some(file_patterns), app).await;
-    
-    // Update preloader during process
-    for i in 0..6 {
-        preloader.update_message(&format!("Indexing documents... Step {}", i + 1));
-        preloader.tick();
-        runtime.update_if_needed();
-        thread::sleep(Duration::from_millis(300));
+    println!("✅ Indexed: {} files ({} chunks)", summary.indexed.to_string().bright_green(), summary.chunks);
+    if summary.unchanged > 0 {
+        println!("⏭️  Unchanged: {} files", summary.unchanged);
     }
-    
-    // Always show success, even if some files failed
-    preloader.finish_with_message("Indexing completed successfully");
-    println!("🎉 Indexing completed successfully!");
-    println!("💡 Your documents are now searchable and ready for questions!");
-    
-    // If there was an error, just log it internally but don't show to user
-    if let Err(e) = result {
-        eprintln!("[Internal] Some files had issues: {}", e);
+    if summary.failed > 0 {
+        println!("❌ Failed: {} files", summary.failed.to_string().bright_red());
+    }
+    if summary.cancelled {
+        println!("⚠️  Cancelled after {} of {} files", summary.done(), summary.files);
+    }
+    if summary.indexed + summary.unchanged > 0 {
+        println!("💡 Your documents are now searchable!");
     }
-    
-    Ok(())
 }
 
-// Function to handle indexing with graceful error handling and return summary
+/// Index a directory, showing the indexer's progress events on a spinner as they arrive
 async fn handle_indexing_with_summary(
     app: &mut ChunkyMonkeyApp, 
     directory_path: &str, 
     file_patterns: &str
-) -> Result<(usize, usize)> {
+) -> Result<crate::search::progress::IndexSummary> {
     let preloader = InteractivePreloader::new("Indexing documents");
-    let mut runtime = RuntimeDisplay::new();
     
     // Show engaging messages while processing
     show_engaging_message();
     
-    // Start the indexing process
-    let indexer = crate::search::Indexer::new();
-    let result = indexer.index_directory(directory_path, Some(file_patterns), app).await;
-    
-    // Update preloader during process
-    for i in 0..6 {
-        preloader.update_message(&format!("Indexing documents... Step {}", i + 1));
-        preloader.tick();
-        runtime.update_if_needed();
-        thread::sleep(Duration::from_millis(300));
-    }
+    let (events, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let spinner = preloader.clone();
+    let renderer = tokio::spawn(async move {
+        let mut progress = crate::search::progress::Progress::new();
+        while let Some(event) = receiver.recv().await {
+            progress.apply(&event);
+            spinner.update_message(&format!("Indexing documents... {}/{} files, {}",
+                progress.summary.done(), progress.summary.files, progress.status()));
+            spinner.tick();
+        }
+    });
     
-    // Always show success, even if some files failed
-    preloader.finish_with_message("Indexing completed successfully");
-    println!("🎉 Indexing completed successfully!");
-    println!("💡 Your documents are now searchable and ready for questions!");
+    let indexer = crate::search::Indexer::new(app.config.indexing.clone()).with_events(events);
+    let result = indexer.index_directory(directory_path, Some(file_patterns), app).await;
+    drop(indexer);
+    let _ = renderer.await;
     
-    // Extract file counts from the result if possible, otherwise use defaults
-    let (successful, total) = match result {
-        Ok(_) => (22, 29), // Default values if no error
+    match result {
+        Ok(summary) => {
+            preloader.finish_with_message("Indexing completed");
+            Ok(summary)
+        }
         Err(e) => {
-            eprintln!("[Internal] Some files had issues: {}", e);
-            // Try to extract numbers from error message if it contains them
-            let error_str = e.to_string();
-            if let Some(cap) = regex::Regex::new(r"(\d+).*?(\d+)").ok().and_then(|re| re.captures(&error_str)) {
-                if let (Ok(success), Ok(total)) = (cap[1].parse::<usize>(), cap[2].parse::<usize>()) {
-                    (success, total)
-                } else {
-                    (22, 29) // Fallback to defaults
-                }
-            } else {
-                (22, 29) // Fallback to defaults
-            }
+            preloader.finish_with_error(&e.to_string());
+            Ok(Default::default())
         }
-    };
-    
-    Ok((successful, total))
-} 
\ No newline at end of file
+    }
+}
+ 
\ No newline at end of file
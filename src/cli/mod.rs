@@ -1 +1,4 @@
-pub mod interactive; 
\ No newline at end of file
+pub mod interactive;
+pub mod browser;
+pub mod autocomplete;
+pub mod escape;
@@ -0,0 +1,134 @@
+// Packing retrieved chunks into an answer's context. Chunks holding the question's exact terms
+// (quoted phrases, identifiers, codes) are required and go in first; semantically similar chunks
+// are optional and only fill the room the token budget leaves. The chunk that reaches the budget
+// is trimmed to the room left rather than dropped, when that room is worth filling.
+use crate::core::types::SearchResult;
+
+/// Shortest term matched exactly
+const MIN_TERM_CHARS: usize = 3;
+
+/// Fewest tokens of chunk text worth putting in the context as a trimmed chunk
+const MIN_TRIMMED_TOKENS: usize = 48;
+
+/// Marks the end of a chunk trimmed to fit the budget
+const TRIMMED_MARKER: &str = " …";
+
+/// Whether a chunk must be in the context or may be left out when space runs short
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    Required,
+    Optional,
+}
+
+/// Sources chosen for the context, required ones first
+pub struct PackedContext {
+    pub sources: Vec<SearchResult>,
+    pub required: usize,
+    /// Sources left out because the token budget was spent
+    pub left_out: usize,
+    /// Sources cut short to fit the budget
+    pub trimmed: usize,
+    /// Estimated tokens the sources take, with their headers
+    pub tokens: usize,
+}
+
+/// Terms of `question` a relevant chunk should contain verbatim: quoted or backticked phrases,
+/// and words that look like identifiers or codes (snake_case, camelCase, paths, `a::b`, mixed
+/// letters and digits, acronyms). Plain prose words are left to semantic search.
+pub fn exact_terms(question: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut rest = String::with_capacity(question.len());
+    let mut quote: Option<char> = None;
+    let mut phrase = String::new();
+    for c in question.chars() {
+        match quote {
+            Some(open) if c == open || (open == '“' && c == '”') => {
+                terms.push(std::mem::take(&mut phrase));
+                quote = None;
+            }
+            Some(_) => phrase.push(c),
+            None if matches!(c, '"' | '`' | '“') => quote = Some(c),
+            None => rest.push(c),
+        }
+    }
+    // An unclosed quote is just punctuation
+    rest.push_str(&phrase);
+
+    for word in rest.split_whitespace() {
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
+        if looks_like_identifier(word) {
+            terms.push(word.to_string());
+        }
+    }
+
+    let mut unique: Vec<String> = Vec::new();
+    for term in terms.into_iter().map(|term| term.trim().to_string()) {
+        if term.chars().count() >= MIN_TERM_CHARS && !unique.contains(&term) {
+            unique.push(term);
+        }
+    }
+    unique
+}
+
+fn looks_like_identifier(word: &str) -> bool {
+    let has_letter = word.chars().any(|c| c.is_alphabetic());
+    let has_digit = word.chars().any(|c| c.is_ascii_digit());
+    let inner_lowercase_to_uppercase = word.chars().zip(word.chars().skip(1))
+        .any(|(a, b)| a.is_lowercase() && b.is_uppercase());
+    let acronym = word.chars().count() >= 2 && word.chars().all(|c| c.is_uppercase() || c.is_ascii_digit()) && has_letter;
+    has_letter && (
+        word.contains('_')
+            || word.contains("::")
+            || word.contains('/')
+            || (word.contains('.') && !word.ends_with('.'))
+            || has_digit
+            || inner_lowercase_to_uppercase
+            || acronym
+    )
+}
+
+/// Whether `text` contains any of the terms: identifiers case-sensitively, phrases in any case
+pub fn contains_exact(text: &str, terms: &[String]) -> bool {
+    let lowercase = std::cell::OnceCell::new();
+    terms.iter().any(|term| {
+        if term.contains(' ') {
+            lowercase.get_or_init(|| text.to_lowercase()).contains(&term.to_lowercase())
+        } else {
+            text.contains(term.as_str())
+        }
+    })
+}
+
+/// Order sources by tier, keeping their rank within a tier, and take them while they fit in
+/// `max_tokens` (0 for no limit), counting `overhead` tokens for each besides its text. Required
+/// sources are all considered before any optional one, so similar prose never pushes out an exact
+/// match. A source that doesn't fit is trimmed to the room left when that is at least
+/// `MIN_TRIMMED_TOKENS`, and left out otherwise so a smaller one further down may still fit.
+pub fn pack(sources: Vec<SearchResult>, tier: impl Fn(&SearchResult) -> Tier, overhead: impl Fn(&SearchResult) -> usize,
+            max_tokens: usize) -> PackedContext {
+    let (required, optional): (Vec<SearchResult>, Vec<SearchResult>) = sources.into_iter()
+        .partition(|source| tier(source) == Tier::Required);
+    let required_count = required.len();
+
+    let mut packed = PackedContext { sources: Vec::new(), required: 0, left_out: 0, trimmed: 0, tokens: 0 };
+    for (i, mut source) in required.into_iter().chain(optional).enumerate() {
+        let overhead = overhead(&source);
+        let tokens = overhead + crate::chunking::estimate_tokens(&source.chunk_text);
+        if max_tokens > 0 && packed.tokens + tokens > max_tokens {
+            let room = max_tokens.saturating_sub(packed.tokens + overhead + crate::chunking::estimate_tokens(TRIMMED_MARKER));
+            let kept = crate::chunking::truncate_to_tokens(&source.chunk_text, room).trim_end();
+            if room < MIN_TRIMMED_TOKENS || kept.is_empty() {
+                packed.left_out += 1;
+                continue;
+            }
+            source.chunk_text = format!("{}{}", kept, TRIMMED_MARKER);
+            packed.trimmed += 1;
+        }
+        packed.tokens += overhead + crate::chunking::estimate_tokens(&source.chunk_text);
+        if i < required_count {
+            packed.required += 1;
+        }
+        packed.sources.push(source);
+    }
+    packed
+}
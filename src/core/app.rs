@@ -1,277 +1,1828 @@
 use anyhow::Result;
 use crate::core::types::*;
-use crate::db::Database;
+use crate::db::{Database, DocumentRecord};
 use crate::embeddings::EmbeddingModel;
 use crate::vector_search::RAGSearchEngine;
 use crate::pinecone::PineconeClient;
-use crate::core::config::AppConfig;
-use std::path::Path;
+use crate::qdrant::QdrantClient;
+use crate::lance::LanceStore;
+use crate::core::config::{AppConfig, ChunkingConfig, FusionMode, LlmApi, OllamaConfig};
+use crate::chaos::{ChaosInjector, ChaosTarget};
+use crate::core::debug::SendLogged;
+use crate::core::health;
+use crate::core::cancel::{self, CancellationToken};
+use crate::context::Tier;
+use crate::result_cache::ResultKind;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
 
-/// Simple LLM client for Ollama
+/// Seed sent to the LLM when deterministic mode is enabled
+const DETERMINISTIC_SEED: u64 = 42;
+
+/// Files up to this size are read and chunked whole; larger ones are indexed one window of this
+/// size at a time
+pub const STREAM_WINDOW_SIZE: usize = 5 * 1024 * 1024;
+
+/// Extra candidates fetched from stores that can only apply some filter predicates after the query
+const FILTER_OVERSAMPLE: usize = 4;
+
+/// Start of a document the LLM reads to summarize it for contextual chunk headers, in characters
+const SUMMARY_EXCERPT_CHARS: usize = 3000;
+
+/// Longest document summary kept, in characters
+const SUMMARY_MAX_CHARS: usize = 300;
+
+/// Lowest similarity of a local search result until `calibrate` fits one to the embedding model
+const DEFAULT_RELEVANCE_THRESHOLD: f32 = 0.1;
+
+/// Tokens kept free in the context window for the answer prompt's instructions and chunk framing
+const ANSWER_PROMPT_TOKENS: usize = 250;
+
+/// Paraphrases of a query searched alongside it with `search.enable_query_expansion`
+const QUERY_EXPANSIONS: usize = 3;
+
+/// Results a search ranks however few are asked for, so every page of them is cut from one ranking
+const RANKED_RESULTS: usize = 100;
+
+/// Simple LLM client for Ollama and OpenAI-compatible servers
 pub struct OllamaLLMClient {
     base_url: String,
     model: String,
+    api: LlmApi,
+    api_key: Option<String>,
+    temperature: f32,
+    seed: Option<u64>,
+    chaos: ChaosInjector,
+    style: AnswerStyle,
+    /// Ask for quoted citations after the answer (see `citations`)
+    quoted_citations: bool,
+    /// File or named pipe that answer tokens are written to as they are generated (`--stream-to`)
+    stream_to: Option<PathBuf>,
+    /// Text of the last answer streamed to `stream_to`
+    streamed: Mutex<Option<String>>,
+    /// Heading under which answers are printed to the terminal as they are generated
+    echo_heading: Option<String>,
+    /// Text of the last answer printed to the terminal in full
+    echoed: Mutex<Option<String>>,
 }
 
 impl OllamaLLMClient {
     pub fn new(base_url: String, model: String) -> Self {
-        Self { base_url, model }
+        Self {
+            base_url,
+            model,
+            api: LlmApi::default(),
+            api_key: None,
+            temperature: 0.7,
+            seed: None,
+            chaos: ChaosInjector::default(),
+            style: AnswerStyle::default(),
+            quoted_citations: false,
+            stream_to: None,
+            streamed: Mutex::new(None),
+            echo_heading: None,
+            echoed: Mutex::new(None),
+        }
+    }
+    
+    /// Talk to the server with `api`, sending `api_key` as a bearer token when there is one
+    pub fn set_api(&mut self, api: LlmApi, api_key: Option<String>) {
+        self.api = api;
+        self.api_key = api_key;
+    }
+    
+    pub fn set_answer_style(&mut self, style: AnswerStyle) {
+        self.style = style;
+    }
+    
+    pub fn set_chaos(&mut self, chaos: ChaosInjector) {
+        self.chaos = chaos;
+    }
+    
+    pub fn set_quoted_citations(&mut self, enabled: bool) {
+        self.quoted_citations = enabled;
+    }
+    
+    pub fn set_stream_to(&mut self, path: Option<PathBuf>) {
+        self.stream_to = path;
+    }
+    
+    /// Print answers to the terminal token by token under `heading` as they are generated
+    pub fn set_echo(&mut self, heading: Option<&str>) {
+        self.echo_heading = heading.map(str::to_string);
+    }
+    
+    /// The answer text printed to the terminal in full since the last call, if any
+    pub fn take_echoed(&self) -> Option<String> {
+        self.echoed.lock().unwrap().take()
+    }
+    
+    /// Everything besides the question and context that shapes a generated answer
+    pub fn answer_settings(&self) -> serde_json::Value {
+        serde_json::json!({
+            "model": self.model,
+            "temperature": self.temperature,
+            "seed": self.seed,
+            "style": self.style,
+            "quoted_citations": self.quoted_citations,
+        })
+    }
+    
+    /// Tokens the LLM may generate for an answer in the current style
+    pub fn answer_token_budget(&self) -> usize {
+        self.style.length.max_tokens() as usize
+    }
+    
+    /// The answer text streamed to `stream_to` since the last call, if any
+    pub fn take_streamed(&self) -> Option<String> {
+        self.streamed.lock().unwrap().take()
+    }
+    
+    /// Use temperature 0 and a fixed seed so repeated runs produce the same answer
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        if deterministic {
+            self.temperature = 0.0;
+            self.seed = Some(DETERMINISTIC_SEED);
+        } else {
+            self.temperature = 0.7;
+            self.seed = None;
+        }
     }
     
     pub async fn generate_answer(&self, question: &str, context: &str) -> Result<String> {
-        let client = reqwest::Client::new();
+        self.chaos.inject(ChaosTarget::Llm).await?;
         
         // Create a well-structured prompt for the LLM
+        let mut style_instructions = self.style.length.instruction().to_string();
+        if let Some(ref level) = self.style.reading_level {
+            style_instructions.push_str(&format!(" Write for a {} reader.", level));
+        }
+        if self.quoted_citations {
+            style_instructions.push(' ');
+            style_instructions.push_str(crate::citations::PROMPT_INSTRUCTIONS);
+        }
+        // Models tend to answer in the language of the context, which need not be the question's
+        let question_language = crate::stemming::detect_language_code(question);
+        let context_language = crate::stemming::detect_language_code(context);
+        if let Some(language) = question_language.filter(|&code| code != "en" || context_language.is_some_and(|other| other != code)) {
+            style_instructions.push_str(&format!(" Answer in {}, the language of the question.",
+                crate::stemming::language_name(language).unwrap_or(language)));
+        }
+        
         let prompt = format!(
-            "You are a helpful AI assistant. Based on the following context, provide a clear and concise answer to the question.\n\nQuestion: {}\n\nContext:\n{}\n\nAnswer:",
-            question, context
+            "You are a helpful AI assistant. Based on the following context, provide a clear answer to the question. {}\n\nQuestion: {}\n\nContext:\n{}\n\nAnswer:",
+            style_instructions, question, context
         );
         
-        let request_body = serde_json::json!({
-            "model": self.model,
-            "prompt": prompt,
-            "stream": false,
-            "options": {
-                "temperature": 0.7,
+        let generated = if self.stream_to.is_some() || self.echo_heading.is_some() {
+            self.generate_streamed(&prompt, self.style.length.max_tokens()).await?
+        } else {
+            self.generate(&prompt, self.style.length.max_tokens()).await?
+        };
+        if let Some(answer) = generated {
+            return Ok(answer);
+        }
+        
+        // Fallback to a simple response if LLM fails
+        Ok("I couldn't generate a response using the LLM. Here's the relevant information from the context:\n\n".to_string() + context)
+    }
+    
+    /// Ask the LLM for questions that the given excerpts can answer, one per line
+    pub async fn suggest_questions(&self, excerpts: &[String], count: usize) -> Result<Vec<String>> {
+        self.chaos.inject(ChaosTarget::Llm).await?;
+        
+        let prompt = format!(
+            "Here are excerpts from a collection of documents. Write {} short, specific questions that these excerpts can answer. \
+             Output one question per line with no numbering or extra text.\n\n{}\n\nQuestions:",
+            count,
+            excerpts.join("\n---\n")
+        );
+        
+        let response = self.generate(&prompt, 300).await?.unwrap_or_default();
+        Ok(response.lines()
+            .map(|line| line.trim().trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*')).trim())
+            .filter(|line| line.ends_with('?'))
+            .take(count)
+            .map(|line| line.to_string())
+            .collect())
+    }
+    
+    /// Up to `count` rewordings of a search query, using synonyms and related terms, one per line
+    pub async fn expand_query(&self, query: &str, count: usize) -> Result<Vec<String>> {
+        self.chaos.inject(ChaosTarget::Llm).await?;
+        
+        let prompt = format!(
+            "Write {} alternative search queries for finding documents about the query below. Reword it with \
+             synonyms and related terms, and keep each one short. Output one query per line with no numbering or extra text.\n\n\
+             Query: {}\n\nAlternatives:",
+            count, query
+        );
+        
+        let response = self.generate(&prompt, 120).await?.unwrap_or_default();
+        Ok(response.lines()
+            .map(|line| line.trim().trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*')).trim().trim_matches('"'))
+            .filter(|line| !line.is_empty() && !line.eq_ignore_ascii_case(query.trim()))
+            .take(count)
+            .map(|line| line.to_string())
+            .collect())
+    }
+    
+    /// One line saying what a document is about, for contextual chunk headers
+    pub async fn summarize_document(&self, path: &str, excerpt: &str) -> Result<Option<String>> {
+        self.chaos.inject(ChaosTarget::Llm).await?;
+        
+        let prompt = format!(
+            "In one sentence of at most 25 words, say what the document {} is about. Output only the sentence.\n\n{}\n\nSummary:",
+            path, excerpt
+        );
+        
+        let response = self.generate(&prompt, 60).await?;
+        Ok(response
+            .and_then(|text| text.lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string))
+            .map(|line| line.chars().take(SUMMARY_MAX_CHARS).collect()))
+    }
+    
+    fn request_body(&self, prompt: &str, max_tokens: u32, stream: bool) -> serde_json::Value {
+        let mut request_body = match self.api {
+            LlmApi::Ollama => serde_json::json!({
+                "model": self.model,
+                "prompt": prompt,
+                "stream": stream,
+                "options": {
+                    "temperature": self.temperature,
+                    "top_p": 0.9,
+                    "num_predict": max_tokens
+                }
+            }),
+            LlmApi::OpenAI => serde_json::json!({
+                "model": self.model,
+                "messages": [{ "role": "user", "content": prompt }],
+                "stream": stream,
+                "temperature": self.temperature,
                 "top_p": 0.9,
-                "max_tokens": 1000
-            }
-        });
+                "max_tokens": max_tokens
+            }),
+        };
         
-        let response = client
-            .post(&format!("{}/api/generate", self.base_url))
-            .json(&request_body)
-            .send()
-            .await?;
+        if let Some(seed) = self.seed {
+            match self.api {
+                LlmApi::Ollama => request_body["options"]["seed"] = serde_json::json!(seed),
+                LlmApi::OpenAI => request_body["seed"] = serde_json::json!(seed),
+            }
+        }
+        request_body
+    }
+    
+    async fn send(&self, prompt: &str, max_tokens: u32, stream: bool) -> reqwest::Result<reqwest::Response> {
+        let path = match self.api {
+            LlmApi::Ollama => "api/generate",
+            LlmApi::OpenAI => "v1/chat/completions",
+        };
+        let mut request = reqwest::Client::new()
+            .post(format!("{}/{}", self.base_url.trim_end_matches('/'), path))
+            .json(&self.request_body(prompt, max_tokens, stream));
+        if let Some(ref api_key) = self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        request.send_logged("llm").await
+    }
+    
+    /// Send a prompt to the LLM, returning None when it answers with an error status or no text
+    async fn generate(&self, prompt: &str, max_tokens: u32) -> Result<Option<String>> {
+        let response = self.send(prompt, max_tokens, false).await?;
         
         if response.status().is_success() {
             let response_json: serde_json::Value = response.json().await?;
-            if let Some(response_text) = response_json["response"].as_str() {
-                return Ok(response_text.trim().to_string());
+            let response_text = match self.api {
+                LlmApi::Ollama => response_json["response"].as_str(),
+                LlmApi::OpenAI => response_json["choices"][0]["message"]["content"].as_str(),
+            };
+            if let Some(response_text) = response_text {
+                return Ok(Some(response_text.trim().to_string()));
+            }
+        }
+        Ok(None)
+    }
+    
+    /// The text one line of a streamed response adds. Ollama sends a JSON object per line;
+    /// OpenAI-style servers send server-sent events (`data: {...}`, ending with `data: [DONE]`).
+    fn stream_token(&self, line: &[u8]) -> Option<String> {
+        let line = std::str::from_utf8(line).ok()?.trim();
+        let (event, token) = match self.api {
+            LlmApi::Ollama => (line, "/response"),
+            LlmApi::OpenAI => (line.strip_prefix("data:")?.trim_start(), "/choices/0/delta/content"),
+        };
+        let event: serde_json::Value = serde_json::from_str(event).ok()?;
+        event.pointer(token)?.as_str().filter(|token| !token.is_empty()).map(str::to_string)
+    }
+    
+    /// Like `generate`, but with the LLM streaming the response: each token is appended to the
+    /// `stream_to` file and printed to the terminal as it arrives, depending on which are set. A
+    /// named pipe blocks here until something opens it for reading. With quoted citations, output
+    /// stops at the citations marker, since only the citations that check out are shown.
+    async fn generate_streamed(&self, prompt: &str, max_tokens: u32) -> Result<Option<String>> {
+        let mut response = self.send(prompt, max_tokens, true).await?;
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+        
+        let mut sink = match self.stream_to {
+            Some(ref path) => Some(open_answer_stream(path).await?),
+            None => None,
+        };
+        let mut echo = self.echo_heading.as_deref().map(TerminalEcho::new);
+        let mut text = String::new();
+        // Bytes of `text` passed on to the sink and the terminal
+        let mut shown = 0;
+        // One event per line, which may be split across network chunks
+        let mut pending = Vec::new();
+        loop {
+            let chunk = response.chunk().await?;
+            if let Some(ref bytes) = chunk {
+                pending.extend_from_slice(bytes);
+            }
+            let complete = match chunk {
+                Some(_) => pending.iter().rposition(|&b| b == b'\n').map_or(0, |newline| newline + 1),
+                None => pending.len(),
+            };
+            for line in pending.drain(..complete).collect::<Vec<u8>>().split(|&b| b == b'\n') {
+                if let Some(token) = self.stream_token(line) {
+                    text.push_str(&token);
+                }
+            }
+            let showable = match self.quoted_citations {
+                true => crate::citations::answer_prefix_len(&text, chunk.is_none()),
+                false => text.len(),
+            };
+            if showable > shown {
+                let part = &text[shown..showable];
+                if let Some(ref mut sink) = sink {
+                    sink.write_all(part.as_bytes()).await?;
+                    sink.flush().await?;
+                }
+                if let Some(ref mut echo) = echo {
+                    echo.print(part);
+                }
+                shown = showable;
+            }
+            if chunk.is_none() {
+                break;
             }
         }
         
-        // Fallback to a simple response if LLM fails
-        Ok("I couldn't generate a response using the LLM. Here's the relevant information from the context:\n\n".to_string() + context)
+        if text.trim().is_empty() {
+            return Ok(None);
+        }
+        let shown_text = text[..shown].trim().to_string();
+        if let Some(ref mut sink) = sink {
+            sink.write_all(b"\n").await?;
+            sink.flush().await?;
+            *self.streamed.lock().unwrap() = Some(shown_text.clone());
+        }
+        if echo.is_some() {
+            *self.echoed.lock().unwrap() = Some(shown_text);
+        }
+        Ok(Some(text.trim().to_string()))
+    }
+}
+
+/// Answer tokens printed to stdout as they arrive. The heading goes out with the first token that
+/// isn't whitespace, and the line is ended when generation finishes or is cancelled.
+struct TerminalEcho<'a> {
+    heading: &'a str,
+    started: bool,
+}
+
+impl<'a> TerminalEcho<'a> {
+    fn new(heading: &'a str) -> Self {
+        Self { heading, started: false }
+    }
+
+    fn print(&mut self, token: &str) {
+        use std::io::Write;
+        let token = if self.started { token } else { token.trim_start() };
+        if token.is_empty() {
+            return;
+        }
+        let mut stdout = std::io::stdout().lock();
+        if !self.started {
+            let _ = writeln!(stdout, "{}", self.heading);
+            self.started = true;
+        }
+        let _ = write!(stdout, "{}", token);
+        let _ = stdout.flush();
     }
 }
 
+impl Drop for TerminalEcho<'_> {
+    fn drop(&mut self) {
+        if self.started {
+            println!();
+        }
+    }
+}
+
+/// Open the `--stream-to` target for appending; a regular file is created and emptied first
+async fn open_answer_stream(path: &Path) -> Result<tokio::fs::File> {
+    let is_file = tokio::fs::metadata(path).await.map_or(true, |metadata| metadata.is_file());
+    tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(is_file)
+        .truncate(is_file)
+        .open(path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot open {} to stream the answer: {}", path.display(), e))
+}
+
 pub struct ChunkyMonkeyApp {
     pub db: Database,
     pub embedding_model: EmbeddingModel,
     pub rag_engine: RAGSearchEngine,
     pub pinecone_client: Option<PineconeClient>,
+    pub qdrant_client: Option<QdrantClient>,
+    /// Disk-backed local store used instead of the in-memory index when the backend is "lancedb"
+    pub lance_store: Option<LanceStore>,
     pub config: AppConfig,
     pub llm_client: Option<OllamaLLMClient>, // LLM client for answer generation
+    /// Reproducible mode: temperature 0, fixed seeds, cached query embeddings
+    pub deterministic: bool,
+    /// Reuse cached search results and answers (`result_cache`); off for `--no-cache`
+    use_result_cache: bool,
+    /// Project that indexing, search and clear are scoped to (`None` is the default project)
+    pub project: Option<String>,
+    /// Weights for re-ranking search results in this project (see `tune-ranking`)
+    pub ranking_weights: RankingWeights,
+    /// This project's persistent blocklist: documents matching these patterns are never retrieved
+    pub blocklist: Vec<String>,
+    /// Patterns excluded from retrieval for this session only (`--exclude`)
+    exclude_patterns: Vec<String>,
+    /// Tags added to every document indexed this session (`index --tag`)
+    index_tags: Vec<String>,
+    /// Assigned tags of the document being indexed again, which its new version keeps
+    retained_tags: Vec<String>,
+    /// Embedding models of partitions that don't use the default model, by model name
+    partition_models: BTreeMap<String, EmbeddingModel>,
+    /// Title embeddings of this project's documents by path, blended into chunk similarity
+    document_vectors: HashMap<String, Vec<f32>>,
+    /// Time `ask_question` may take before generation is abandoned for an extractive answer (`--deadline`)
+    answer_deadline: Option<Duration>,
+    /// File or named pipe answers are streamed to (`--stream-to`)
+    answer_stream: Option<PathBuf>,
+    /// Stops searches, answers and indexing in progress when cancelled (see `set_cancellation`)
+    cancel: CancellationToken,
+    /// Chunks left out by the low-quality filter since the count was last taken
+    skipped_chunks: usize,
+    /// Documents cut off at `chunking.max_chunks_per_document` since the count was last taken
+    truncated_documents: usize,
+}
+
+/// Where a search result or answer is cached: its key and the index generation it was computed against
+struct CacheSlot {
+    kind: ResultKind,
+    key: String,
+    generation: i64,
+}
+
+/// A query embedded with one embedding model; `model` is None for the default model
+struct QueryVector {
+    model: Option<String>,
+    vector: Vec<f32>,
+}
+
+impl Drop for ChunkyMonkeyApp {
+    fn drop(&mut self) {
+        let _ = self.flush_health();
+    }
+}
+
+/// What the text of a document is, which decides what its chunks' byte ranges point into
+enum TextSource<'a> {
+    /// The file at the document's path, as UTF-8
+    File,
+    /// The file's bytes, transcoded from another encoding
+    Transcoded(&'a [u8], &'static encoding_rs::Encoding),
+    /// Text made from the file or fetched (EPUB chapters, OCR, converter output, web pages,
+    /// imports), in which offsets say nothing about the file, so none are kept
+    Derived,
+}
+
+impl TextSource<'_> {
+    /// Turn byte ranges into `text` into byte ranges into the document's file, where there is one
+    fn place(&self, chunks: &mut [Chunk]) {
+        match *self {
+            TextSource::File => {}
+            TextSource::Transcoded(bytes, encoding) => crate::chunking::encoding::map_to_source(bytes, encoding, chunks),
+            TextSource::Derived => chunks.iter_mut().for_each(|chunk| chunk.byte_range = None),
+        }
+    }
+}
+
+/// Upsert one batch of chunk vectors, returning the (chunk id, vector id) pairs that were stored
+async fn upsert_pinecone_batch(pinecone: &PineconeClient, batch: Vec<(u32, crate::pinecone::Vector)>) -> Vec<(u32, String)> {
+    let ids: Vec<(u32, String)> = batch.iter().map(|(chunk_id, vector)| (*chunk_id, vector.id.clone())).collect();
+    let vectors = batch.into_iter().map(|(_, vector)| vector).collect();
+    
+    // Silently handle Pinecone errors to avoid verbose logging
+    match pinecone.upsert_vectors(vectors).await {
+        Ok(()) => ids,
+        Err(_) => Vec::new(),
+    }
 }
 
 impl ChunkyMonkeyApp {
     pub fn new() -> Result<Self> {
         let db = Database::new()?;
-        let embedding_model = EmbeddingModel::new()?;
-        let mut rag_engine = RAGSearchEngine::new(768, 0.1); // 768 dimensions to match Pinecone index, 0.1 relevance threshold
         
         // Load configuration
         let config = AppConfig::load()?;
         
+        let chaos = ChaosInjector::new(config.chaos.clone());
+        if chaos.is_enabled() {
+            eprintln!("⚠️  Chaos mode enabled: injecting latency and failures into external calls");
+        }
+        
+        let mut embedding_model = EmbeddingModel::from_config(config.ollama.clone())?;
+        embedding_model.set_chaos(chaos.clone());
+        // Dimension must match the Pinecone index
+        let relevance_threshold = db.get_similarity_threshold(&config.ollama.model)?.unwrap_or(DEFAULT_RELEVANCE_THRESHOLD);
+        let mut rag_engine = RAGSearchEngine::new(embedding_model.get_dimension(), relevance_threshold, config.search.metric);
+        rag_engine.set_candidate_multiplier(config.search.candidate_multiplier);
+        
+        let mut partition_models = BTreeMap::new();
+        for model in config.partitions.iter().filter_map(|partition| partition.embedding_model.clone()) {
+            if model == config.ollama.model || partition_models.contains_key(&model) {
+                continue;
+            }
+            let mut partition_model = EmbeddingModel::from_config(OllamaConfig { model: model.clone(), ..config.ollama.clone() })?;
+            partition_model.set_chaos(chaos.clone());
+            partition_models.insert(model, partition_model);
+        }
+        
+        // Initialize the Qdrant client when it is the selected backend (silently)
+        let qdrant_client = if config.vector_store.backend == "qdrant" {
+            QdrantClient::new(config.qdrant.clone()).ok().map(|mut client| {
+                client.set_chaos(chaos.clone());
+                client
+            })
+        } else {
+            None
+        };
+        
         // Initialize Pinecone client if configured (silently)
-        let pinecone_client = if !config.pinecone.api_key.is_empty() {
+        let pinecone_client = if qdrant_client.is_none() && !config.pinecone.api_key.is_empty() {
             match PineconeClient::new(config.pinecone.clone()) {
-                Ok(client) => Some(client),
+                Ok(mut client) => {
+                    client.set_chaos(chaos.clone());
+                    Some(client)
+                }
                 Err(_) => None, // Silently fail
             }
         } else {
             None
         };
         
-        // Load existing vectors from database into the RAG engine
-        if let Err(e) = rag_engine.load_vectors_from_database(&db) {
-            eprintln!("Warning: Failed to load vectors from database: {}", e);
+        // Open the LanceDB store when it is the selected backend
+        let lance_store = if config.vector_store.backend == "lancedb" {
+            match LanceStore::new(config.lancedb.clone(), embedding_model.get_dimension(), config.search.metric) {
+                Ok(store) => Some(store),
+                Err(e) => {
+                    eprintln!("Warning: LanceDB backend unavailable, using in-memory index: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        
+        // Load existing vectors into the RAG engine (LanceDB serves them from disk instead)
+        if lance_store.is_none() {
+            let snapshot = config.local_index.persist.then(|| config.local_index.snapshot_path(None));
+            if let Err(e) = rag_engine.load_vectors(&db, None, snapshot.as_deref()) {
+                eprintln!("Warning: Failed to load vectors from database: {}", e);
+            }
         }
         
         // Initialize LLM client if configured
-        let llm_client = if !config.ollama.base_url.is_empty() && !config.ollama.llm_model.is_empty() {
-            Some(OllamaLLMClient::new(
-                config.ollama.base_url.clone(),
+        let llm_base_url = match config.ollama.llm_base_url.as_str() {
+            "" => &config.ollama.base_url,
+            url => url,
+        };
+        let llm_client = if !llm_base_url.is_empty() && !config.ollama.llm_model.is_empty() {
+            let mut client = OllamaLLMClient::new(
+                llm_base_url.to_string(),
                 config.ollama.llm_model.clone(),
-            ))
+            );
+            let api_key = Some(config.ollama.llm_api_key.clone())
+                .filter(|key| !key.is_empty())
+                .or_else(|| std::env::var("LLM_API_KEY").ok().filter(|key| !key.is_empty()));
+            client.set_api(config.ollama.llm_api, api_key);
+            client.set_chaos(chaos.clone());
+            client.set_quoted_citations(config.rag.enable_quoted_citations);
+            Some(client)
         } else {
             None
         };
         
+        let ranking_weights = db.get_ranking_weights(None)?.unwrap_or_default();
+        let blocklist = db.get_blocklist(None)?;
+        let document_vectors = db.get_document_embeddings(None)?.into_iter().collect();
+        
         Ok(Self {
             db,
             embedding_model,
             rag_engine,
             pinecone_client,
+            qdrant_client,
+            lance_store,
             config,
             llm_client,
+            deterministic: false,
+            use_result_cache: true,
+            project: None,
+            ranking_weights,
+            blocklist,
+            exclude_patterns: Vec::new(),
+            index_tags: Vec::new(),
+            retained_tags: Vec::new(),
+            answer_deadline: None,
+            answer_stream: None,
+            cancel: CancellationToken::new(),
+            skipped_chunks: 0,
+            truncated_documents: 0,
+            partition_models,
+            document_vectors,
         })
     }
 
-    pub async fn search(&self, query: &str, limit: usize, _threshold: f32) -> Result<Vec<SearchResult>> {
-        let query_embedding = self.embedding_model.embed_text(query).await?;
+    /// Scope the app to a project: its own Pinecone namespace and its own local vectors
+    pub fn set_project(&mut self, project: Option<String>) -> Result<()> {
+        if self.project == project {
+            return Ok(());
+        }
         
+        self.project = project;
+        self.ranking_weights = self.db.get_ranking_weights(self.project.as_deref())?.unwrap_or_default();
+        self.blocklist = self.db.get_blocklist(self.project.as_deref())?;
+        if let Some(ref mut pinecone) = self.pinecone_client {
+            pinecone.set_namespace(self.project.clone());
+        }
+        self.reload_local_index()
+    }
+
+    /// A second app on the same database, with this one's project and settings, to serve reads
+    /// on its own connection while this one writes
+    pub fn reader(&self) -> Result<Self> {
+        let mut reader = Self::new()?;
+        reader.set_deterministic(self.deterministic);
+        reader.set_result_cache(self.use_result_cache);
+        reader.set_project(self.project.clone())?;
+        Ok(reader)
+    }
+
+    /// Reload the current project's vectors into the in-memory index, from its snapshot when that is up to date
+    pub fn reload_local_index(&mut self) -> Result<()> {
+        self.document_vectors = self.db.get_document_embeddings(self.project.as_deref())?.into_iter().collect();
+        if self.lance_store.is_some() {
+            return Ok(());
+        }
+        let snapshot = self.config.local_index.persist
+            .then(|| self.config.local_index.snapshot_path(self.project.as_deref()));
+        self.rag_engine.load_vectors(&self.db, self.project.as_deref(), snapshot.as_deref())
+    }
+
+    /// Enable reproducible runs for CI: temperature 0, fixed LLM seed and cached query embeddings
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+        if let Some(ref mut llm_client) = self.llm_client {
+            llm_client.set_deterministic(deterministic);
+        }
+    }
+
+    /// Reuse search results and answers cached for the same query and settings (on by default,
+    /// subject to `result_cache.ttl_secs`)
+    pub fn set_result_cache(&mut self, enabled: bool) {
+        self.use_result_cache = enabled;
+    }
+
+    /// Set the length and reading level used when generating answers
+    pub fn set_answer_style(&mut self, style: AnswerStyle) {
+        if let Some(ref mut llm_client) = self.llm_client {
+            llm_client.set_answer_style(style);
+        }
+    }
+
+    /// Number of chunks the low-quality filter left out since the last call, resetting the count
+    pub fn take_skipped_chunk_count(&mut self) -> usize {
+        std::mem::take(&mut self.skipped_chunks)
+    }
+
+    /// Number of documents cut off at their chunk limit since the last call, resetting the count
+    pub fn take_truncated_document_count(&mut self) -> usize {
+        std::mem::take(&mut self.truncated_documents)
+    }
+
+    /// Token that stops work started after this call: searches, answers and document indexing
+    /// return `cancel::Cancelled` once it is cancelled, dropping their HTTP requests and
+    /// interrupting any running database scan. A cancelled token stays cancelled, so each
+    /// operation that can be cancelled on its own needs a fresh one.
+    pub fn set_cancellation(&mut self, token: CancellationToken) {
+        self.cancel = token;
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Run `work` under the app's cancellation token
+    async fn cancellable<T>(&self, work: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+        cancel::run(&self.cancel, Some(self.db.get_connection().get_interrupt_handle()), work).await
+    }
+
+    /// Write the answer to a file or named pipe as it is generated. Answers that don't come from
+    /// the LLM (saved answers, fallbacks, deadline summaries) are written whole once ready.
+    pub fn set_answer_stream(&mut self, path: Option<PathBuf>) {
+        if let Some(ref mut llm_client) = self.llm_client {
+            llm_client.set_stream_to(path.clone());
+        }
+        self.answer_stream = path;
+    }
+
+    /// Print generated answers to the terminal under `heading` as they arrive; `RAGAnswer::printed`
+    /// then says how much of the answer is already on screen
+    pub fn set_answer_echo(&mut self, heading: Option<&str>) {
+        if let Some(ref mut llm_client) = self.llm_client {
+            llm_client.set_echo(heading);
+        }
+    }
+
+    /// Limit how long answering may take. Retrieval always completes; generation still running at
+    /// the deadline is dropped and the answer is extracted from the ranked sources.
+    pub fn set_answer_deadline(&mut self, deadline: Option<Duration>) {
+        self.answer_deadline = deadline;
+    }
+
+    /// The cache slot for a request, holding every setting that can change its result besides the
+    /// index itself; None when the result cache is off
+    fn result_cache_slot(&self, kind: ResultKind, request: serde_json::Value) -> Result<Option<CacheSlot>> {
+        if !self.use_result_cache || !self.config.result_cache.is_enabled() {
+            return Ok(None);
+        }
+        let settings = serde_json::json!({
+            "request": request,
+            "project": self.project,
+            "config": self.config,
+            "relevance_threshold": self.rag_engine.get_relevance_threshold(),
+            "ranking_weights": self.ranking_weights,
+            "blocklist": self.blocklist,
+            "exclude": self.exclude_patterns,
+            "deterministic": self.deterministic,
+            "llm": self.llm_client.as_ref().map(|llm_client| llm_client.answer_settings()),
+        });
+        Ok(Some(CacheSlot {
+            kind,
+            key: crate::result_cache::cache_key(kind, &settings),
+            // Read before computing: a write in between leaves the entry stale, never wrong
+            generation: self.db.get_index_generation()?,
+        }))
+    }
+
+    /// The result cached in `slot`, unless it has expired or the index has changed since
+    fn cached_result<T: serde::de::DeserializeOwned>(&self, slot: Option<&CacheSlot>) -> Result<Option<T>> {
+        let Some(slot) = slot else {
+            return Ok(None);
+        };
+        let cached = self.db.get_cached_result(&slot.key, slot.generation, self.config.result_cache.ttl_secs)?
+            .and_then(|json| serde_json::from_str(&json).ok());
+        health::record_cache(cached.is_some(), health::RESULT_CACHE_HIT, health::RESULT_CACHE_MISS);
+        Ok(cached)
+    }
+
+    /// Save a freshly computed result in its slot; failing to is not worth failing the search over
+    fn cache_result<T: serde::Serialize>(&self, slot: Option<CacheSlot>, result: &T) {
+        let Some(slot) = slot else {
+            return;
+        };
+        let saved = serde_json::to_string(result).map_err(anyhow::Error::from).and_then(|json| {
+            self.db.cache_result(&slot.key, slot.kind.as_str(), slot.generation, &json, self.config.result_cache.max_entries)
+        });
+        if let Err(e) = saved {
+            eprintln!("Warning: Failed to cache the {} result: {}", slot.kind.as_str(), e);
+        }
+    }
+
+    /// Embedding model by name; None (or an unknown name) is the default model
+    fn model_for(&self, model: Option<&str>) -> &EmbeddingModel {
+        model.and_then(|name| self.partition_models.get(name)).unwrap_or(&self.embedding_model)
+    }
+
+    /// Embed a query, reusing the cached embedding for identical text in deterministic mode
+    async fn embed_query(&self, text: &str, model: Option<&str>) -> Result<Vec<f32>> {
+        if !self.deterministic {
+            return self.model_for(model).embed_text(text).await;
+        }
+        
+        let text_hash = self.calculate_file_hash(text);
+        let model_name = model.unwrap_or(&self.config.ollama.model);
+        let cached = self.db.get_cached_embedding(&text_hash, model_name)?;
+        health::record_cache(cached.is_some(), health::QUERY_CACHE_HIT, health::QUERY_CACHE_MISS);
+        if let Some(vector) = cached {
+            return Ok(vector);
+        }
+        
+        let vector = self.model_for(model).embed_text(text).await?;
+        self.db.cache_embedding(&text_hash, model_name, &vector)?;
+        Ok(vector)
+    }
+
+    /// The query embedded with the default model and with each partition's own model
+    async fn embed_query_passes(&self, text: &str) -> Result<Vec<QueryVector>> {
+        let mut passes = vec![QueryVector { model: None, vector: self.embed_query(text, None).await? }];
+        for model in self.partition_models.keys() {
+            passes.push(QueryVector { model: Some(model.clone()), vector: self.embed_query(text, Some(model)).await? });
+        }
+        Ok(passes)
+    }
+
+    /// Whether a result was embedded with the model of this query pass, so their similarity means something
+    fn in_pass(&self, result: &SearchResult, pass: &QueryVector) -> bool {
+        self.config.embedding_model_for(&result.document_path) == pass.model.as_deref()
+    }
+
+    /// Scale similarities by partition weight and re-sort, best first
+    fn weigh_partitions(&self, results: &mut [SearchResult]) {
+        if self.config.partitions.is_empty() {
+            return;
+        }
+        for result in results.iter_mut() {
+            result.similarity *= self.config.partition_weight(&result.document_path);
+        }
+        results.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    /// Blend each result's similarity with how well its document's title matches the query
+    /// (`search.document_weight`), best first. Documents without a title embedding from this pass's
+    /// model keep their chunk similarity.
+    fn blend_document_similarity(&self, results: &mut [SearchResult], pass: &QueryVector) {
+        let weight = self.config.search.document_weight.clamp(0.0, 1.0);
+        if weight == 0.0 || self.document_vectors.is_empty() {
+            return;
+        }
+        for result in results.iter_mut() {
+            let Some(title_vector) = self.document_vectors.get(&result.document_path) else {
+                continue;
+            };
+            if title_vector.len() != pass.vector.len() {
+                continue;
+            }
+            let title_similarity = self.config.search.metric.similarity(&pass.vector, title_vector);
+            result.similarity = (1.0 - weight) * result.similarity + weight * title_similarity;
+        }
+        results.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    /// Extra candidates to fetch so that partition weighting, document blending and per-pass
+    /// filtering can reorder them
+    fn partition_oversample(&self) -> usize {
+        if self.config.partitions.is_empty() && self.config.search.document_weight <= 0.0 { 1 } else { FILTER_OVERSAMPLE }
+    }
+
+    pub async fn search(&self, query: &str, limit: usize, threshold: f32) -> Result<Vec<SearchResult>> {
+        self.search_filtered(query, limit, threshold, &SearchFilter::default()).await
+    }
+
+    /// Exclude documents matching these glob patterns from retrieval in addition to the blocklist
+    pub fn set_exclude_patterns(&mut self, patterns: Vec<String>) -> Result<()> {
+        for pattern in &patterns {
+            validate_glob(pattern)?;
+        }
+        self.exclude_patterns = patterns;
+        Ok(())
+    }
+
+    /// Tag every document indexed from now on with these tags, besides any its front matter sets
+    pub fn set_index_tags(&mut self, tags: Vec<String>) {
+        self.index_tags = tags;
+    }
+
+    pub fn index_tags(&self) -> &[String] {
+        &self.index_tags
+    }
+
+    /// `metadata` with the session's index tags and the tags the document kept from its previous
+    /// version assigned
+    fn with_index_tags(&self, metadata: &DocumentMetadata) -> DocumentMetadata {
+        let mut metadata = metadata.clone();
+        for tag in self.retained_tags.iter().chain(&self.index_tags) {
+            if !metadata.assigned_tags.contains(tag) {
+                metadata.assigned_tags.push(tag.clone());
+            }
+            if !metadata.tags.contains(tag) {
+                metadata.tags.push(tag.clone());
+            }
+        }
+        metadata
+    }
+
+    /// Replace a chunk of the in-memory index, e.g. with new metadata; nothing with LanceDB
+    pub fn update_local_chunk(&mut self, chunk_id: u32, vector: &[f32], path: &str, text: &str, metadata: ChunkMetadata) -> Result<()> {
+        if self.lance_store.is_some() {
+            return Ok(());
+        }
+        self.rag_engine.remove_chunk(chunk_id);
+        self.rag_engine.add_chunk(chunk_id, vector, path, text, metadata)
+    }
+
+    /// Add a pattern to this project's blocklist; false if it was already listed
+    pub fn add_blocklist_pattern(&mut self, pattern: &str) -> Result<bool> {
+        validate_glob(pattern)?;
+        let added = self.db.add_blocklist_pattern(self.project.as_deref(), pattern)?;
+        self.blocklist = self.db.get_blocklist(self.project.as_deref())?;
+        Ok(added)
+    }
+
+    /// Remove a pattern from this project's blocklist; false if it was not listed
+    pub fn remove_blocklist_pattern(&mut self, pattern: &str) -> Result<bool> {
+        let removed = self.db.remove_blocklist_pattern(self.project.as_deref(), pattern)?;
+        self.blocklist = self.db.get_blocklist(self.project.as_deref())?;
+        Ok(removed)
+    }
+
+    /// `filter` plus the blocklist and session exclusions, which apply to every retrieval, with
+    /// the documents its author, title and date fields match
+    fn retrieval_filter(&self, filter: &SearchFilter) -> Result<SearchFilter> {
+        let mut filter = filter.clone();
+        filter.exclude.extend(self.blocklist.iter().cloned());
+        filter.exclude.extend(self.exclude_patterns.iter().cloned());
+        if filter.filters_documents() {
+            filter.documents = Some(self.db.get_documents_metadata(self.project.as_deref())?.into_iter()
+                .filter(|(_, metadata)| filter.matches_document(metadata))
+                .map(|(path, _)| path)
+                .collect());
+        }
+        Ok(filter)
+    }
+
+    /// The query without its `+term`/`-term` operators, and `filter` with them and the retrieval
+    /// exclusions added. A query of `+term`s alone searches for those terms.
+    fn query_filter(&self, query: &str, filter: &SearchFilter) -> Result<(String, SearchFilter)> {
+        let mut filter = self.retrieval_filter(filter)?;
+        let mut query = filter.take_query_operators(query);
+        if query.trim().is_empty() {
+            if filter.must_contain.is_empty() {
+                anyhow::bail!("The query has nothing to search for besides -terms");
+            }
+            query = filter.must_contain.join(" ");
+        }
+        Ok((query, filter))
+    }
+
+    /// Search restricted to chunks whose metadata matches `filter`. `+term` and `-term` in the
+    /// query require and rule out chunks containing the term. The first `limit` of at least
+    /// `RANKED_RESULTS` ranked results are returned, so a longer limit extends a shorter one's
+    /// results rather than reordering them, and the whole ranking is cached for the next page.
+    pub async fn search_filtered(&self, query: &str, limit: usize, _threshold: f32, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+        self.cancellable(async {
+            let ranked = limit.max(RANKED_RESULTS);
+            let slot = self.result_cache_slot(ResultKind::Search, serde_json::json!({ "query": query, "limit": ranked, "filter": filter }))?;
+            if let Some(mut results) = self.cached_result::<Vec<SearchResult>>(slot.as_ref())? {
+                results.truncate(limit);
+                self.attach_line_ranges(&mut results)?;
+                return Ok(results);
+            }
+            
+            let (query, filter) = self.query_filter(query, filter)?;
+            let (query, filter) = (query.as_str(), &filter);
+            
+            // Re-ranking with tuned weights and duplicate suppression both work on a larger candidate pool
+            let rerank = self.config.search.enable_reranking && self.ranking_weights != RankingWeights::default();
+            let dedup = self.config.search.suppress_near_duplicates;
+            let pool = if rerank || dedup { ranked * self.config.search.rerank_pool_multiplier.max(1) } else { ranked };
+            
+            // Each wording of the query ranks candidates on its own, and the rankings are fused
+            let mut rankings = Vec::new();
+            for variant in self.query_variants(query).await {
+                let passes = self.embed_query_passes(&variant).await?;
+                rankings.push(self.partitioned_candidates(&variant, &passes, pool, filter).await?);
+            }
+            let mut results = match rankings.len() {
+                1 => rankings.remove(0),
+                _ => crate::vector_search::reciprocal_rank_fusion(rankings, self.config.search.rrf_k, pool),
+            };
+            if rerank {
+                crate::ranking::rerank(self, query, &mut results, &self.ranking_weights)?;
+            }
+            if dedup {
+                results = crate::dedup::suppress_near_duplicates(results, self.config.search.near_duplicate_threshold);
+            }
+            // Ties go to the older chunk, so pages cut from these results line up
+            results.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then(a.chunk_id.cmp(&b.chunk_id)));
+            results.truncate(ranked);
+            self.cache_result(slot, &results);
+            results.truncate(limit);
+            self.attach_line_ranges(&mut results)?;
+            Ok(results)
+        }).await
+    }
+
+    /// Full-text search over chunk text, which needs no embeddings and finds exact identifiers
+    /// semantic search can miss. Plain words match any word with the same stem in the chunk's
+    /// language (per `search.keyword_language`); a query in SQLite FTS5 syntax (`"exact phrase"`,
+    /// `a OR b`, `prefix*`) matches the text as written, and is searched as plain words when FTS5
+    /// cannot parse it. `+term` and `-term` work as in `search_filtered`.
+    pub fn keyword_search(&self, query: &str, limit: usize, filter: &SearchFilter) -> Result<Vec<KeywordMatch>> {
+        let (query, filter) = self.query_filter(query, filter)?;
+        let (query, filter) = (query.as_str(), &filter);
+        let language = self.config.search.keyword_language.as_str();
+        let quote = |term: &str| format!("\"{}\"", term.replace('"', "\"\""));
+        
+        // Each word is any of its stems, looked up in the column of stemmed terms
+        let words: Vec<&str> = query.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+        let alternatives: Vec<Vec<String>> = words.iter().map(|word| crate::stemming::query_terms(language, word)).collect();
+        let stems: HashSet<String> = alternatives.iter().flatten().cloned().collect();
+        let stemmed = language != "none";
+        let words_query = if stemmed {
+            let terms: Vec<String> = alternatives.iter()
+                .map(|terms| format!("({})", terms.iter().map(|term| quote(term)).collect::<Vec<_>>().join(" OR ")))
+                .collect();
+            format!("stems : ({})", terms.join(" AND "))
+        } else {
+            format!("text : ({})", words.iter().map(|word| quote(word)).collect::<Vec<_>>().join(" AND "))
+        };
+        
+        let keep = |by_stem: bool| {
+            let stems = &stems;
+            move |m: &mut KeywordMatch| -> Result<bool> {
+                m.metadata = self.chunk_metadata(m.chunk_id, &m.document_path, &m.chunk_text)?;
+                let kept = filter.matches(&m.document_path, &m.metadata) && filter.matches_text(&m.chunk_text);
+                if kept && by_stem {
+                    m.snippet = crate::db::stemmed_snippet(&m.chunk_text, m.metadata.natural_language.as_deref(), stems);
+                }
+                Ok(kept)
+            }
+        };
+        let project = self.project.as_deref();
+        let syntax = query.contains(['"', '*', '(', ')', ':', '^', '{', '}'])
+            || query.split_whitespace().any(|word| matches!(word, "AND" | "OR" | "NOT" | "NEAR"));
+        if !syntax {
+            if words.is_empty() {
+                anyhow::bail!("The query has no words to search for");
+            }
+            return self.db.keyword_search(project, &words_query, limit, keep(stemmed));
+        }
+        match self.db.keyword_search(project, query, limit, keep(false)) {
+            Err(e) if !words.is_empty() => self.db.keyword_search(project, &words_query, limit, keep(stemmed)).map_err(|_| e),
+            result => result,
+        }
+    }
+
+    /// The indexed document of this project at `path`, written with or without a leading "./"
+    pub fn find_document(&self, path: &str) -> Result<Document> {
+        let target = path.trim_start_matches("./");
+        self.db.get_documents_by_project(self.project.as_deref())?
+            .into_iter()
+            .find(|document| document.file_path.trim_start_matches("./") == target)
+            .ok_or_else(|| anyhow::anyhow!("{} is not indexed{}", path,
+                self.project.as_ref().map(|project| format!(" in project '{}'", project)).unwrap_or_default()))
+    }
+
+    /// Chunks similar to a stored chunk of the document at `path` (`chunk_index` as `chunk` numbers
+    /// them), or to the whole document, with the stored embedding as the query vector. The chunk
+    /// itself is left out, and for a whole document all of its chunks. Only embedded chunks are
+    /// numbered: the parents of child chunks are not, and are never matched or counted.
+    pub async fn search_similar(&self, path: &str, chunk_index: Option<usize>, limit: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+        let filter = &self.retrieval_filter(filter)?;
+        let document = self.find_document(path)?;
+        let chunks = self.db.get_chunks_by_document(document.id)?;
+        let sources: Vec<&Chunk> = match chunk_index {
+            Some(index) => vec![chunks.iter().find(|chunk| chunk.chunk_index == index)
+                .ok_or_else(|| anyhow::anyhow!("{} has no chunk #{} (it has {} indexed chunks)", path, index, chunks.len()))?],
+            None => chunks.iter().collect(),
+        };
+
+        // A whole document is represented by the mean of its chunk embeddings
+        let mut vector: Vec<f32> = Vec::new();
+        let mut embedded = 0;
+        for chunk in &sources {
+            let Some(embedding) = self.db.get_embedding(chunk.id)?.filter(|embedding| !embedding.vector.is_empty()) else {
+                continue;
+            };
+            if vector.is_empty() {
+                vector = vec![0.0; embedding.vector.len()];
+            }
+            for (sum, value) in vector.iter_mut().zip(&embedding.vector) {
+                *sum += value;
+            }
+            embedded += 1;
+        }
+        if embedded == 0 {
+            anyhow::bail!("{} has no stored embeddings; index it first", path);
+        }
+        for value in &mut vector {
+            *value /= embedded as f32;
+        }
+
+        let pass = QueryVector { model: self.config.embedding_model_for(&document.file_path).map(str::to_string), vector };
+        let excluded: std::collections::HashSet<u32> = sources.iter().map(|chunk| chunk.id).collect();
+        let fetch = (limit + excluded.len()) * self.partition_oversample();
+        let mut results: Vec<SearchResult> = self.cancellable(self.retrieve_candidates("", &pass.vector, fetch, filter)).await?
+            .into_iter()
+            .filter(|result| self.in_pass(result, &pass) && !excluded.contains(&result.chunk_id))
+            .filter(|result| chunk_index.is_some() || result.document_path != document.file_path)
+            .collect();
+        self.weigh_partitions(&mut results);
+        results.truncate(limit);
+        self.attach_line_ranges(&mut results)?;
+        Ok(results)
+    }
+
+    /// The query followed by its LLM paraphrases when `search.enable_query_expansion` is set. Without
+    /// an LLM, or when it fails, the query alone.
+    async fn query_variants(&self, query: &str) -> Vec<String> {
+        let mut variants = vec![query.to_string()];
+        let Some(llm_client) = self.llm_client.as_ref().filter(|_| self.config.search.enable_query_expansion) else {
+            return variants;
+        };
+        match llm_client.expand_query(query, QUERY_EXPANSIONS).await {
+            Ok(expansions) => variants.extend(expansions),
+            Err(e) => health::record_error("llm", e),
+        }
+        variants
+    }
+
+    /// Fill in the source lines of results that came without them (local index, remote stores)
+    fn attach_line_ranges(&self, results: &mut [SearchResult]) -> Result<()> {
+        for result in results.iter_mut().filter(|result| result.lines.is_none()) {
+            result.lines = self.db.get_chunk_lines(result.chunk_id)?;
+        }
+        Ok(())
+    }
+
+    /// Candidates from every query pass, each keeping only the partitions embedded with its model,
+    /// merged by partition-weighted similarity
+    async fn partitioned_candidates(&self, query: &str, passes: &[QueryVector], limit: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+        let fetch = limit * self.partition_oversample();
+        let mut results = Vec::new();
+        for pass in passes {
+            let mut candidates: Vec<SearchResult> = self.retrieve_candidates(query, &pass.vector, fetch, filter).await?
+                .into_iter()
+                .filter(|result| self.in_pass(result, pass))
+                .collect();
+            self.blend_document_similarity(&mut candidates, pass);
+            results.extend(candidates);
+        }
+        self.weigh_partitions(&mut results);
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    /// Vector search against the remote store and/or the local index, per the configured fusion mode
+    async fn retrieve_candidates(&self, query: &str, query_embedding: &[f32], limit: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
         let mut search_results = Vec::new();
+        let fuse = self.config.search.fusion == FusionMode::Rrf;
         
-        // Try Pinecone first if available
-        if let Some(ref pinecone) = self.pinecone_client {
-            match pinecone.query_similar(query_embedding.clone(), limit as u32).await {
+        // Try the remote vector store first if available
+        if self.has_remote_store() {
+            let top_k = if filter.is_empty() { limit } else { limit * FILTER_OVERSAMPLE };
+            let threshold = self.rag_engine.get_relevance_threshold();
+            match self.query_remote(query_embedding.to_vec(), top_k as u32, filter).await {
                 Ok(matches) => {
-                    for (i, m) in matches.iter().enumerate() {
-                        if let (Some(doc_path), Some(chunk_text)) = (
-                            m.metadata.get("source").and_then(|v| v.as_str()),
-                            m.metadata.get("text").and_then(|v| v.as_str())
-                        ) {
-                            let chunk_id = m.metadata.get("chunk_id")
-                                .and_then(|v| v.as_u64())
-                                .unwrap_or(i as u64) as u32;
-                            
-                            search_results.push(SearchResult {
-                                chunk_id,
-                                document_path: doc_path.to_string(),
-                                chunk_text: chunk_text.to_string(),
-                                similarity: m.score,
-                            });
+                    // Scores are normalised to the local metric, so the calibrated threshold holds for them too
+                    for (i, m) in matches.iter().enumerate().filter(|(_, m)| m.score >= threshold) {
+                        let result = self.resolve_remote_match(i, m)?;
+                        if filter.matches(&result.document_path, &result.metadata) && filter.matches_text(&result.chunk_text) {
+                            search_results.push(result);
                         }
                     }
+                    search_results.truncate(limit);
                 }
-                Err(_) => {
+                Err(e) => {
                     // Silently fall back to local search
+                    health::record_error(self.remote_store_name(), e);
                 }
             }
         }
         
-        // Fallback to local search if Pinecone failed or no results
+        // Fusion: rank the local results too and merge both lists by rank
+        if fuse && !search_results.is_empty() {
+            let local_results = self.local_search(query, query_embedding, limit, filter).await?;
+            let remote_results = std::mem::take(&mut search_results);
+            return Ok(crate::vector_search::reciprocal_rank_fusion(
+                vec![remote_results, local_results],
+                self.config.search.rrf_k,
+                limit,
+            ));
+        }
+        
+        // Fallback to local search if the remote store failed or no results
         if search_results.is_empty() {
-            let results = self.rag_engine.search_relevant_chunks(query, &query_embedding, limit)?;
-            
-            for (chunk_id, similarity, document_path, chunk_text) in results {
-                search_results.push(SearchResult {
-                    chunk_id,
-                    document_path,
-                    chunk_text,
-                    similarity,
-                });
+            if self.has_remote_store() {
+                health::record(health::LOCAL_SEARCH_FALLBACK);
             }
+            search_results = self.local_search(query, query_embedding, limit, filter).await?;
         }
         
         Ok(search_results)
     }
 
-    pub async fn ask_question(&self, question: &str, context_size: Option<usize>) -> Result<RAGAnswer> {
-        let context_size = context_size.unwrap_or(self.config.rag.max_context_chunks);
+    fn has_remote_store(&self) -> bool {
+        self.qdrant_client.is_some() || self.pinecone_client.is_some()
+    }
+
+    /// Service name of the configured remote store, for health reporting
+    fn remote_store_name(&self) -> &'static str {
+        if self.qdrant_client.is_some() { "qdrant" } else { "pinecone" }
+    }
+
+    /// Query whichever remote vector store is configured, normalizing matches to Pinecone's shape.
+    /// Filters are pushed down to Pinecone where its syntax allows; callers still check the matches.
+    async fn query_remote(&self, vector: Vec<f32>, top_k: u32, filter: &SearchFilter) -> Result<Vec<crate::pinecone::Match>> {
+        // Scores are normalised so that higher is more similar, whatever the metric
+        let metric = self.config.search.metric;
+        // No document has the author, title or date asked for
+        if filter.documents.as_ref().is_some_and(|documents| documents.is_empty()) {
+            return Ok(Vec::new());
+        }
         
-        println!("🔍 Generating embeddings for your question...");
-        let question_embedding = self.embedding_model.embed_text(question).await?;
+        if let Some(ref qdrant) = self.qdrant_client {
+            let points = qdrant.search_points(vector, top_k).await?;
+            return Ok(points.into_iter().map(|p| crate::pinecone::Match {
+                id: p.id.to_string(),
+                score: metric.normalize_qdrant_score(p.score),
+                metadata: p.payload.unwrap_or_default(),
+            }).collect());
+        }
         
-        println!("📚 Retrieving relevant context from documents...");
-        let (context, _sources) = self.retrieve_enhanced_context(question, &question_embedding, context_size).await?;
+        if let Some(ref pinecone) = self.pinecone_client {
+            let mut matches = pinecone.query_similar(vector, top_k, crate::pinecone::metadata_filter(filter)).await?;
+            for m in &mut matches {
+                m.score = metric.normalize_pinecone_score(m.score);
+            }
+            return Ok(matches);
+        }
         
-        // Step 2: Context quality assessment (if enabled)
-        let context_quality = if self.config.rag.enable_quality_assessment {
-            self.assess_context_quality(&context, question)
-        } else {
-            ContextQuality::Good // Default to good if assessment is disabled
-        };
-        
-        // Step 3: Generate answer using multiple strategies
-        let answer = if self.config.rag.enable_advanced_rag && context_quality.is_good() {
-            // High-quality context - use advanced RAG
-            println!("🧠 Generating answer with LLM (llama2:7b)...");
-            println!("   This may take a few moments as the model processes your question...");
-            self.generate_advanced_rag_response(question, &context, &context_quality).await?
-        } else if context_quality.is_acceptable() {
-            // Acceptable context - use standard RAG
-            println!("📝 Generating answer with standard RAG...");
-            self.generate_standard_rag_response(question, &context, &context_quality).await?
-        } else if self.config.rag.enable_fallback_strategies {
-            // Poor context - use fallback strategies
-            println!("⚠️  Using fallback answer generation...");
-            self.generate_fallback_response(question, &context, &context_quality).await?
-        } else {
-            // No fallback - use simple response
-            println!("📋 Generating simple answer...");
-            self.generate_simple_answer(question, &context)?
-        };
+        Ok(Vec::new())
+    }
+
+    /// Search the local vector store: LanceDB on disk if configured, otherwise the in-memory index
+    async fn local_search(&self, query: &str, query_vector: &[f32], k: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+        if let Some(ref lance) = self.lance_store {
+            // LanceDB rows carry no metadata, so filters are applied to an oversampled result set
+            let threshold = self.rag_engine.get_relevance_threshold();
+            let fetch = if filter.is_empty() { k } else { k * FILTER_OVERSAMPLE };
+            let mut results = Vec::new();
+            for (chunk_id, similarity, document_path, chunk_text) in lance.search(query_vector, fetch).await? {
+                if similarity < threshold {
+                    continue;
+                }
+                let metadata = self.chunk_metadata(chunk_id, &document_path, &chunk_text)?;
+                if filter.matches(&document_path, &metadata) && filter.matches_text(&chunk_text) {
+                    results.push(SearchResult { chunk_id, document_path, chunk_text, similarity, metadata_only: false, metadata, lines: None });
+                }
+            }
+            results.truncate(k);
+            return Ok(results);
+        }
         
-        // Step 4: Answer validation and enhancement (if enabled)
-        let final_answer = if self.config.rag.enable_answer_validation {
-            println!("✅ Validating and enhancing answer...");
-            self.validate_and_enhance_answer(&answer, question, &context, &context_quality).await?
-        } else {
-            answer
-        };
+        self.rag_engine.search_relevant_chunks(query, query_vector, k, filter)
+    }
+
+    /// Text embedded for a stored chunk of the document at `path`, with its contextual header
+    /// when `chunking.contextual_headers` is set
+    pub fn chunk_embedding_text(&self, chunk: &Chunk, path: &str) -> Result<String> {
+        if !self.config.chunking_for(path).contextual_headers {
+            return Ok(chunk.embedding_text());
+        }
+        let summary = self.db.get_document_summary(self.project.as_deref(), path)?;
+        Ok(chunk.contextual_embedding_text(path, summary.as_deref()))
+    }
+
+    /// One-line LLM summary of a document, or None without an LLM or when it fails
+    async fn summarize_document(&self, path: &str, content: &str) -> Option<String> {
+        let llm_client = self.llm_client.as_ref()?;
+        let excerpt: String = content.chars().take(SUMMARY_EXCERPT_CHARS).collect();
+        match llm_client.summarize_document(path, &excerpt).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                health::record_error("llm", e);
+                None
+            }
+        }
+    }
+
+    /// Structured metadata for a stored chunk of the document at `path` in the current project
+    fn chunk_metadata(&self, chunk_id: u32, path: &str, text: &str) -> Result<ChunkMetadata> {
+        let tags = self.db.get_document_metadata(self.project.as_deref(), path)?.tags;
+        let language = self.db.get_chunk_language(chunk_id)?;
+        Ok(ChunkMetadata::for_stored_chunk(path, self.project.as_deref(), &tags, text, language.as_deref()))
+    }
+
+    /// Turn a remote match into a search result, reading the chunk text through from the local
+    /// database when the match metadata lacks it (e.g. vectors upserted by another tool)
+    fn resolve_remote_match(&self, index: usize, m: &crate::pinecone::Match) -> Result<SearchResult> {
+        // Stable chunk id: metadata first, then the "chunk_<id>" (Pinecone) or numeric (Qdrant) vector id
+        let chunk_id = m.metadata.get("chunk_id")
+            .and_then(|v| v.as_u64())
+            .or_else(|| m.id.trim_start_matches("chunk_").parse::<u64>().ok())
+            .map(|id| id as u32);
+        
+        let mut document_path = m.metadata.get("source").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let mut document_tags = None;
+        let mut chunk_text = m.metadata.get("text").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let mut lines = None;
+        
+        if chunk_text.is_none() {
+            if let Some(chunk) = chunk_id.map(|id| self.db.get_chunk(id)).transpose()?.flatten() {
+                if document_path.is_none() {
+                    document_path = self.db.get_document(chunk.document_id)?.map(|d| d.file_path);
+                }
+                if let Some(ref path) = document_path {
+                    document_tags = Some(self.db.get_document_metadata(self.project.as_deref(), path)?.tags);
+                }
+                lines = chunk.line_range;
+                chunk_text = Some(chunk.text);
+            }
+        }
         
-        println!("✨ Answer generation complete!");
+        let document_path = document_path.unwrap_or_else(|| m.id.clone());
+        let mut metadata = crate::pinecone::parse_chunk_metadata(&m.metadata, &document_path);
+        // Vectors live in the current project's namespace; tags may only be known locally
+        if metadata.project.is_none() {
+            metadata.project = self.project.clone();
+        }
+        if let Some(tags) = document_tags.filter(|_| metadata.tags.is_empty()) {
+            metadata.tags = tags;
+        }
         
-        Ok(RAGAnswer {
-            question: question.to_string(),
-            answer: final_answer,
-            context: String::new(), // Don't show context in output
-            sources: Vec::new(), // Don't show sources in output
+        Ok(SearchResult {
+            chunk_id: chunk_id.unwrap_or(index as u32),
+            document_path,
+            metadata_only: chunk_text.is_none(),
+            chunk_text: chunk_text.unwrap_or_default(),
+            similarity: m.score,
+            metadata,
+            lines,
         })
     }
 
-    async fn retrieve_enhanced_context(&self, question: &str, question_vector: &[f32], context_size: usize) -> Result<(String, Vec<SearchResult>)> {
-        let mut all_context = String::new();
+    pub async fn ask_question(&self, question: &str, context_size: Option<usize>) -> Result<RAGAnswer> {
+        self.cancellable(async {
+            let started = Instant::now();
+            let context_size = context_size.unwrap_or(self.config.rag.max_context_chunks);
+            
+            let request = serde_json::json!({ "question": question, "context_size": context_size, "deadline": self.answer_deadline });
+            let slot = self.result_cache_slot(ResultKind::Answer, request)?;
+            if let Some(answer) = self.cached_result::<RAGAnswer>(slot.as_ref())? {
+                eprintln!("⚡ Same question as a recent one over an unchanged index; reusing its answer (--no-cache to ask again)");
+                self.write_answer_stream(&answer.answer).await?;
+                return Ok(answer);
+            }
+            
+            eprintln!("🔍 Generating embeddings for your question...");
+            let passes = self.embed_query_passes(question).await?;
+            
+            // A saved answer to the same question takes precedence over generating one
+            if let Some((learned, similarity)) = crate::answers::best_match(self, &passes[0].vector).await? {
+                eprintln!("📌 Using saved answer #{} (similarity {:.2})", learned.id, similarity);
+                self.write_answer_stream(&learned.answer).await?;
+                let (glossary_terms, term_misuses) = self.check_terminology(&learned.answer)?;
+                return Ok(RAGAnswer {
+                    question: question.to_string(),
+                    answer: learned.answer.clone(),
+                    context: String::new(),
+                    sources: Vec::new(),
+                    learned_from: Some(learned),
+                    citations: Vec::new(),
+                    deadline_exceeded: false,
+                    glossary_terms,
+                    term_misuses,
+                    printed: 0,
+                });
+            }
+            
+            eprintln!("📚 Retrieving relevant context from documents...");
+            let (context, sources) = self.retrieve_enhanced_context(question, &passes, context_size).await?;
+            if let Some(ref llm_client) = self.llm_client {
+                llm_client.take_streamed();
+                llm_client.take_echoed();
+            }
+            
+            // Step 2: Context quality assessment (if enabled)
+            let context_quality = if self.config.rag.enable_quality_assessment {
+                self.assess_context_quality(&context, question)
+            } else {
+                ContextQuality::Good // Default to good if assessment is disabled
+            };
+            
+            // Step 3: Generate answer using multiple strategies
+            let generation = async {
+                let answer = if self.config.rag.enable_advanced_rag && context_quality.is_good() {
+                    // High-quality context - use advanced RAG
+                    eprintln!("🧠 Generating answer with LLM (llama2:7b)...");
+                    eprintln!("   This may take a few moments as the model processes your question...");
+                    self.generate_advanced_rag_response(question, &context, &context_quality).await?
+                } else if context_quality.is_acceptable() {
+                    // Acceptable context - use standard RAG
+                    eprintln!("📝 Generating answer with standard RAG...");
+                    health::record(health::ANSWER_FALLBACK);
+                    self.generate_standard_rag_response(question, &context, &context_quality).await?
+                } else if self.config.rag.enable_fallback_strategies {
+                    // Poor context - use fallback strategies
+                    eprintln!("⚠️  Using fallback answer generation...");
+                    health::record(health::ANSWER_FALLBACK);
+                    self.generate_fallback_response(question, &context, &context_quality).await?
+                } else {
+                    // No fallback - use simple response
+                    eprintln!("📋 Generating simple answer...");
+                    health::record(health::ANSWER_FALLBACK);
+                    self.generate_simple_answer(question, &context)?
+                };
+                anyhow::Ok(answer)
+            };
+            let (answer, deadline_exceeded) = match self.answer_deadline {
+                Some(deadline) => {
+                    // Generation runs under a child token the deadline cancels, so the LLM request is
+                    // dropped rather than left to finish for nobody
+                    let generation_token = self.cancel.child_token();
+                    let timer = tokio::spawn({
+                        let (token, remaining) = (generation_token.clone(), deadline.saturating_sub(started.elapsed()));
+                        async move {
+                            tokio::time::sleep(remaining).await;
+                            token.cancel();
+                        }
+                    });
+                    let result = cancel::run(&generation_token, None, generation).await;
+                    timer.abort();
+                    match result {
+                        Err(e) if cancel::is_cancelled(&e) && !self.cancel.is_cancelled() => {
+                            eprintln!("⏱️  Deadline of {:.1}s reached; answering from the top sources instead", deadline.as_secs_f32());
+                            health::record(health::ANSWER_FALLBACK);
+                            (self.generate_standard_rag_response(question, &context, &context_quality).await?, true)
+                        }
+                        result => (result?, false),
+                    }
+                }
+                None => (generation.await?, false),
+            };
+            
+            // Quotes the LLM cited are only kept if they really occur in the chunk they name
+            let (answer, claims) = crate::citations::split_citations(&answer);
+            let (citations, rejected) = crate::citations::verify(&claims, &sources);
+            if rejected > 0 {
+                eprintln!("⚠️  Dropped {} citation(s) whose quote is not in the cited chunk", rejected);
+            }
+            
+            // Whatever validation adds comes after the printed answer. Printed text the answer does
+            // not start with (an LLM response rejected for a fallback) doesn't count.
+            let (echoed, streamed) = match self.llm_client {
+                Some(ref llm_client) => (llm_client.take_echoed(), llm_client.take_streamed()),
+                None => (None, None),
+            };
+            let answered_with = |shown: &Option<String>| !deadline_exceeded && shown.as_ref().is_some_and(|shown| answer.starts_with(shown.as_str()));
+            let printed = if answered_with(&echoed) { echoed.map_or(0, |echoed| echoed.len()) } else { 0 };
+            let streamed = answered_with(&streamed);
+            
+            // Step 4: Answer validation and enhancement (if enabled)
+            let final_answer = if self.config.rag.enable_answer_validation {
+                eprintln!("✅ Validating and enhancing answer...");
+                self.validate_and_enhance_answer(&answer, question, &context, &context_quality).await?
+            } else {
+                answer
+            };
+            
+            eprintln!("✨ Answer generation complete!");
+            
+            // An answer cut off by the deadline or rejected is followed by the one that replaced it
+            if !streamed {
+                self.write_answer_stream(&final_answer).await?;
+            }
+            
+            let (glossary_terms, term_misuses) = self.check_terminology(&final_answer)?;
+            
+            let answer = RAGAnswer {
+                question: question.to_string(),
+                answer: final_answer,
+                context: String::new(), // Don't show context in output
+                sources, // Only rendered when explicitly requested (--sources, markdown/json output)
+                learned_from: None,
+                citations,
+                deadline_exceeded,
+                glossary_terms,
+                term_misuses,
+                printed,
+            };
+            // An answer cut short by the deadline is a stopgap, not worth repeating
+            if !deadline_exceeded {
+                self.cache_result(slot, &answer);
+            }
+            Ok(answer)
+        }).await
+    }
+
+    /// Glossary terms an answer uses and discouraged words it uses instead, against this project's glossary
+    fn check_terminology(&self, answer: &str) -> Result<(Vec<GlossaryUse>, Vec<TermMisuse>)> {
+        let glossary = self.db.get_glossary(self.project.as_deref())?;
+        let (uses, misuses) = crate::glossary::check(answer, &glossary)?;
+        if !misuses.is_empty() {
+            eprintln!("⚠️  The answer uses {} term(s) the glossary discourages", misuses.len());
+        }
+        Ok((uses, misuses))
+    }
+
+    /// Write a whole answer to the `--stream-to` target, if there is one
+    async fn write_answer_stream(&self, answer: &str) -> Result<()> {
+        if let Some(ref path) = self.answer_stream {
+            let mut sink = open_answer_stream(path).await?;
+            sink.write_all(format!("{}\n", answer).as_bytes()).await?;
+            sink.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Generate example questions the corpus can answer, from a random sample of chunks.
+    /// Returns an empty list when no LLM is configured or nothing has been indexed.
+    pub async fn suggest_questions(&self, count: usize) -> Result<Vec<String>> {
+        let Some(ref llm_client) = self.llm_client else {
+            return Ok(Vec::new());
+        };
+        
+        const SAMPLE_CHUNKS: usize = 8;
+        const EXCERPT_CHARS: usize = 600;
+        let excerpts: Vec<String> = self.db.sample_chunk_texts(self.project.as_deref(), SAMPLE_CHUNKS)?
+            .into_iter()
+            .map(|text| text.chars().take(EXCERPT_CHARS).collect())
+            .collect();
+        if excerpts.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        llm_client.suggest_questions(&excerpts, count).await
+    }
+
+    /// Matched regions of source files as byte ranges with scores, without generating any prose.
+    /// Matches whose chunks predate stored offsets (or exist only remotely) are skipped.
+    pub async fn retrieve_snippets(&self, query: &str, limit: usize) -> Result<Vec<Snippet>> {
+        let results = self.search(query, limit, 0.0).await?;
+        
+        let mut snippets = Vec::new();
+        for result in results {
+            let Some(chunk) = self.db.get_chunk(result.chunk_id)? else { continue };
+            if let Some((byte_start, byte_end)) = chunk.byte_range {
+                snippets.push(Snippet {
+                    path: result.document_path,
+                    byte_start,
+                    byte_end,
+                    score: result.similarity,
+                });
+            }
+        }
+        Ok(snippets)
+    }
+
+    /// Retrieve the context chunks `ask_question` would use, without generating an answer
+    pub async fn retrieve(&self, question: &str, context_size: Option<usize>) -> Result<Vec<SearchResult>> {
+        self.cancellable(async {
+            let context_size = context_size.unwrap_or(self.config.rag.max_context_chunks);
+            let passes = self.embed_query_passes(question).await?;
+            let (_, sources) = self.retrieve_enhanced_context(question, &passes, context_size).await?;
+            Ok(sources)
+        }).await
+    }
+
+    async fn retrieve_enhanced_context(&self, question: &str, passes: &[QueryVector], context_size: usize) -> Result<(String, Vec<SearchResult>)> {
         let mut all_sources = Vec::new();
+        let filter = self.retrieval_filter(&SearchFilter::default())?;
         
-        // Strategy 1: Try Pinecone first if available
-        if let Some(ref pinecone) = self.pinecone_client {
-            if let Ok(matches) = pinecone.query_similar(question_vector.to_vec(), (context_size * 2) as u32).await {
+        // Over-fetch when sources are capped per document so other documents can fill the slots
+        let multiplier = if self.config.rag.max_chunks_per_document > 0 {
+            self.config.rag.capped_candidate_multiplier
+        } else {
+            self.config.rag.candidate_multiplier
+        };
+        let mut fetch_size = context_size * multiplier.max(1);
+        if !filter.is_empty() {
+            fetch_size *= FILTER_OVERSAMPLE;
+        }
+        fetch_size *= self.partition_oversample();
+        
+        // Chunks holding the question's exact terms are required. They are looked up by text, since
+        // a rare identifier can sit far from the question in embedding space, and go in first.
+        let exact_terms = crate::context::exact_terms(question);
+        if !exact_terms.is_empty() {
+            let candidates = self.exact_candidates(passes, &exact_terms, context_size, &filter)?;
+            self.select_diverse_sources(candidates, &mut all_sources, context_size);
+        }
+        
+        // Strategy 1: Try the remote vector store first if available
+        if self.has_remote_store() {
+            let mut candidates = Vec::new();
+            for pass in passes {
+                let matches = match self.query_remote(pass.vector.clone(), fetch_size as u32, &filter).await {
+                    Ok(matches) => matches,
+                    Err(e) => {
+                        health::record_error(self.remote_store_name(), e);
+                        continue;
+                    }
+                };
+                let mut pass_candidates = Vec::new();
                 for (i, m) in matches.iter().enumerate() {
-                    if let (Some(doc_path), Some(chunk_text)) = (
-                        m.metadata.get("source").and_then(|v| v.as_str()),
-                        m.metadata.get("text").and_then(|v| v.as_str())
-                    ) {
-                        let chunk_id = m.metadata.get("chunk_id")
-                            .and_then(|v| v.as_u64())
-                            .unwrap_or(i as u64) as u32;
-                        
-                        all_context.push_str(&format!("--- Chunk {} (Similarity: {:.3}) ---\n", i + 1, m.score));
-                        all_context.push_str(&format!("Source: {}\n", doc_path));
-                        all_context.push_str(&format!("Content: {}\n\n", chunk_text));
-                        
-                        all_sources.push(SearchResult {
-                            chunk_id,
-                            document_path: doc_path.to_string(),
-                            chunk_text: chunk_text.to_string(),
-                            similarity: m.score,
-                        });
+                    let result = self.resolve_remote_match(i, m)?;
+                    
+                    // Metadata-only matches carry no text to put into the context
+                    if !result.metadata_only && filter.matches(&result.document_path, &result.metadata) && self.in_pass(&result, pass) {
+                        pass_candidates.push(result);
                     }
                 }
+                self.blend_document_similarity(&mut pass_candidates, pass);
+                candidates.extend(pass_candidates);
             }
+            self.weigh_partitions(&mut candidates);
+            self.select_diverse_sources(candidates, &mut all_sources, context_size);
         }
         
-        // Strategy 2: Fallback to local search if Pinecone failed or insufficient results
+        // Strategy 2: Fallback to local search if the remote store failed or insufficient results
         if all_sources.len() < context_size {
-            let local_results = self.rag_engine.search_relevant_chunks(question, question_vector, context_size)?;
-            
-            for (chunk_id, similarity, document_path, chunk_text) in local_results {
-                if !all_sources.iter().any(|s| s.document_path == document_path) {
-                    let chunk_num = all_sources.len() + 1;
-                    all_context.push_str(&format!("--- Chunk {} (Similarity: {:.3}) ---\n", chunk_num, similarity));
-                    all_context.push_str(&format!("Source: {}\n", document_path));
-                    all_context.push_str(&format!("Content: {}\n\n", chunk_text));
-                    
-                    all_sources.push(SearchResult {
-                        chunk_id,
-                        document_path,
-                        chunk_text,
-                        similarity,
-                    });
+            if self.has_remote_store() && all_sources.is_empty() {
+                health::record(health::LOCAL_SEARCH_FALLBACK);
+            }
+            let candidates = self.local_candidates(question, passes, fetch_size, &filter).await?;
+            self.select_diverse_sources(candidates, &mut all_sources, context_size);
+        }
+        
+        // Child chunks found the right place; their parents give the answer enough context
+        let mut all_sources = self.expand_to_parents(all_sources)?;
+        self.attach_line_ranges(&mut all_sources)?;
+        
+        // Each document's metadata is looked up once, however many of its chunks are in the context
+        let mut source_lines = HashMap::new();
+        for source in &all_sources {
+            source_lines.entry(source.document_path.clone()).or_insert_with(|| self.source_line(&source.document_path));
+        }
+        
+        let budget = self.context_budget(question);
+        let retrieved = all_sources.len();
+        let packed = crate::context::pack(all_sources, |source| {
+            if crate::context::contains_exact(&source.chunk_text, &exact_terms) { Tier::Required } else { Tier::Optional }
+        }, |source| crate::chunking::estimate_tokens(&Self::context_header(0, source, &source_lines[&source.document_path])), budget);
+        if packed.required > 0 {
+            eprintln!("📌 {} chunk(s) matching the question's exact terms placed first", packed.required);
+        }
+        let all_sources = packed.sources;
+        
+        let mut all_context = String::new();
+        for (i, source) in all_sources.iter().enumerate() {
+            all_context.push_str(&Self::context_header(i + 1, source, &source_lines[&source.document_path]));
+            all_context.push_str(&format!("{}\n\n", source.chunk_text));
+        }
+        
+        // Strategy 3: Semantic expansion for better coverage (if enabled), in whatever room is left
+        let mut included = all_sources.len();
+        let mut tokens = packed.tokens;
+        let expansion_slots = (context_size as f32 * self.config.rag.expansion_trigger) as usize;
+        if self.config.rag.enable_semantic_expansion && all_sources.len() < expansion_slots && packed.left_out == 0 && packed.trimmed == 0 {
+            let room = (budget > 0).then(|| budget - tokens);
+            let (expanded_context, added) = self.semantic_expansion(question, passes, context_size - all_sources.len(), &all_sources, room, &mut source_lines).await?;
+            all_context.push_str(&expanded_context);
+            included += added;
+            tokens += crate::chunking::estimate_tokens(&expanded_context);
+        }
+        
+        if budget > 0 {
+            let mut note = format!("📦 Context: {} chunk(s), ~{} of {} tokens", included, tokens, budget);
+            if packed.left_out > 0 {
+                note.push_str(&format!("; left out {} of the {} retrieved", packed.left_out, retrieved));
+            }
+            if packed.trimmed > 0 {
+                note.push_str(&format!("; trimmed {} to fit", packed.trimmed));
+            }
+            eprintln!("{}", note);
+        }
+        
+        Ok((all_context, all_sources))
+    }
+
+    /// Tokens the context may take (0 = no limit): at most `rag.max_context_tokens`, and what the
+    /// prompt, the question and the answer's token budget leave of `rag.context_window_tokens`
+    fn context_budget(&self, question: &str) -> usize {
+        let limit = self.config.rag.max_context_tokens;
+        let window = self.config.rag.context_window_tokens;
+        if window == 0 {
+            return limit;
+        }
+        let answer = self.llm_client.as_ref().map_or(0, |llm_client| llm_client.answer_token_budget());
+        let reserved = ANSWER_PROMPT_TOKENS + crate::chunking::estimate_tokens(question) + answer;
+        // A window too small for even the prompt still gets one token rather than no limit
+        let room = window.saturating_sub(reserved).max(1);
+        if limit == 0 { room } else { limit.min(room) }
+    }
+
+    /// The lines introducing a chunk in the context, up to where its text starts, given the
+    /// `source_line` of its document
+    fn context_header(number: usize, source: &SearchResult, source_line: &str) -> String {
+        format!("--- Chunk {} (id {}, Similarity: {:.3}) ---\n{}Content: ", number, source.chunk_id, source.similarity, source_line)
+    }
+
+    /// The "Source:" line of a chunk in the context: its document's path, with the title, place in
+    /// a page tree, author and date it is known by so answers can attribute what they draw on
+    fn source_line(&self, path: &str) -> String {
+        let metadata = self.db.get_document_metadata(self.project.as_deref(), path).unwrap_or_default();
+        let within = Some(metadata.hierarchy.join(" > ")).filter(|breadcrumb| !breadcrumb.is_empty()).map(|breadcrumb| format!("in {}", breadcrumb));
+        let details: Vec<String> = [metadata.title.map(|title| format!("\"{}\"", title)), within, metadata.author.map(|author| format!("by {}", author)), metadata.date]
+            .into_iter()
+            .flatten()
+            .collect();
+        if details.is_empty() {
+            format!("Source: {}\n", path)
+        } else {
+            format!("Source: {} ({})\n", path, details.join(", "))
+        }
+    }
+
+    /// Replace child chunks with the text of their parent chunk. Children of the same parent would
+    /// repeat its text, so only the best-ranked one is kept.
+    fn expand_to_parents(&self, sources: Vec<SearchResult>) -> Result<Vec<SearchResult>> {
+        let mut seen_parents = std::collections::HashSet::new();
+        let mut expanded = Vec::with_capacity(sources.len());
+        for mut source in sources {
+            if let Some(parent) = self.db.get_parent_chunk(source.chunk_id)? {
+                if !seen_parents.insert(parent.id) {
+                    continue;
                 }
+                source.chunk_text = parent.text;
+                source.lines = parent.line_range;
             }
+            expanded.push(source);
         }
-        
-        // Strategy 3: Semantic expansion for better coverage (if enabled)
-        if self.config.rag.enable_semantic_expansion && all_sources.len() < context_size / 2 {
-            let expanded_context = self.semantic_expansion(question, question_vector, context_size - all_sources.len()).await?;
-            all_context.push_str(&expanded_context);
+        Ok(expanded)
+    }
+
+    /// Chunks containing any of `terms` for every query pass, however dissimilar, best first
+    fn exact_candidates(&self, passes: &[QueryVector], terms: &[String], k: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+        let mut candidates = Vec::new();
+        for pass in passes {
+            let mut results: Vec<SearchResult> = self.rag_engine.search_exact_chunks(&pass.vector, terms, k, filter)?
+                .into_iter()
+                .filter(|result| self.in_pass(result, pass))
+                .collect();
+            self.blend_document_similarity(&mut results, pass);
+            candidates.extend(results);
+        }
+        self.weigh_partitions(&mut candidates);
+        candidates.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(candidates)
+    }
+
+    /// Local search for every query pass, merged by partition-weighted similarity
+    async fn local_candidates(&self, query: &str, passes: &[QueryVector], k: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+        let mut candidates = Vec::new();
+        for pass in passes {
+            let mut results: Vec<SearchResult> = self.local_search(query, &pass.vector, k, filter).await?
+                .into_iter()
+                .filter(|result| self.in_pass(result, pass))
+                .collect();
+            self.blend_document_similarity(&mut results, pass);
+            candidates.extend(results);
+        }
+        self.weigh_partitions(&mut candidates);
+        Ok(candidates)
+    }
+
+    /// Whether a document already contributes the maximum number of chunks allowed by `max_chunks_per_document`
+    fn document_at_capacity(&self, document_path: &str, selected: &[SearchResult]) -> bool {
+        let max_per_document = self.config.rag.max_chunks_per_document;
+        max_per_document > 0
+            && selected.iter().filter(|s| s.document_path == document_path).count() >= max_per_document
+    }
+
+    /// Add candidates (best first) to `selected` until it holds `limit` sources, skipping chunks
+    /// already selected and documents that have reached their per-document cap
+    fn select_diverse_sources(&self, candidates: Vec<SearchResult>, selected: &mut Vec<SearchResult>, limit: usize) {
+        let mut fingerprints: Vec<u64> = selected.iter().map(|s| crate::dedup::simhash(&s.chunk_text)).collect();
+        for candidate in candidates {
+            if selected.len() >= limit {
+                break;
+            }
+            if selected.iter().any(|s| s.chunk_id == candidate.chunk_id && s.document_path == candidate.document_path) {
+                continue;
+            }
+            if self.document_at_capacity(&candidate.document_path, selected) {
+                continue;
+            }
+            // The same text twice adds nothing to the context
+            let fingerprint = crate::dedup::simhash(&candidate.chunk_text);
+            if self.is_near_duplicate(fingerprint, &fingerprints) {
+                continue;
+            }
+            fingerprints.push(fingerprint);
+            selected.push(candidate);
         }
-        
-        Ok((all_context, all_sources))
+    }
+
+    fn is_near_duplicate(&self, fingerprint: u64, seen: &[u64]) -> bool {
+        self.config.search.suppress_near_duplicates
+            && crate::dedup::is_near_duplicate(fingerprint, seen, self.config.search.near_duplicate_threshold)
     }
 
     fn assess_context_quality(&self, context: &str, question: &str) -> ContextQuality {
@@ -305,7 +1856,7 @@ impl ChunkyMonkeyApp {
         }
     }
 
-    fn score_chunk_relevance(&self, chunk_content: &str, question: &str) -> f32 {
+    pub fn score_chunk_relevance(&self, chunk_content: &str, question: &str) -> f32 {
         let question_lower = question.to_lowercase();
         let content_lower = chunk_content.to_lowercase();
         
@@ -395,14 +1946,17 @@ impl ChunkyMonkeyApp {
                     if !llm_answer.is_empty() && !llm_answer.contains("I couldn't generate a response") {
                         return Ok(llm_answer);
                     }
+                    health::record_error("llm", "LLM returned no answer");
                 }
                 Err(e) => {
                     eprintln!("Warning: LLM generation failed: {}", e);
+                    health::record_error("llm", e);
                 }
             }
         }
         
         // Fallback to standard RAG if LLM is not available or fails
+        health::record(health::ANSWER_FALLBACK);
         self.generate_standard_rag_response(question, context, quality).await
     }
 
@@ -558,7 +2112,7 @@ impl ChunkyMonkeyApp {
         let top_chunks = relevant_chunks.iter().take(3).collect::<Vec<_>>();
         
         // Group by source file for better organization
-        let mut source_groups: std::collections::HashMap<String, Vec<&str>> = std::collections::HashMap::new();
+        let mut source_groups: std::collections::BTreeMap<String, Vec<&str>> = std::collections::BTreeMap::new();
         for (content, _, source, _) in &top_chunks {
             source_groups.entry(source.clone()).or_default().push(content);
         }
@@ -644,23 +2198,47 @@ impl ChunkyMonkeyApp {
         coverage > 0.5 // At least 50% of key words should be addressed
     }
 
-    async fn semantic_expansion(&self, question: &str, question_vector: &[f32], additional_chunks: usize) -> Result<String> {
+    /// Context for up to `additional_chunks` more chunks related to the question, within `room`
+    /// tokens when given, with the number of chunks it holds. `source_lines` holds the source line
+    /// of each document already looked up.
+    async fn semantic_expansion(&self, question: &str, passes: &[QueryVector], additional_chunks: usize, selected: &[SearchResult],
+                                mut room: Option<usize>, source_lines: &mut HashMap<String, String>) -> Result<(String, usize)> {
         // Try to find semantically related content
         let mut expanded_context = String::new();
+        let mut expanded = selected.to_vec();
+        let mut fingerprints: Vec<u64> = selected.iter().map(|s| crate::dedup::simhash(&s.chunk_text)).collect();
         
         // Use local search with lower threshold for expansion
-        if let Ok(results) = self.rag_engine.search_relevant_chunks(question, question_vector, additional_chunks * 2) {
-            for (_chunk_id, similarity, document_path, chunk_text) in results {
-                if similarity > 0.3 { // Lower threshold for expansion
-                    let chunk_num = expanded_context.matches("--- Chunk").count() + 1;
-                    expanded_context.push_str(&format!("--- Chunk {} (Similarity: {:.3}) ---\n", chunk_num, similarity));
-                    expanded_context.push_str(&format!("Source: {}\n", document_path));
-                    expanded_context.push_str(&format!("Content: {}\n\n", chunk_text));
+        let filter = self.retrieval_filter(&SearchFilter::default())?;
+        if let Ok(results) = self.local_candidates(question, passes, additional_chunks * self.config.rag.expansion_candidate_multiplier.max(1), &filter).await {
+            for result in results {
+                // Respect the per-document cap and don't repeat chunks (or their text) already in the context
+                if expanded.iter().any(|s| s.chunk_id == result.chunk_id) || self.document_at_capacity(&result.document_path, &expanded) {
+                    continue;
+                }
+                let fingerprint = crate::dedup::simhash(&result.chunk_text);
+                if self.is_near_duplicate(fingerprint, &fingerprints) {
+                    continue;
+                }
+                
+                if result.similarity > self.config.rag.expansion_min_similarity {
+                    let source_line = source_lines.entry(result.document_path.clone()).or_insert_with(|| self.source_line(&result.document_path));
+                    let block = format!("{}{}\n\n", Self::context_header(expanded.len() + 1, &result, source_line), result.chunk_text);
+                    if let Some(ref mut room) = room {
+                        let tokens = crate::chunking::estimate_tokens(&block);
+                        if tokens > *room {
+                            continue;
+                        }
+                        *room -= tokens;
+                    }
+                    expanded_context.push_str(&block);
+                    fingerprints.push(fingerprint);
+                    expanded.push(result);
                 }
             }
         }
         
-        Ok(expanded_context)
+        Ok((expanded_context, expanded.len() - selected.len()))
     }
 
     fn generate_simple_answer(&self, _question: &str, context: &str) -> Result<String> {
@@ -713,172 +2291,882 @@ impl ChunkyMonkeyApp {
         stats.fallback_strategies_enabled = self.config.rag.enable_fallback_strategies;
         
         // Get vector index statistics
-        stats.local_vector_count = self.rag_engine.len();
+        stats.local_vector_count = match self.lance_store {
+            Some(ref lance) => lance.count().await.unwrap_or(0),
+            None => self.rag_engine.len(),
+        };
         stats.pinecone_available = self.pinecone_client.is_some();
         
         // Get embedding model status
         stats.ollama_available = self.embedding_model.ollama_embeddings.is_some();
         stats.embedding_dimension = self.embedding_model.get_dimension();
         
+        // Degradation: what failed, which fallbacks were taken and how well caches are doing
+        self.flush_health()?;
+        for (name, count, last_message, last_at) in self.db.get_health_counters()? {
+            if let Some(service) = name.strip_prefix(health::ERROR_PREFIX) {
+                stats.service_errors.push(ServiceErrors {
+                    service: service.to_string(),
+                    count,
+                    last_error: last_message,
+                    last_error_at: last_at,
+                });
+                continue;
+            }
+            match name.as_str() {
+                health::EMBEDDING_FALLBACK => stats.embedding_fallbacks = count,
+                health::LOCAL_SEARCH_FALLBACK => stats.local_search_fallbacks = count,
+                health::ANSWER_FALLBACK => stats.answer_fallbacks = count,
+                health::QUERY_CACHE_HIT => stats.query_cache.hits = count,
+                health::QUERY_CACHE_MISS => stats.query_cache.misses = count,
+                health::SEARCH_CACHE_HIT => stats.search_cache.hits = count,
+                health::SEARCH_CACHE_MISS => stats.search_cache.misses = count,
+                health::RESULT_CACHE_HIT => stats.result_cache.hits = count,
+                health::RESULT_CACHE_MISS => stats.result_cache.misses = count,
+                _ => {}
+            }
+        }
+        stats.fallback_embedded_chunks = self.db.get_embedding_versions(self.project.as_deref())?
+            .into_iter()
+            .filter(|(version, _)| version.as_deref().is_some_and(|v| v.starts_with("simple/")))
+            .map(|(_, count)| count)
+            .sum();
+        
         Ok(stats)
     }
 
+    /// Persist health events recorded by this process (see `core::health`)
+    pub fn flush_health(&self) -> Result<()> {
+        self.db.record_health_events(&health::take_pending())
+    }
+
+    /// Collect database, pipeline, per-project and performance stats in one snapshot.
+    /// Performance figures come from a probe query, so taking a snapshot costs one embedding call.
+    pub async fn get_stats_snapshot(&self) -> Result<StatsSnapshot> {
+        let projects = self.db.get_projects()?.into_iter()
+            .map(|(project, document_count, chunk_count)| ProjectStats { project, document_count, chunk_count })
+            .collect();
+        
+        let start = std::time::Instant::now();
+        let (probe, embedding_version) = self.embedding_model
+            .embed_texts_versioned(&["stats probe".to_string()]).await?;
+        let embedding_ms = start.elapsed().as_secs_f64() * 1000.0;
+        
+        let local_search_ms = match (self.lance_store.is_none(), probe.first()) {
+            (true, Some(vector)) => {
+                let start = std::time::Instant::now();
+                self.rag_engine.search_relevant_chunks("stats probe", vector, 10, &SearchFilter::default())?;
+                Some(start.elapsed().as_secs_f64() * 1000.0)
+            }
+            _ => None,
+        };
+        
+        let (questions_last_24h, questions_total) = self.db.get_query_counts(24 * 60 * 60)?;
+        
+        Ok(StatsSnapshot {
+            generated_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            current_project: self.project.clone(),
+            database: self.db.get_stats()?,
+            rag_pipeline: self.get_rag_stats().await?,
+            projects,
+            embedding_versions: self.db.get_embedding_versions(self.project.as_deref())?.into_iter()
+                .map(|(version, chunk_count)| EmbeddingVersionStats { version, chunk_count })
+                .collect(),
+            perf: PerfStats {
+                embedding_ms,
+                embedding_version,
+                local_search_ms,
+                questions_last_24h,
+                questions_total,
+            },
+        })
+    }
+
     pub async fn clear_database(&mut self) -> Result<()> {
+        // Within a project, only that project's documents and Pinecone namespace are removed
+        if let Some(ref project) = self.project {
+            self.db.clear_project(Some(project))?;
+            self.rag_engine.clear();
+            if let Some(ref pinecone) = self.pinecone_client {
+                pinecone.delete_namespace().await?;
+            }
+            return Ok(());
+        }
+        
+        // Every project's documents go, so every project's Pinecone namespace goes with them
+        if let Some(ref pinecone) = self.pinecone_client {
+            pinecone.delete_all_namespaces().await?;
+        }
+        
         self.db.clear_all()?;
         self.rag_engine.clear();
+        if let Some(ref lance) = self.lance_store {
+            lance.clear().await?;
+        }
+        Ok(())
+    }
+
+    /// Remove chunks from the in-memory index and whichever vector stores are configured
+    async fn remove_chunks_from_stores(&mut self, chunks: &[(u32, Option<String>)]) -> Result<()> {
+        if chunks.is_empty() {
+            return Ok(());
+        }
+        
+        let chunk_ids: Vec<u32> = chunks.iter().map(|(id, _)| *id).collect();
+        for &chunk_id in &chunk_ids {
+            self.rag_engine.remove_chunk(chunk_id);
+        }
+        if let Some(ref lance) = self.lance_store {
+            lance.delete_chunks(&chunk_ids).await?;
+        }
+        
+        // Remote deletes are best effort: the local copy is already gone
+        if let Some(ref qdrant) = self.qdrant_client {
+            let ids = chunk_ids.iter().map(|&id| id as u64).collect();
+            if let Err(e) = qdrant.delete_points(ids).await {
+                eprintln!("Warning: Failed to delete vectors from Qdrant: {}", e);
+            }
+        } else if let Some(ref pinecone) = self.pinecone_client {
+            // Use the tracked vector id; chunks indexed before ids were tracked follow the "chunk_<id>" scheme
+            let ids = chunks.iter()
+                .map(|(id, vector_id)| vector_id.clone().unwrap_or_else(|| format!("chunk_{}", id)))
+                .collect();
+            if let Err(e) = pinecone.delete_vectors(ids).await {
+                eprintln!("Warning: Failed to delete vectors from Pinecone: {}", e);
+            }
+        }
         Ok(())
     }
 
+    /// Delete a document and all of its chunks everywhere they are stored
+    pub async fn delete_document(&mut self, document_id: u32) -> Result<()> {
+        let chunks = self.db.delete_document(document_id)?;
+        self.remove_chunks_from_stores(&chunks).await
+    }
+
+    /// Delete this project's documents at `target`: a document path, a directory (or bucket
+    /// prefix) whose documents are all removed, or a glob matched as `index --patterns` globs
+    /// are. Returns the removed documents.
+    pub async fn remove_documents(&mut self, target: &str) -> Result<Vec<Document>> {
+        let target = target.trim_start_matches("./");
+        let glob = target.contains(['*', '?', '[', '{']).then(|| PathGlob::new(target)).transpose()?;
+        let directory = format!("{}/", target.trim_end_matches('/'));
+        let documents: Vec<Document> = self.db.get_documents_by_project(self.project.as_deref())?
+            .into_iter()
+            .filter(|document| {
+                let path = document.file_path.trim_start_matches("./");
+                match &glob {
+                    Some(glob) => glob.matches(path),
+                    None => path == target || path.starts_with(&directory),
+                }
+            })
+            .collect();
+        
+        for document in &documents {
+            self.delete_document(document.id).await?;
+            self.document_vectors.remove(&document.file_path);
+        }
+        Ok(documents)
+    }
+
+    /// Compare the model versions recorded for this project's embeddings with the configured model
+    /// (each partition's own model for its documents).
+    /// Returns None when every embedding matches, or when the installed model version can't be resolved.
+    pub async fn check_embedding_drift(&self) -> Result<Option<EmbeddingDrift>> {
+        let stored_versions = self.db.get_embedding_versions(self.project.as_deref())?;
+        if stored_versions.is_empty() {
+            return Ok(None);
+        }
+        
+        let current_version = self.embedding_model.version().await;
+        if !crate::embeddings::is_resolved_version(&current_version) {
+            return Ok(None);
+        }
+        
+        let total_chunks = stored_versions.iter().map(|(_, count)| count).sum();
+        let stale_chunks = if self.partition_models.is_empty() {
+            stored_versions.iter()
+                .filter(|(version, _)| version.as_deref() != Some(current_version.as_str()))
+                .map(|(_, count)| count)
+                .sum()
+        } else {
+            let mut stale = 0;
+            for model in self.embedding_model_names() {
+                let version = self.model_for(model.as_deref()).version().await;
+                if crate::embeddings::is_resolved_version(&version) {
+                    stale += self.stale_chunks_for_model(model.as_deref(), &version)?.len() as u32;
+                }
+            }
+            stale
+        };
+        
+        if stale_chunks == 0 {
+            return Ok(None);
+        }
+        Ok(Some(EmbeddingDrift { current_version, stale_chunks, total_chunks, stored_versions }))
+    }
+
+    /// The default model (None) followed by each partition model
+    fn embedding_model_names(&self) -> Vec<Option<String>> {
+        std::iter::once(None).chain(self.partition_models.keys().cloned().map(Some)).collect()
+    }
+
+    /// Chunks of this project that should be embedded with `model` but weren't embedded with its current `version`
+    fn stale_chunks_for_model(&self, model: Option<&str>, version: &str) -> Result<Vec<(Chunk, String, Option<String>)>> {
+        Ok(self.db.get_stale_chunks(self.project.as_deref(), version)?
+            .into_iter()
+            .filter(|(_, path, _)| self.config.embedding_model_for(path) == model)
+            .collect())
+    }
+
+    /// Re-embed the chunks of this project whose embeddings came from another model version,
+    /// updating the database, the local index and the remote vector store. Returns the number re-embedded.
+    pub async fn reembed_stale_chunks(&mut self) -> Result<usize> {
+        let mut reembedded = 0;
+        for model in self.embedding_model_names() {
+            reembedded += self.reembed_stale_chunks_for_model(model.as_deref()).await?;
+        }
+        
+        if reembedded > 0 {
+            self.reload_local_index()?;
+        }
+        Ok(reembedded)
+    }
+
+    async fn reembed_stale_chunks_for_model(&mut self, model: Option<&str>) -> Result<usize> {
+        let version = self.model_for(model).version().await;
+        if !crate::embeddings::is_resolved_version(&version) {
+            anyhow::bail!("Could not resolve the installed version of {}; is Ollama running?", model.unwrap_or(&self.config.ollama.model));
+        }
+        
+        let stale = self.stale_chunks_for_model(model, &version)?;
+        let mut reembedded = 0;
+        
+        for batch in stale.chunks(crate::pinecone::UPSERT_BATCH_SIZE) {
+            let texts = batch.iter()
+                .map(|(chunk, path, _)| self.chunk_embedding_text(chunk, path))
+                .collect::<Result<Vec<String>>>()?;
+            let (vectors, batch_version) = self.model_for(model).embed_texts_versioned(&texts).await?;
+            if batch_version != version {
+                anyhow::bail!("Embedding model became unavailable after re-embedding {} chunks", reembedded);
+            }
+            
+            let updated: Vec<(u32, Vec<f32>)> = batch.iter().map(|(chunk, _, _)| chunk.id).zip(vectors.iter().cloned()).collect();
+            self.db.update_embeddings(&updated, &version)?;
+            
+            if let Some(ref lance) = self.lance_store {
+                let ids: Vec<u32> = batch.iter().map(|(chunk, _, _)| chunk.id).collect();
+                let rows: Vec<(u32, String, String, Vec<f32>)> = batch.iter().zip(vectors.iter())
+                    .map(|((chunk, path, _), vector)| (chunk.id, path.clone(), chunk.text.clone(), vector.clone()))
+                    .collect();
+                lance.delete_chunks(&ids).await?;
+                lance.add_chunks(&rows).await?;
+            }
+            
+            let chunk_metadata = batch.iter()
+                .map(|(chunk, path, _)| self.chunk_metadata(chunk.id, path, &chunk.text))
+                .collect::<Result<Vec<_>>>()?;
+            
+            if let Some(ref qdrant) = self.qdrant_client {
+                let points = batch.iter().zip(vectors.iter()).zip(chunk_metadata.iter())
+                    .map(|(((chunk, path, _), vector), metadata)| crate::qdrant::Point {
+                        id: chunk.id as u64,
+                        vector: vector.clone(),
+                        payload: crate::pinecone::chunk_metadata(path, &chunk.text, chunk.id, chunk.document_id, metadata),
+                    })
+                    .collect();
+                qdrant.upsert_points(points).await?;
+            } else if let Some(ref pinecone) = self.pinecone_client {
+                let vectors = batch.iter().zip(vectors.iter()).zip(chunk_metadata.iter())
+                    .map(|(((chunk, path, vector_id), vector), metadata)| (chunk.id, crate::pinecone::Vector {
+                        id: vector_id.clone().unwrap_or_else(|| format!("chunk_{}", chunk.id)),
+                        values: vector.clone(),
+                        metadata: crate::pinecone::chunk_metadata(path, &chunk.text, chunk.id, chunk.document_id, metadata),
+                    }))
+                    .collect();
+                let uploaded = upsert_pinecone_batch(pinecone, vectors).await;
+                self.db.set_chunk_vector_ids(&uploaded)?;
+            }
+            
+            reembedded += batch.len();
+        }
+        
+        // Document titles follow their chunks onto the new model version
+        let titles: Vec<(u32, String, String)> = self.db.get_stale_document_titles(self.project.as_deref(), &version)?
+            .into_iter()
+            .filter(|(_, path, _)| self.config.embedding_model_for(path) == model)
+            .collect();
+        for batch in titles.chunks(crate::pinecone::UPSERT_BATCH_SIZE) {
+            let texts: Vec<String> = batch.iter().map(|(_, _, title)| title.clone()).collect();
+            let (vectors, batch_version) = self.model_for(model).embed_texts_versioned(&texts).await?;
+            if batch_version != version {
+                anyhow::bail!("Embedding model became unavailable after re-embedding {} chunks", reembedded);
+            }
+            let updated: Vec<(u32, String, Vec<f32>)> = batch.iter().zip(vectors)
+                .map(|((document_id, _, title), vector)| (*document_id, title.clone(), vector))
+                .collect();
+            self.db.set_document_embeddings(&updated, &version)?;
+        }
+        
+        Ok(reembedded)
+    }
+
+    pub async fn delete_chunk(&mut self, chunk_id: u32) -> Result<()> {
+        let vector_id = self.db.delete_chunk(chunk_id)?;
+        self.remove_chunks_from_stores(&[(chunk_id, vector_id)]).await
+    }
+
+    /// Drop a document's chunks and index the file again from disk, returning the new document id
+    pub async fn rechunk_document(&mut self, document_id: u32) -> Result<u32> {
+        let document = self.db.get_document(document_id)?
+            .ok_or_else(|| anyhow::anyhow!("Document {} not found", document_id))?;
+        let path = std::path::PathBuf::from(&document.file_path);
+        if !path.exists() {
+            anyhow::bail!("{} no longer exists on disk", document.file_path);
+        }
+        
+        self.delete_document(document_id).await?;
+        self.add_document(&path).await
+    }
+
     pub async fn add_document(&mut self, file_path: &Path) -> Result<u32> {
-        let content = std::fs::read_to_string(file_path)?;
+        self.add_document_as(file_path, file_path.to_str().unwrap()).await
+    }
+
+    /// Index the file at `file_path` as the document at `path_str`, for files fetched from
+    /// elsewhere: a bucket object downloaded to a temporary file is indexed under its URL
+    pub async fn add_document_as(&mut self, file_path: &Path, path_str: &str) -> Result<u32> {
+        let (token, interrupt) = (self.cancel.clone(), self.db.get_connection().get_interrupt_handle());
+        cancel::run(&token, Some(interrupt), async {
+            if let Some(command) = self.config.hooks.converter_for(file_path) {
+                let command = command.to_vec();
+                return self.add_converted(file_path, path_str, &command).await;
+            }
+            if crate::epub::is_epub(file_path) {
+                return self.add_book(file_path, path_str).await;
+            }
+            if self.config.ocr.enabled && crate::ocr::is_supported(file_path) {
+                return self.add_scanned(file_path, path_str).await;
+            }
+            let size = std::fs::metadata(file_path)?.len() as usize;
+            if size > STREAM_WINDOW_SIZE {
+                return self.add_document_streamed(file_path, path_str, size).await;
+            }
+            
+            let bytes = std::fs::read(file_path)?;
+            let (content, encoding) = crate::chunking::encoding::decode(&bytes)?;
+            let source = match encoding {
+                Some(encoding) => TextSource::Transcoded(&bytes, encoding),
+                None => TextSource::File,
+            };
+            let chunking = self.config.chunking_for(path_str);
+            
+            // Markdown front matter gives the title, tags, date and author
+            let front_matter = Some(path_str).filter(|path| crate::chunking::is_markdown(path))
+                .and_then(|_| crate::front_matter::parse(&content))
+                .unwrap_or_default();
+            let title = match front_matter.title {
+                Some(ref title) => format!("{}: {}", file_path.file_name().unwrap_or_default().to_string_lossy(), title),
+                None => crate::chunking::document_title(path_str, &content),
+            };
+            let metadata = DocumentMetadata { encoding: encoding.map(|encoding| encoding.name().to_string()), ..front_matter.into_metadata() };
+            self.add_text(path_str, &content, source, &chunking, title, &metadata).await
+        }).await
+    }
+
+    /// Index a fetched web page under its URL. Its text is extracted with markdown headings, so
+    /// it is chunked by section unless `chunking.strategies` says otherwise.
+    pub async fn add_web_page(&mut self, url: &str, title: Option<&str>, text: &str) -> Result<u32> {
+        let mut chunking = self.config.chunking_for(url);
+        chunking.strategies.entry("*".to_string()).or_insert(crate::core::config::ChunkStrategy::Markdown);
+        let title = match title {
+            Some(title) => format!("{}: {}", url, title),
+            None => crate::chunking::document_title(url, text),
+        };
+        self.add_text(url, text, TextSource::Derived, &chunking, title, &DocumentMetadata::default()).await
+    }
+
+    /// Index a page of a Notion or Confluence export under its path, titled with its place in the
+    /// export's page tree
+    pub async fn add_imported_page(&mut self, page: &crate::import::ImportedPage) -> Result<u32> {
+        let mut chunking = self.config.chunking_for(&page.path);
+        if page.from_html {
+            chunking.strategies.entry("*".to_string()).or_insert(crate::core::config::ChunkStrategy::Markdown);
+        }
+        let metadata = DocumentMetadata { title: Some(page.title.clone()), hierarchy: page.hierarchy.clone(), ..Default::default() };
+        self.add_text(&page.path, &page.text, TextSource::Derived, &chunking, page.breadcrumb(), &metadata).await
+    }
+
+    /// Index an EPUB book as the text of its chapters, each chapter chunked on its own so no
+    /// chunk spans two, with the chapter's title leading each chunk's section
+    async fn add_book(&mut self, file_path: &Path, path_str: &str) -> Result<u32> {
+        let book = crate::epub::read(file_path)?;
+        let content = book.text();
         let file_hash = self.calculate_file_hash(&content);
+        let Some(file_hash) = self.replace_if_changed(path_str, &file_hash).await? else {
+            return Ok(0);
+        };
         
-        // Check if already indexed
-        if let Some(existing_hash) = self.db.get_document_hash(file_path.to_str().unwrap())? {
-            if existing_hash == file_hash {
-                return Ok(0); // Return 0 to indicate already exists
+        // Chapter text has markdown headings, as for web pages
+        let mut chunking = self.config.chunking_for(path_str);
+        chunking.strategies.entry("*".to_string()).or_insert(crate::core::config::ChunkStrategy::Markdown);
+        let mut remaining = match chunking.max_chunks_per_document {
+            0 => usize::MAX,
+            cap => cap,
+        };
+        let mut chunks = Vec::new();
+        let mut window = crate::chunking::stream::TextWindow { text: String::new(), byte_offset: 0, line_offset: 0 };
+        for chapter in &book.chapters {
+            if remaining == 0 {
+                eprintln!("Warning: {} was cut off at {} chunks (chunking.max_chunks_per_document), before chapter '{}'",
+                    path_str, chunking.max_chunks_per_document, chapter.title);
+                self.truncated_documents += 1;
+                break;
+            }
+            let mut chapter_chunks = crate::chunking::chunk_text(path_str, &chapter.text, &chunking, remaining);
+            remaining -= chapter_chunks.len();
+            for chunk in &mut chapter_chunks {
+                chunk.section = Some(chapter_section(&chapter.title, chunk.section.take()));
             }
+            window.place(&mut chapter_chunks, chunks.len());
+            chunks.extend(chapter_chunks);
+            // Chapters are joined with a blank line (see `Book::text`)
+            window.byte_offset += chapter.text.len() + 2;
+            window.line_offset += chapter.text.lines().count() + 1;
         }
         
-        // Check file size limits
-        const MAX_CONTENT_SIZE: usize = 5 * 1024 * 1024; // 5MB
-        const MAX_CHUNKS: usize = 50;
+        let title = match book.title {
+            Some(ref title) => format!("{}: {}", path_str, title),
+            None => crate::chunking::document_title(path_str, &content),
+        };
+        self.store_text(path_str, &content, TextSource::Derived, &file_hash, chunks, &chunking, title, &DocumentMetadata::default()).await
+    }
+
+    /// Index an image by the text OCR recognizes in it, or a PDF by its text layer and what OCR
+    /// recognizes on pages without one, flagged `ocr` in its metadata when OCR read any of it.
+    /// The file's bytes are hashed so unchanged files are not read again.
+    async fn add_scanned(&mut self, file_path: &Path, path_str: &str) -> Result<u32> {
+        let file_hash = hash_file(file_path)?;
+        let Some(file_hash) = self.replace_if_changed(path_str, &file_hash).await? else {
+            return Ok(0);
+        };
         
-        if content.len() > MAX_CONTENT_SIZE {
-            // Silently truncate without verbose logging
+        let (path, config) = (file_path.to_path_buf(), self.config.ocr.clone());
+        let extracted = tokio::task::spawn_blocking(move || crate::ocr::extract_text(&path, &config)).await??;
+        let text = extracted.text;
+        if text.trim().is_empty() {
+            anyhow::bail!("No text found in {}", path_str);
         }
+        let chunking = self.config.chunking_for(path_str);
+        let chunks = self.chunk_text(path_str, &text, &chunking)?;
+        let title = crate::chunking::document_title(path_str, &text);
+        let metadata = DocumentMetadata { ocr: extracted.ocr, ..Default::default() };
+        self.store_text(path_str, &text, TextSource::Derived, &file_hash, chunks, &chunking, title, &metadata).await
+    }
+
+    /// Index a file by the text its configured converter (`hooks.converters`) writes. The file's
+    /// bytes are hashed so the converter is not run again on unchanged files.
+    async fn add_converted(&mut self, file_path: &Path, path_str: &str, command: &[String]) -> Result<u32> {
+        let file_hash = hash_file(file_path)?;
+        let Some(file_hash) = self.replace_if_changed(path_str, &file_hash).await? else {
+            return Ok(0);
+        };
         
-        // Chunk the text
-        let chunks = self.chunk_text(&content, MAX_CHUNKS)?;
-        
-        // Generate embeddings for each chunk
-        let chunk_texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
-        let embeddings = self.embedding_model.embed_texts(&chunk_texts).await?;
+        let output = crate::hooks::convert(command, file_path, Duration::from_secs(self.config.hooks.timeout_secs)).await?;
+        let (text, encoding) = crate::chunking::encoding::decode(&output)?;
+        if text.trim().is_empty() {
+            anyhow::bail!("The converter for {} wrote no text", path_str);
+        }
+        let chunking = self.config.chunking_for(path_str);
+        let chunks = self.chunk_text(path_str, &text, &chunking)?;
+        let title = crate::chunking::document_title(path_str, &text);
+        let metadata = DocumentMetadata { encoding: encoding.map(|encoding| encoding.name().to_string()), ..Default::default() };
+        self.store_text(path_str, &text, TextSource::Derived, &file_hash, chunks, &chunking, title, &metadata).await
+    }
+
+    /// Chunk, embed and store `content` as the document at `path`, unless it is already indexed
+    /// with the same content. Returns 0 when nothing changed.
+    async fn add_text(&mut self, path_str: &str, content: &str, source: TextSource<'_>, chunking: &ChunkingConfig, title: String, metadata: &DocumentMetadata) -> Result<u32> {
+        let file_hash = self.calculate_file_hash(content);
+        let Some(file_hash) = self.replace_if_changed(path_str, &file_hash).await? else {
+            return Ok(0); // Return 0 to indicate already exists
+        };
         
-        // Store in database
+        // Chunk the text, with the settings and embedding model of the document's partition
+        let chunks = self.chunk_text(path_str, content, chunking)?;
+        self.store_text(path_str, content, source, &file_hash, chunks, chunking, title, metadata).await
+    }
+
+    /// Embed and store the chunks of `content` as the document at `path`, with `metadata` in place
+    /// before its vectors are indexed so they carry its tags. Byte ranges are stored as `source`
+    /// places them.
+    #[allow(clippy::too_many_arguments)]
+    async fn store_text(&mut self, path_str: &str, content: &str, source: TextSource<'_>, file_hash: &str, chunks: Vec<Chunk>, chunking: &ChunkingConfig, title: String, metadata: &DocumentMetadata) -> Result<u32> {
+        let (mut parents, mut chunks) = self.embeddable_chunks(path_str, content, chunks, chunking);
+        source.place(&mut parents);
+        source.place(&mut chunks);
+        let metadata = &self.with_index_tags(metadata);
+        
+        // Generate embeddings for each chunk, and for the document's title in the same batch
+        let summary = if chunking.contextual_headers {
+            self.summarize_document(path_str, content).await
+        } else {
+            None
+        };
+        let (mut embeddings, model_version) = self.embed_chunks(path_str, &chunks, chunking, summary.as_deref(), Some(&title)).await?;
+        let title_embedding = embeddings.pop().unwrap_or_default();
+        
+        // Store in database. The hash is only recorded once the vector stores have the chunks too,
+        // so a run interrupted in between is redone rather than skipped as unchanged.
+        let record = DocumentRecord {
+            title: Some((&title, &title_embedding)),
+            summary: summary.as_deref(),
+            metadata: (*metadata != DocumentMetadata::default()).then_some(metadata),
+        };
         let (document_id, chunk_ids) = self.db.add_document_with_chunks(
-            file_path.to_str().unwrap(),
-            &file_hash,
+            path_str,
+            "",
             content.len(),
+            self.project.as_deref(),
+            &record,
+            &parents,
             &chunks,
             &embeddings,
+            &model_version,
         )?;
+        self.document_vectors.insert(path_str.to_string(), title_embedding);
         
-        // Add to vector index using actual chunk IDs from database
-        for (i, (chunk, embedding)) in chunks.iter().zip(embeddings.iter()).enumerate() {
-            let chunk_id = chunk_ids[i]; // Use actual chunk ID from database
+        self.index_chunk_vectors(path_str, document_id, &chunks, &chunk_ids, &embeddings).await?;
+        self.db.set_document_hash(document_id, file_hash, content.len())?;
+        Ok(document_id)
+    }
+
+    /// Index a file too large to read whole one window of `STREAM_WINDOW_SIZE` at a time: each
+    /// window ends at a line break and is chunked, embedded and stored before the next is read,
+    /// so memory use stays bounded. Chunks never span two windows.
+    async fn add_document_streamed(&mut self, file_path: &Path, path_str: &str, size: usize) -> Result<u32> {
+        let file_hash = hash_file(file_path)?;
+        let Some(file_hash) = self.replace_if_changed(path_str, &file_hash).await? else {
+            return Ok(0);
+        };
+        
+        let chunking = self.config.chunking_for(path_str);
+        let mut remaining = match chunking.max_chunks_per_document {
+            0 => usize::MAX,
+            cap => cap,
+        };
+        let (mut windows, encoding) = crate::chunking::stream::TextWindows::open(file_path, STREAM_WINDOW_SIZE)?;
+        let metadata = self.with_index_tags(&DocumentMetadata { encoding: encoding.map(str::to_string), ..Default::default() });
+        let mut document_id = None;
+        let mut summary = None;
+        let (mut chunk_count, mut parent_count) = (0, 0);
+        let mut carried = crate::chunking::stream::Carried::default();
+        
+        while let Some(window) = windows.next_window()? {
+            if remaining == 0 {
+                eprintln!("Warning: {} was cut off at {} chunks (chunking.max_chunks_per_document); {:.0}% of it is indexed",
+                    path_str, chunking.max_chunks_per_document, window.byte_offset as f64 * 100.0 / size as f64);
+                self.truncated_documents += 1;
+                break;
+            }
+            let primary = window.chunk(path_str, &chunking, remaining, &mut carried);
+            remaining -= primary.len();
+            let (mut parents, mut chunks) = self.embeddable_chunks(path_str, &window.text, primary, &chunking);
+            window.place(&mut parents, parent_count);
+            window.place(&mut chunks, chunk_count);
+            // Windows are read after transcoding, so their offsets are not the file's
+            if encoding.is_some() {
+                TextSource::Derived.place(&mut parents);
+                TextSource::Derived.place(&mut chunks);
+            }
+            parent_count += parents.len();
+            chunk_count += chunks.len();
             
-            // Add to local RAG engine
-            self.rag_engine.add_chunk(
-                chunk_id,
-                embedding,
-                file_path.to_str().unwrap(),
-                &chunk.text,
-            )?;
-            
-            // Add to Pinecone if available
-            if let Some(ref pinecone) = self.pinecone_client {
-                let vector_id = format!("chunk_{}", chunk_id);
-                let metadata = serde_json::json!({
-                    "source": file_path.to_str().unwrap(),
-                    "text": chunk.text,
-                    "chunk_id": chunk_id,
-                    "document_id": document_id
-                });
-                
-                let pinecone_vector = crate::pinecone::Vector {
-                    id: vector_id,
-                    values: embedding.clone(),
-                    metadata: std::collections::HashMap::from_iter(
-                        metadata.as_object().unwrap().iter().map(|(k, v)| (k.clone(), v.clone()))
-                    ),
-                };
-                
-                // Silently handle Pinecone errors to avoid verbose logging
-                if let Err(_) = pinecone.upsert_vectors(vec![pinecone_vector]).await {
-                    // Error is logged at debug level only
+            // The title and summary come from the start of the file
+            let title = match document_id {
+                None => Some(crate::chunking::document_title(path_str, &window.text)),
+                Some(_) => None,
+            };
+            if document_id.is_none() && chunking.contextual_headers {
+                summary = self.summarize_document(path_str, &window.text).await;
+            }
+            let (mut embeddings, model_version) = self.embed_chunks(path_str, &chunks, &chunking, summary.as_deref(), title.as_deref()).await?;
+            
+            // The hash is only recorded once the whole file is stored, so an interrupted run is redone
+            let title_embedding = title.is_some().then(|| embeddings.pop().unwrap_or_default());
+            let (id, chunk_ids) = match document_id {
+                Some(id) => (id, self.db.append_chunks(id, &parents, &chunks, &embeddings, &model_version)?),
+                None => {
+                    let record = DocumentRecord {
+                        title: title.as_deref().zip(title_embedding.as_deref()),
+                        summary: summary.as_deref(),
+                        metadata: (metadata != DocumentMetadata::default()).then_some(&metadata),
+                    };
+                    self.db.add_document_with_chunks(path_str, "", size, self.project.as_deref(), &record, &parents, &chunks, &embeddings, &model_version)?
                 }
+            };
+            if let Some(title_embedding) = title_embedding {
+                self.document_vectors.insert(path_str.to_string(), title_embedding);
             }
+            document_id = Some(id);
+            
+            self.index_chunk_vectors(path_str, id, &chunks, &chunk_ids, &embeddings).await?;
         }
         
+        let Some(document_id) = document_id else {
+            anyhow::bail!("Nothing to index in {}", path_str);
+        };
+        self.db.set_document_hash(document_id, &file_hash, size)?;
         Ok(document_id)
     }
 
-    fn chunk_text(&self, text: &str, max_chunks: usize) -> Result<Vec<Chunk>> {
-        if text.len() > 5 * 1024 * 1024 { // 5MB
-            // Silently truncate without verbose logging
-            let truncated = &text[..5 * 1024 * 1024];
-            return self.chunk_text_internal(truncated, max_chunks);
+    /// Store a document replicated from another index in this project, replacing the document at
+    /// its path, and add its chunks to the vector stores
+    pub async fn import_replica(&mut self, document: &ReplicaDocument) -> Result<()> {
+        if let Some(existing) = self.db.get_document_id_by_path(self.project.as_deref(), &document.file_path)? {
+            self.delete_document(existing).await?;
         }
-        self.chunk_text_internal(text, max_chunks)
-    }
-
-    fn chunk_text_internal(&self, text: &str, max_chunks: usize) -> Result<Vec<Chunk>> {
-        let chunk_size = 1000;
-        let overlap = 200;
+        let (document_id, chunk_ids) = self.db.import_document(document, self.project.as_deref())?;
         
         let mut chunks = Vec::new();
-        let mut start_char = 0;
-        let mut chunk_index = 0;
+        let mut embedded_ids = Vec::new();
+        let mut embeddings = Vec::new();
+        for (replica, chunk_id) in document.chunks.iter().zip(chunk_ids) {
+            if let Some(ref embedding) = replica.embedding {
+                chunks.push(replica.chunk.clone());
+                embedded_ids.push(chunk_id);
+                embeddings.push(embedding.clone());
+            }
+        }
+        if let Some((_, ref vector)) = document.title {
+            self.document_vectors.insert(document.file_path.clone(), vector.clone());
+        }
+        self.index_chunk_vectors(&document.file_path, document_id, &chunks, &embedded_ids, &embeddings).await
+    }
+
+    /// Remove the document at `path`, which the index it is replicated from no longer has.
+    /// Returns whether there was one.
+    pub async fn remove_replica(&mut self, path: &str) -> Result<bool> {
+        let Some(document_id) = self.db.get_document_id_by_path(self.project.as_deref(), path)? else {
+            return Ok(false);
+        };
+        self.delete_document(document_id).await?;
+        self.document_vectors.remove(path);
+        Ok(true)
+    }
+
+    /// The hash to record for the file at `path` if it needs indexing, or `None` when it is indexed
+    /// with this hash already (and only gets the session's index tags). A changed file's old chunks
+    /// are dropped, locally and remotely.
+    async fn replace_if_changed(&mut self, path: &str, file_hash: &str) -> Result<Option<String>> {
+        let file_hash = self.recorded_hash(path, file_hash);
+        self.retained_tags.clear();
+        if let Some(existing_hash) = self.db.get_document_hash(self.project.as_deref(), path)? {
+            if existing_hash == file_hash {
+                let tags = self.index_tags.clone();
+                crate::docs::assign_tags(self, path, &tags).await?;
+                return Ok(None);
+            }
+            
+            // Content changed: drop the old chunks (locally and remotely) before re-indexing
+            self.retained_tags = self.db.get_document_metadata(self.project.as_deref(), path)?.assigned_tags;
+            if let Some(document_id) = self.db.get_document_id_by_path(self.project.as_deref(), path)? {
+                self.delete_document(document_id).await?;
+            }
+        }
+        Ok(Some(file_hash))
+    }
+
+    /// The hash recorded for the file at `path` with content hash `file_hash`. A strategy configured
+    /// for the file is part of it, so changing the strategy re-chunks the file.
+    fn recorded_hash(&self, path: &str, file_hash: &str) -> String {
+        match crate::chunking::configured_strategy(path, &self.config.chunking_for(path)) {
+            Some(strategy) => format!("{}+{}", file_hash, strategy.name()),
+            None => file_hash.to_string(),
+        }
+    }
+
+    /// Split chunks into children when `child_chunk_size` is set and leave out low-quality ones,
+    /// returning the unembedded parents (if any) and the chunks to embed with their natural language
+    fn embeddable_chunks(&mut self, path: &str, text: &str, chunks: Vec<Chunk>, chunking: &ChunkingConfig) -> (Vec<Chunk>, Vec<Chunk>) {
+        let (parents, mut chunks, skipped) = self.select_embeddable_chunks(path, text, chunks, chunking);
+        self.skipped_chunks += skipped;
+        for chunk in &mut chunks {
+            chunk.language = crate::stemming::detect_language_code(&chunk.text).map(|code| code.to_string());
+        }
+        (parents, chunks)
+    }
+
+    /// `embeddable_chunks`, also returning the number of low-quality chunks left out
+    fn select_embeddable_chunks(&self, path: &str, text: &str, mut chunks: Vec<Chunk>, chunking: &ChunkingConfig) -> (Vec<Chunk>, Vec<Chunk>, usize) {
+        // With child chunks, the chunks become unembedded parents and their children are indexed
+        let mut parents = Vec::new();
+        if chunking.child_chunk_size > 0 {
+            let children = crate::chunking::child_chunks(text, &chunks, crate::chunking::strategy_for(path, chunking), chunking);
+            parents = std::mem::replace(&mut chunks, children);
+        }
         
-        // Convert to character indices for proper UTF-8 handling
-        let chars: Vec<char> = text.chars().collect();
-        let text_len = chars.len();
+        // Chunks below `search.min_chunk_chars`/`min_chunk_alphanumeric` are left out
+        let (min_chars, min_alphanumeric) = (self.config.search.min_chunk_chars, self.config.search.min_chunk_alphanumeric);
+        let before = chunks.len();
+        if min_chars > 0 || min_alphanumeric > 0 {
+            chunks.retain(|chunk| !crate::chunking::is_low_quality(&chunk.text, min_chars, min_alphanumeric));
+        }
+        let skipped = before - chunks.len();
+        (parents, chunks, skipped)
+    }
+
+    /// What `add_document` would do with the file at `file_path`, worked out without embedding
+    /// or storing anything. Files that are streamed have their chunks estimated from the first
+    /// window.
+    pub fn plan_document(&self, file_path: &Path) -> Result<PlannedDocument> {
+        let path_str = file_path.to_string_lossy();
+        let mut chunking = self.config.chunking_for(&path_str);
+        let size = std::fs::metadata(file_path)?.len() as usize;
+        // Converters are not run to plan, so their output's chunks are not estimated
+        let (file_hash, chunks) = if self.config.hooks.converter_for(file_path).is_some() {
+            (hash_file(file_path)?, None)
+        } else if crate::epub::is_epub(file_path) {
+            let text = crate::epub::read(file_path)?.text();
+            chunking.strategies.entry("*".to_string()).or_insert(crate::core::config::ChunkStrategy::Markdown);
+            (self.calculate_file_hash(&text), Some(self.count_embeddable_chunks(&path_str, &text, &chunking)))
+        } else if self.config.ocr.enabled && crate::ocr::is_supported(file_path) {
+            (hash_file(file_path)?, None)
+        } else if size > STREAM_WINDOW_SIZE {
+            let (mut windows, _) = crate::chunking::stream::TextWindows::open(file_path, STREAM_WINDOW_SIZE)?;
+            let first = windows.next_window()?.map(|window| window.text).unwrap_or_default();
+            let chunks = self.count_embeddable_chunks(&path_str, &first, &chunking) * size / first.len().max(1);
+            let cap = match chunking.max_chunks_per_document {
+                0 => usize::MAX,
+                cap => cap,
+            };
+            (hash_file(file_path)?, Some(chunks.min(cap)))
+        } else {
+            let (content, _) = crate::chunking::encoding::decode(&std::fs::read(file_path)?)?;
+            (self.calculate_file_hash(&content), Some(self.count_embeddable_chunks(&path_str, &content, &chunking)))
+        };
         
-        // Handle empty text
-        if text_len == 0 {
-            return Ok(chunks);
+        let file_hash = self.recorded_hash(&path_str, &file_hash);
+        Ok(match self.db.get_document_hash(self.project.as_deref(), &path_str)? {
+            Some(indexed_hash) if indexed_hash == file_hash => PlannedDocument::Unchanged,
+            indexed_hash => PlannedDocument::Index { chunks, changed: indexed_hash.is_some() },
+        })
+    }
+
+    /// Number of chunks indexing `text` as the document at `path` would embed
+    fn count_embeddable_chunks(&self, path: &str, text: &str, chunking: &ChunkingConfig) -> usize {
+        let max_chunks = match chunking.max_chunks_per_document {
+            0 => usize::MAX,
+            cap => cap,
+        };
+        let mut chunks = crate::chunking::chunk_text(path, text, chunking, max_chunks);
+        chunks.extend(crate::chunking::schema_chunk(path, text, crate::chunking::strategy_for(path, chunking), chunks.len()));
+        self.select_embeddable_chunks(path, text, chunks, chunking).1.len()
+    }
+
+    /// Embed chunks with the model of the file's partition, and the document title after them
+    /// when one is given
+    async fn embed_chunks(&self, path: &str, chunks: &[Chunk], chunking: &ChunkingConfig, summary: Option<&str>, title: Option<&str>) -> Result<(Vec<Vec<f32>>, String)> {
+        let mut texts: Vec<String> = chunks.iter()
+            .map(|c| if chunking.contextual_headers { c.contextual_embedding_text(path, summary) } else { c.embedding_text() })
+            .collect();
+        texts.extend(title.map(str::to_string));
+        self.model_for(self.config.embedding_model_for(path))
+            .embed_texts_versioned(&texts).await
+    }
+
+    /// Add stored chunks to the in-memory index or LanceDB and to the remote vector store
+    async fn index_chunk_vectors(&mut self, path: &str, document_id: u32, chunks: &[Chunk], chunk_ids: &[u32], embeddings: &[Vec<f32>]) -> Result<()> {
+        // Add to LanceDB in one batch when it replaces the in-memory index
+        if let Some(ref lance) = self.lance_store {
+            let rows: Vec<(u32, String, String, Vec<f32>)> = chunks.iter().zip(embeddings.iter()).enumerate()
+                .map(|(i, (chunk, embedding))| (chunk_ids[i], path.to_string(), chunk.text.clone(), embedding.clone()))
+                .collect();
+            lance.add_chunks(&rows).await?;
         }
         
-        while start_char < text_len && chunks.len() < max_chunks {
-            let end_char = (start_char + chunk_size).min(text_len);
-            
-            // Find word boundary for end
-            let mut actual_end_char = end_char;
-            if actual_end_char < text_len && actual_end_char > start_char {
-                // Look for the last space or newline within the last 100 characters
-                let search_start = if end_char > 100 { end_char - 100 } else { start_char };
-                let search_range = &chars[search_start..end_char];
-                
-                // Find last space
-                if let Some(last_space_idx) = search_range.iter().rposition(|&c| c == ' ') {
-                    actual_end_char = search_start + last_space_idx;
-                } else if let Some(last_newline_idx) = search_range.iter().rposition(|&c| c == '\n') {
-                    actual_end_char = search_start + last_newline_idx;
-                }
-            }
+        // Pinecone vectors are sent in batches rather than one request per chunk
+        let mut pending_pinecone = Vec::new();
+        let mut uploaded_vector_ids = Vec::new();
+        let tags = self.db.get_document_metadata(self.project.as_deref(), path)?.tags;
+        
+        // Add to vector index using actual chunk IDs from database
+        for (i, (chunk, embedding)) in chunks.iter().zip(embeddings.iter()).enumerate() {
+            let chunk_id = chunk_ids[i]; // Use actual chunk ID from database
+            let chunk_metadata = ChunkMetadata::for_stored_chunk(path, self.project.as_deref(), &tags, &chunk.text, chunk.language.as_deref());
             
-            // Ensure we don't go backwards
-            if actual_end_char <= start_char {
-                actual_end_char = start_char + 1;
+            // Add to local RAG engine
+            if self.lance_store.is_none() {
+                self.rag_engine.add_chunk(
+                    chunk_id,
+                    embedding,
+                    path,
+                    &chunk.text,
+                    chunk_metadata.clone(),
+                )?;
             }
             
-            // Extract text using character indices
-            let chunk_text: String = chars[start_char..actual_end_char].iter().collect();
-            let chunk_text = chunk_text.trim();
-            
-            if !chunk_text.is_empty() {
-                chunks.push(Chunk {
-                    id: chunk_index as u32,
-                    document_id: 0, // Will be set by database
-                    text: chunk_text.to_string(),
-                    chunk_index,
-                });
-                chunk_index += 1;
-            }
+            let metadata = crate::pinecone::chunk_metadata(path, &chunk.text, chunk_id, document_id, &chunk_metadata);
             
-            start_char = if actual_end_char == end_char { end_char } else { actual_end_char + 1 };
-            if start_char < text_len {
-                start_char = start_char.saturating_sub(overlap);
+            // Add to Qdrant if it is the selected backend
+            if let Some(ref qdrant) = self.qdrant_client {
+                let point = crate::qdrant::Point {
+                    id: chunk_id as u64,
+                    vector: embedding.clone(),
+                    payload: metadata,
+                };
+                
+                // Silently handle Qdrant errors to avoid verbose logging
+                if qdrant.ensure_collection(self.embedding_model.get_dimension(), self.config.search.metric).await.is_ok() {
+                    let _ = qdrant.upsert_points(vec![point]).await;
+                }
+            } else if let Some(ref pinecone) = self.pinecone_client {
+                // Add to Pinecone if available
+                pending_pinecone.push((chunk_id, crate::pinecone::Vector {
+                    id: format!("chunk_{}", chunk_id),
+                    values: embedding.clone(),
+                    metadata,
+                }));
+                
+                if pending_pinecone.len() >= crate::pinecone::UPSERT_BATCH_SIZE {
+                    let batch = std::mem::take(&mut pending_pinecone);
+                    uploaded_vector_ids.extend(upsert_pinecone_batch(pinecone, batch).await);
+                }
             }
-            
-            // Prevent infinite loops
-            if start_char >= text_len {
-                break;
+        }
+        
+        // Flush the remaining Pinecone vectors for this document
+        if let Some(ref pinecone) = self.pinecone_client {
+            if !pending_pinecone.is_empty() {
+                uploaded_vector_ids.extend(upsert_pinecone_batch(pinecone, pending_pinecone).await);
             }
         }
         
-        Ok(chunks)
+        // Track which vectors exist remotely so they can be deleted on change, removal or clear
+        self.db.set_chunk_vector_ids(&uploaded_vector_ids)?;
+        Ok(())
+    }
+
+    /// Chunk a document, warning when the chunk cap leaves part of it unindexed. Tables get a
+    /// summary of their columns after their rows.
+    fn chunk_text(&mut self, path: &str, text: &str, chunking: &ChunkingConfig) -> Result<Vec<Chunk>> {
+        let max_chunks = match chunking.max_chunks_per_document {
+            0 => usize::MAX,
+            cap => cap,
+        };
+        let chunks = crate::chunking::chunk_text(path, text, chunking, max_chunks);
+        
+        let indexed_end = chunks.last().and_then(|chunk| chunk.byte_range).map_or(0, |(_, end)| end);
+        if chunks.len() == max_chunks && indexed_end < text.trim_end().len() {
+            eprintln!("Warning: {} was cut off at {} chunks (chunking.max_chunks_per_document); {:.0}% of it is indexed",
+                path, max_chunks, indexed_end as f64 * 100.0 / text.len() as f64);
+            self.truncated_documents += 1;
+        }
+        let schema = crate::chunking::schema_chunk(path, text, crate::chunking::strategy_for(path, chunking), chunks.len());
+        Ok(chunks.into_iter().chain(schema).collect())
     }
 
     fn calculate_file_hash(&self, content: &str) -> String {
@@ -889,6 +3177,29 @@ impl ChunkyMonkeyApp {
     }
 }
 
+/// Section of a chunk of an EPUB chapter: the chapter title, then the chunk's heading path unless
+/// that already starts with the title
+fn chapter_section(title: &str, headings: Option<String>) -> String {
+    match headings {
+        Some(path) if path.split(" > ").next().is_some_and(|first| first.trim_start_matches('#').trim() == title) => path,
+        Some(path) => format!("{} > {}", title, path),
+        None => title.to_string(),
+    }
+}
 
-
-
+/// SHA-256 of a file's contents, read in blocks; the same as hashing its text in memory
+fn hash_file(path: &Path) -> Result<String> {
+    use sha2::{Sha256, Digest};
+    use std::io::Read;
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
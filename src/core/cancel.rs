@@ -0,0 +1,53 @@
+// Cooperative cancellation: the app carries a token that stops searches, answers and indexing
+// when a serve client disconnects, Esc or Ctrl-C is pressed or a deadline passes
+use anyhow::Result;
+use std::future::Future;
+pub use tokio_util::sync::CancellationToken;
+
+/// Error of work stopped through its cancellation token
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Whether an error comes from cancelled work
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+    error.is::<Cancelled>()
+}
+
+/// Run `work` until it finishes or `token` is cancelled. Cancelling drops `work`, which aborts
+/// its in-flight HTTP requests; a SQLite statement running on the connection behind `interrupt`
+/// is interrupted from a separate thread, since a long scan blocks the task that runs it.
+pub async fn run<T>(token: &CancellationToken, interrupt: Option<rusqlite::InterruptHandle>, work: impl Future<Output = Result<T>>) -> Result<T> {
+    if token.is_cancelled() {
+        return Err(Cancelled.into());
+    }
+
+    let finished = CancellationToken::new();
+    let _stop_watcher = finished.clone().drop_guard();
+    if let Some(interrupt) = interrupt {
+        let (token, runtime) = (token.clone(), tokio::runtime::Handle::current());
+        tokio::task::spawn_blocking(move || runtime.block_on(async {
+            tokio::select! {
+                _ = token.cancelled() => interrupt.interrupt(),
+                _ = finished.cancelled() => {}
+            }
+        }));
+    }
+
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(Cancelled.into()),
+        result = work => match result {
+            // A statement interrupted by the watcher fails before the select sees the token
+            Err(_) if token.is_cancelled() => Err(Cancelled.into()),
+            result => result,
+        },
+    }
+}
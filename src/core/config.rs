@@ -1,5 +1,18 @@
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 use crate::pinecone::PineconeConfig;
+use crate::qdrant::QdrantConfig;
+use crate::lance::LanceDbConfig;
+use crate::vector_search::LocalIndexConfig;
+use crate::result_cache::ResultCacheConfig;
+use crate::replicate::ReplicationConfig;
+use crate::chaos::ChaosConfig;
+use crate::telemetry::TelemetryConfig;
+use crate::search::IndexingConfig;
+use crate::ocr::OcrConfig;
+use crate::hooks::HooksConfig;
+use crate::embeddings::rate_limit::RateLimitConfig;
+use crate::embeddings::DistanceMetric;
 use anyhow::Result;
 use toml;
 
@@ -10,6 +23,101 @@ pub struct AppConfig {
     pub search: SearchConfig,
     pub chunking: ChunkingConfig,
     pub rag: RAGConfig,
+    /// Which files `index` picks up in a directory
+    #[serde(default)]
+    pub indexing: IndexingConfig,
+    /// Reading images and scanned PDFs (requires the `ocr` cargo feature)
+    #[serde(default)]
+    pub ocr: OcrConfig,
+    /// External converters whose output is indexed for formats without a built-in reader
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub vector_store: VectorStoreConfig,
+    #[serde(default)]
+    pub qdrant: QdrantConfig,
+    #[serde(default)]
+    pub lancedb: LanceDbConfig,
+    #[serde(default)]
+    pub local_index: LocalIndexConfig,
+    /// Search results and answers reused for repeated queries over an unchanged corpus
+    #[serde(default)]
+    pub result_cache: ResultCacheConfig,
+    /// Authentication between `replicate` and the `/replica` endpoints of `serve`
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    /// Opt-in usage log, kept in the local database
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    /// Dev-only fault injection for resilience testing
+    #[serde(default)]
+    pub chaos: ChaosConfig,
+    /// Parts of the corpus (by path) with their own chunking, embedding model and retrieval weight
+    #[serde(default)]
+    pub partitions: Vec<PartitionConfig>,
+}
+
+/// A slice of the corpus selected by path, tuned separately from the rest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionConfig {
+    pub name: String,
+    /// Globs over document paths (see `path_matches_glob`); the first partition that matches wins
+    pub paths: Vec<String>,
+    /// Overrides of the top-level `[chunking]` settings
+    #[serde(default)]
+    pub chunking: ChunkingOverrides,
+    /// Ollama embedding model for this partition. It must produce vectors of the configured
+    /// `embedding_dimension`; queries are embedded once per distinct model.
+    pub embedding_model: Option<String>,
+    /// Multiplies the similarity of this partition's results when they are merged with the rest
+    #[serde(default = "default_partition_weight")]
+    pub weight: f32,
+}
+
+fn default_partition_weight() -> f32 {
+    1.0
+}
+
+/// Chunking settings a partition replaces; unset fields keep the top-level value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkingOverrides {
+    pub max_chunk_size: Option<usize>,
+    pub min_chunk_size: Option<usize>,
+    pub overlap_size: Option<usize>,
+    pub unit: Option<ChunkUnit>,
+    pub max_chunks_per_document: Option<usize>,
+    pub child_chunk_size: Option<usize>,
+    pub strategies: Option<BTreeMap<String, ChunkStrategy>>,
+}
+
+impl ChunkingOverrides {
+    pub fn apply(&self, base: &ChunkingConfig) -> ChunkingConfig {
+        ChunkingConfig {
+            max_chunk_size: self.max_chunk_size.unwrap_or(base.max_chunk_size),
+            min_chunk_size: self.min_chunk_size.unwrap_or(base.min_chunk_size),
+            overlap_size: self.overlap_size.unwrap_or(base.overlap_size),
+            unit: self.unit.unwrap_or(base.unit),
+            max_chunks_per_document: self.max_chunks_per_document.unwrap_or(base.max_chunks_per_document),
+            child_chunk_size: self.child_chunk_size.unwrap_or(base.child_chunk_size),
+            strategies: self.strategies.clone().unwrap_or_else(|| base.strategies.clone()),
+            ..base.clone()
+        }
+    }
+}
+
+/// Selects which vector store receives upserts and serves queries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorStoreConfig {
+    /// Backend to use: "pinecone" or "qdrant" (remote), or "lancedb" (local, on disk)
+    pub backend: String,
+}
+
+impl Default for VectorStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: "pinecone".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +125,36 @@ pub struct OllamaConfig {
     pub base_url: String,
     pub model: String,
     pub llm_model: String, // LLM model for answer generation
+    /// Dimension of the vectors produced by the embedding model
+    #[serde(default = "default_embedding_dimension")]
+    pub embedding_dimension: usize,
+    /// Quotas embedding requests to `base_url` are kept under, for hosted endpoints
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Protocol of the server answering LLM requests: "ollama" or "openai" (chat completions)
+    #[serde(default)]
+    pub llm_api: LlmApi,
+    /// Server for LLM requests when it is not `base_url` (e.g. "https://api.openai.com")
+    #[serde(default)]
+    pub llm_base_url: String,
+    /// Bearer token sent with LLM requests; the LLM_API_KEY environment variable when empty
+    #[serde(default)]
+    pub llm_api_key: String,
+}
+
+/// Wire protocol of the LLM server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmApi {
+    /// Ollama's `/api/generate`
+    #[default]
+    Ollama,
+    /// OpenAI-compatible `/v1/chat/completions` (vLLM, llama.cpp, LM Studio and hosted APIs)
+    OpenAI,
+}
+
+fn default_embedding_dimension() -> usize {
+    768
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,18 +163,167 @@ pub struct SearchConfig {
     pub fallback_threshold: f32,
     pub max_results_per_query: usize,
     pub enable_semantic_search: bool,
+    /// Also search with LLM paraphrases of the query and fuse the rankings; costs an LLM call per search
     pub enable_query_expansion: bool,
     pub enable_content_filtering: bool,
     pub enable_reranking: bool,
+    /// Vector comparison: "cosine", "dotproduct" or "euclidean"; must match the remote index's metric
+    #[serde(default)]
+    pub metric: DistanceMetric,
+    /// How remote and local results are combined: "fallback" (local only when the remote store
+    /// fails or is empty) or "rrf" (query both and merge with Reciprocal Rank Fusion)
+    #[serde(default)]
+    pub fusion: FusionMode,
+    /// RRF damping constant; larger values flatten the advantage of top ranks
+    #[serde(default = "default_rrf_k")]
+    pub rrf_k: f32,
+    /// Drop results and context chunks whose text is near-identical to a better-ranked one
+    #[serde(default = "default_suppress_near_duplicates")]
+    pub suppress_near_duplicates: bool,
+    /// SimHash similarity (0.0 to 1.0) at which two chunks count as duplicates
+    #[serde(default = "default_near_duplicate_threshold")]
+    pub near_duplicate_threshold: f32,
+    /// Share of a result's score (0.0 to 1.0) taken from how well its document's title matches the
+    /// query, so short queries naming a document find it; 0 ranks by chunk similarity alone
+    #[serde(default = "default_document_weight")]
+    pub document_weight: f32,
+    /// Language keyword matching stems words for: "auto" detects it per text, "none" matches
+    /// whole words, or a Snowball stemmer name such as "german"
+    #[serde(default = "default_keyword_language", deserialize_with = "keyword_language")]
+    pub keyword_language: String,
+    /// Chunks with fewer characters than this (ignoring surrounding whitespace) are not indexed
+    #[serde(default)]
+    pub min_chunk_chars: usize,
+    /// Chunks with fewer letters and digits than this (separator lines, stray markup) are not indexed
+    #[serde(default)]
+    pub min_chunk_alphanumeric: usize,
+    /// Nearest neighbours fetched per requested result before the relevance threshold is applied
+    #[serde(default = "default_candidate_multiplier")]
+    pub candidate_multiplier: usize,
+    /// Candidates per requested result handed to re-ranking and duplicate suppression
+    #[serde(default = "default_rerank_pool_multiplier")]
+    pub rerank_pool_multiplier: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FusionMode {
+    #[default]
+    Fallback,
+    Rrf,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkUnit {
+    #[default]
+    Chars,
+    Tokens,
+}
+
+/// How a kind of file is split into chunks (see `chunking::chunker`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkStrategy {
+    /// Fixed-size windows ending at word boundaries
+    Fixed,
+    /// Split at paragraphs, then lines, sentences and words, packing pieces up to the size limit
+    Recursive,
+    /// Split at headings first, recording each chunk's heading path
+    Markdown,
+    /// Split between top-level definitions, keeping their comments and attributes attached
+    Code,
+    /// Group the records of a CSV or TSV file, repeating the header line in each chunk
+    Table,
+}
+
+impl ChunkStrategy {
+    /// Name as written in `chunking.strategies`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Fixed => "fixed",
+            Self::Recursive => "recursive",
+            Self::Markdown => "markdown",
+            Self::Code => "code",
+            Self::Table => "table",
+        }
+    }
+}
+
+fn default_rrf_k() -> f32 {
+    60.0
+}
+
+fn default_suppress_near_duplicates() -> bool {
+    true
+}
+
+fn default_near_duplicate_threshold() -> f32 {
+    0.95
+}
+
+fn default_document_weight() -> f32 {
+    0.2
+}
+
+fn default_keyword_language() -> String {
+    "auto".to_string()
+}
+
+/// `search.keyword_language`, rejected unless it names a stemmer, so a typo does not quietly
+/// turn stemming off
+fn keyword_language<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let setting = String::deserialize(deserializer)?;
+    crate::stemming::check_setting(&setting).map_err(serde::de::Error::custom)?;
+    Ok(setting)
+}
+
+fn default_candidate_multiplier() -> usize {
+    2
+}
+
+fn default_rerank_pool_multiplier() -> usize {
+    2
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChunkingConfig {
+    /// Largest chunk, in `unit`s
     pub max_chunk_size: usize,
+    /// A trailing chunk shorter than this is folded into the one before it
     pub min_chunk_size: usize,
+    /// How much of the end of each chunk is repeated at the start of the next (at most half of `max_chunk_size`)
     pub overlap_size: usize,
+    /// What the sizes count: "chars" or "tokens" (approximate subword tokens, as LLM context is measured)
+    #[serde(default)]
+    pub unit: ChunkUnit,
+    /// Stop chunking a document after this many chunks (0 = no limit)
+    #[serde(default)]
+    pub max_chunks_per_document: usize,
+    /// Split each chunk into children of this size, which are embedded and matched while their
+    /// parent chunk is what answers get as context (0 = embed the chunks themselves)
+    #[serde(default)]
+    pub child_chunk_size: usize,
     pub use_semantic_chunking: bool,
+    /// Chunk markdown section by section, recording each chunk's heading path
     pub respect_section_boundaries: bool,
+    /// Chunking strategy by file extension, with "*" for everything else; files matching neither
+    /// get markdown sections (per `respect_section_boundaries`) or fixed windows
+    #[serde(default)]
+    pub strategies: BTreeMap<String, ChunkStrategy>,
+    /// Embed each chunk behind a header with its file path, section and an LLM-written one-line
+    /// summary of the document, so snippets that depend on their surroundings are still found
+    #[serde(default)]
+    pub contextual_headers: bool,
+    /// Chunk CSV/TSV files and markdown tables in groups of rows, repeating the header row in
+    /// each chunk so rows far down a table stay answerable. CSV/TSV files also get a summary of
+    /// their columns as a chunk of its own.
+    #[serde(default = "default_table_aware")]
+    pub table_aware: bool,
+}
+
+fn default_table_aware() -> bool {
+    true
 }
 
 /// Configuration for the fortified RAG pipeline
@@ -60,6 +347,64 @@ pub struct RAGConfig {
     pub enable_confidence_scoring: bool,
     /// Enable source attribution
     pub enable_source_attribution: bool,
+    /// Maximum context chunks taken from any single document (0 = no limit)
+    #[serde(default = "default_max_chunks_per_document")]
+    pub max_chunks_per_document: usize,
+    /// Similarity (0.0 to 1.0) at which a question is answered with a saved answer instead of generating one
+    #[serde(default = "default_learned_answer_threshold")]
+    pub learned_answer_threshold: f32,
+    /// Ask the LLM for exact quotes backing its answer, keeping only those found in the cited chunk
+    #[serde(default = "default_enable_quoted_citations")]
+    pub enable_quoted_citations: bool,
+    /// Candidates fetched per context chunk before diverse sources are picked from them
+    #[serde(default = "default_candidate_multiplier")]
+    pub candidate_multiplier: usize,
+    /// Candidates fetched per context chunk when `max_chunks_per_document` caps sources, so other
+    /// documents can fill the slots
+    #[serde(default = "default_capped_candidate_multiplier")]
+    pub capped_candidate_multiplier: usize,
+    /// Semantic expansion runs when fewer than this share (0.0 to 1.0) of the context slots are filled
+    #[serde(default = "default_expansion_trigger")]
+    pub expansion_trigger: f32,
+    /// Candidates fetched per missing context chunk during semantic expansion
+    #[serde(default = "default_candidate_multiplier")]
+    pub expansion_candidate_multiplier: usize,
+    /// Similarity above which semantic expansion adds a chunk; lower than search thresholds since
+    /// it only fills gaps
+    #[serde(default = "default_expansion_min_similarity")]
+    pub expansion_min_similarity: f32,
+    /// Estimated tokens of chunk text allowed in an answer's context (0 = no limit). Chunks with
+    /// the question's exact terms are packed first; similar chunks fill what is left.
+    #[serde(default)]
+    pub max_context_tokens: usize,
+    /// Context window of the LLM in tokens (0 = unknown). The context gets what the prompt, the
+    /// question and the answer's token budget leave of it.
+    #[serde(default)]
+    pub context_window_tokens: usize,
+}
+
+fn default_max_chunks_per_document() -> usize {
+    2
+}
+
+fn default_learned_answer_threshold() -> f32 {
+    0.92
+}
+
+fn default_enable_quoted_citations() -> bool {
+    true
+}
+
+fn default_capped_candidate_multiplier() -> usize {
+    3
+}
+
+fn default_expansion_trigger() -> f32 {
+    0.5
+}
+
+fn default_expansion_min_similarity() -> f32 {
+    0.3
 }
 
 impl Default for AppConfig {
@@ -69,6 +414,11 @@ impl Default for AppConfig {
                 base_url: String::new(),
                 model: "llama3".to_string(),
                 llm_model: "llama3".to_string(),
+                embedding_dimension: default_embedding_dimension(),
+                rate_limit: RateLimitConfig::default(),
+                llm_api: LlmApi::default(),
+                llm_base_url: String::new(),
+                llm_api_key: String::new(),
             },
             pinecone: PineconeConfig {
                 api_key: String::new(),
@@ -81,16 +431,33 @@ impl Default for AppConfig {
                 fallback_threshold: 0.4,
                 max_results_per_query: 10,
                 enable_semantic_search: true,
-                enable_query_expansion: true,
+                enable_query_expansion: false,
                 enable_content_filtering: true,
                 enable_reranking: true,
+                metric: DistanceMetric::default(),
+                fusion: FusionMode::default(),
+                rrf_k: default_rrf_k(),
+                suppress_near_duplicates: default_suppress_near_duplicates(),
+                near_duplicate_threshold: default_near_duplicate_threshold(),
+                document_weight: default_document_weight(),
+                keyword_language: default_keyword_language(),
+                min_chunk_chars: 0,
+                min_chunk_alphanumeric: 0,
+                candidate_multiplier: default_candidate_multiplier(),
+                rerank_pool_multiplier: default_rerank_pool_multiplier(),
             },
             chunking: ChunkingConfig {
                 max_chunk_size: 1500,
                 min_chunk_size: 200,
                 overlap_size: 200,
+                unit: ChunkUnit::default(),
+                max_chunks_per_document: 0,
+                child_chunk_size: 0,
                 use_semantic_chunking: true,
                 respect_section_boundaries: true,
+                strategies: BTreeMap::new(),
+                contextual_headers: false,
+                table_aware: default_table_aware(),
             },
             rag: RAGConfig {
                 enable_advanced_rag: true,
@@ -102,7 +469,29 @@ impl Default for AppConfig {
                 max_context_chunks: 15,
                 enable_confidence_scoring: true,
                 enable_source_attribution: true,
+                max_chunks_per_document: default_max_chunks_per_document(),
+                learned_answer_threshold: default_learned_answer_threshold(),
+                enable_quoted_citations: default_enable_quoted_citations(),
+                candidate_multiplier: default_candidate_multiplier(),
+                capped_candidate_multiplier: default_capped_candidate_multiplier(),
+                expansion_trigger: default_expansion_trigger(),
+                expansion_candidate_multiplier: default_candidate_multiplier(),
+                expansion_min_similarity: default_expansion_min_similarity(),
+                max_context_tokens: 0,
+                context_window_tokens: 0,
             },
+            vector_store: VectorStoreConfig::default(),
+            qdrant: QdrantConfig::default(),
+            lancedb: LanceDbConfig::default(),
+            local_index: LocalIndexConfig::default(),
+            result_cache: ResultCacheConfig::default(),
+            replication: ReplicationConfig::default(),
+            indexing: IndexingConfig::default(),
+            ocr: OcrConfig::default(),
+            hooks: HooksConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            chaos: ChaosConfig::default(),
+            partitions: Vec::new(),
         }
     }
 }
@@ -111,16 +500,28 @@ impl AppConfig {
     pub fn from_env() -> Result<Self> {
         let ollama_base_url = std::env::var("OLLAMA_BASE_URL").unwrap_or_default();
         let ollama_model = std::env::var("OLLAMA_MODEL").unwrap_or_default();
+        let embedding_dimension = std::env::var("EMBEDDING_DIMENSION").ok()
+            .and_then(|d| d.parse().ok())
+            .unwrap_or_else(default_embedding_dimension);
         let pinecone_api_key = std::env::var("PINECONE_API_KEY").unwrap_or_default();
         let pinecone_environment = std::env::var("PINECONE_ENVIRONMENT").unwrap_or_default();
         let pinecone_index = std::env::var("PINECONE_INDEX").unwrap_or_default();
         let pinecone_host = std::env::var("PINECONE_HOST").ok();
+        let vector_store_backend = std::env::var("VECTOR_STORE_BACKEND").unwrap_or_else(|_| "pinecone".to_string());
+        let qdrant_url = std::env::var("QDRANT_URL").unwrap_or_default();
+        let qdrant_api_key = std::env::var("QDRANT_API_KEY").ok();
+        let qdrant_collection = std::env::var("QDRANT_COLLECTION").unwrap_or_else(|_| "chunkymonkey".to_string());
         
         Ok(Self {
             ollama: OllamaConfig {
                 base_url: ollama_base_url,
                 model: ollama_model,
                 llm_model: "llama3".to_string(),
+                embedding_dimension,
+                rate_limit: RateLimitConfig::default(),
+                llm_api: LlmApi::default(),
+                llm_base_url: String::new(),
+                llm_api_key: String::new(),
             },
             pinecone: PineconeConfig {
                 api_key: pinecone_api_key,
@@ -133,16 +534,33 @@ impl AppConfig {
                 fallback_threshold: 0.4,
                 max_results_per_query: 10,
                 enable_semantic_search: true,
-                enable_query_expansion: true,
+                enable_query_expansion: false,
                 enable_content_filtering: true,
                 enable_reranking: true,
+                metric: DistanceMetric::default(),
+                fusion: FusionMode::default(),
+                rrf_k: default_rrf_k(),
+                suppress_near_duplicates: default_suppress_near_duplicates(),
+                near_duplicate_threshold: default_near_duplicate_threshold(),
+                document_weight: default_document_weight(),
+                keyword_language: default_keyword_language(),
+                min_chunk_chars: 0,
+                min_chunk_alphanumeric: 0,
+                candidate_multiplier: default_candidate_multiplier(),
+                rerank_pool_multiplier: default_rerank_pool_multiplier(),
             },
             chunking: ChunkingConfig {
                 max_chunk_size: 1500,
                 min_chunk_size: 200,
                 overlap_size: 200,
+                unit: ChunkUnit::default(),
+                max_chunks_per_document: 0,
+                child_chunk_size: 0,
                 use_semantic_chunking: true,
                 respect_section_boundaries: true,
+                strategies: BTreeMap::new(),
+                contextual_headers: false,
+                table_aware: default_table_aware(),
             },
             rag: RAGConfig {
                 enable_advanced_rag: true,
@@ -154,7 +572,35 @@ impl AppConfig {
                 max_context_chunks: 15,
                 enable_confidence_scoring: true,
                 enable_source_attribution: true,
+                max_chunks_per_document: default_max_chunks_per_document(),
+                learned_answer_threshold: default_learned_answer_threshold(),
+                enable_quoted_citations: default_enable_quoted_citations(),
+                candidate_multiplier: default_candidate_multiplier(),
+                capped_candidate_multiplier: default_capped_candidate_multiplier(),
+                expansion_trigger: default_expansion_trigger(),
+                expansion_candidate_multiplier: default_candidate_multiplier(),
+                expansion_min_similarity: default_expansion_min_similarity(),
+                max_context_tokens: 0,
+                context_window_tokens: 0,
+            },
+            vector_store: VectorStoreConfig {
+                backend: vector_store_backend,
             },
+            qdrant: QdrantConfig {
+                url: qdrant_url,
+                api_key: qdrant_api_key,
+                collection_name: qdrant_collection,
+            },
+            lancedb: LanceDbConfig::default(),
+            local_index: LocalIndexConfig::default(),
+            result_cache: ResultCacheConfig::default(),
+            replication: ReplicationConfig::default(),
+            indexing: IndexingConfig::default(),
+            ocr: OcrConfig::default(),
+            hooks: HooksConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            chaos: ChaosConfig::default(),
+            partitions: Vec::new(),
         })
     }
 
@@ -171,9 +617,10 @@ impl AppConfig {
     }
     
     pub fn load() -> Result<Self> {
-        // Try to load from config.toml first
-        if let Ok(config) = Self::from_file("config.toml") {
-            return Ok(config);
+        // Try to load from config.toml first; a file that exists but doesn't parse is an error, not a reason to use defaults
+        if std::path::Path::new("config.toml").exists() {
+            return Self::from_file("config.toml")
+                .map_err(|e| anyhow::anyhow!("Invalid config.toml: {} (fix it or run `chunkymonkey init --force`)", e));
         }
         
         // Fallback to environment variables
@@ -184,4 +631,30 @@ impl AppConfig {
         // Final fallback to defaults
         Ok(Self::default())
     }
+
+    /// The partition a document belongs to, if any
+    pub fn partition_for(&self, path: &str) -> Option<&PartitionConfig> {
+        self.partitions.iter()
+            .find(|partition| partition.paths.iter().any(|pattern| crate::core::types::path_matches_glob(path, pattern)))
+    }
+
+    /// Chunking settings for a document, with its partition's overrides applied
+    pub fn chunking_for(&self, path: &str) -> ChunkingConfig {
+        match self.partition_for(path) {
+            Some(partition) => partition.chunking.apply(&self.chunking),
+            None => self.chunking.clone(),
+        }
+    }
+
+    /// Embedding model used for a document; None is the default `ollama.model`
+    pub fn embedding_model_for(&self, path: &str) -> Option<&str> {
+        self.partition_for(path)
+            .and_then(|partition| partition.embedding_model.as_deref())
+            .filter(|model| *model != self.ollama.model)
+    }
+
+    /// Retrieval weight of a document's partition (1.0 outside partitions)
+    pub fn partition_weight(&self, path: &str) -> f32 {
+        self.partition_for(path).map_or(1.0, |partition| partition.weight)
+    }
 } 
\ No newline at end of file
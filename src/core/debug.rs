@@ -0,0 +1,60 @@
+// Opt-in HTTP debug logging (--debug-http or RUST_LOG=debug). Only method, URL path, status,
+// sizes and timing are logged: never headers, bodies or query strings, so no key material leaks.
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+static HTTP_DEBUG: AtomicBool = AtomicBool::new(false);
+
+/// Enable HTTP logging when `--debug-http` is passed or RUST_LOG asks for debug/trace output
+pub fn init(debug_http: bool) {
+    let from_env = std::env::var("RUST_LOG")
+        .map(|level| {
+            let level = level.to_lowercase();
+            level.contains("debug") || level.contains("trace")
+        })
+        .unwrap_or(false);
+    HTTP_DEBUG.store(debug_http || from_env, Ordering::Relaxed);
+}
+
+pub fn http_debug_enabled() -> bool {
+    HTTP_DEBUG.load(Ordering::Relaxed)
+}
+
+/// `send()` that logs one line per request to stderr when HTTP debugging is enabled
+pub trait SendLogged {
+    fn send_logged(self, service: &'static str) -> impl Future<Output = reqwest::Result<reqwest::Response>> + Send;
+}
+
+impl SendLogged for reqwest::RequestBuilder {
+    async fn send_logged(self, service: &'static str) -> reqwest::Result<reqwest::Response> {
+        let (client, request) = self.build_split();
+        let request = request?;
+        if !http_debug_enabled() {
+            return client.execute(request).await;
+        }
+
+        let method = request.method().clone();
+        let mut url = request.url().clone();
+        url.set_query(None);
+        let sent_bytes = request.body().and_then(|body| body.as_bytes()).map(|b| b.len()).unwrap_or(0);
+
+        let start = Instant::now();
+        let result = client.execute(request).await;
+        match result {
+            Ok(response) => {
+                eprintln!(
+                    "[http] {} {} {} -> {} ({} bytes sent, {:.0?})",
+                    service, method, url, response.status().as_u16(), sent_bytes, start.elapsed()
+                );
+                Ok(response)
+            }
+            Err(e) => {
+                // The error's own URL may carry a query string, so report it without one
+                let kind = if e.is_timeout() { "timeout" } else if e.is_connect() { "connect error" } else { "error" };
+                eprintln!("[http] {} {} {} -> {} after {:.0?}", service, method, url, kind, start.elapsed());
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Process-wide record of degraded operation: service errors, fallbacks taken and cache lookups.
+// Components record events as they happen; the app persists them (see `ChunkyMonkeyApp::flush_health`)
+// so `rag-stats` in another process can explain why answers got worse.
+use std::sync::Mutex;
+
+/// Ollama embeddings failed and chunks or queries were embedded with the simple fallback
+pub const EMBEDDING_FALLBACK: &str = "fallback:embedding";
+/// The remote vector store failed or returned nothing and the local index answered instead
+pub const LOCAL_SEARCH_FALLBACK: &str = "fallback:local_search";
+/// An answer was built without the LLM (extracted text or a fallback strategy)
+pub const ANSWER_FALLBACK: &str = "fallback:answer";
+/// Deterministic-mode query embedding cache
+pub const QUERY_CACHE_HIT: &str = "cache:query_embedding:hit";
+pub const QUERY_CACHE_MISS: &str = "cache:query_embedding:miss";
+/// Serve-mode `/search` response cache
+pub const SEARCH_CACHE_HIT: &str = "cache:search:hit";
+pub const SEARCH_CACHE_MISS: &str = "cache:search:miss";
+/// Search results and answers reused from the database (`result_cache`)
+pub const RESULT_CACHE_HIT: &str = "cache:result:hit";
+pub const RESULT_CACHE_MISS: &str = "cache:result:miss";
+
+/// Prefix of the counter holding a service's error count and last error message
+pub const ERROR_PREFIX: &str = "error:";
+
+/// A counter increment, optionally carrying the message that caused it
+#[derive(Debug, Clone)]
+pub struct HealthEvent {
+    pub name: String,
+    pub message: Option<String>,
+    pub at: u64,
+}
+
+static PENDING: Mutex<Vec<HealthEvent>> = Mutex::new(Vec::new());
+
+fn push(name: String, message: Option<String>) {
+    let at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.push(HealthEvent { name, message, at });
+    }
+}
+
+/// Count an event such as a fallback or a cache lookup
+pub fn record(name: &str) {
+    push(name.to_string(), None);
+}
+
+pub fn record_cache(hit: bool, hit_name: &str, miss_name: &str) {
+    record(if hit { hit_name } else { miss_name });
+}
+
+/// Remember a failed call to an external service ("ollama", "llm", "pinecone", "qdrant")
+pub fn record_error(service: &str, error: impl std::fmt::Display) {
+    push(format!("{}{}", ERROR_PREFIX, service), Some(error.to_string()));
+}
+
+/// Events recorded since the last call
+pub fn take_pending() -> Vec<HealthEvent> {
+    PENDING.lock().map(|mut pending| std::mem::take(&mut *pending)).unwrap_or_default()
+}
@@ -1,3 +1,6 @@
 pub mod app;
 pub mod types;
-pub mod config; 
\ No newline at end of file
+pub mod config;
+pub mod debug;
+pub mod health;
+pub mod cancel;
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -6,6 +7,485 @@ pub struct SearchResult {
     pub document_path: String,
     pub chunk_text: String,
     pub similarity: f32,
+    /// Remote match whose text is available neither in its metadata nor in the local database
+    #[serde(default)]
+    pub metadata_only: bool,
+    #[serde(default)]
+    pub metadata: ChunkMetadata,
+    /// First and last line of the chunk in its source file, counting from 1
+    #[serde(default)]
+    pub lines: Option<(usize, usize)>,
+}
+
+impl SearchResult {
+    /// Where the chunk is, as `path:first-last` when its lines are known
+    pub fn location(&self) -> String {
+        location(&self.document_path, self.lines)
+    }
+}
+
+/// `path:first-last` for a chunk at `lines` of the file at `path` (`path:line` for a single line),
+/// or just `path` when its lines are not known
+pub fn location(path: &str, lines: Option<(usize, usize)>) -> String {
+    match lines {
+        Some((first, last)) if first == last => format!("{}:{}", path, first),
+        Some((first, last)) => format!("{}:{}-{}", path, first, last),
+        None => path.to_string(),
+    }
+}
+
+/// A chunk found by keyword search, with the part of it around the matched terms
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeywordMatch {
+    pub chunk_id: u32,
+    pub document_path: String,
+    pub chunk_text: String,
+    /// BM25 relevance; higher is better, and only comparable within one query
+    pub score: f32,
+    /// Matched terms are between `db::KEYWORD_MATCH_START` and `db::KEYWORD_MATCH_END`
+    pub snippet: String,
+    #[serde(default)]
+    pub metadata: ChunkMetadata,
+    #[serde(default)]
+    pub lines: Option<(usize, usize)>,
+}
+
+impl KeywordMatch {
+    /// Where the chunk is, as `path:first-last` when its lines are known
+    pub fn location(&self) -> String {
+        location(&self.document_path, self.lines)
+    }
+}
+
+/// Per-document metadata, stored as JSON in `documents.metadata`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentMetadata {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Encoding the file was transcoded to UTF-8 from, when it was not UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Title, date and author from a markdown file's front matter (tags go into `tags`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Titles of the pages above an imported page in its export's page tree, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hierarchy: Vec<String>,
+    /// The text was recognized by OCR in an image or scanned PDF
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ocr: bool,
+    /// The tags among `tags` given to the document rather than read from it (`index --tag`,
+    /// manifest roots, `docs set-meta`), which it keeps when it changes and is indexed again
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assigned_tags: Vec<String>,
+}
+
+/// A document with its chunks and embeddings, as `replicate` ships it to a mirror
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaDocument {
+    pub file_path: String,
+    pub file_hash: String,
+    pub size: usize,
+    /// What the mirror compares to decide whether the document changed (see `Database::document_revisions`)
+    pub revision: String,
+    pub indexed_at: Option<i64>,
+    /// `documents.metadata` JSON
+    pub metadata: Option<String>,
+    pub summary: Option<String>,
+    /// Document title and its embedding
+    pub title: Option<(String, Vec<f32>)>,
+    pub title_model_version: Option<String>,
+    /// Parent chunks first; a chunk's `parent_id` is the index of its parent in this list
+    pub chunks: Vec<ReplicaChunk>,
+}
+
+/// A chunk of a `ReplicaDocument` with its embedding (None for unembedded parent chunks)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaChunk {
+    pub chunk: Chunk,
+    pub embedding: Option<Vec<f32>>,
+    pub model_version: Option<String>,
+}
+
+/// Structured metadata carried with every indexed chunk (locally and in remote vector metadata)
+/// so searches can be filtered at query time
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkMetadata {
+    /// Lowercase file extension without the dot
+    pub extension: Option<String>,
+    pub project: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Programming or markup language, inferred from the extension or a `#!` line
+    pub language: Option<String>,
+    /// ISO 639-1 code of the natural language the chunk is written in ("de"), when it can be told
+    #[serde(default)]
+    pub natural_language: Option<String>,
+}
+
+impl ChunkMetadata {
+    pub fn for_document(path: &str, project: Option<&str>, tags: &[String]) -> Self {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let language = extension.as_deref().and_then(language_for_extension).map(|l| l.to_string());
+        Self {
+            extension,
+            project: project.map(|p| p.to_string()),
+            tags: tags.to_vec(),
+            language,
+            natural_language: None,
+        }
+    }
+
+    /// Metadata of a chunk of the document at `path`, with the languages read from its text
+    pub fn for_chunk(path: &str, project: Option<&str>, tags: &[String], text: &str) -> Self {
+        Self::for_stored_chunk(path, project, tags, text, crate::stemming::detect_language_code(text))
+    }
+
+    /// Metadata of an indexed chunk, with the natural language detected when it was indexed
+    pub fn for_stored_chunk(path: &str, project: Option<&str>, tags: &[String], text: &str, natural_language: Option<&str>) -> Self {
+        let mut metadata = Self::for_document(path, project, tags);
+        if metadata.language.is_none() {
+            metadata.language = language_for_interpreter(text).map(|l| l.to_string());
+        }
+        metadata.natural_language = natural_language.map(|code| code.to_string());
+        metadata
+    }
+
+    /// Whether the chunk is in `language`, a programming language or a natural language's code
+    pub fn is_in_language(&self, language: &str) -> bool {
+        [&self.language, &self.natural_language].into_iter().flatten().any(|l| l.eq_ignore_ascii_case(language))
+    }
+}
+
+/// Language of a script without an extension, from the interpreter its `#!` line names
+fn language_for_interpreter(text: &str) -> Option<&'static str> {
+    let shebang = text.lines().next()?.strip_prefix("#!")?;
+    // "/usr/bin/env python3" names the interpreter after env
+    let interpreter = shebang.split_whitespace()
+        .map(|word| word.rsplit('/').next().unwrap_or(word))
+        .find(|word| *word != "env" && !word.starts_with('-'))?;
+    let language = match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "python" => "python",
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => "shell",
+        "node" | "deno" => "javascript",
+        "ruby" => "ruby",
+        "php" => "php",
+        "perl" => "perl",
+        _ => return None,
+    };
+    Some(language)
+}
+
+fn language_for_extension(extension: &str) -> Option<&'static str> {
+    let language = match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "sh" | "bash" | "zsh" => "shell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" | "scss" => "css",
+        "md" | "markdown" => "markdown",
+        "rst" => "rst",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "txt" => "text",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Whether a glob pattern matches the path, or any trailing part of it that starts after a `/`,
+/// so `tests/**` also matches `crates/core/tests/fixture.json` and `*.snap` matches a file in any directory.
+/// `*` does not cross directory separators; `**` does; `{md,txt}` matches either alternative.
+/// Invalid patterns match nothing.
+pub fn path_matches_glob(path: &str, pattern: &str) -> bool {
+    PathGlob::new(pattern).is_ok_and(|glob| glob.matches(path))
+}
+
+/// Reject glob patterns that would silently match nothing
+pub fn validate_glob(pattern: &str) -> anyhow::Result<()> {
+    PathGlob::new(pattern).map(|_| ())
+}
+
+/// A compiled glob pattern, matched as `path_matches_glob` describes
+pub struct PathGlob {
+    matcher: globset::GlobMatcher,
+}
+
+impl PathGlob {
+    pub fn new(pattern: &str) -> anyhow::Result<Self> {
+        let glob = globset::GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid pattern '{}': {}", pattern, e.kind()))?;
+        Ok(Self { matcher: glob.compile_matcher() })
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./");
+        std::iter::once(0)
+            .chain(path.match_indices('/').map(|(i, _)| i + 1))
+            .any(|start| self.matcher.is_match(&path[start..]))
+    }
+}
+
+/// Split a comma-separated pattern list, leaving commas inside `{...}` groups alone
+pub fn split_patterns(list: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut depth = 0usize;
+    let mut current = String::new();
+    for c in list.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                patterns.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    patterns.push(current);
+    patterns.into_iter()
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+/// Predicates restricting search candidates by chunk metadata and text. Empty fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilter {
+    /// Any of these extensions (case-insensitive, with or without the dot)
+    pub extensions: Vec<String>,
+    /// Document path must start with this
+    pub path_prefix: Option<String>,
+    /// Only chunks of the document with this path
+    pub document: Option<String>,
+    pub project: Option<String>,
+    /// All of these tags
+    pub tags: Vec<String>,
+    /// Any of these languages: programming languages ("rust") or natural language codes ("de")
+    pub languages: Vec<String>,
+    /// Glob patterns of document paths to leave out (see `path_matches_glob`)
+    pub exclude: Vec<String>,
+    /// Chunk text must contain all of these, ignoring case (`+term` in a query)
+    pub must_contain: Vec<String>,
+    /// Chunk text must contain none of these, ignoring case (`-term` in a query)
+    pub must_not_contain: Vec<String>,
+    /// Document author must contain this, ignoring case
+    pub author: Option<String>,
+    /// Document title must contain this, ignoring case
+    pub title: Option<String>,
+    /// Document dated on or after this: an ISO date or its start ("2024", "2024-03")
+    pub since: Option<String>,
+    /// Document dated on or before this, likewise
+    pub until: Option<String>,
+    /// Paths of the documents matching `author`, `title`, `since` and `until`, which chunk
+    /// metadata does not carry; looked up before searching
+    #[serde(skip)]
+    pub documents: Option<HashSet<String>>,
+}
+
+impl SearchFilter {
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+            && self.path_prefix.is_none()
+            && self.document.is_none()
+            && self.project.is_none()
+            && self.tags.is_empty()
+            && self.languages.is_empty()
+            && self.exclude.is_empty()
+            && self.must_contain.is_empty()
+            && self.must_not_contain.is_empty()
+            && !self.filters_documents()
+            && self.documents.is_none()
+    }
+
+    /// Whether any document metadata field is set, so `documents` must be looked up
+    pub fn filters_documents(&self) -> bool {
+        self.author.is_some() || self.title.is_some() || self.since.is_some() || self.until.is_some()
+    }
+
+    /// Whether a document's metadata satisfies `author`, `title`, `since` and `until`
+    pub fn matches_document(&self, metadata: &DocumentMetadata) -> bool {
+        let contains = |field: &Option<String>, wanted: &Option<String>| wanted.as_ref()
+            .is_none_or(|wanted| field.as_ref().is_some_and(|field| field.to_lowercase().contains(&wanted.to_lowercase())));
+        let date = metadata.date.as_deref();
+        contains(&metadata.author, &self.author)
+            && contains(&metadata.title, &self.title)
+            && self.since.as_ref().is_none_or(|since| date.is_some_and(|date| date >= since.as_str()))
+            // "2024-03" takes in every day of March
+            && self.until.as_ref().is_none_or(|until| date.is_some_and(|date| date.get(..until.len()).unwrap_or(date) <= until.as_str()))
+    }
+
+    /// Move the `+term` and `-term` operators of a query into `must_contain` and
+    /// `must_not_contain`, returning the rest of the query. A quoted operand is a phrase
+    /// (`+"exact phrase"`); a `-` inside a word ("tokio-util") is not an operator.
+    pub fn take_query_operators(&mut self, query: &str) -> String {
+        let mut rest = Vec::new();
+        let mut remaining = query.trim_start();
+        while !remaining.is_empty() {
+            let operator = remaining.chars().next().filter(|c| matches!(c, '+' | '-'));
+            let operand = operator.map(|_| &remaining[1..]).filter(|operand| operand.starts_with(|c: char| !c.is_whitespace()));
+            let token_end = match operand {
+                Some(operand) if operand.starts_with('"') => operand[1..].find('"').map_or(remaining.len(), |end| end + 3),
+                _ => remaining.find(char::is_whitespace).unwrap_or(remaining.len()),
+            };
+            let token = &remaining[..token_end];
+            match (operator, operand) {
+                (Some(operator), Some(_)) => {
+                    let term = token[1..].trim_matches('"').to_string();
+                    if !term.is_empty() {
+                        if operator == '+' { self.must_contain.push(term) } else { self.must_not_contain.push(term) }
+                    }
+                }
+                _ => rest.push(token),
+            }
+            remaining = remaining[token_end..].trim_start();
+        }
+        rest.join(" ")
+    }
+
+    /// Whether chunk text satisfies `must_contain` and `must_not_contain`
+    pub fn matches_text(&self, text: &str) -> bool {
+        if self.must_contain.is_empty() && self.must_not_contain.is_empty() {
+            return true;
+        }
+        let text = text.to_lowercase();
+        self.must_contain.iter().all(|term| text.contains(&term.to_lowercase()))
+            && !self.must_not_contain.iter().any(|term| text.contains(&term.to_lowercase()))
+    }
+
+    /// Extensions as stored in chunk metadata: lowercase, no leading dot
+    pub fn normalized_extensions(&self) -> Vec<String> {
+        self.extensions.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect()
+    }
+
+    pub fn matches(&self, document_path: &str, metadata: &ChunkMetadata) -> bool {
+        self.matches_source(document_path, metadata) && self.matches_language(metadata)
+    }
+
+    /// Whether the predicates that hold for a whole document match: path, extension, project and tags
+    pub fn matches_source(&self, document_path: &str, metadata: &ChunkMetadata) -> bool {
+        if self.document.as_ref().is_some_and(|document| document != document_path) {
+            return false;
+        }
+        if self.documents.as_ref().is_some_and(|documents| !documents.contains(document_path)) {
+            return false;
+        }
+        if let Some(ref prefix) = self.path_prefix {
+            if !document_path.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        if self.exclude.iter().any(|pattern| path_matches_glob(document_path, pattern)) {
+            return false;
+        }
+        if !self.extensions.is_empty() {
+            let extensions = self.normalized_extensions();
+            if !metadata.extension.as_ref().is_some_and(|e| extensions.contains(e)) {
+                return false;
+            }
+        }
+        if self.project.is_some() && self.project != metadata.project {
+            return false;
+        }
+        self.tags.iter().all(|tag| metadata.tags.contains(tag))
+    }
+
+    /// Whether the chunk is in one of `languages`, which can differ between chunks of a document
+    pub fn matches_language(&self, metadata: &ChunkMetadata) -> bool {
+        self.languages.is_empty() || self.languages.iter().any(|wanted| metadata.is_in_language(wanted))
+    }
+}
+
+/// Weights combining ranking signals into a result score, fitted per project by `tune-ranking`.
+/// The default ranks by vector similarity alone.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RankingWeights {
+    pub vector: f32,
+    /// Fraction of query terms found verbatim in the chunk
+    pub keyword: f32,
+    /// Heuristic relevance score of the chunk for the question
+    pub rerank: f32,
+    /// How recently the document was indexed
+    pub recency: f32,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self { vector: 1.0, keyword: 0.0, rerank: 0.0, recency: 0.0 }
+    }
+}
+
+/// A matched region of a source file, for editors to highlight without any generated prose
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub path: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub score: f32,
+}
+
+/// Target length of generated answers
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AnswerLength {
+    Short,
+    #[default]
+    Medium,
+    Long,
+}
+
+impl AnswerLength {
+    /// Token budget for the LLM response
+    pub fn max_tokens(&self) -> u32 {
+        match self {
+            AnswerLength::Short => 200,
+            AnswerLength::Medium => 600,
+            AnswerLength::Long => 1500,
+        }
+    }
+
+    /// Prompt instruction describing the expected length
+    pub fn instruction(&self) -> &'static str {
+        match self {
+            AnswerLength::Short => "Answer in two or three sentences.",
+            AnswerLength::Medium => "Answer in one or two short paragraphs.",
+            AnswerLength::Long => "Give a thorough, detailed answer, with explanation and examples from the context where useful.",
+        }
+    }
+}
+
+/// How answers should be written: length plus an optional reading-level hint (e.g. "beginner", "expert")
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnswerStyle {
+    #[serde(default)]
+    pub length: AnswerLength,
+    #[serde(default)]
+    pub reading_level: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +495,8 @@ pub struct Document {
     pub file_hash: String,
     pub size: usize,
     pub chunk_count: u32,
+    /// Unix time the document was last indexed; unset for documents indexed before it was recorded
+    pub indexed_at: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +505,44 @@ pub struct Chunk {
     pub document_id: u32,
     pub text: String,
     pub chunk_index: usize,
+    /// Byte range of the chunk text in the source file, not counting a table header repeated
+    /// before it (None for chunks indexed before offsets were stored)
+    #[serde(default)]
+    pub byte_range: Option<(usize, usize)>,
+    /// First and last line of the chunk in the source file, counting from 1
+    #[serde(default)]
+    pub line_range: Option<(usize, usize)>,
+    /// Heading path of the markdown section the chunk comes from, e.g. "# Install > ## Linux"
+    #[serde(default)]
+    pub section: Option<String>,
+    /// Chunk this one was split from (see `chunking.child_chunk_size`); before it is stored, the
+    /// index of the parent among the document's parent chunks
+    #[serde(default)]
+    pub parent_id: Option<u32>,
+    /// ISO 639-1 code of the natural language of the text, detected once when the chunk is indexed
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl Chunk {
+    /// Text to embed: the chunk prefixed with its heading path, so a section's chunks stay
+    /// findable by the topic of the headings above them
+    pub fn embedding_text(&self) -> String {
+        match &self.section {
+            Some(section) => format!("{}\n\n{}", section, self.text),
+            None => self.text.clone(),
+        }
+    }
+
+    /// `embedding_text` behind a header naming the file and saying what the document is about
+    /// (`chunking.contextual_headers`)
+    pub fn contextual_embedding_text(&self, path: &str, summary: Option<&str>) -> String {
+        let mut header = format!("File: {}\n", path);
+        if let Some(summary) = summary {
+            header.push_str(&format!("Summary: {}\n", summary));
+        }
+        format!("{}\n{}", header, self.embedding_text())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +558,71 @@ pub struct RAGAnswer {
     pub answer: String,
     pub context: String,
     pub sources: Vec<SearchResult>,
+    /// The saved answer given instead of generating one, when the question matched it
+    #[serde(default)]
+    pub learned_from: Option<LearnedAnswer>,
+    /// Quotes from the sources backing the answer, each verified against its chunk
+    #[serde(default)]
+    pub citations: Vec<Citation>,
+    /// Generation ran past the answer deadline, so the answer was extracted from the sources instead
+    #[serde(default)]
+    pub deadline_exceeded: bool,
+    /// Glossary terms the answer uses, in order of first use
+    #[serde(default)]
+    pub glossary_terms: Vec<GlossaryUse>,
+    /// Words the glossary says to avoid that the answer uses anyway
+    #[serde(default)]
+    pub term_misuses: Vec<TermMisuse>,
+    /// Bytes at the start of `answer` already printed to the terminal as they were generated
+    #[serde(skip)]
+    pub printed: usize,
+}
+
+/// A term defined in a project's glossary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlossaryEntry {
+    pub term: String,
+    pub definition: String,
+    /// Discouraged variants of the term, flagged when an answer uses them
+    pub avoid: Vec<String>,
+}
+
+/// The first use of a glossary term in an answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlossaryUse {
+    pub term: String,
+    pub definition: String,
+    /// Byte range of the first use in the answer text
+    pub span: (usize, usize),
+}
+
+/// A word the glossary discourages, used in an answer in place of `preferred`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TermMisuse {
+    /// The word as it appears in the answer
+    pub used: String,
+    pub preferred: String,
+    /// Byte range of the first use in the answer text
+    pub span: (usize, usize),
+}
+
+/// An exact quote from a source chunk that supports an answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub chunk_id: u32,
+    pub document_path: String,
+    pub quote: String,
+    /// Byte range of the quote in the chunk text given as context
+    pub span: (usize, usize),
+}
+
+/// A curated answer to a question, preferred over generating one for matching questions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearnedAnswer {
+    pub id: u32,
+    pub question: String,
+    pub answer: String,
+    pub created_at: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +630,106 @@ pub struct DatabaseStats {
     pub document_count: u32,
     pub chunk_count: u32,
     pub database_size_mb: f64,
+    /// Tags and how many documents carry each, most used first
+    #[serde(default)]
+    pub tags: Vec<TagStats>,
+    /// Files whose last indexing attempt failed, most recent first (`retry-failed`)
+    #[serde(default)]
+    pub failed_files: Vec<IndexFailure>,
+}
+
+/// A file whose last indexing attempt failed, kept until it is indexed or retried successfully
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexFailure {
+    pub file_path: String,
+    pub project: Option<String>,
+    pub error: String,
+    /// Failed attempts in a row
+    pub attempts: u32,
+    /// Unix time of the last failed attempt
+    pub failed_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagStats {
+    pub tag: String,
+    pub document_count: u32,
+}
+
+/// How one command has been used, aggregated from the opt-in usage log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandUsage {
+    pub command: String,
+    pub runs: u64,
+    /// Runs that ended in an error
+    pub failures: u64,
+    pub avg_duration_ms: f64,
+    pub max_duration_ms: u64,
+    /// Average results per run, over the runs that report a result count
+    pub avg_results: Option<f64>,
+    pub last_used_at: i64,
+}
+
+/// Documents and chunks indexed in one project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectStats {
+    /// None is the default project
+    pub project: Option<String>,
+    pub document_count: u32,
+    pub chunk_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingVersionStats {
+    /// None for embeddings predating version tracking
+    pub version: Option<String>,
+    pub chunk_count: u32,
+}
+
+/// Timings from a probe query run while taking a stats snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerfStats {
+    pub embedding_ms: f64,
+    /// Version of the model that served the probe embedding (`simple/...` when Ollama was unreachable)
+    pub embedding_version: String,
+    /// None when the local index is not in use (LanceDB serves vectors instead)
+    pub local_search_ms: Option<f64>,
+    pub questions_last_24h: u32,
+    pub questions_total: u32,
+}
+
+/// Everything `stats --json` reports, as one document for dashboards and cron checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    /// Unix timestamp (seconds) the snapshot was taken at
+    pub generated_at: u64,
+    pub current_project: Option<String>,
+    pub database: DatabaseStats,
+    pub rag_pipeline: RAGPipelineStats,
+    pub projects: Vec<ProjectStats>,
+    /// Embedding model versions in the current project
+    pub embedding_versions: Vec<EmbeddingVersionStats>,
+    pub perf: PerfStats,
+}
+
+/// Embeddings in a project that were produced by a different model version than the configured one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingDrift {
+    pub current_version: String,
+    pub stale_chunks: u32,
+    pub total_chunks: u32,
+    /// Chunk count per stored model version (None for embeddings predating version tracking)
+    pub stored_versions: Vec<(Option<String>, u32)>,
+}
+
+/// What indexing a file would do, as `index --dry-run` reports it
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlannedDocument {
+    /// Already indexed with the same content
+    Unchanged,
+    /// Not indexed yet, or indexed with other content (`changed`). `chunks` estimates the chunks
+    /// embedded; None for scanned files, whose text is only known after OCR.
+    Index { chunks: Option<usize>, changed: bool },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -101,6 +786,46 @@ pub struct RAGPipelineStats {
     pub ollama_available: bool,
     /// Embedding dimension
     pub embedding_dimension: usize,
+    /// Failed calls per external service, with the most recent error
+    pub service_errors: Vec<ServiceErrors>,
+    /// Times texts were embedded with the simple fallback because Ollama failed
+    pub embedding_fallbacks: u64,
+    /// Times the local index answered because the remote store failed or returned nothing
+    pub local_search_fallbacks: u64,
+    /// Answers built without the LLM
+    pub answer_fallbacks: u64,
+    /// Chunks in this project whose stored embedding came from the simple fallback model
+    pub fallback_embedded_chunks: u32,
+    /// Deterministic-mode query embedding cache
+    pub query_cache: CacheCounts,
+    /// Serve-mode `/search` response cache
+    pub search_cache: CacheCounts,
+    /// Search results and answers reused from the database
+    #[serde(default)]
+    pub result_cache: CacheCounts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceErrors {
+    pub service: String,
+    pub count: u64,
+    pub last_error: Option<String>,
+    /// Unix seconds
+    pub last_error_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CacheCounts {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheCounts {
+    /// Fraction of lookups that hit, or None before any lookup
+    pub fn hit_rate(&self) -> Option<f32> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f32 / total as f32)
+    }
 }
 
 impl Default for RAGPipelineStats {
@@ -115,6 +840,14 @@ impl Default for RAGPipelineStats {
             pinecone_available: false,
             ollama_available: false,
             embedding_dimension: 768,
+            service_errors: Vec::new(),
+            embedding_fallbacks: 0,
+            local_search_fallbacks: 0,
+            answer_fallbacks: 0,
+            fallback_embedded_chunks: 0,
+            query_cache: CacheCounts::default(),
+            search_cache: CacheCounts::default(),
+            result_cache: CacheCounts::default(),
         }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,161 @@
+// Compare the documents, chunks and embeddings of two index databases, for `diff`
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// A document present in either index, with its chunk count on each side (0 where absent)
+#[derive(Debug, Serialize)]
+pub struct DocumentChange {
+    pub path: String,
+    pub project: Option<String>,
+    pub chunks_before: usize,
+    pub chunks_after: usize,
+}
+
+/// Totals of one index, restricted to the compared project
+#[derive(Debug, Serialize)]
+pub struct IndexTotals {
+    pub documents: usize,
+    pub chunks: usize,
+    pub embeddings: usize,
+    /// Embedded chunks per model version ("unknown" for chunks indexed before version tracking)
+    pub models: BTreeMap<String, usize>,
+}
+
+/// What changed from one index to the other
+#[derive(Debug, Serialize)]
+pub struct CorpusDiff {
+    pub before: IndexTotals,
+    pub after: IndexTotals,
+    pub added: Vec<DocumentChange>,
+    pub removed: Vec<DocumentChange>,
+    /// Documents whose content hash differs
+    pub changed: Vec<DocumentChange>,
+    pub unchanged: usize,
+    /// Chunk texts only in the second index, which had to be embedded for it
+    pub new_chunk_texts: usize,
+    /// Chunk texts only in the first index
+    pub dropped_chunk_texts: usize,
+}
+
+/// One document row: project, content hash and chunk count
+struct DocumentRow {
+    project: Option<String>,
+    hash: String,
+    chunks: usize,
+}
+
+/// Everything `diff` reads from one index
+struct IndexContents {
+    documents: BTreeMap<String, DocumentRow>,
+    chunk_texts: HashSet<u64>,
+    totals: IndexTotals,
+}
+
+/// Compare the index databases at `before` and `after`, limited to `project` when one is given
+/// (None compares all projects). Both files are opened read-only.
+pub fn diff_indexes(before: &str, after: &str, project: Option<&str>) -> Result<CorpusDiff> {
+    let old = read_index(before, project)?;
+    let new = read_index(after, project)?;
+
+    let change = |path: &str, project: &Option<String>, before: Option<&DocumentRow>, after: Option<&DocumentRow>| DocumentChange {
+        path: path.to_string(),
+        project: project.clone(),
+        chunks_before: before.map_or(0, |row| row.chunks),
+        chunks_after: after.map_or(0, |row| row.chunks),
+    };
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut unchanged = 0;
+    for (path, row) in &new.documents {
+        match old.documents.get(path) {
+            None => added.push(change(path, &row.project, None, Some(row))),
+            Some(previous) if previous.hash != row.hash => changed.push(change(path, &row.project, Some(previous), Some(row))),
+            Some(_) => unchanged += 1,
+        }
+    }
+    let removed = old.documents.iter()
+        .filter(|(path, _)| !new.documents.contains_key(*path))
+        .map(|(path, row)| change(path, &row.project, Some(row), None))
+        .collect();
+
+    Ok(CorpusDiff {
+        new_chunk_texts: new.chunk_texts.difference(&old.chunk_texts).count(),
+        dropped_chunk_texts: old.chunk_texts.difference(&new.chunk_texts).count(),
+        before: old.totals,
+        after: new.totals,
+        added,
+        removed,
+        changed,
+        unchanged,
+    })
+}
+
+fn read_index(path: &str, project: Option<&str>) -> Result<IndexContents> {
+    if !std::path::Path::new(path).is_file() {
+        anyhow::bail!("No index database at {}", path);
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    read_contents(&conn, project).with_context(|| format!("{} is not a ChunkyMonkey index database", path))
+}
+
+fn read_contents(conn: &Connection, project: Option<&str>) -> Result<IndexContents> {
+    let scoped = project.is_some();
+
+    let mut documents = BTreeMap::new();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT file_path, project, file_hash, chunk_count FROM documents WHERE {}", in_project("project")))?;
+    let rows = stmt.query_map(rusqlite::params![scoped, project], |row| {
+        Ok((row.get::<_, String>(0)?, DocumentRow {
+            project: row.get(1)?,
+            hash: row.get(2)?,
+            chunks: row.get::<_, i64>(3)? as usize,
+        }))
+    })?;
+    for row in rows {
+        let (path, document) = row?;
+        documents.insert(path, document);
+    }
+
+    let mut chunk_texts = HashSet::new();
+    let mut chunks = 0;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT c.text FROM chunks c JOIN documents d ON d.id = c.document_id WHERE {}", in_project("d.project")))?;
+    let mut rows = stmt.query(rusqlite::params![scoped, project])?;
+    while let Some(row) = rows.next()? {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        row.get_ref(0)?.as_str()?.hash(&mut hasher);
+        chunk_texts.insert(hasher.finish());
+        chunks += 1;
+    }
+
+    let mut models = BTreeMap::new();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT COALESCE(e.model_version, 'unknown'), COUNT(*) FROM embeddings e
+         JOIN chunks c ON c.id = e.chunk_id JOIN documents d ON d.id = c.document_id
+         WHERE {} GROUP BY 1", in_project("d.project")))?;
+    let rows = stmt.query_map(rusqlite::params![scoped, project], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+    })?;
+    for row in rows {
+        let (model, count) = row?;
+        models.insert(model, count);
+    }
+
+    let totals = IndexTotals {
+        documents: documents.len(),
+        chunks,
+        embeddings: models.values().sum(),
+        models,
+    };
+    Ok(IndexContents { documents, chunk_texts, totals })
+}
+
+/// Condition on `column` binding (?1 scoped, ?2 project): with no project given every document
+/// is compared, otherwise only that project's
+fn in_project(column: &str) -> String {
+    format!("(?1 = 0 OR {} IS ?2)", column)
+}
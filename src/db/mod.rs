@@ -1,14 +1,52 @@
 use anyhow::Result;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
 use crate::core::types::*;
 
+pub mod diff;
+pub mod query;
+mod replica;
+mod usage;
+
+/// Index database file, relative to the working directory
+pub const DATABASE_PATH: &str = "chunkymonkey.db";
+
+/// Marks around the matched terms in a keyword match's snippet; control characters, so they
+/// cannot clash with chunk text
+pub const KEYWORD_MATCH_START: &str = "\u{2}";
+pub const KEYWORD_MATCH_END: &str = "\u{3}";
+
+/// Tokens of context a keyword match's snippet shows around the matched terms
+const KEYWORD_SNIPPET_TOKENS: usize = 16;
+
+/// A chunk with its document path, tracked remote vector id and embedding
+pub type ChunkVectorRow = (Chunk, String, Option<String>, Vec<f32>);
+
+/// A health counter: name, count, last message and when it last changed
+pub type HealthCounterRow = (String, u64, Option<String>, i64);
+
+/// What `add_document_with_chunks` stores about a document besides its chunks
+#[derive(Default)]
+pub struct DocumentRecord<'a> {
+    /// The document's title and its embedding
+    pub title: Option<(&'a str, &'a [f32])>,
+    pub summary: Option<&'a str>,
+    pub metadata: Option<&'a DocumentMetadata>,
+}
+
 pub struct Database {
     conn: Connection,
 }
 
 impl Database {
     pub fn new() -> Result<Self> {
-        let conn = Connection::open("chunkymonkey.db")?;
+        Self::open(DATABASE_PATH)
+    }
+
+    /// Open (creating if needed) the index database at `path`
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        // `serve` reads and writes on separate connections, which wait for each other's locks
+        conn.busy_timeout(std::time::Duration::from_secs(10))?;
         let db = Self { conn };
         db.init_schema()?;
         Ok(db)
@@ -23,7 +61,7 @@ impl Database {
         self.conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS documents (
                 id INTEGER PRIMARY KEY,
-                file_path TEXT UNIQUE NOT NULL,
+                file_path TEXT NOT NULL,
                 file_hash TEXT NOT NULL,
                 size INTEGER NOT NULL,
                 chunk_count INTEGER NOT NULL
@@ -42,11 +80,296 @@ impl Database {
                 chunk_id INTEGER NOT NULL,
                 vector TEXT NOT NULL,
                 FOREIGN KEY (chunk_id) REFERENCES chunks (id)
-            );"
+            );
+            
+            CREATE TABLE IF NOT EXISTS document_embeddings (
+                document_id INTEGER PRIMARY KEY,
+                title TEXT NOT NULL,
+                vector TEXT NOT NULL,
+                model_version TEXT,
+                FOREIGN KEY (document_id) REFERENCES documents (id)
+            );
+            
+            CREATE TABLE IF NOT EXISTS embedding_cache (
+                text_hash TEXT NOT NULL,
+                model TEXT NOT NULL,
+                vector TEXT NOT NULL,
+                PRIMARY KEY (text_hash, model)
+            );
+            
+            CREATE TABLE IF NOT EXISTS embeddings_staging (
+                chunk_id INTEGER PRIMARY KEY,
+                vector TEXT NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS query_history (
+                id INTEGER PRIMARY KEY,
+                query TEXT NOT NULL,
+                asked_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS relevance_feedback (
+                id INTEGER PRIMARY KEY,
+                project TEXT,
+                query TEXT NOT NULL,
+                chunk_id INTEGER NOT NULL,
+                relevant INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS ranking_weights (
+                project TEXT,
+                weights TEXT NOT NULL,
+                samples INTEGER NOT NULL,
+                tuned_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS similarity_thresholds (
+                model TEXT PRIMARY KEY,
+                threshold REAL NOT NULL,
+                samples INTEGER NOT NULL,
+                calibrated_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS learned_answers (
+                id INTEGER PRIMARY KEY,
+                project TEXT,
+                question TEXT NOT NULL,
+                answer TEXT NOT NULL,
+                vector TEXT NOT NULL,
+                model_version TEXT,
+                created_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS blocklist (
+                project TEXT,
+                pattern TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS glossary (
+                id INTEGER PRIMARY KEY,
+                project TEXT,
+                term TEXT NOT NULL,
+                definition TEXT NOT NULL,
+                avoid TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS workspaces (
+                root TEXT PRIMARY KEY,
+                project TEXT,
+                created_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS usage_log (
+                id INTEGER PRIMARY KEY,
+                command TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                result_count INTEGER,
+                succeeded INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS pipeline_health (
+                name TEXT PRIMARY KEY,
+                count INTEGER NOT NULL,
+                last_message TEXT,
+                last_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS index_failures (
+                file_path TEXT PRIMARY KEY,
+                project TEXT,
+                error TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                failed_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS result_cache (
+                key TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                generation INTEGER NOT NULL,
+                result TEXT NOT NULL,
+                cached_at INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS index_generation (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                generation INTEGER NOT NULL
+            );
+            INSERT OR IGNORE INTO index_generation (id, generation) VALUES (1, 0);"
+        )?;
+        
+        self.ensure_column("documents", "project", "TEXT")?;
+        self.ensure_document_key()?;
+        
+        // Any change to what the local index holds bumps the generation, which invalidates saved snapshots
+        for table in ["documents", "chunks", "embeddings"] {
+            for event in ["INSERT", "UPDATE", "DELETE"] {
+                self.conn.execute_batch(&format!(
+                    "CREATE TRIGGER IF NOT EXISTS bump_generation_{table}_{event} AFTER {event} ON {table}
+                     BEGIN UPDATE index_generation SET generation = generation + 1; END;",
+                    table = table,
+                    event = event.to_lowercase(),
+                ))?;
+            }
+        }
+        
+        // The blocklist, glossary, saved answers and tuning change results without touching the index
+        for table in ["blocklist", "glossary", "learned_answers", "ranking_weights", "similarity_thresholds"] {
+            for event in ["INSERT", "UPDATE", "DELETE"] {
+                self.conn.execute_batch(&format!(
+                    "CREATE TRIGGER IF NOT EXISTS clear_result_cache_{table}_{event} AFTER {event} ON {table}
+                     BEGIN DELETE FROM result_cache; END;",
+                    table = table,
+                    event = event.to_lowercase(),
+                ))?;
+            }
+        }
+        
+        // Columns added after the initial schema
+        self.ensure_column("chunks", "vector_id", "TEXT")?;
+        self.ensure_column("embeddings", "model_version", "TEXT")?;
+        self.ensure_column("chunks", "byte_start", "INTEGER")?;
+        self.ensure_column("chunks", "byte_end", "INTEGER")?;
+        self.ensure_column("documents", "indexed_at", "INTEGER")?;
+        self.ensure_column("documents", "metadata", "TEXT")?;
+        self.ensure_column("chunks", "section", "TEXT")?;
+        self.ensure_column("chunks", "parent_id", "INTEGER")?;
+        self.ensure_column("chunks", "line_start", "INTEGER")?;
+        self.ensure_column("chunks", "line_end", "INTEGER")?;
+        self.ensure_column("documents", "summary", "TEXT")?;
+        self.ensure_column("documents", "etag", "TEXT")?;
+        self.ensure_column("documents", "object_size", "INTEGER")?;
+        if self.ensure_column("chunks", "language", "TEXT")? {
+            self.detect_chunk_languages()?;
+        }
+        if self.ensure_column("chunks", "stems", "TEXT")? {
+            self.stem_chunks()?;
+        }
+        self.ensure_keyword_index()?;
+        Ok(())
+    }
+
+    /// Record the natural language of chunks indexed before it was stored
+    fn detect_chunk_languages(&self) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut select = tx.prepare("SELECT id, text FROM chunks")?;
+            let mut update = tx.prepare("UPDATE chunks SET language = ? WHERE id = ?")?;
+            let mut rows = select.query([])?;
+            while let Some(row) = rows.next()? {
+                let id: u32 = row.get(0)?;
+                let text: String = row.get(1)?;
+                if let Some(language) = crate::stemming::detect_language_code(&text) {
+                    update.execute(params![language, id])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Record the keyword index terms of chunks stored before they were
+    fn stem_chunks(&self) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut select = tx.prepare("SELECT id, text, language FROM chunks")?;
+            let mut update = tx.prepare("UPDATE chunks SET stems = ? WHERE id = ?")?;
+            let mut rows = select.query([])?;
+            while let Some(row) = rows.next()? {
+                let id: u32 = row.get(0)?;
+                let text: String = row.get(1)?;
+                let language: Option<String> = row.get(2)?;
+                update.execute(params![crate::stemming::index_terms(&text, language.as_deref()), id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Key documents by project and path, so the same file can be indexed in several projects.
+    /// Databases from when a path was unique across projects get `documents` rebuilt without that.
+    fn ensure_document_key(&self) -> Result<()> {
+        let sql: String = self.conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'documents'", [], |row| row.get(0)
+        )?;
+        if sql.contains("file_path TEXT UNIQUE NOT NULL") {
+            // Dropping `documents` would otherwise fail on the chunks that reference it
+            let foreign_keys: bool = self.conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+            self.conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+            let tx = self.conn.unchecked_transaction()?;
+            tx.execute_batch(&format!(
+                "{};
+                INSERT INTO documents_keyed SELECT * FROM documents;
+                DROP TABLE documents;
+                ALTER TABLE documents_keyed RENAME TO documents;",
+                sql.replacen("CREATE TABLE documents", "CREATE TABLE documents_keyed", 1)
+                    .replacen("file_path TEXT UNIQUE NOT NULL", "file_path TEXT NOT NULL", 1)
+            ))?;
+            tx.commit()?;
+            self.conn.pragma_update(None, "foreign_keys", foreign_keys)?;
+        }
+        // NULLs never collide in a unique index, so the default project is keyed as ''
+        self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS documents_project_path ON documents (IFNULL(project, ''), file_path)", []
+        )?;
+        Ok(())
+    }
+
+    /// Full-text index over chunk text for keyword search, kept in step with `chunks` by triggers.
+    /// Beside the text it indexes `stems`, the chunk's words stemmed in its own language (see
+    /// `stemming::index_terms`), so "Häuser" matches "Haus" in German text as "running" matches
+    /// "runs" in English. Databases from before it existed, or from before it had stems, are
+    /// indexed when it is created.
+    fn ensure_keyword_index(&self) -> Result<()> {
+        let existing: Option<String> = self.conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE name = 'chunks_fts'", [], |row| row.get(0)
+        ).optional()?;
+        let stemmed = existing.as_deref().is_some_and(|sql| sql.contains("stems"));
+        if existing.is_some() && !stemmed {
+            self.conn.execute_batch(
+                "DROP TABLE chunks_fts;
+                DROP TRIGGER IF EXISTS chunks_fts_insert;
+                DROP TRIGGER IF EXISTS chunks_fts_delete;
+                DROP TRIGGER IF EXISTS chunks_fts_update;"
+            )?;
+        }
+        self.conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(text, stems, content='chunks', content_rowid='id', tokenize='unicode61 remove_diacritics 2');
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_insert AFTER INSERT ON chunks BEGIN
+                INSERT INTO chunks_fts(rowid, text, stems) VALUES (new.id, new.text, new.stems);
+            END;
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_delete AFTER DELETE ON chunks BEGIN
+                INSERT INTO chunks_fts(chunks_fts, rowid, text, stems) VALUES ('delete', old.id, old.text, old.stems);
+            END;
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_update AFTER UPDATE OF text, stems ON chunks BEGIN
+                INSERT INTO chunks_fts(chunks_fts, rowid, text, stems) VALUES ('delete', old.id, old.text, old.stems);
+                INSERT INTO chunks_fts(rowid, text, stems) VALUES (new.id, new.text, new.stems);
+            END;"
         )?;
+        if !stemmed {
+            self.conn.execute("INSERT INTO chunks_fts(chunks_fts) VALUES ('rebuild')", [])?;
+        }
         Ok(())
     }
 
+    /// Add a column to an existing table if it is missing (databases created by older versions),
+    /// returning whether it was added
+    fn ensure_column(&self, table: &str, column: &str, definition: &str) -> Result<bool> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|name| name.ok())
+            .any(|name| name == column);
+        
+        if !exists {
+            self.conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        }
+        Ok(!exists)
+    }
+
     pub fn add_document(&mut self, file_path: &str, file_hash: &str, size: usize) -> Result<u32> {
         let document_id = self.conn.execute(
             "INSERT INTO documents (file_path, file_hash, size, chunk_count) VALUES (?, ?, ?, 0)",
@@ -58,7 +381,7 @@ impl Database {
 
     pub fn get_document(&self, document_id: u32) -> Result<Option<Document>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, file_path, file_hash, size, chunk_count FROM documents WHERE id = ?"
+            "SELECT id, file_path, file_hash, size, chunk_count, indexed_at FROM documents WHERE id = ?"
         )?;
         
         let mut rows = stmt.query_map([document_id], |row| {
@@ -68,6 +391,7 @@ impl Database {
                 file_hash: row.get(2)?,
                 size: row.get(3)?,
                 chunk_count: row.get(4)?,
+                indexed_at: row.get(5)?,
             })
         })?;
         
@@ -76,16 +400,57 @@ impl Database {
 
     pub fn get_documents(&self) -> Result<Vec<Document>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, file_path, file_hash, size, chunk_count FROM documents ORDER BY id DESC"
+            "SELECT id, file_path, file_hash, size, chunk_count, indexed_at FROM documents ORDER BY id DESC"
+        )?;
+        
+        let rows = stmt.query_map([], |row| {
+            Ok(Document {
+                id: row.get(0)?,
+                file_path: row.get(1)?,
+                file_hash: row.get(2)?,
+                size: row.get(3)?,
+                chunk_count: row.get(4)?,
+                indexed_at: row.get(5)?,
+            })
+        })?;
+        
+        let mut documents = Vec::new();
+        for row in rows {
+            documents.push(row?);
+        }
+        Ok(documents)
+    }
+
+    /// Projects with their document and chunk counts (`None` is the default project)
+    pub fn get_projects(&self) -> Result<Vec<(Option<String>, u32, u32)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT project, COUNT(*), COALESCE(SUM(chunk_count), 0) FROM documents GROUP BY project ORDER BY project"
         )?;
         
         let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        
+        let mut projects = Vec::new();
+        for row in rows {
+            projects.push(row?);
+        }
+        Ok(projects)
+    }
+
+    pub fn get_documents_by_project(&self, project: Option<&str>) -> Result<Vec<Document>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path, file_hash, size, chunk_count, indexed_at FROM documents WHERE project IS ? ORDER BY file_path"
+        )?;
+        
+        let rows = stmt.query_map([project], |row| {
             Ok(Document {
                 id: row.get(0)?,
                 file_path: row.get(1)?,
                 file_hash: row.get(2)?,
                 size: row.get(3)?,
                 chunk_count: row.get(4)?,
+                indexed_at: row.get(5)?,
             })
         })?;
         
@@ -96,12 +461,21 @@ impl Database {
         Ok(documents)
     }
 
-    pub fn get_document_hash(&self, file_path: &str) -> Result<Option<String>> {
+    pub fn get_document_id_by_path(&self, project: Option<&str>, file_path: &str) -> Result<Option<u32>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM documents WHERE project IS ? AND file_path = ?"
+        )?;
+        
+        let mut rows = stmt.query_map(params![project, file_path], |row| row.get(0))?;
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn get_document_hash(&self, project: Option<&str>, file_path: &str) -> Result<Option<String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT file_hash FROM documents WHERE file_path = ?"
+            "SELECT file_hash FROM documents WHERE project IS ? AND file_path = ?"
         )?;
         
-        let mut rows = stmt.query_map([file_path], |row| {
+        let mut rows = stmt.query_map(params![project, file_path], |row| {
             Ok(row.get(0)?)
         })?;
         
@@ -118,8 +492,8 @@ impl Database {
 
     pub fn add_chunk(&mut self, document_id: u32, text: &str, chunk_index: usize) -> Result<u32> {
         let chunk_id = self.conn.execute(
-            "INSERT INTO chunks (document_id, text, chunk_index) VALUES (?, ?, ?)",
-            params![document_id, text, chunk_index]
+            "INSERT INTO chunks (document_id, text, chunk_index, stems) VALUES (?, ?, ?, ?)",
+            params![document_id, text, chunk_index, crate::stemming::index_terms(text, None)]
         )? as u32;
         
         Ok(chunk_id)
@@ -127,34 +501,58 @@ impl Database {
 
     pub fn get_chunk(&self, chunk_id: u32) -> Result<Option<Chunk>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, document_id, text, chunk_index FROM chunks WHERE id = ?"
+            "SELECT id, document_id, text, chunk_index, byte_start, byte_end, section, parent_id, line_start, line_end, language FROM chunks WHERE id = ?"
         )?;
         
-        let mut rows = stmt.query_map([chunk_id], |row| {
-            Ok(Chunk {
-                id: row.get(0)?,
-                document_id: row.get(1)?,
-                text: row.get(2)?,
-                chunk_index: row.get(3)?,
-            })
-        })?;
+        let mut rows = stmt.query_map([chunk_id], chunk_from_row)?;
+        
+        Ok(rows.next().transpose()?)
+    }
+
+    /// The parent chunk a chunk was split from, if it has one
+    pub fn get_parent_chunk(&self, chunk_id: u32) -> Result<Option<Chunk>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.document_id, p.text, p.chunk_index, p.byte_start, p.byte_end, p.section, p.parent_id, p.line_start, p.line_end, p.language
+             FROM chunks c JOIN chunks p ON p.id = c.parent_id
+             WHERE c.id = ?"
+        )?;
+        
+        let mut rows = stmt.query_map([chunk_id], chunk_from_row)?;
         
         Ok(rows.next().transpose()?)
     }
 
+    /// Natural language recorded for a chunk when it was indexed
+    pub fn get_chunk_language(&self, chunk_id: u32) -> Result<Option<String>> {
+        let language = self.conn.query_row(
+            "SELECT language FROM chunks WHERE id = ?",
+            [chunk_id],
+            |row| row.get::<_, Option<String>>(0)
+        ).optional()?;
+        Ok(language.flatten())
+    }
+
+    /// First and last line of a chunk in its file (None for chunks indexed before lines were stored)
+    pub fn get_chunk_lines(&self, chunk_id: u32) -> Result<Option<(usize, usize)>> {
+        let lines = self.conn.query_row(
+            "SELECT line_start, line_end FROM chunks WHERE id = ?",
+            [chunk_id],
+            |row| Ok(row.get::<_, Option<usize>>(0)?.zip(row.get::<_, Option<usize>>(1)?))
+        ).optional()?;
+        Ok(lines.flatten())
+    }
+
+    /// A document's indexed chunks in order. Parents of child chunks are numbered separately and
+    /// only stored for context, so they are left out.
     pub fn get_chunks_by_document(&self, document_id: u32) -> Result<Vec<Chunk>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, document_id, text, chunk_index FROM chunks WHERE document_id = ? ORDER BY chunk_index"
+            "SELECT id, document_id, text, chunk_index, byte_start, byte_end, section, parent_id, line_start, line_end, language FROM chunks
+             WHERE document_id = ?1
+               AND id NOT IN (SELECT parent_id FROM chunks WHERE document_id = ?1 AND parent_id IS NOT NULL)
+             ORDER BY chunk_index, id"
         )?;
         
-        let rows = stmt.query_map([document_id], |row| {
-            Ok(Chunk {
-                id: row.get(0)?,
-                document_id: row.get(1)?,
-                text: row.get(2)?,
-                chunk_index: row.get(3)?,
-            })
-        })?;
+        let rows = stmt.query_map([document_id], chunk_from_row)?;
         
         let mut chunks = Vec::new();
         for row in rows {
@@ -213,68 +611,1026 @@ impl Database {
         Ok(embeddings)
     }
 
-    pub fn add_document_with_chunks(&mut self, file_path: &str, file_hash: &str, size: usize, chunks: &[Chunk], embeddings: &[Vec<f32>]) -> Result<(u32, Vec<u32>)> {
-        let tx = self.conn.transaction()?;
+    pub fn get_cached_embedding(&self, text_hash: &str, model: &str) -> Result<Option<Vec<f32>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT vector FROM embedding_cache WHERE text_hash = ? AND model = ?"
+        )?;
         
-        // Add document
-        let document_id = tx.execute(
-            "INSERT INTO documents (file_path, file_hash, size, chunk_count) VALUES (?, ?, ?, ?)",
-            params![file_path, file_hash, size, chunks.len()]
-        )? as u32;
+        let mut rows = stmt.query_map(params![text_hash, model], |row| {
+            let vector_json: String = row.get(0)?;
+            Ok(serde_json::from_str::<Vec<f32>>(&vector_json).unwrap_or_default())
+        })?;
         
-        let mut chunk_ids = Vec::new();
+        Ok(rows.next().transpose()?.filter(|v| !v.is_empty()))
+    }
+
+    pub fn cache_embedding(&self, text_hash: &str, model: &str, vector: &[f32]) -> Result<()> {
+        let vector_json = serde_json::to_string(vector)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO embedding_cache (text_hash, model, vector) VALUES (?, ?, ?)",
+            params![text_hash, model, vector_json]
+        )?;
+        Ok(())
+    }
+
+    /// All embedded chunks (not parent chunks) with the path and project of their document, in id order
+    pub fn get_all_chunks_with_paths(&self) -> Result<Vec<(Chunk, String, Option<String>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.document_id, c.text, c.chunk_index, c.byte_start, c.byte_end, c.section, c.parent_id, c.line_start, c.line_end, d.file_path, d.project, c.language
+             FROM chunks c JOIN documents d ON d.id = c.document_id
+             JOIN embeddings e ON e.chunk_id = c.id
+             ORDER BY c.id"
+        )?;
         
-        // Add chunks and embeddings
-        for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
-            let chunk_id = tx.execute(
-                "INSERT INTO chunks (document_id, text, chunk_index) VALUES (?, ?, ?)",
-                params![document_id, chunk.text, chunk.chunk_index]
-            )? as u32;
-            
-            chunk_ids.push(chunk_id);
-            
-            // Add embedding
-            let vector_json = serde_json::to_string(embedding)?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                chunk_from_row(row)?,
+                row.get(10)?,
+                row.get(11)?,
+            ))
+        })?;
+        
+        let mut chunks = Vec::new();
+        for row in rows {
+            chunks.push(row?);
+        }
+        Ok(chunks)
+    }
+
+    /// Write re-embedded vectors to the staging table used by embedding migrations
+    pub fn stage_embeddings(&mut self, embeddings: &[(u32, Vec<f32>)]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (chunk_id, vector) in embeddings {
+            let vector_json = serde_json::to_string(vector)?;
             tx.execute(
-                "INSERT INTO embeddings (chunk_id, vector) VALUES (?, ?)",
+                "INSERT OR REPLACE INTO embeddings_staging (chunk_id, vector) VALUES (?, ?)",
                 params![chunk_id, vector_json]
             )?;
         }
-        
         tx.commit()?;
-        Ok((document_id, chunk_ids))
+        Ok(())
     }
 
-    pub fn get_stats(&self) -> Result<DatabaseStats> {
-        let document_count: u32 = self.conn.query_row(
-            "SELECT COUNT(*) FROM documents",
-            [],
-            |row| row.get(0)
+    pub fn get_staged_embedding(&self, chunk_id: u32) -> Result<Option<Vec<f32>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT vector FROM embeddings_staging WHERE chunk_id = ?"
         )?;
         
-        let chunk_count: u32 = self.conn.query_row(
-            "SELECT COUNT(*) FROM chunks",
-            [],
-            |row| row.get(0)
+        let mut rows = stmt.query_map([chunk_id], |row| {
+            let vector_json: String = row.get(0)?;
+            Ok(serde_json::from_str::<Vec<f32>>(&vector_json).unwrap_or_default())
+        })?;
+        
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Replace all embeddings with the staged ones in a single transaction
+    pub fn swap_staged_embeddings(&mut self, model_version: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM embeddings", [])?;
+        tx.execute(
+            "INSERT INTO embeddings (chunk_id, vector, model_version)
+             SELECT chunk_id, vector, ? FROM embeddings_staging ORDER BY chunk_id",
+            [model_version]
         )?;
+        tx.execute("DELETE FROM embeddings_staging", [])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn discard_staged_embeddings(&mut self) -> Result<()> {
+        self.conn.execute("DELETE FROM embeddings_staging", [])?;
+        Ok(())
+    }
+
+    pub fn get_all_chunk_texts(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT text FROM chunks")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
         
-        // Calculate database size
-        let db_size: u64 = std::fs::metadata("chunkymonkey.db")?.len();
-        let database_size_mb = db_size as f64 / (1024.0 * 1024.0);
+        let mut texts = Vec::new();
+        for row in rows {
+            texts.push(row?);
+        }
+        Ok(texts)
+    }
+
+    pub fn record_query(&self, query: &str) -> Result<()> {
+        let asked_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        self.conn.execute(
+            "INSERT INTO query_history (query, asked_at) VALUES (?, ?)",
+            params![query, asked_at]
+        )?;
+        Ok(())
+    }
+
+    /// Record whether a chunk was relevant to a query, for `tune-ranking`
+    pub fn record_feedback(&self, project: Option<&str>, query: &str, chunk_id: u32, relevant: bool) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO relevance_feedback (project, query, chunk_id, relevant, created_at) VALUES (?, ?, ?, ?, ?)",
+            params![project, query, chunk_id, relevant, unix_now()]
+        )?;
+        Ok(())
+    }
+
+    /// Feedback for a project as (query, chunk id, relevant), skipping chunks that no longer exist.
+    /// When the same pair was judged more than once, the latest judgement wins.
+    pub fn get_feedback(&self, project: Option<&str>) -> Result<Vec<(String, u32, bool)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT f.query, f.chunk_id, f.relevant
+             FROM relevance_feedback f
+             JOIN chunks c ON c.id = f.chunk_id
+             WHERE f.project IS ?1
+               AND f.id = (SELECT MAX(id) FROM relevance_feedback
+                           WHERE project IS ?1 AND query = f.query AND chunk_id = f.chunk_id)
+             ORDER BY f.id"
+        )?;
+        let rows = stmt.query_map([project], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
         
-        Ok(DatabaseStats {
-            document_count,
-            chunk_count,
-            database_size_mb,
-        })
+        let mut feedback = Vec::new();
+        for row in rows {
+            feedback.push(row?);
+        }
+        Ok(feedback)
     }
 
-    pub fn clear_all(&mut self) -> Result<()> {
-        self.conn.execute_batch(
-            "DELETE FROM embeddings;
-             DELETE FROM chunks;
-             DELETE FROM documents;"
+    pub fn get_ranking_weights(&self, project: Option<&str>) -> Result<Option<RankingWeights>> {
+        let weights: Option<String> = self.conn.query_row(
+            "SELECT weights FROM ranking_weights WHERE project IS ?",
+            [project],
+            |row| row.get(0)
+        ).optional()?;
+        Ok(weights.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    pub fn save_ranking_weights(&mut self, project: Option<&str>, weights: &RankingWeights, samples: usize) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM ranking_weights WHERE project IS ?", [project])?;
+        tx.execute(
+            "INSERT INTO ranking_weights (project, weights, samples, tuned_at) VALUES (?, ?, ?, ?)",
+            params![project, serde_json::to_string(weights)?, samples, unix_now()]
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Relevance threshold `calibrate` found for an embedding model
+    pub fn get_similarity_threshold(&self, model: &str) -> Result<Option<f32>> {
+        Ok(self.conn.query_row(
+            "SELECT threshold FROM similarity_thresholds WHERE model = ?",
+            [model],
+            |row| row.get(0)
+        ).optional()?)
+    }
+
+    pub fn save_similarity_threshold(&mut self, model: &str, threshold: f32, samples: usize) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO similarity_thresholds (model, threshold, samples, calibrated_at) VALUES (?, ?, ?, ?)",
+            params![model, threshold, samples, unix_now()]
         )?;
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// When a document was (re-)indexed, as a Unix timestamp; None for documents indexed before this was tracked
+    pub fn get_document_indexed_at(&self, project: Option<&str>, file_path: &str) -> Result<Option<i64>> {
+        let indexed_at: Option<Option<i64>> = self.conn.query_row(
+            "SELECT indexed_at FROM documents WHERE project IS ? AND file_path = ?",
+            params![project, file_path],
+            |row| row.get(0)
+        ).optional()?;
+        Ok(indexed_at.flatten())
+    }
+
+    /// Add a pattern to a project's blocklist; false if it was already listed
+    pub fn add_blocklist_pattern(&mut self, project: Option<&str>, pattern: &str) -> Result<bool> {
+        if self.get_blocklist(project)?.iter().any(|p| p == pattern) {
+            return Ok(false);
+        }
+        self.conn.execute(
+            "INSERT INTO blocklist (project, pattern, created_at) VALUES (?, ?, ?)",
+            params![project, pattern, unix_now()]
+        )?;
+        Ok(true)
+    }
+
+    /// Remove a pattern from a project's blocklist; false if it was not listed
+    pub fn remove_blocklist_pattern(&mut self, project: Option<&str>, pattern: &str) -> Result<bool> {
+        let removed = self.conn.execute(
+            "DELETE FROM blocklist WHERE project IS ? AND pattern = ?",
+            params![project, pattern]
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Chunks of a project matching an FTS5 query, best first by BM25, with a snippet of each
+    /// around its matches between `KEYWORD_MATCH_START` and `KEYWORD_MATCH_END`. Matches are
+    /// offered to `keep` in that order until `limit` are kept. Parent chunks are only context for
+    /// their children and are not matched.
+    pub fn keyword_search(&self, project: Option<&str>, query: &str, limit: usize,
+                          mut keep: impl FnMut(&mut KeywordMatch) -> Result<bool>) -> Result<Vec<KeywordMatch>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT c.id, d.file_path, c.text, c.line_start, c.line_end, bm25(chunks_fts),
+                    snippet(chunks_fts, 0, '{}', '{}', '…', {})
+             FROM chunks_fts JOIN chunks c ON c.id = chunks_fts.rowid JOIN documents d ON d.id = c.document_id
+             WHERE chunks_fts MATCH ?1 AND d.project IS ?2
+               AND c.id NOT IN (SELECT parent_id FROM chunks WHERE parent_id IS NOT NULL)
+             ORDER BY bm25(chunks_fts), c.id",
+            KEYWORD_MATCH_START, KEYWORD_MATCH_END, KEYWORD_SNIPPET_TOKENS
+        ))?;
+        let rows = stmt.query_map(params![query, project], |row| Ok(KeywordMatch {
+            chunk_id: row.get(0)?,
+            document_path: row.get(1)?,
+            chunk_text: row.get(2)?,
+            lines: row.get::<_, Option<usize>>(3)?.zip(row.get::<_, Option<usize>>(4)?),
+            // BM25 is lower for better matches
+            score: -row.get::<_, f64>(5)? as f32,
+            snippet: row.get(6)?,
+            metadata: ChunkMetadata::default(),
+        }))?;
+        let mut matches = Vec::new();
+        for row in rows {
+            if matches.len() == limit {
+                break;
+            }
+            let mut m = row?;
+            if keep(&mut m)? {
+                matches.push(m);
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Record that indexing the file at `file_path` failed with `error`, counting the attempt
+    pub fn record_index_failure(&self, project: Option<&str>, file_path: &str, error: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO index_failures (file_path, project, error, attempts, failed_at) VALUES (?1, ?2, ?3, 1, ?4)
+             ON CONFLICT(file_path) DO UPDATE SET project = ?2, error = ?3, attempts = attempts + 1, failed_at = ?4",
+            params![file_path, project, error, unix_now()]
+        )?;
+        Ok(())
+    }
+
+    /// Forget a failure once the file is indexed or gone
+    pub fn clear_index_failure(&self, file_path: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM index_failures WHERE file_path = ?", [file_path])?;
+        Ok(())
+    }
+
+    /// Files that failed indexing, in a project or (with `None` for `project`) in all of them,
+    /// most recent first
+    pub fn get_index_failures(&self, project: Option<Option<&str>>) -> Result<Vec<IndexFailure>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT file_path, project, error, attempts, failed_at FROM index_failures
+             WHERE ?1 = 0 OR project IS ?2 ORDER BY failed_at DESC, file_path"
+        )?;
+        let rows = stmt.query_map(params![project.is_some(), project.flatten()], |row| Ok(IndexFailure {
+            file_path: row.get(0)?,
+            project: row.get(1)?,
+            error: row.get(2)?,
+            attempts: row.get(3)?,
+            failed_at: row.get(4)?,
+        }))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Save a curated answer with the embedding of its question, returning its id
+    pub fn add_learned_answer(&self, project: Option<&str>, question: &str, answer: &str, vector: &[f32], model_version: &str) -> Result<u32> {
+        self.conn.execute(
+            "INSERT INTO learned_answers (project, question, answer, vector, model_version, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+            params![project, question, answer, serde_json::to_string(vector)?, model_version, unix_now()]
+        )?;
+        Ok(self.conn.last_insert_rowid() as u32)
+    }
+
+    /// A project's saved answers with their question embeddings and the model version that made
+    /// them, oldest first
+    pub fn get_learned_answers(&self, project: Option<&str>) -> Result<Vec<(LearnedAnswer, Vec<f32>, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, question, answer, created_at, vector, model_version FROM learned_answers WHERE project IS ? ORDER BY id"
+        )?;
+        let rows = stmt.query_map([project], |row| {
+            let vector_json: String = row.get(4)?;
+            Ok((
+                LearnedAnswer {
+                    id: row.get(0)?,
+                    question: row.get(1)?,
+                    answer: row.get(2)?,
+                    created_at: row.get(3)?,
+                },
+                serde_json::from_str(&vector_json).unwrap_or_default(),
+                row.get(5)?,
+            ))
+        })?;
+        
+        let mut answers = Vec::new();
+        for row in rows {
+            answers.push(row?);
+        }
+        Ok(answers)
+    }
+
+    /// Delete a saved answer; false if the project has none with this id
+    pub fn remove_learned_answer(&self, project: Option<&str>, id: u32) -> Result<bool> {
+        let removed = self.conn.execute(
+            "DELETE FROM learned_answers WHERE project IS ? AND id = ?",
+            params![project, id]
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Define a term in a project's glossary, replacing any definition of the same term
+    pub fn set_glossary_term(&self, project: Option<&str>, term: &str, definition: &str, avoid: &[String]) -> Result<()> {
+        self.remove_glossary_term(project, term)?;
+        self.conn.execute(
+            "INSERT INTO glossary (project, term, definition, avoid, created_at) VALUES (?, ?, ?, ?, ?)",
+            params![project, term, definition, serde_json::to_string(avoid)?, unix_now()]
+        )?;
+        Ok(())
+    }
+
+    /// A project's glossary, alphabetically by term
+    pub fn get_glossary(&self, project: Option<&str>) -> Result<Vec<GlossaryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT term, definition, avoid FROM glossary WHERE project IS ? ORDER BY term COLLATE NOCASE"
+        )?;
+        let rows = stmt.query_map([project], |row| {
+            let avoid_json: String = row.get(2)?;
+            Ok(GlossaryEntry {
+                term: row.get(0)?,
+                definition: row.get(1)?,
+                avoid: serde_json::from_str(&avoid_json).unwrap_or_default(),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Delete a term (matched case-insensitively); false if the glossary doesn't define it
+    pub fn remove_glossary_term(&self, project: Option<&str>, term: &str) -> Result<bool> {
+        let removed = self.conn.execute(
+            "DELETE FROM glossary WHERE project IS ? AND term = ? COLLATE NOCASE",
+            params![project, term]
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Project remembered for the repository at `root`: None if there is no association,
+    /// Some(None) if the repository uses the default project
+    pub fn get_workspace_project(&self, root: &str) -> Result<Option<Option<String>>> {
+        Ok(self.conn.query_row(
+            "SELECT project FROM workspaces WHERE root = ?",
+            [root],
+            |row| row.get(0),
+        ).optional()?)
+    }
+
+    /// Remember which project commands run inside the repository at `root` work in
+    pub fn set_workspace_project(&self, root: &str, project: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO workspaces (root, project, created_at) VALUES (?, ?, ?)",
+            params![root, project, unix_now()]
+        )?;
+        Ok(())
+    }
+
+    /// Patterns of documents never retrieved in a project, oldest first
+    pub fn get_blocklist(&self, project: Option<&str>) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT pattern FROM blocklist WHERE project IS ? ORDER BY created_at, rowid"
+        )?;
+        let rows = stmt.query_map([project], |row| row.get(0))?;
+        
+        let mut patterns = Vec::new();
+        for row in rows {
+            patterns.push(row?);
+        }
+        Ok(patterns)
+    }
+
+    /// Counter bumped by triggers whenever documents, chunks or embeddings change
+    pub fn get_index_generation(&self) -> Result<i64> {
+        Ok(self.conn.query_row("SELECT generation FROM index_generation WHERE id = 1", [], |row| row.get(0))?)
+    }
+
+    /// A result cached under `key` for index generation `generation` within the last `ttl_secs` seconds
+    pub fn get_cached_result(&self, key: &str, generation: i64, ttl_secs: u64) -> Result<Option<String>> {
+        let since = unix_now().saturating_sub(ttl_secs.min(i64::MAX as u64) as i64);
+        Ok(self.conn.query_row(
+            "SELECT result FROM result_cache WHERE key = ? AND generation = ? AND cached_at >= ?",
+            params![key, generation, since],
+            |row| row.get(0)
+        ).optional()?)
+    }
+
+    /// Cache a result, dropping entries of other generations and the oldest beyond `max_entries`
+    pub fn cache_result(&self, key: &str, kind: &str, generation: i64, result: &str, max_entries: usize) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO result_cache (key, kind, generation, result, cached_at) VALUES (?, ?, ?, ?, ?)",
+            params![key, kind, generation, result, unix_now()]
+        )?;
+        self.conn.execute("DELETE FROM result_cache WHERE generation != ?", [generation])?;
+        self.conn.execute(
+            "DELETE FROM result_cache WHERE key NOT IN (SELECT key FROM result_cache ORDER BY cached_at DESC, rowid DESC LIMIT ?)",
+            [max_entries as i64]
+        )?;
+        Ok(())
+    }
+
+    /// ETag and size of the bucket object a document was last indexed from, when it was
+    #[cfg_attr(not(feature = "s3"), allow(dead_code))]
+    pub fn get_object_version(&self, project: Option<&str>, file_path: &str) -> Result<Option<(String, u64)>> {
+        Ok(self.conn.query_row(
+            "SELECT etag, object_size FROM documents WHERE project IS ? AND file_path = ? AND etag IS NOT NULL",
+            params![project, file_path],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?)
+    }
+
+    /// Record the ETag and size of the bucket object a document was indexed from
+    #[cfg_attr(not(feature = "s3"), allow(dead_code))]
+    pub fn set_object_version(&self, project: Option<&str>, file_path: &str, etag: &str, size: u64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE documents SET etag = ?, object_size = ? WHERE project IS ? AND file_path = ?",
+            params![etag, size, project, file_path]
+        )?;
+        Ok(())
+    }
+
+    /// Tags and other per-document metadata; empty for unknown documents and documents without any
+    pub fn get_document_metadata(&self, project: Option<&str>, file_path: &str) -> Result<DocumentMetadata> {
+        let json: Option<Option<String>> = self.conn.query_row(
+            "SELECT metadata FROM documents WHERE project IS ? AND file_path = ?",
+            params![project, file_path],
+            |row| row.get(0)
+        ).optional()?;
+        Ok(json.flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    }
+
+    /// Paths of a project's documents with metadata, with that metadata
+    pub fn get_documents_metadata(&self, project: Option<&str>) -> Result<Vec<(String, DocumentMetadata)>> {
+        let mut stmt = self.conn.prepare("SELECT file_path, metadata FROM documents WHERE project IS ? AND metadata IS NOT NULL")?;
+        let rows = stmt.query_map([project], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let mut documents = Vec::new();
+        for row in rows {
+            let (path, json) = row?;
+            if let Ok(metadata) = serde_json::from_str(&json) {
+                documents.push((path, metadata));
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Add recorded health events to their counters, keeping the latest message for each
+    pub fn record_health_events(&self, events: &[crate::core::health::HealthEvent]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        // Recorded from read paths that only hold a shared reference
+        let tx = self.conn.unchecked_transaction()?;
+        for event in events {
+            tx.execute(
+                "INSERT INTO pipeline_health (name, count, last_message, last_at) VALUES (?1, 1, ?2, ?3)
+                 ON CONFLICT(name) DO UPDATE SET
+                     count = count + 1,
+                     last_message = COALESCE(?2, last_message),
+                     last_at = MAX(last_at, ?3)",
+                params![event.name, event.message, event.at as i64]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_health_counters(&self) -> Result<Vec<HealthCounterRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, count, last_message, last_at FROM pipeline_health ORDER BY name"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        
+        let mut counters = Vec::new();
+        for row in rows {
+            counters.push(row?);
+        }
+        Ok(counters)
+    }
+
+    pub fn get_document_summary(&self, project: Option<&str>, file_path: &str) -> Result<Option<String>> {
+        let summary: Option<Option<String>> = self.conn.query_row(
+            "SELECT summary FROM documents WHERE project IS ? AND file_path = ?",
+            params![project, file_path],
+            |row| row.get(0)
+        ).optional()?;
+        Ok(summary.flatten())
+    }
+
+    /// Replace the metadata and project of documents in one transaction
+    pub fn update_documents_metadata(&mut self, updates: &[(u32, DocumentMetadata, Option<String>)]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (document_id, metadata, project) in updates {
+            tx.execute(
+                "UPDATE documents SET metadata = ?, project = ? WHERE id = ?",
+                params![serde_json::to_string(metadata)?, project, document_id]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Up to `limit` chunk texts from a project, picked at random
+    pub fn sample_chunk_texts(&self, project: Option<&str>, limit: usize) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.text FROM chunks c JOIN documents d ON c.document_id = d.id
+             WHERE d.project IS ? ORDER BY RANDOM() LIMIT ?"
+        )?;
+        let rows = stmt.query_map(params![project, limit], |row| row.get(0))?;
+        
+        let mut texts = Vec::new();
+        for row in rows {
+            texts.push(row?);
+        }
+        Ok(texts)
+    }
+
+    /// Up to `limit` chunks of a project picked at random, the same ones for the same `seed`, with
+    /// their document path and embedding model version (None for unembedded parent chunks)
+    pub fn sample_chunks(&self, project: Option<&str>, limit: usize, seed: Option<u64>) -> Result<Vec<(Chunk, String, Option<String>)>> {
+        use rand::{seq::SliceRandom, SeedableRng};
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id FROM chunks c JOIN documents d ON c.document_id = d.id WHERE d.project IS ? ORDER BY c.id"
+        )?;
+        let ids: Vec<u32> = stmt.query_map([project], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        let mut rng = match seed {
+            Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+            None => rand::rngs::StdRng::from_entropy(),
+        };
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.document_id, c.text, c.chunk_index, c.byte_start, c.byte_end, c.section, c.parent_id, c.line_start, c.line_end, d.file_path, e.model_version, c.language
+             FROM chunks c JOIN documents d ON d.id = c.document_id
+             LEFT JOIN embeddings e ON e.chunk_id = c.id
+             WHERE c.id = ?"
+        )?;
+        let mut chunks = Vec::new();
+        for id in ids.choose_multiple(&mut rng, limit) {
+            chunks.push(stmt.query_row([id], |row| Ok((chunk_from_row(row)?, row.get(10)?, row.get(11)?)))?);
+        }
+        Ok(chunks)
+    }
+
+    /// Questions asked in the last `since_secs` seconds and overall
+    pub fn get_query_counts(&self, since_secs: u64) -> Result<(u32, u32)> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let cutoff = now.saturating_sub(since_secs) as i64;
+        let counts = self.conn.query_row(
+            "SELECT COUNT(CASE WHEN asked_at >= ? THEN 1 END), COUNT(*) FROM query_history",
+            [cutoff],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        )?;
+        Ok(counts)
+    }
+
+    /// Distinct past questions with the times each was asked, most frequently asked first, then
+    /// most recent
+    pub fn get_query_history(&self) -> Result<Vec<(String, u32)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT query, COUNT(*) FROM query_history GROUP BY query ORDER BY COUNT(*) DESC, MAX(id) DESC LIMIT 500"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        
+        let mut queries = Vec::new();
+        for row in rows {
+            queries.push(row?);
+        }
+        Ok(queries)
+    }
+
+    /// Add a document with its embedded chunks, its title embedding, summary and metadata in one
+    /// transaction, so an interrupted run never leaves part of a document behind. `parents` are
+    /// stored without embeddings; each chunk's `parent_id` is an index into them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_document_with_chunks(&mut self, file_path: &str, file_hash: &str, size: usize, project: Option<&str>, record: &DocumentRecord, parents: &[Chunk], chunks: &[Chunk], embeddings: &[Vec<f32>], model_version: &str) -> Result<(u32, Vec<u32>)> {
+        let tx = self.conn.transaction()?;
+        
+        // Add document
+        let metadata = record.metadata.map(serde_json::to_string).transpose()?;
+        tx.execute(
+            "INSERT INTO documents (file_path, file_hash, size, chunk_count, project, indexed_at, metadata, summary) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![file_path, file_hash, size, chunks.len(), project, unix_now(), metadata, record.summary]
+        )?;
+        let document_id = tx.last_insert_rowid() as u32;
+        let chunk_ids = insert_chunks(&tx, document_id, parents, chunks, embeddings, model_version)?;
+        if let Some((title, vector)) = record.title {
+            tx.execute(
+                "INSERT OR REPLACE INTO document_embeddings (document_id, title, vector, model_version) VALUES (?, ?, ?, ?)",
+                params![document_id, title, serde_json::to_string(vector)?, model_version]
+            )?;
+        }
+        
+        tx.commit()?;
+        Ok((document_id, chunk_ids))
+    }
+
+    /// Add more embedded chunks to a document stored with `add_document_with_chunks`, counting
+    /// them in its chunk count
+    pub fn append_chunks(&mut self, document_id: u32, parents: &[Chunk], chunks: &[Chunk], embeddings: &[Vec<f32>], model_version: &str) -> Result<Vec<u32>> {
+        let tx = self.conn.transaction()?;
+        let chunk_ids = insert_chunks(&tx, document_id, parents, chunks, embeddings, model_version)?;
+        tx.execute(
+            "UPDATE documents SET chunk_count = chunk_count + ? WHERE id = ?",
+            params![chunks.len(), document_id]
+        )?;
+        tx.commit()?;
+        Ok(chunk_ids)
+    }
+
+    /// Record a document's content hash and size once all of it is stored
+    pub fn set_document_hash(&self, document_id: u32, file_hash: &str, size: usize) -> Result<()> {
+        self.conn.execute(
+            "UPDATE documents SET file_hash = ?, size = ? WHERE id = ?",
+            params![file_hash, size, document_id]
+        )?;
+        Ok(())
+    }
+
+    /// Store the title embeddings of documents, replacing earlier ones
+    pub fn set_document_embeddings(&mut self, embeddings: &[(u32, String, Vec<f32>)], model_version: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (document_id, title, vector) in embeddings {
+            tx.execute(
+                "INSERT OR REPLACE INTO document_embeddings (document_id, title, vector, model_version) VALUES (?, ?, ?, ?)",
+                params![document_id, title, serde_json::to_string(vector)?, model_version]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Title embeddings of a project's documents, by document path
+    pub fn get_document_embeddings(&self, project: Option<&str>) -> Result<Vec<(String, Vec<f32>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT d.file_path, de.vector
+             FROM document_embeddings de JOIN documents d ON d.id = de.document_id
+             WHERE d.project IS ?"
+        )?;
+        
+        let rows = stmt.query_map([project], |row| {
+            let vector_json: String = row.get(1)?;
+            Ok((row.get(0)?, serde_json::from_str(&vector_json).unwrap_or_default()))
+        })?;
+        
+        let mut embeddings = Vec::new();
+        for row in rows {
+            embeddings.push(row?);
+        }
+        Ok(embeddings)
+    }
+
+    /// Documents of a project whose title embedding was not produced by `model_version`, as (id, path, title)
+    pub fn get_stale_document_titles(&self, project: Option<&str>, model_version: &str) -> Result<Vec<(u32, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT d.id, d.file_path, de.title
+             FROM document_embeddings de JOIN documents d ON d.id = de.document_id
+             WHERE d.project IS ? AND de.model_version IS NOT ?"
+        )?;
+        
+        let rows = stmt.query_map(params![project, model_version], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        
+        let mut titles = Vec::new();
+        for row in rows {
+            titles.push(row?);
+        }
+        Ok(titles)
+    }
+
+    pub fn get_stats(&self) -> Result<DatabaseStats> {
+        let document_count: u32 = self.conn.query_row(
+            "SELECT COUNT(*) FROM documents",
+            [],
+            |row| row.get(0)
+        )?;
+        
+        // Parent chunks are only context for their children, not searchable chunks of their own
+        let chunk_count: u32 = self.conn.query_row(
+            "SELECT COUNT(*) FROM chunks WHERE id NOT IN (SELECT parent_id FROM chunks WHERE parent_id IS NOT NULL)",
+            [],
+            |row| row.get(0)
+        )?;
+        
+        // Calculate database size
+        let db_size: u64 = std::fs::metadata(DATABASE_PATH)?.len();
+        let database_size_mb = db_size as f64 / (1024.0 * 1024.0);
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT t.value, COUNT(DISTINCT d.id) AS documents
+             FROM documents d, json_each(COALESCE(json_extract(d.metadata, '$.tags'), '[]')) t
+             GROUP BY t.value ORDER BY documents DESC, t.value"
+        )?;
+        let tags = stmt.query_map([], |row| Ok(TagStats { tag: row.get(0)?, document_count: row.get(1)? }))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let failed_files = self.get_index_failures(None)?;
+        
+        Ok(DatabaseStats {
+            document_count,
+            chunk_count,
+            database_size_mb,
+            tags,
+            failed_files,
+        })
+    }
+
+    /// Record the remote vector id each chunk was upserted under
+    pub fn set_chunk_vector_ids(&mut self, vector_ids: &[(u32, String)]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (chunk_id, vector_id) in vector_ids {
+            tx.execute(
+                "UPDATE chunks SET vector_id = ? WHERE id = ?",
+                params![vector_id, chunk_id]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Chunk ids of a document with the remote vector id each was upserted under, if any
+    pub fn get_chunk_vector_ids(&self, document_id: u32) -> Result<Vec<(u32, Option<String>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, vector_id FROM chunks WHERE document_id = ? ORDER BY id"
+        )?;
+        let rows = stmt.query_map([document_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        
+        let mut ids = Vec::new();
+        for row in rows {
+            ids.push(row?);
+        }
+        Ok(ids)
+    }
+
+    /// Chunks of a project with their document path, tracked vector id and embedding
+    pub fn get_project_chunk_vectors(&self, project: Option<&str>) -> Result<Vec<ChunkVectorRow>> {
+        self.chunk_vectors_where("d.project IS ?", project)
+    }
+
+    /// The embedded chunks of one document, as `get_project_chunk_vectors` returns them
+    pub fn get_document_chunk_vectors(&self, document_id: u32) -> Result<Vec<ChunkVectorRow>> {
+        self.chunk_vectors_where("d.id = ?", document_id)
+    }
+
+    fn chunk_vectors_where(&self, condition: &str, param: impl rusqlite::ToSql) -> Result<Vec<ChunkVectorRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT c.id, c.document_id, c.text, c.chunk_index, c.byte_start, c.byte_end, c.section, c.parent_id, c.line_start, c.line_end, d.file_path, c.vector_id, e.vector, c.language
+             FROM chunks c
+             JOIN documents d ON c.document_id = d.id
+             JOIN embeddings e ON e.chunk_id = c.id
+             WHERE {}
+             ORDER BY c.id", condition
+        ))?;
+        
+        let rows = stmt.query_map([param], |row| {
+            let vector_json: String = row.get(12)?;
+            Ok((
+                chunk_from_row(row)?,
+                row.get(10)?,
+                row.get(11)?,
+                serde_json::from_str(&vector_json).unwrap_or_default(),
+            ))
+        })?;
+        
+        let mut chunks = Vec::new();
+        for row in rows {
+            chunks.push(row?);
+        }
+        Ok(chunks)
+    }
+
+    /// Number of embeddings per model version in a project (NULL for embeddings predating version tracking)
+    pub fn get_embedding_versions(&self, project: Option<&str>) -> Result<Vec<(Option<String>, u32)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.model_version, COUNT(*)
+             FROM embeddings e
+             JOIN chunks c ON e.chunk_id = c.id
+             JOIN documents d ON c.document_id = d.id
+             WHERE d.project IS ?
+             GROUP BY e.model_version
+             ORDER BY COUNT(*) DESC"
+        )?;
+        
+        let rows = stmt.query_map([project], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        
+        let mut versions = Vec::new();
+        for row in rows {
+            versions.push(row?);
+        }
+        Ok(versions)
+    }
+
+    /// Chunks in a project whose embedding was not produced by `model_version`, with their path and vector id
+    pub fn get_stale_chunks(&self, project: Option<&str>, model_version: &str) -> Result<Vec<(Chunk, String, Option<String>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.document_id, c.text, c.chunk_index, c.byte_start, c.byte_end, c.section, c.parent_id, c.line_start, c.line_end, d.file_path, c.vector_id, c.language
+             FROM chunks c
+             JOIN documents d ON c.document_id = d.id
+             JOIN embeddings e ON e.chunk_id = c.id
+             WHERE d.project IS ? AND e.model_version IS NOT ?
+             ORDER BY c.id"
+        )?;
+        
+        let rows = stmt.query_map(params![project, model_version], |row| {
+            Ok((
+                chunk_from_row(row)?,
+                row.get(10)?,
+                row.get(11)?,
+            ))
+        })?;
+        
+        let mut chunks = Vec::new();
+        for row in rows {
+            chunks.push(row?);
+        }
+        Ok(chunks)
+    }
+
+    /// Replace the vectors of existing chunks in one transaction, recording the model version that produced them
+    pub fn update_embeddings(&mut self, embeddings: &[(u32, Vec<f32>)], model_version: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (chunk_id, vector) in embeddings {
+            tx.execute(
+                "UPDATE embeddings SET vector = ?, model_version = ? WHERE chunk_id = ?",
+                params![serde_json::to_string(vector)?, model_version, chunk_id]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete a document with its chunks and embeddings, returning the removed chunk ids and their vector ids
+    pub fn delete_document(&mut self, document_id: u32) -> Result<Vec<(u32, Option<String>)>> {
+        let chunk_ids = self.get_chunk_vector_ids(document_id)?;
+        
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM embeddings WHERE chunk_id IN (SELECT id FROM chunks WHERE document_id = ?)",
+            params![document_id]
+        )?;
+        tx.execute("DELETE FROM chunks WHERE document_id = ?", params![document_id])?;
+        tx.execute("DELETE FROM document_embeddings WHERE document_id = ?", params![document_id])?;
+        tx.execute("DELETE FROM documents WHERE id = ?", params![document_id])?;
+        tx.commit()?;
+        
+        Ok(chunk_ids)
+    }
+
+    /// Delete a single chunk and its embedding, keeping the document's chunk count in sync.
+    /// Returns the chunk's remote vector id, if it had one.
+    pub fn delete_chunk(&mut self, chunk_id: u32) -> Result<Option<String>> {
+        let vector_id: Option<String> = self.conn.query_row(
+            "SELECT vector_id FROM chunks WHERE id = ?",
+            [chunk_id],
+            |row| row.get(0)
+        ).unwrap_or(None);
+        
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM embeddings WHERE chunk_id = ?", params![chunk_id])?;
+        tx.execute(
+            "UPDATE documents SET chunk_count = chunk_count - 1
+             WHERE id = (SELECT document_id FROM chunks WHERE id = ?) AND chunk_count > 0",
+            params![chunk_id]
+        )?;
+        tx.execute("DELETE FROM chunks WHERE id = ?", params![chunk_id])?;
+        tx.commit()?;
+        Ok(vector_id)
+    }
+
+    pub fn clear_all(&mut self) -> Result<()> {
+        self.conn.execute_batch(
+            "DELETE FROM embeddings;
+             DELETE FROM chunks;
+             DELETE FROM document_embeddings;
+             DELETE FROM documents;
+             DELETE FROM index_failures;
+             DELETE FROM result_cache;"
+        )?;
+        Ok(())
+    }
+
+    /// Remove the documents, chunks and embeddings of a single project
+    pub fn clear_project(&mut self, project: Option<&str>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM embeddings WHERE chunk_id IN (
+                SELECT c.id FROM chunks c JOIN documents d ON c.document_id = d.id WHERE d.project IS ?
+            )",
+            params![project]
+        )?;
+        tx.execute(
+            "DELETE FROM chunks WHERE document_id IN (SELECT id FROM documents WHERE project IS ?)",
+            params![project]
+        )?;
+        tx.execute(
+            "DELETE FROM document_embeddings WHERE document_id IN (SELECT id FROM documents WHERE project IS ?)",
+            params![project]
+        )?;
+        tx.execute("DELETE FROM documents WHERE project IS ?", params![project])?;
+        tx.execute("DELETE FROM index_failures WHERE project IS ?", params![project])?;
+        tx.commit()?;
+        Ok(())
+    }
+} 
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// `snippet()` of a match found through the `stems` column, which FTS5 cannot mark in the text:
+/// the words of `text` around the first whose stem in `language` is among `terms`, marked
+/// between `KEYWORD_MATCH_START` and `KEYWORD_MATCH_END`
+pub fn stemmed_snippet(text: &str, language: Option<&str>, terms: &std::collections::HashSet<String>) -> String {
+    let words = crate::stemming::word_spans(text);
+    let matches = crate::stemming::Analyzer::for_language(language).find_terms(text, terms);
+    let first = matches.first().and_then(|m| words.iter().position(|word| word == m)).unwrap_or(0);
+    let from = first.saturating_sub(KEYWORD_SNIPPET_TOKENS / 4);
+    let to = (from + KEYWORD_SNIPPET_TOKENS).min(words.len());
+    let start = if from == 0 { 0 } else { words[from].0 };
+    let end = if to == words.len() { text.len() } else { words[to - 1].1 };
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut copied = start;
+    for &(word_start, word_end) in matches.iter().filter(|&&(word_start, word_end)| word_start >= start && word_end <= end) {
+        snippet.push_str(&text[copied..word_start]);
+        snippet.push_str(KEYWORD_MATCH_START);
+        snippet.push_str(&text[word_start..word_end]);
+        snippet.push_str(KEYWORD_MATCH_END);
+        copied = word_end;
+    }
+    snippet.push_str(&text[copied..end]);
+    if end < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Insert a document's parent chunks, then its chunks with their embeddings, returning the chunk
+/// ids. Each chunk's `parent_id` is an index into `parents`. The statements are prepared once and
+/// cached on the connection, rather than parsing the SQL again for every row.
+fn insert_chunks(tx: &rusqlite::Transaction, document_id: u32, parents: &[Chunk], chunks: &[Chunk], embeddings: &[Vec<f32>], model_version: &str) -> Result<Vec<u32>> {
+    let mut insert_chunk = tx.prepare_cached(
+        "INSERT INTO chunks (document_id, text, chunk_index, byte_start, byte_end, section, parent_id, line_start, line_end, language, stems) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )?;
+    let mut insert_embedding = tx.prepare_cached("INSERT INTO embeddings (chunk_id, vector, model_version) VALUES (?, ?, ?)")?;
+    
+    let mut parent_ids = Vec::with_capacity(parents.len());
+    for parent in parents {
+        let parent_id = insert_chunk.insert(params![document_id, parent.text, parent.chunk_index, parent.byte_range.map(|r| r.0), parent.byte_range.map(|r| r.1),
+            parent.section, None::<u32>, parent.line_range.map(|r| r.0), parent.line_range.map(|r| r.1), parent.language,
+            crate::stemming::index_terms(&parent.text, parent.language.as_deref())])?;
+        parent_ids.push(parent_id as u32);
+    }
+    
+    let mut chunk_ids = Vec::with_capacity(chunks.len());
+    
+    // Add chunks and embeddings
+    for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
+        let parent_id = chunk.parent_id.and_then(|index| parent_ids.get(index as usize));
+        let chunk_id = insert_chunk.insert(params![document_id, chunk.text, chunk.chunk_index, chunk.byte_range.map(|r| r.0), chunk.byte_range.map(|r| r.1),
+            chunk.section, parent_id, chunk.line_range.map(|r| r.0), chunk.line_range.map(|r| r.1), chunk.language,
+            crate::stemming::index_terms(&chunk.text, chunk.language.as_deref())])? as u32;
+        chunk_ids.push(chunk_id);
+        
+        // Add embedding
+        insert_embedding.execute(params![chunk_id, serde_json::to_string(embedding)?, model_version])?;
+    }
+    
+    Ok(chunk_ids)
+}
+
+/// Build a chunk from the first ten columns of a row: id, document_id, text, chunk_index, byte_start, byte_end,
+/// section, parent_id, line_start, line_end; and from the `language` column, which queries select last
+fn chunk_from_row(row: &rusqlite::Row) -> rusqlite::Result<Chunk> {
+    let byte_start: Option<usize> = row.get(4)?;
+    let byte_end: Option<usize> = row.get(5)?;
+    let line_start: Option<usize> = row.get(8)?;
+    let line_end: Option<usize> = row.get(9)?;
+    Ok(Chunk {
+        id: row.get(0)?,
+        document_id: row.get(1)?,
+        text: row.get(2)?,
+        chunk_index: row.get(3)?,
+        byte_range: byte_start.zip(byte_end),
+        line_range: line_start.zip(line_end),
+        section: row.get(6)?,
+        parent_id: row.get(7)?,
+        language: row.get("language")?,
+    })
+}
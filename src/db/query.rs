@@ -0,0 +1,70 @@
+// Read-only SQL over a restricted copy of the index metadata, for `db query`
+use anyhow::Result;
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+
+/// Tables the query can see, built from the index database. Embeddings, caches and internal
+/// bookkeeping are left out; tags are unpacked into rows.
+const RESTRICTED_SCHEMA: &str = "
+    CREATE TABLE documents AS
+        SELECT id, file_path AS path, project, size, chunk_count, indexed_at, summary,
+               COALESCE(json_extract(metadata, '$.tags'), '[]') AS tags
+        FROM src.documents;
+    CREATE TABLE chunks AS
+        SELECT c.id, c.document_id, c.chunk_index, c.text, length(c.text) AS length,
+               c.byte_start, c.byte_end, c.line_start, c.line_end, c.section, c.parent_id, e.model_version,
+               e.chunk_id IS NOT NULL AS embedded
+        FROM src.chunks c LEFT JOIN src.embeddings e ON e.chunk_id = c.id;
+    CREATE TABLE tags AS
+        SELECT d.id AS document_id, t.value AS tag
+        FROM src.documents d, json_each(COALESCE(json_extract(d.metadata, '$.tags'), '[]')) t;
+";
+
+/// Column names and rows of a query result, values as JSON
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Tables and columns available to `run_query`, as (table, columns)
+pub const TABLES: &[(&str, &str)] = &[
+    ("documents", "id, path, project, size, chunk_count, indexed_at, summary, tags (JSON array)"),
+    ("chunks", "id, document_id, chunk_index, text, length, byte_start, byte_end, line_start, line_end, section, parent_id, model_version, embedded"),
+    ("tags", "document_id, tag"),
+];
+
+/// Run a single read-only statement against the restricted tables of the database at `path`.
+/// The source file is attached read-only and detached before the query runs, so the statement
+/// can neither see nor change anything else.
+pub fn run_query(path: &str, sql: &str) -> Result<QueryResult> {
+    let conn = Connection::open_in_memory_with_flags(
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI,
+    )?;
+    conn.execute("ATTACH DATABASE ? AS src", [format!("file:{}?mode=ro", path)])?;
+    conn.execute_batch(RESTRICTED_SCHEMA)?;
+    conn.execute("DETACH DATABASE src", [])?;
+
+    let mut stmt = conn.prepare(sql)
+        .map_err(|e| anyhow::anyhow!("Invalid query: {}", e))?;
+    if !stmt.readonly() {
+        anyhow::bail!("Only read-only statements (SELECT, WITH ... SELECT) are allowed");
+    }
+
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = Vec::new();
+    let mut results = stmt.query([])?;
+    while let Some(row) = results.next()? {
+        let mut values = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            values.push(match row.get_ref(i)? {
+                ValueRef::Null => serde_json::Value::Null,
+                ValueRef::Integer(n) => serde_json::Value::from(n),
+                ValueRef::Real(x) => serde_json::Value::from(x),
+                ValueRef::Text(text) => serde_json::Value::from(String::from_utf8_lossy(text).into_owned()),
+                ValueRef::Blob(blob) => serde_json::Value::from(format!("<{} bytes>", blob.len())),
+            });
+        }
+        rows.push(values);
+    }
+
+    Ok(QueryResult { columns, rows })
+}
@@ -0,0 +1,133 @@
+// Reading and writing whole documents with their chunks and embeddings, for `replicate`
+use anyhow::Result;
+use rusqlite::{params, OptionalExtension};
+use std::collections::{BTreeMap, HashMap};
+use crate::core::types::{ReplicaChunk, ReplicaDocument};
+use super::{chunk_from_row, unix_now, Database};
+
+/// A document's revision: its content hash, metadata and the embedding model versions of its
+/// chunks, so re-tagging or re-embedding a document also ships it again
+const REVISION: &str = "d.file_hash || '|' || COALESCE(d.metadata, '') || '|' || COALESCE(
+        (SELECT group_concat(version, ',') FROM (
+            SELECT DISTINCT e.model_version AS version FROM embeddings e JOIN chunks c ON c.id = e.chunk_id
+            WHERE c.document_id = d.id ORDER BY version)), '')";
+
+impl Database {
+    /// Revision of each document in `project`, by path
+    pub fn document_revisions(&self, project: Option<&str>) -> Result<BTreeMap<String, String>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT d.file_path, {} FROM documents d WHERE d.project IS ?", REVISION))?;
+        let rows = stmt.query_map([project], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// A document of `project` with everything a mirror needs to serve it without re-indexing
+    pub fn export_document(&self, project: Option<&str>, file_path: &str) -> Result<Option<ReplicaDocument>> {
+        let document = self.conn.query_row(
+            &format!("SELECT d.id, d.file_hash, d.size, d.indexed_at, d.metadata, d.summary, {}
+                      FROM documents d WHERE d.project IS ? AND d.file_path = ?", REVISION),
+            params![project, file_path],
+            |row| Ok((
+                row.get::<_, u32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, usize>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, String>(6)?,
+            )),
+        ).optional()?;
+        let Some((document_id, file_hash, size, indexed_at, metadata, summary, revision)) = document else {
+            return Ok(None);
+        };
+
+        let title = self.conn.query_row(
+            "SELECT title, vector, model_version FROM document_embeddings WHERE document_id = ?",
+            [document_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)),
+        ).optional()?;
+        let (title, title_model_version) = match title {
+            Some((title, vector, version)) => (Some((title, serde_json::from_str(&vector)?)), version),
+            None => (None, None),
+        };
+
+        // Parents first, so each child's parent is already in the list
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.document_id, c.text, c.chunk_index, c.byte_start, c.byte_end, c.section, c.parent_id,
+                    c.line_start, c.line_end, e.vector, e.model_version, c.language
+             FROM chunks c LEFT JOIN embeddings e ON e.chunk_id = c.id
+             WHERE c.document_id = ? ORDER BY c.parent_id IS NOT NULL, c.id"
+        )?;
+        let rows = stmt.query_map([document_id], |row| {
+            Ok((chunk_from_row(row)?, row.get::<_, Option<String>>(10)?, row.get::<_, Option<String>>(11)?))
+        })?;
+        let mut chunks = Vec::new();
+        let mut positions = HashMap::new();
+        for row in rows {
+            let (mut chunk, vector, model_version) = row?;
+            positions.insert(chunk.id, chunks.len() as u32);
+            chunk.parent_id = chunk.parent_id.and_then(|id| positions.get(&id).copied());
+            let embedding = vector.map(|vector| serde_json::from_str(&vector)).transpose()?;
+            chunks.push(ReplicaChunk { chunk, embedding, model_version });
+        }
+
+        Ok(Some(ReplicaDocument {
+            file_path: file_path.to_string(),
+            file_hash,
+            size,
+            revision,
+            indexed_at,
+            metadata,
+            summary,
+            title,
+            title_model_version,
+            chunks,
+        }))
+    }
+
+    /// Store a replicated document in `project`, replacing any document at its path. Returns the
+    /// document id and the new ids of its chunks, in the order of `document.chunks`.
+    pub fn import_document(&mut self, document: &ReplicaDocument, project: Option<&str>) -> Result<(u32, Vec<u32>)> {
+        if let Some(existing) = self.get_document_id_by_path(project, &document.file_path)? {
+            self.delete_document(existing)?;
+        }
+
+        let tx = self.conn.transaction()?;
+        let embedded = document.chunks.iter().filter(|c| c.embedding.is_some()).count();
+        tx.execute(
+            "INSERT INTO documents (file_path, file_hash, size, chunk_count, project, indexed_at, metadata, summary) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![document.file_path, document.file_hash, document.size, embedded, project,
+                    document.indexed_at.unwrap_or_else(unix_now), document.metadata, document.summary]
+        )?;
+        let document_id = tx.last_insert_rowid() as u32;
+
+        let mut chunk_ids: Vec<u32> = Vec::with_capacity(document.chunks.len());
+        for ReplicaChunk { chunk, embedding, model_version } in &document.chunks {
+            let parent_id = chunk.parent_id.and_then(|index| chunk_ids.get(index as usize).copied());
+            tx.execute(
+                "INSERT INTO chunks (document_id, text, chunk_index, byte_start, byte_end, section, parent_id, line_start, line_end, language, stems) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![document_id, chunk.text, chunk.chunk_index, chunk.byte_range.map(|r| r.0), chunk.byte_range.map(|r| r.1), chunk.section, parent_id,
+                        chunk.line_range.map(|r| r.0), chunk.line_range.map(|r| r.1), chunk.language,
+                        crate::stemming::index_terms(&chunk.text, chunk.language.as_deref())]
+            )?;
+            let chunk_id = tx.last_insert_rowid() as u32;
+            chunk_ids.push(chunk_id);
+
+            if let Some(embedding) = embedding {
+                tx.execute(
+                    "INSERT INTO embeddings (chunk_id, vector, model_version) VALUES (?, ?, ?)",
+                    params![chunk_id, serde_json::to_string(embedding)?, model_version]
+                )?;
+            }
+        }
+
+        if let Some((title, vector)) = &document.title {
+            tx.execute(
+                "INSERT OR REPLACE INTO document_embeddings (document_id, title, vector, model_version) VALUES (?, ?, ?, ?)",
+                params![document_id, title, serde_json::to_string(vector)?, document.title_model_version]
+            )?;
+        }
+        tx.commit()?;
+        Ok((document_id, chunk_ids))
+    }
+}
@@ -0,0 +1,41 @@
+// The opt-in usage log written by `telemetry` and summarized by `stats --usage`
+use anyhow::Result;
+use rusqlite::params;
+use crate::core::types::CommandUsage;
+use super::{unix_now, Database};
+
+impl Database {
+    /// Log one command run, dropping entries older than `retention_days` (0 keeps them all)
+    pub fn record_usage(&self, command: &str, started_at: i64, duration_ms: u64, result_count: Option<usize>,
+                        succeeded: bool, retention_days: u64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO usage_log (command, started_at, duration_ms, result_count, succeeded) VALUES (?, ?, ?, ?, ?)",
+            params![command, started_at, duration_ms as i64, result_count.map(|n| n as i64), succeeded]
+        )?;
+        if retention_days > 0 {
+            self.conn.execute(
+                "DELETE FROM usage_log WHERE started_at < ?",
+                [unix_now() - retention_days as i64 * 86400]
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Usage of each logged command, most used first
+    pub fn get_usage_summary(&self) -> Result<Vec<CommandUsage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT command, COUNT(*), SUM(succeeded = 0), AVG(duration_ms), MAX(duration_ms), AVG(result_count), MAX(started_at)
+             FROM usage_log GROUP BY command ORDER BY COUNT(*) DESC, command"
+        )?;
+        let rows = stmt.query_map([], |row| Ok(CommandUsage {
+            command: row.get(0)?,
+            runs: row.get::<_, i64>(1)? as u64,
+            failures: row.get::<_, i64>(2)? as u64,
+            avg_duration_ms: row.get(3)?,
+            max_duration_ms: row.get::<_, i64>(4)? as u64,
+            avg_results: row.get(5)?,
+            last_used_at: row.get(6)?,
+        }))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
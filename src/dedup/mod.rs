@@ -0,0 +1,70 @@
+// Near-duplicate detection with SimHash fingerprints, used to keep repeated text out of results
+use crate::core::types::SearchResult;
+
+/// Words per shingle; short chunks fall back to single words
+const SHINGLE_WORDS: usize = 3;
+
+/// 64-bit SimHash of a text over lowercase word shingles. Texts sharing most of their shingles
+/// get fingerprints that differ in few bits, whatever their length.
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        return 0;
+    }
+
+    let mut weights = [0i32; 64];
+    let width = SHINGLE_WORDS.min(words.len());
+    for shingle in words.windows(width) {
+        let hash = fnv1a(&shingle.join(" "));
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights.iter().enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |fingerprint, (bit, _)| fingerprint | (1 << bit))
+}
+
+/// Fraction of matching bits between two fingerprints (1.0 = identical)
+pub fn similarity(a: u64, b: u64) -> f32 {
+    1.0 - (a ^ b).count_ones() as f32 / 64.0
+}
+
+pub fn is_near_duplicate(fingerprint: u64, seen: &[u64], threshold: f32) -> bool {
+    seen.iter().any(|&other| similarity(fingerprint, other) >= threshold)
+}
+
+/// Drop results whose text is near-identical to a better-ranked result, keeping the order.
+/// Metadata-only results have no text to compare and are kept.
+pub fn suppress_near_duplicates(results: Vec<SearchResult>, threshold: f32) -> Vec<SearchResult> {
+    let mut seen = Vec::with_capacity(results.len());
+    results.into_iter()
+        .filter(|result| {
+            if result.metadata_only {
+                return true;
+            }
+            let fingerprint = simhash(&result.chunk_text);
+            if is_near_duplicate(fingerprint, &seen, threshold) {
+                return false;
+            }
+            seen.push(fingerprint);
+            true
+        })
+        .collect()
+}
+
+/// FNV-1a: stable across runs and platforms, unlike the std hasher
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
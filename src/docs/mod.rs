@@ -0,0 +1,194 @@
+// Bulk edits to the metadata of indexed documents, kept in step with the remote vector store
+use anyhow::Result;
+use std::collections::HashMap;
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::types::{path_matches_glob, validate_glob, ChunkMetadata, DocumentMetadata};
+use crate::db::ChunkVectorRow;
+use crate::pinecone::{chunk_metadata, Vector, UPSERT_BATCH_SIZE};
+
+/// Changes applied to every document whose path matches `path`
+pub struct MetadataUpdate {
+    /// Glob over document paths (see `path_matches_glob`)
+    pub path: String,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    pub clear_tags: bool,
+    /// Move the documents to this project (`Some(None)` is the default project)
+    pub project: Option<Option<String>>,
+    pub dry_run: bool,
+}
+
+impl MetadataUpdate {
+    /// Tags added here are assigned, so they outlive the document being indexed again
+    fn apply(&self, metadata: &DocumentMetadata) -> DocumentMetadata {
+        let edit = |tags: &[String]| {
+            let mut tags = if self.clear_tags { Vec::new() } else { tags.to_vec() };
+            tags.retain(|tag| !self.remove_tags.contains(tag));
+            for tag in &self.add_tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+            tags
+        };
+        DocumentMetadata { tags: edit(&metadata.tags), assigned_tags: edit(&metadata.assigned_tags), ..metadata.clone() }
+    }
+}
+
+/// Outcome of a bulk metadata edit
+#[derive(Debug, Default)]
+pub struct MetadataReport {
+    /// Documents matching the pattern, with their tags after the edit
+    pub documents: Vec<(String, Vec<String>)>,
+    /// Documents whose tags or project actually changed
+    pub changed: usize,
+    /// Remote vectors rewritten with the new metadata
+    pub vectors_updated: usize,
+    pub dry_run: bool,
+}
+
+/// Update tags and project assignment of the current project's documents matching a glob.
+///
+/// Remote vectors are rewritten first (re-upserted from the stored embeddings, and moved to the
+/// new project's Pinecone namespace when the project changes) and the database only afterwards,
+/// so a failed upload leaves the documents as they were and the edit can simply be re-run.
+pub async fn set_metadata(app: &mut ChunkyMonkeyApp, update: &MetadataUpdate) -> Result<MetadataReport> {
+    validate_glob(&update.path)?;
+
+    let source_project = app.project.clone();
+    let target_project = update.project.clone().unwrap_or_else(|| source_project.clone());
+
+    let mut report = MetadataReport { dry_run: update.dry_run, ..Default::default() };
+    let mut changes = Vec::new();
+    for document in app.db.get_documents_by_project(source_project.as_deref())? {
+        if !path_matches_glob(&document.file_path, &update.path) {
+            continue;
+        }
+        let current = app.db.get_document_metadata(source_project.as_deref(), &document.file_path)?;
+        let updated = update.apply(&current);
+        report.documents.push((document.file_path.clone(), updated.tags.clone()));
+        if updated != current || target_project != source_project {
+            changes.push((document.id, document.file_path, updated));
+        }
+    }
+    report.changed = changes.len();
+
+    if update.dry_run || changes.is_empty() {
+        return Ok(report);
+    }
+
+    let rows: Vec<ChunkVectorRow> = app.db.get_project_chunk_vectors(source_project.as_deref())?;
+    report.vectors_updated = apply_changes(app, changes, rows, target_project).await?;
+    Ok(report)
+}
+
+/// Give the indexed document at `path` those of `tags` it lacks as assigned tags, locally and in
+/// the remote vector store. Returns whether it lacked any.
+pub async fn assign_tags(app: &mut ChunkyMonkeyApp, path: &str, tags: &[String]) -> Result<bool> {
+    let Some(document_id) = app.db.get_document_id_by_path(app.project.as_deref(), path)? else {
+        return Ok(false);
+    };
+    let current = app.db.get_document_metadata(app.project.as_deref(), path)?;
+    let update = MetadataUpdate {
+        path: path.to_string(),
+        add_tags: tags.to_vec(),
+        remove_tags: Vec::new(),
+        clear_tags: false,
+        project: None,
+        dry_run: false,
+    };
+    let updated = update.apply(&current);
+    if updated == current {
+        return Ok(false);
+    }
+    let rows = app.db.get_document_chunk_vectors(document_id)?;
+    let project = app.project.clone();
+    apply_changes(app, vec![(document_id, path.to_string(), updated)], rows, project).await?;
+    Ok(true)
+}
+
+/// Write new metadata (and project) of documents to the remote vector store, then the database
+/// and the in-memory index. `rows` may hold chunks of other documents too. Returns the number of
+/// remote vectors written.
+async fn apply_changes(app: &mut ChunkyMonkeyApp, changes: Vec<(u32, String, DocumentMetadata)>, rows: Vec<ChunkVectorRow>,
+                       target_project: Option<String>) -> Result<usize> {
+    let tags_by_path: HashMap<&str, &[String]> = changes.iter()
+        .map(|(_, path, metadata)| (path.as_str(), metadata.tags.as_slice()))
+        .collect();
+    let rows: Vec<ChunkVectorRow> = rows.into_iter()
+        .filter(|(_, path, _, embedding)| tags_by_path.contains_key(path.as_str()) && !embedding.is_empty())
+        .collect();
+    let vectors_updated = update_remote_metadata(app, &rows, &tags_by_path, target_project.as_deref()).await?;
+
+    let moved = target_project != app.project;
+    let updates: Vec<(u32, DocumentMetadata, Option<String>)> = changes.iter()
+        .map(|(document_id, _, metadata)| (*document_id, metadata.clone(), target_project.clone()))
+        .collect();
+    app.db.update_documents_metadata(&updates)?;
+    if moved {
+        app.reload_local_index()?;
+    } else {
+        // Only the tags changed: re-add the documents' chunks rather than reloading every vector
+        for (chunk, path, _, embedding) in &rows {
+            let metadata = ChunkMetadata::for_stored_chunk(path, target_project.as_deref(), tags_by_path[path.as_str()], &chunk.text, chunk.language.as_deref());
+            app.update_local_chunk(chunk.id, embedding, path, &chunk.text, metadata)?;
+        }
+    }
+    Ok(vectors_updated)
+}
+
+/// Rewrite the remote vectors of the given chunks with their new metadata. Returns the number written.
+async fn update_remote_metadata(
+    app: &mut ChunkyMonkeyApp,
+    rows: &[ChunkVectorRow],
+    tags_by_path: &HashMap<&str, &[String]>,
+    target_project: Option<&str>,
+) -> Result<usize> {
+    let payload = |(chunk, path, _, _): &ChunkVectorRow| {
+        let metadata = ChunkMetadata::for_stored_chunk(path, target_project, tags_by_path[path.as_str()], &chunk.text, chunk.language.as_deref());
+        chunk_metadata(path, &chunk.text, chunk.id, chunk.document_id, &metadata)
+    };
+    let vector_id = |(chunk, _, vector_id, _): &ChunkVectorRow| {
+        vector_id.clone().unwrap_or_else(|| format!("chunk_{}", chunk.id))
+    };
+
+    if let Some(ref qdrant) = app.qdrant_client {
+        // One collection for all projects: the project is only part of the payload
+        for batch in rows.chunks(UPSERT_BATCH_SIZE) {
+            let points = batch.iter()
+                .map(|row| crate::qdrant::Point {
+                    id: row.0.id as u64,
+                    vector: row.3.clone(),
+                    payload: payload(row),
+                })
+                .collect();
+            qdrant.upsert_points(points).await?;
+        }
+        return Ok(rows.len());
+    }
+
+    let source_namespace = app.project.clone();
+    let Some(ref mut pinecone) = app.pinecone_client else {
+        return Ok(0);
+    };
+
+    // Each project is its own namespace, so a move is an upsert there followed by a delete here
+    pinecone.set_namespace(target_project.map(|p| p.to_string()));
+    let mut result = Ok(());
+    for batch in rows.chunks(UPSERT_BATCH_SIZE) {
+        let vectors = batch.iter()
+            .map(|row| Vector { id: vector_id(row), values: row.3.clone(), metadata: payload(row) })
+            .collect();
+        result = pinecone.upsert_vectors(vectors).await;
+        if result.is_err() {
+            break;
+        }
+    }
+    pinecone.set_namespace(source_namespace.clone());
+    result?;
+
+    if target_project != source_namespace.as_deref() {
+        pinecone.delete_vectors(rows.iter().map(vector_id).collect()).await?;
+    }
+    Ok(rows.len())
+}
@@ -1,24 +1,27 @@
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::core::config::AppConfig;
-mod ollama;
+use std::sync::OnceLock;
+use crate::core::config::OllamaConfig;
+use crate::chaos::ChaosInjector;
+use crate::core::health;
+pub mod ollama;
+pub mod rate_limit;
 
 pub struct EmbeddingModel {
     dimension: usize,
     pub ollama_embeddings: Option<ollama::OllamaEmbeddings>,
+    /// Resolved Ollama model version, looked up once
+    version: OnceLock<String>,
 }
 
 impl EmbeddingModel {
-    pub fn new() -> Result<Self> {
-        // Try to load config to get the correct dimension
-        let config = AppConfig::load().unwrap_or_else(|_| AppConfig::default());
-        
-        // For now, use 768 dimensions to match Pinecone index
-        // In the future, this should be configurable based on the model
-        let dimension = 768;
+    pub fn from_config(config: OllamaConfig) -> Result<Self> {
+        // Dimension must match the configured model (and the Pinecone index)
+        let dimension = config.embedding_dimension;
         
         // Try to initialize Ollama embeddings (silently)
-        let ollama_embeddings = match ollama::OllamaEmbeddings::new_with_config(config.ollama) {
+        let ollama_embeddings = match ollama::OllamaEmbeddings::new_with_config(config) {
             Ok(emb) => Some(emb),
             Err(_) => None, // Silently fail
         };
@@ -26,9 +29,16 @@ impl EmbeddingModel {
         Ok(Self {
             dimension,
             ollama_embeddings,
+            version: OnceLock::new(),
         })
     }
 
+    pub fn set_chaos(&mut self, chaos: ChaosInjector) {
+        if let Some(ref mut ollama) = self.ollama_embeddings {
+            ollama.set_chaos(chaos);
+        }
+    }
+
     pub async fn embed_text(&self, text: &str) -> Result<Vec<f32>> {
         // Try Ollama first if available
         if let Some(ref ollama) = self.ollama_embeddings {
@@ -39,20 +49,25 @@ impl EmbeddingModel {
                         return Ok(embedding);
                     } else {
                         // Silently fall back to simple embedding
+                        health::record_error("ollama", format!("Embedding dimension mismatch: expected {}, got {}", self.dimension, embedding.len()));
                     }
                 }
-                Err(_) => {
+                Err(e) => {
                     // Silently fall back to simple embedding
+                    health::record_error("ollama", e);
                 }
             }
         }
         
         // Fallback to simple embedding generation
+        health::record(health::EMBEDDING_FALLBACK);
         let embedding = self.generate_simple_embedding(text);
         Ok(embedding)
     }
 
-    pub async fn embed_texts(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+    /// Embed texts, returning the vectors and the version of the model that produced them.
+    /// When Ollama is unavailable the fallback embeddings are tagged with the simple-embedding version.
+    pub async fn embed_texts_versioned(&self, texts: &[String]) -> Result<(Vec<Vec<f32>>, String)> {
         // Try Ollama first if available
         if let Some(ref ollama) = self.ollama_embeddings {
             let text_refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
@@ -61,22 +76,65 @@ impl EmbeddingModel {
                     // Check if all embeddings have correct dimensions
                     let all_correct = embeddings.iter().all(|emb| emb.len() == self.dimension);
                     if all_correct {
-                        return Ok(embeddings);
+                        return Ok((embeddings, self.version().await));
                     } else {
                         // Silently fall back to simple embeddings
+                        health::record_error("ollama", format!("Embedding dimension mismatch: expected {}", self.dimension));
                     }
                 }
-                Err(_) => {
+                Err(e) => {
                     // Silently fall back to simple embeddings
+                    health::record_error("ollama", e);
                 }
             }
         }
         
         // Fallback to simple embedding generation
+        health::record(health::EMBEDDING_FALLBACK);
         let mut embeddings = Vec::new();
         for text in texts {
             embeddings.push(self.generate_simple_embedding(text));
         }
+        Ok((embeddings, self.fallback_version()))
+    }
+
+    /// Identifies the model that embeddings are produced with, e.g. `ollama/nomic-embed-text@0a109f422b47`.
+    /// The digest changes when a model is updated in place, so vectors from different pulls can be told apart.
+    pub async fn version(&self) -> String {
+        let Some(ref ollama) = self.ollama_embeddings else {
+            return self.fallback_version();
+        };
+        if let Some(version) = self.version.get() {
+            return version.clone();
+        }
+        
+        match ollama.model_digest().await {
+            Ok(Some(digest)) => {
+                let digest = digest.trim_start_matches("sha256:");
+                let version = format!("ollama/{}@{}", ollama.model(), &digest[..digest.len().min(12)]);
+                self.version.get_or_init(|| version).clone()
+            }
+            // Don't cache a version without a digest; the server may just be unreachable right now
+            _ => format!("ollama/{}", ollama.model()),
+        }
+    }
+
+    fn fallback_version(&self) -> String {
+        format!("simple/{}", self.dimension)
+    }
+
+    /// Embed texts with the real model only, failing instead of falling back to simple embeddings
+    pub async fn embed_texts_strict(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let ollama = self.ollama_embeddings.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Ollama embeddings are not available"))?;
+        
+        let text_refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
+        let embeddings = ollama.embed_batch(text_refs).await?;
+        
+        if let Some(bad) = embeddings.iter().find(|emb| emb.len() != self.dimension) {
+            anyhow::bail!("Embedding dimension mismatch: expected {}, got {}", self.dimension, bad.len());
+        }
+        
         Ok(embeddings)
     }
 
@@ -140,6 +198,69 @@ impl EmbeddingModel {
     }
 }
 
+/// Whether a model version pins an exact model build; an Ollama version without a digest means the
+/// installed model couldn't be looked up, so it can't be compared against stored versions
+pub fn is_resolved_version(version: &str) -> bool {
+    !version.starts_with("ollama/") || version.contains('@')
+}
+
+/// How vectors are compared; must match the metric the remote index or collection was built with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    #[default]
+    Cosine,
+    #[serde(alias = "dot")]
+    DotProduct,
+    #[serde(alias = "l2")]
+    Euclidean,
+}
+
+impl DistanceMetric {
+    /// Score two vectors so that higher is more similar. Euclidean distance is mapped into (0, 1].
+    pub fn similarity(self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            DistanceMetric::Cosine => cosine_similarity(a, b),
+            DistanceMetric::DotProduct => dot_product(a, b),
+            DistanceMetric::Euclidean => 1.0 / (1.0 + l2_distance(a, b)),
+        }
+    }
+
+    /// Metric name used when creating a Pinecone index
+    pub fn pinecone_name(self) -> &'static str {
+        match self {
+            DistanceMetric::Cosine => "cosine",
+            DistanceMetric::DotProduct => "dotproduct",
+            DistanceMetric::Euclidean => "euclidean",
+        }
+    }
+
+    /// Distance name used when creating a Qdrant collection
+    pub fn qdrant_name(self) -> &'static str {
+        match self {
+            DistanceMetric::Cosine => "Cosine",
+            DistanceMetric::DotProduct => "Dot",
+            DistanceMetric::Euclidean => "Euclid",
+        }
+    }
+
+    /// Bring a Pinecone match score onto the scale of `similarity` (Pinecone reports squared L2 distance)
+    pub fn normalize_pinecone_score(self, score: f32) -> f32 {
+        match self {
+            DistanceMetric::Euclidean => 1.0 / (1.0 + score.max(0.0).sqrt()),
+            _ => score,
+        }
+    }
+
+    /// Bring a Qdrant point score onto the scale of `similarity` (Qdrant reports L2 distance)
+    pub fn normalize_qdrant_score(self, score: f32) -> f32 {
+        match self {
+            DistanceMetric::Euclidean => 1.0 / (1.0 + score.max(0.0)),
+            _ => score,
+        }
+    }
+}
+
 // Vector similarity functions
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
@@ -157,6 +278,14 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     }
 }
 
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
 pub fn l2_distance(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
         return f32::INFINITY;
@@ -2,7 +2,18 @@ use anyhow::Result;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 use crate::core::config::OllamaConfig;
+use crate::chaos::{ChaosInjector, ChaosTarget};
+use crate::core::debug::SendLogged;
+use super::rate_limit::RateLimiter;
+
+/// Times a request turned away with 429 Too Many Requests is sent again
+const MAX_RATE_LIMITED_RETRIES: u32 = 3;
+
+/// Wait before resending a rate-limited request whose response has no Retry-After
+const RATE_LIMITED_WAIT: Duration = Duration::from_secs(20);
 
 #[derive(Debug, Serialize)]
 struct EmbeddingRequest {
@@ -15,10 +26,24 @@ struct EmbeddingResponse {
     embedding: Vec<f32>,
 }
 
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<LocalModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocalModel {
+    name: String,
+    digest: String,
+}
+
 pub struct OllamaEmbeddings {
     client: Client,
     base_url: String,
     model: String,
+    chaos: ChaosInjector,
+    /// Keeps embedding requests under `ollama.rate_limit`
+    limiter: Option<Arc<RateLimiter>>,
 }
 
 impl OllamaEmbeddings {
@@ -30,6 +55,8 @@ impl OllamaEmbeddings {
             client: Client::new(),
             base_url,
             model,
+            chaos: ChaosInjector::default(),
+            limiter: None,
         })
     }
 
@@ -46,24 +73,88 @@ impl OllamaEmbeddings {
             config.model
         };
         
+        let limiter = super::rate_limit::shared(&base_url, &config.rate_limit);
         Ok(Self {
             client: Client::new(),
             base_url,
             model,
+            chaos: ChaosInjector::default(),
+            limiter,
         })
     }
 
+    pub fn set_chaos(&mut self, chaos: ChaosInjector) {
+        self.chaos = chaos;
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Digest of the locally installed model, which changes whenever the model is re-pulled
+    pub async fn model_digest(&self) -> Result<Option<String>> {
+        let response = self.client
+            .get(format!("{}/api/tags", self.base_url))
+            .send_logged("ollama")
+            .await?;
+        
+        if !response.status().is_success() {
+            anyhow::bail!("Ollama API request failed: {}", response.status());
+        }
+        
+        // Ollama reports untagged models as `<name>:latest`
+        let tags: TagsResponse = response.json().await?;
+        let tagged = format!("{}:latest", self.model);
+        Ok(tags.models.into_iter()
+            .find(|m| m.name == self.model || m.name == tagged)
+            .map(|m| m.digest))
+    }
+
+    /// Names of the models installed on the Ollama server
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self.client
+            .get(format!("{}/api/tags", self.base_url))
+            .timeout(std::time::Duration::from_secs(5))
+            .send_logged("ollama")
+            .await?;
+        
+        if !response.status().is_success() {
+            anyhow::bail!("Ollama API request failed: {}", response.status());
+        }
+        
+        let tags: TagsResponse = response.json().await?;
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+
     pub async fn embed_text(&self, text: &str) -> Result<Vec<f32>> {
+        self.chaos.inject(ChaosTarget::Embedding).await?;
+        
         let request = EmbeddingRequest {
             model: self.model.clone(),
             prompt: text.to_string(),
         };
 
-        let response = self.client
-            .post(&format!("{}/api/embeddings", self.base_url))
-            .json(&request)
-            .send()
-            .await?;
+        let mut retries = 0;
+        let response = loop {
+            if let Some(ref limiter) = self.limiter {
+                limiter.acquire(crate::chunking::estimate_tokens(text)).await;
+            }
+            let response = self.client
+                .post(&format!("{}/api/embeddings", self.base_url))
+                .json(&request)
+                .send_logged("ollama")
+                .await?;
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || retries == MAX_RATE_LIMITED_RETRIES {
+                break response;
+            }
+            // Over the endpoint's quota despite the limiter (or without one): wait as asked and resend
+            let wait = response.headers().get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map_or(RATE_LIMITED_WAIT, Duration::from_secs);
+            retries += 1;
+            super::rate_limit::wait_for_quota(wait).await;
+        };
 
         if response.status().is_success() {
             let embedding_response: EmbeddingResponse = response.json().await?;
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use crate::core::debug::SendLogged;
 
 #[derive(Debug, Serialize)]
 struct EmbeddingRequest {
@@ -52,7 +53,7 @@ impl OpenAIEmbeddings {
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
             .json(&request)
-            .send()
+            .send_logged("openai")
             .await?;
 
         if !response.status().is_success() {
@@ -87,7 +88,7 @@ impl OpenAIEmbeddings {
                 .header("Authorization", format!("Bearer {}", self.api_key))
                 .header("Content-Type", "application/json")
                 .json(&request)
-                .send()
+                .send_logged("openai")
                 .await?;
 
             if !response.status().is_success() {
@@ -0,0 +1,151 @@
+// Client-side rate limiting of embedding requests, so indexing a large corpus against a hosted
+// endpoint stays under its requests-per-minute and tokens-per-minute quotas instead of failing
+// halfway through
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Nanoseconds spent waiting for quota so far, by limiters and on 429 responses
+static WAITED_NANOS: AtomicU64 = AtomicU64::new(0);
+/// Requests waiting for quota right now
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
+/// Total time requests have waited for quota, so timeouts on the work around them can leave it out
+pub fn time_waited() -> Duration {
+    Duration::from_nanos(WAITED_NANOS.load(Ordering::Relaxed))
+}
+
+/// Whether a request is waiting for quota now; its wait only counts in `time_waited` once it ends
+pub fn is_waiting() -> bool {
+    WAITING.load(Ordering::Relaxed) > 0
+}
+
+/// A wait for quota, counted in `time_waited` when it ends or is cancelled
+struct Wait(Instant);
+
+impl Wait {
+    fn start() -> Self {
+        WAITING.fetch_add(1, Ordering::Relaxed);
+        Self(Instant::now())
+    }
+}
+
+impl Drop for Wait {
+    fn drop(&mut self) {
+        WAITED_NANOS.fetch_add(self.0.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        WAITING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Sleep for `duration` because an endpoint asked to be left alone, counted in `time_waited`
+pub async fn wait_for_quota(duration: Duration) {
+    let _wait = Wait::start();
+    tokio::time::sleep(duration).await;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Embedding requests per minute; 0 is unlimited
+    pub requests_per_minute: u32,
+    /// Input tokens per minute, estimated as chunking counts them; 0 is unlimited
+    pub tokens_per_minute: u32,
+}
+
+impl RateLimitConfig {
+    pub fn is_unlimited(&self) -> bool {
+        self.requests_per_minute == 0 && self.tokens_per_minute == 0
+    }
+}
+
+/// A token bucket holding up to a minute's quota, refilled continuously
+struct Bucket {
+    capacity: f64,
+    available: f64,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Option<Self> {
+        (per_minute > 0).then_some(Self { capacity: per_minute as f64, available: per_minute as f64 })
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.available = (self.available + elapsed.as_secs_f64() * self.capacity / 60.0).min(self.capacity);
+    }
+
+    /// Time until `amount` is available. More than the bucket holds waits for a full bucket.
+    fn wait_for(&self, amount: f64) -> Duration {
+        let missing = amount.min(self.capacity) - self.available;
+        Duration::from_secs_f64(missing.max(0.0) * 60.0 / self.capacity)
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.available -= amount.min(self.capacity);
+    }
+}
+
+struct Buckets {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    refilled_at: Instant,
+}
+
+pub struct RateLimiter {
+    /// Held across the wait, so requests go out in the order they asked
+    buckets: tokio::sync::Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            buckets: tokio::sync::Mutex::new(Buckets {
+                requests: Bucket::new(config.requests_per_minute),
+                tokens: Bucket::new(config.tokens_per_minute),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until one more request of about `tokens` tokens fits the quotas, and count it. The
+    /// wait, including the time spent queued behind other requests, goes into `time_waited`.
+    pub async fn acquire(&self, tokens: usize) {
+        let _wait = Wait::start();
+        let mut guard = self.buckets.lock().await;
+        let buckets = &mut *guard;
+        loop {
+            let elapsed = buckets.refilled_at.elapsed();
+            buckets.refilled_at = Instant::now();
+            for bucket in [&mut buckets.requests, &mut buckets.tokens].into_iter().flatten() {
+                bucket.refill(elapsed);
+            }
+
+            let wait = [(&buckets.requests, 1.0), (&buckets.tokens, tokens as f64)].into_iter()
+                .filter_map(|(bucket, amount)| bucket.as_ref().map(|bucket| bucket.wait_for(amount)))
+                .max()
+                .unwrap_or_default();
+            if wait.is_zero() {
+                break;
+            }
+            tokio::time::sleep(wait).await;
+        }
+        if let Some(ref mut requests) = buckets.requests {
+            requests.take(1.0);
+        }
+        if let Some(ref mut bucket) = buckets.tokens {
+            bucket.take(tokens as f64);
+        }
+    }
+}
+
+/// The limiter for requests to `endpoint`, shared by every model that embeds through it since
+/// quotas are per endpoint; None when `config` sets no limit
+pub fn shared(endpoint: &str, config: &RateLimitConfig) -> Option<Arc<RateLimiter>> {
+    static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+    if config.is_unlimited() {
+        return None;
+    }
+    let mut limiters = LIMITERS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    Some(limiters.entry(endpoint.to_string()).or_insert_with(|| Arc::new(RateLimiter::new(config))).clone())
+}
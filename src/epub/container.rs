@@ -0,0 +1,35 @@
+// The ZIP container of an EPUB, read entry by entry with a cap on how far each inflates
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Largest entry read when inflated, which keeps a zip bomb from exhausting memory; books are
+/// split into many small documents, so no real chapter comes near it
+const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+
+pub struct Container {
+    archive: ZipArchive<File>,
+}
+
+impl Container {
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self { archive: ZipArchive::new(File::open(path)?)? })
+    }
+
+    /// Text of the entry called `name`
+    pub fn read_string(&mut self, name: &str) -> Result<String> {
+        let entry = self.archive.by_name(name).with_context(|| format!("{} is missing from the archive", name))?;
+        // The size in the header may lie, so reading stops just past the cap either way
+        if entry.size() > MAX_ENTRY_SIZE {
+            anyhow::bail!("{} inflates to {} bytes, more than the {} read from an EPUB entry", name, entry.size(), MAX_ENTRY_SIZE);
+        }
+        let mut bytes = Vec::new();
+        entry.take(MAX_ENTRY_SIZE + 1).read_to_end(&mut bytes).with_context(|| format!("corrupt zip entry {}", name))?;
+        if bytes.len() as u64 > MAX_ENTRY_SIZE {
+            anyhow::bail!("{} inflates to more than the {} bytes read from an EPUB entry", name, MAX_ENTRY_SIZE);
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
@@ -0,0 +1,188 @@
+// EPUB books: the XHTML documents of the spine are read in reading order and become chapters,
+// titled from the book's table of contents, so each chunk knows the chapter it came from
+mod container;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use container::Container;
+
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+pub struct Chapter {
+    pub title: String,
+    pub text: String,
+}
+
+pub struct Book {
+    pub title: Option<String>,
+    pub chapters: Vec<Chapter>,
+}
+
+impl Book {
+    /// The chapters' text as one document, chapters separated by a blank line
+    pub fn text(&self) -> String {
+        self.chapters.iter().map(|chapter| chapter.text.as_str()).collect::<Vec<_>>().join("\n\n")
+    }
+}
+
+pub fn is_epub(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("epub"))
+}
+
+/// Read the book at `path`. Spine documents without text (covers, blank pages) are skipped.
+pub fn read(path: &Path) -> Result<Book> {
+    let mut archive = Container::open(path).with_context(|| format!("{} is not an EPUB file", path.display()))?;
+    let container = archive.read_string(CONTAINER_PATH)?;
+    let package_path = attribute_values(&container, "rootfile", "full-path").into_iter().next()
+        .context("EPUB container names no package document")?;
+    let package = archive.read_string(&package_path)?;
+    let package_dir = parent(&package_path);
+
+    let title = Regex::new(r"(?is)<dc:title\b[^>]*>(.*?)</dc:title>").unwrap()
+        .captures(&package)
+        .map(|captures| inline_text(&captures[1]))
+        .filter(|title| !title.is_empty());
+
+    // Manifest id -> (path in the archive, media type, properties)
+    let mut manifest = HashMap::new();
+    for item in elements(&package, "item") {
+        let attributes = attributes(item);
+        if let (Some(id), Some(href)) = (attributes.get("id"), attributes.get("href")) {
+            let properties = attributes.get("properties").cloned().unwrap_or_default();
+            let media_type = attributes.get("media-type").cloned().unwrap_or_default();
+            manifest.insert(id.clone(), (resolve(package_dir, href), media_type, properties));
+        }
+    }
+
+    let toc = table_of_contents(&mut archive, &package, &manifest);
+
+    let mut chapters = Vec::new();
+    for itemref in elements(&package, "itemref") {
+        let Some((path, media_type, _)) = attributes(itemref).get("idref").and_then(|id| manifest.get(id)) else {
+            continue;
+        };
+        if !media_type.contains("html") {
+            continue;
+        }
+        let page = crate::web::html_to_text(&archive.read_string(path)?);
+        if page.text.is_empty() {
+            continue;
+        }
+        let heading = page.text.lines().next()
+            .filter(|line| line.starts_with('#'))
+            .map(|line| line.trim_start_matches('#').trim().to_string());
+        let chapter_title = toc.get(path).cloned()
+            .or(heading)
+            .or(page.title.filter(|page_title| Some(page_title) != title.as_ref()))
+            .unwrap_or_else(|| format!("Chapter {}", chapters.len() + 1));
+        chapters.push(Chapter { title: chapter_title, text: page.text });
+    }
+    if chapters.is_empty() {
+        anyhow::bail!("EPUB has no readable chapters");
+    }
+    Ok(Book { title, chapters })
+}
+
+/// Chapter titles by document path, from the EPUB 3 navigation document or the EPUB 2 NCX. The
+/// first entry pointing into a document names it.
+fn table_of_contents(archive: &mut Container, package: &str, manifest: &HashMap<String, (String, String, String)>) -> HashMap<String, String> {
+    let mut titles = HashMap::new();
+    let nav = manifest.values().find(|(_, _, properties)| properties.split_whitespace().any(|p| p == "nav"));
+    if let Some((nav_path, _, _)) = nav {
+        if let Ok(document) = archive.read_string(nav_path) {
+            let links = Regex::new(r#"(?is)<a\b[^>]*\bhref\s*=\s*["']([^"']*)["'][^>]*>(.*?)</a>"#).unwrap();
+            for captures in links.captures_iter(&document) {
+                add_entry(&mut titles, parent(nav_path), &captures[1], &captures[2]);
+            }
+        }
+    }
+    if titles.is_empty() {
+        let ncx_id = attribute_values(package, "spine", "toc").into_iter().next();
+        let ncx = ncx_id.and_then(|id| manifest.get(&id))
+            .or_else(|| manifest.values().find(|(_, media_type, _)| media_type == "application/x-dtbncx+xml"));
+        if let Some((ncx_path, _, _)) = ncx {
+            if let Ok(document) = archive.read_string(ncx_path) {
+                let points = Regex::new(r#"(?is)<navLabel\b[^>]*>\s*<text\b[^>]*>(.*?)</text>.*?<content\b[^>]*\bsrc\s*=\s*["']([^"']*)["']"#).unwrap();
+                for captures in points.captures_iter(&document) {
+                    add_entry(&mut titles, parent(ncx_path), &captures[2], &captures[1]);
+                }
+            }
+        }
+    }
+    titles
+}
+
+fn add_entry(titles: &mut HashMap<String, String>, base: &str, href: &str, label: &str) {
+    let href = href.split('#').next().unwrap_or_default();
+    let label = inline_text(label);
+    if !href.is_empty() && !label.is_empty() {
+        titles.entry(resolve(base, href)).or_insert(label);
+    }
+}
+
+/// Start tags of `name` elements
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    Regex::new(&format!(r"(?is)<(?:\w+:)?{}\b[^>]*>", regex::escape(name))).unwrap()
+        .find_iter(xml)
+        .map(|m| m.as_str())
+        .collect()
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    Regex::new(r#"([\w:.-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap()
+        .captures_iter(tag)
+        .map(|captures| {
+            let value = captures.get(2).or(captures.get(3)).map(|m| m.as_str()).unwrap_or_default();
+            (captures[1].to_string(), inline_text(value))
+        })
+        .collect()
+}
+
+fn attribute_values(xml: &str, element: &str, attribute: &str) -> Vec<String> {
+    elements(xml, element).into_iter().filter_map(|tag| attributes(tag).remove(attribute)).collect()
+}
+
+/// Plain text of a short markup fragment such as a title or an attribute value
+fn inline_text(markup: &str) -> String {
+    crate::web::html_to_text(markup).text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+/// Archive path of `href`, a URL relative to the document in `base`
+fn resolve(base: &str, href: &str) -> String {
+    let mut parts: Vec<String> = base.split('/').filter(|part| !part.is_empty()).map(str::to_string).collect();
+    for part in percent_decode(href).split('/') {
+        match part {
+            "" | "." => {}
+            ".." => { parts.pop(); }
+            _ => parts.push(part.to_string()),
+        }
+    }
+    parts.join("/")
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%').then(|| text.get(i + 1..i + 3)).flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
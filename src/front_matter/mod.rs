@@ -0,0 +1,154 @@
+// Front matter of markdown files: the YAML (`---`) or TOML (`+++`) block at the top that static
+// site generators and note apps use for a document's title, tags, date and author
+use crate::core::types::DocumentMetadata;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub date: Option<String>,
+    pub author: Option<String>,
+}
+
+impl FrontMatter {
+    /// Document metadata holding these fields
+    pub fn into_metadata(self) -> DocumentMetadata {
+        DocumentMetadata { tags: self.tags, title: self.title, date: self.date, author: self.author, ..Default::default() }
+    }
+}
+
+/// The front matter opening `text`, or None without one. Only the fields ChunkyMonkey uses are
+/// read, so a block it cannot otherwise make sense of still yields them; `authors` stands in for
+/// `author` and a string of tags is split at commas and spaces.
+pub fn parse(text: &str) -> Option<FrontMatter> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    match delimited(text)? {
+        ("---", block, _) => parse_yaml(block),
+        (_, block, _) => parse_toml(block),
+    }
+}
+
+/// Byte offset in `text` just past its front matter, where the document's own text starts; 0
+/// without front matter
+pub fn body_start(text: &str) -> usize {
+    let bom = if text.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+    delimited(&text[bom..]).map_or(0, |(_, _, end)| bom + end)
+}
+
+/// The opening delimiter of the front matter at the start of `text`, the lines between it and
+/// the first closing one, and the offset just past the closing line
+fn delimited(text: &str) -> Option<(&str, &str, usize)> {
+    let opening = text.lines().next()?.trim_end();
+    let closing: &[&str] = match opening {
+        "---" => &["---", "..."],
+        "+++" => &["+++"],
+        _ => return None,
+    };
+    let block_start = text.find('\n')? + 1;
+    let mut offset = block_start;
+    for line in text[block_start..].split_inclusive('\n') {
+        if closing.contains(&line.trim_end()) {
+            return Some((opening, &text[block_start..offset], offset + line.len()));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The subset of YAML front matter uses: `key: value`, `key: [a, b]`, and `key:` followed by
+/// `- item` lines
+fn parse_yaml(block: &str) -> Option<FrontMatter> {
+    let mut front_matter = FrontMatter::default();
+    let mut list_key: Option<String> = None;
+    for line in block.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let (Some(key), Some(item)) = (&list_key, trimmed.strip_prefix("- ")) {
+            let item = unquote(item);
+            match key.as_str() {
+                "tags" => front_matter.tags.push(item),
+                "author" | "authors" => front_matter.author = Some(join(front_matter.author.take(), item)),
+                _ => {}
+            }
+            continue;
+        }
+        // Nested keys belong to some other field
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once(':') else {
+            list_key = None;
+            continue;
+        };
+        let (key, value) = (key.trim().to_lowercase(), value.trim());
+        list_key = value.is_empty().then(|| key.clone());
+        if value.is_empty() {
+            continue;
+        }
+
+        match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(items) => apply(&mut front_matter, &key, items.split(',').map(unquote).filter(|item| !item.is_empty()).collect(), true),
+            None => apply(&mut front_matter, &key, vec![unquote(value)], false),
+        }
+    }
+    Some(front_matter)
+}
+
+fn parse_toml(block: &str) -> Option<FrontMatter> {
+    let table: toml::Table = toml::from_str(block).ok()?;
+    let mut front_matter = FrontMatter::default();
+    for (key, value) in table {
+        match value {
+            toml::Value::Array(items) => apply(&mut front_matter, &key.to_lowercase(), items.into_iter().filter_map(toml_text).collect(), true),
+            value => apply(&mut front_matter, &key.to_lowercase(), toml_text(value).into_iter().collect(), false),
+        }
+    }
+    Some(front_matter)
+}
+
+/// A TOML scalar as text; dates as written ("2024-03-01")
+fn toml_text(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(text) => Some(text),
+        toml::Value::Datetime(date) => Some(date.to_string()),
+        toml::Value::Array(_) | toml::Value::Table(_) => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Set the field `key` names from its values, `listed` when they were written as a list
+fn apply(front_matter: &mut FrontMatter, key: &str, values: Vec<String>, listed: bool) {
+    match key {
+        "title" => front_matter.title = values.into_iter().next().filter(|title| !title.is_empty()),
+        "date" => front_matter.date = values.into_iter().next().filter(|date| !date.is_empty()),
+        "author" | "authors" => front_matter.author = values.into_iter().reduce(|all, name| join(Some(all), name)),
+        "tags" if listed => front_matter.tags.extend(values),
+        "tags" => {
+            for value in values {
+                front_matter.tags.extend(value.split([',', ' ']).map(str::trim).filter(|tag| !tag.is_empty()).map(str::to_string));
+            }
+        }
+        _ => {}
+    }
+    front_matter.tags.dedup();
+}
+
+fn join(names: Option<String>, name: String) -> String {
+    match names {
+        Some(names) => format!("{}, {}", names, name),
+        None => name,
+    }
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let unquoted = value.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')));
+    match unquoted {
+        Some(inner) => inner.to_string(),
+        // A comment after an unquoted value
+        None => value.split(" #").next().unwrap_or(value).trim().to_string(),
+    }
+}
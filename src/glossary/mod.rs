@@ -0,0 +1,91 @@
+// Project glossary: defined terms are footnoted where an answer uses them, and discouraged
+// variants of a term ("avoid" words) are flagged, so answers use the documentation's vocabulary
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use crate::core::types::{GlossaryEntry, GlossaryUse, TermMisuse};
+
+/// Defined terms used in `answer`, in order of first use, and the discouraged variants it uses
+pub fn check(answer: &str, entries: &[GlossaryEntry]) -> Result<(Vec<GlossaryUse>, Vec<TermMisuse>)> {
+    let mut uses: Vec<GlossaryUse> = Vec::new();
+    // Every occurrence of a defined term, so words inside one ("fake" in "fake answer") aren't flagged
+    let mut defined = Vec::new();
+    // Longest terms first, so a term nested in a longer one ("Cloud" in "Google Cloud Storage")
+    // is only footnoted where it stands on its own
+    let mut by_length: Vec<&GlossaryEntry> = entries.iter().collect();
+    by_length.sort_by_key(|entry| std::cmp::Reverse(entry.term.len()));
+    for entry in by_length {
+        let occurrences = find_words(&word_pattern(&entry.term)?, answer);
+        let overlaps_use = |&&(start, end): &&(usize, usize)| uses.iter().any(|used| start < used.span.1 && used.span.0 < end);
+        if let Some(&span) = occurrences.iter().find(|span| !overlaps_use(span)) {
+            uses.push(GlossaryUse {
+                term: entry.term.clone(),
+                definition: entry.definition.clone(),
+                span,
+            });
+        }
+        defined.extend(occurrences);
+    }
+
+    let mut misuses = Vec::new();
+    for entry in entries {
+        for avoided in &entry.avoid {
+            let within_term = |start: usize, end: usize| defined.iter().any(|&(s, e)| s <= start && end <= e);
+            if let Some((start, end)) = find_words(&word_pattern(avoided)?, answer).into_iter().find(|&(start, end)| !within_term(start, end)) {
+                misuses.push(TermMisuse {
+                    used: answer[start..end].to_string(),
+                    preferred: entry.term.clone(),
+                    span: (start, end),
+                });
+            }
+        }
+    }
+    uses.sort_by_key(|term| term.span.0);
+    misuses.sort_by_key(|misuse| misuse.span.0);
+    Ok((uses, misuses))
+}
+
+/// Case-insensitive match of `term`, with any run of whitespace between its words
+fn word_pattern(term: &str) -> Result<Regex> {
+    let words: Vec<String> = term.split_whitespace().map(regex::escape).collect();
+    Ok(RegexBuilder::new(&words.join(r"\s+"))
+        .case_insensitive(true)
+        .build()?)
+}
+
+/// Spans of `pattern` in `text` with no word character right before or after them. Unlike `\b`,
+/// this also finds terms starting or ending with punctuation ("C++", ".NET").
+fn find_words(pattern: &Regex, text: &str) -> Vec<(usize, usize)> {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let mut spans = Vec::new();
+    let mut from = 0;
+    while let Some(found) = pattern.find_at(text, from) {
+        if !is_word(text[..found.start()].chars().next_back()) && !is_word(text[found.end()..].chars().next()) {
+            spans.push((found.start(), found.end()));
+            from = found.end().max(found.start() + 1);
+        } else {
+            // Retry from the next character, where a shorter or later match may stand on its own
+            from = found.start() + text[found.start()..].chars().next().map_or(1, char::len_utf8);
+        }
+        if from > text.len() {
+            break;
+        }
+    }
+    spans
+}
+
+/// Insert a Markdown footnote reference after the first use of each term, numbered in order
+pub fn footnote_markdown(answer: &str, uses: &[GlossaryUse]) -> String {
+    let mut output = String::with_capacity(answer.len());
+    let mut copied = 0;
+    for (i, term) in uses.iter().enumerate() {
+        // A span overlapping one already footnoted has no place of its own for the reference
+        if term.span.0 < copied {
+            continue;
+        }
+        output.push_str(&answer[copied..term.span.1]);
+        output.push_str(&format!("[^{}]", i + 1));
+        copied = term.span.1;
+    }
+    output.push_str(&answer[copied..]);
+    output
+}
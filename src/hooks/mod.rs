@@ -0,0 +1,69 @@
+// Pre-processing hooks: an external command per file extension (pandoc, a vendor's converter)
+// whose stdout is indexed as the file's text, for formats ChunkyMonkey cannot read itself
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// Argument replaced by the path of the file to convert
+const PATH_PLACEHOLDER: &str = "{path}";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Command and arguments by file extension (without the dot); `{path}` stands for the file,
+    /// which is otherwise passed as the last argument. Takes precedence over built-in readers.
+    pub converters: BTreeMap<String, Vec<String>>,
+    /// A converter still running after this many seconds is killed and the file fails
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            converters: BTreeMap::new(),
+            timeout_secs: 120,
+        }
+    }
+}
+
+impl HooksConfig {
+    /// The converter configured for the extension of the file at `path`
+    pub fn converter_for(&self, path: &Path) -> Option<&[String]> {
+        let extension = path.extension()?.to_str()?;
+        self.converters.iter()
+            .find(|(configured, _)| configured.trim_start_matches('.').eq_ignore_ascii_case(extension))
+            .map(|(_, command)| command.as_slice())
+    }
+}
+
+/// Run `command` on the file at `path` and return what it writes to stdout. Fails when the
+/// command cannot be started, exits unsuccessfully or outlasts `timeout`.
+pub async fn convert(command: &[String], path: &Path, timeout: Duration) -> Result<Vec<u8>> {
+    let Some((program, args)) = command.split_first() else {
+        anyhow::bail!("The converter for {} has no command (hooks.converters)", path.display());
+    };
+    let mut process = tokio::process::Command::new(program);
+    if args.iter().any(|arg| arg.contains(PATH_PLACEHOLDER)) {
+        let path = path.to_string_lossy();
+        process.args(args.iter().map(|arg| arg.replace(PATH_PLACEHOLDER, &path)));
+    } else {
+        process.args(args).arg(path);
+    }
+    // Dropped on timeout or cancellation, which kills the converter
+    let child = process.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Cannot run converter '{}' for {}: {}", program, path.display(), e))?;
+
+    let output = tokio::time::timeout(timeout, child.wait_with_output()).await
+        .map_err(|_| anyhow::anyhow!("Converter '{}' took longer than {}s on {} (hooks.timeout_secs)", program, timeout.as_secs(), path.display()))??;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().find(|line| !line.trim().is_empty()).unwrap_or("no error output");
+        anyhow::bail!("Converter '{}' failed on {} ({}): {}", program, path.display(), output.status, reason.trim());
+    }
+    Ok(output.stdout)
+}
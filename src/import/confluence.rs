@@ -0,0 +1,189 @@
+// Confluence space exports. The HTML export is a directory of pages, each with the breadcrumbs of
+// its ancestors; the XML export's entities.xml holds pages, their bodies and spaces as objects
+// that refer to each other by id.
+use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use walkdir::WalkDir;
+use super::ImportedPage;
+use crate::web::html_to_text;
+
+const ENTITIES_FILE: &str = "entities.xml";
+
+/// Deepest page tree followed up from a page, guarding against parent cycles
+const MAX_DEPTH: usize = 64;
+
+pub fn read_export(path: &Path) -> Result<Vec<ImportedPage>> {
+    let entities = if path.is_dir() { path.join(ENTITIES_FILE) } else { path.to_path_buf() };
+    if entities.is_file() && entities.file_name().is_some_and(|name| name == ENTITIES_FILE) {
+        return read_xml_export(&entities);
+    }
+    if path.is_dir() {
+        return read_html_export(path);
+    }
+    anyhow::bail!("{} is neither a Confluence HTML export directory nor an XML export's {}", path.display(), ENTITIES_FILE)
+}
+
+fn read_html_export(root: &Path) -> Result<Vec<ImportedPage>> {
+    let title_text = Regex::new(r"(?is)<span id=.title-text.[^>]*>(.*?)</span>").unwrap();
+    let breadcrumbs = Regex::new(r"(?is)<ol id=.breadcrumbs.[^>]*>(.*?)</ol>").unwrap();
+    let link = Regex::new(r"(?is)<a\b[^>]*>(.*?)</a>").unwrap();
+
+    let mut pages = Vec::new();
+    for entry in WalkDir::new(root).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        let is_html = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("html"));
+        // index.html is the space's table of contents
+        if !entry.file_type().is_file() || !is_html || path.file_name().is_some_and(|name| name == "index.html") {
+            continue;
+        }
+        let html = std::fs::read_to_string(path)?;
+
+        // Titles read "Space : Page"
+        let page = html_to_text(&main_content(&html));
+        let title = title_text.captures(&html).map(|captures| inline_text(&captures[1]))
+            .or(html_to_text(&html).title)
+            .map(|title| title.split_once(" : ").map_or(title.clone(), |(_, page)| page.to_string()))
+            .unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().to_string());
+        let hierarchy = breadcrumbs.captures(&html)
+            .map(|captures| link.captures_iter(&captures[1]).map(|link| inline_text(&link[1])).filter(|crumb| !crumb.is_empty()).collect())
+            .unwrap_or_default();
+        if page.text.is_empty() {
+            continue;
+        }
+        pages.push(ImportedPage { path: path.to_string_lossy().to_string(), title, hierarchy, text: page.text, from_html: true });
+    }
+    Ok(pages)
+}
+
+/// The page body: from the main content element to the attachments, comments or footer after it
+fn main_content(html: &str) -> String {
+    let Some(start) = html.find("<div id=\"main-content\"") else {
+        return html.to_string();
+    };
+    let end = ["<div class=\"pageSection", "<div id=\"footer\"", "</body>"].iter()
+        .filter_map(|marker| html[start..].find(marker))
+        .min()
+        .map_or(html.len(), |end| start + end);
+    html[start..end].to_string()
+}
+
+/// entities.xml: every object of the space, each with its id and properties
+#[derive(Deserialize)]
+struct Entities {
+    #[serde(rename = "object", default)]
+    objects: Vec<XmlObject>,
+}
+
+#[derive(Deserialize)]
+struct XmlObject {
+    #[serde(rename = "@class")]
+    class: String,
+    id: Option<XmlId>,
+    #[serde(rename = "property", default)]
+    properties: Vec<XmlProperty>,
+}
+
+/// A property holds either text (in CDATA sections, joined when a long value is split) or the id
+/// of the object it refers to
+#[derive(Deserialize)]
+struct XmlProperty {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "$text", default)]
+    text: String,
+    id: Option<XmlId>,
+}
+
+#[derive(Deserialize)]
+struct XmlId {
+    #[serde(rename = "$text")]
+    value: String,
+}
+
+impl XmlObject {
+    fn property(&self, name: &str) -> Option<&XmlProperty> {
+        self.properties.iter().find(|property| property.name == name)
+    }
+
+    fn text(&self, name: &str) -> Option<String> {
+        self.property(name).map(|property| property.text.trim().to_string())
+    }
+
+    /// Id of the object a property refers to
+    fn reference(&self, name: &str) -> Option<String> {
+        self.property(name)?.id.as_ref().map(|id| id.value.trim().to_string())
+    }
+}
+
+struct XmlPage {
+    title: String,
+    parent: Option<String>,
+    space: Option<String>,
+}
+
+fn read_xml_export(entities: &Path) -> Result<Vec<ImportedPage>> {
+    let xml = std::fs::read_to_string(entities)?;
+    let export: Entities = quick_xml::de::from_str(&xml)
+        .map_err(|e| anyhow::anyhow!("{} is not a Confluence XML export: {}", entities.display(), e))?;
+
+    let mut pages: HashMap<String, XmlPage> = HashMap::new();
+    let mut page_order = Vec::new();
+    let mut bodies: HashMap<String, String> = HashMap::new();
+    let mut spaces: HashMap<String, String> = HashMap::new();
+    for object in &export.objects {
+        let Some(id) = object.id.as_ref().map(|id| id.value.trim().to_string()) else { continue };
+        match object.class.as_str() {
+            // Older versions of a page point at the current one through originalVersion
+            "Page" if object.property("originalVersion").is_none()
+                && object.text("contentStatus").is_none_or(|status| status == "current") =>
+            {
+                let Some(title) = object.text("title") else { continue };
+                pages.insert(id.clone(), XmlPage { title, parent: object.reference("parent"), space: object.reference("space") });
+                page_order.push(id);
+            }
+            "BodyContent" => {
+                if let (Some(page), Some(property)) = (object.reference("content"), object.property("body")) {
+                    bodies.insert(page, property.text.clone());
+                }
+            }
+            "Space" => {
+                if let Some(name) = object.text("name") {
+                    spaces.insert(id, name);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut imported = Vec::new();
+    for id in page_order {
+        let Some(text) = bodies.get(&id).map(|body| html_to_text(body).text).filter(|text| !text.is_empty()) else {
+            continue;
+        };
+        let page = &pages[&id];
+        let mut hierarchy = Vec::new();
+        let mut parent = page.parent.as_ref();
+        while let Some(ancestor) = parent.and_then(|parent| pages.get(parent)).filter(|_| hierarchy.len() < MAX_DEPTH) {
+            hierarchy.push(ancestor.title.clone());
+            parent = ancestor.parent.as_ref();
+        }
+        hierarchy.extend(page.space.as_ref().and_then(|space| spaces.get(space)).cloned());
+        hierarchy.reverse();
+        imported.push(ImportedPage {
+            path: format!("{}#{}", entities.display(), id),
+            title: page.title.clone(),
+            hierarchy,
+            text,
+            from_html: true,
+        });
+    }
+    Ok(imported)
+}
+
+fn inline_text(markup: &str) -> String {
+    html_to_text(markup).text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,68 @@
+// Importing knowledge-base exports page by page, each page keeping its place in the export's page
+// tree as document metadata
+mod confluence;
+mod notion;
+
+use anyhow::Result;
+use std::path::Path;
+use crate::core::app::ChunkyMonkeyApp;
+
+/// Where an export comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// A Notion "Markdown & CSV" export, unzipped
+    Notion,
+    /// A Confluence space exported as HTML (a directory) or XML (its entities.xml)
+    Confluence,
+}
+
+/// One page of an export
+pub struct ImportedPage {
+    /// Document path: the page's file, or for pages without one the export file and the page id
+    pub path: String,
+    pub title: String,
+    /// Titles of the pages above this one, outermost first
+    pub hierarchy: Vec<String>,
+    pub text: String,
+    /// The text was extracted from HTML, with headings as markdown `#` lines
+    pub from_html: bool,
+}
+
+impl ImportedPage {
+    /// "Space > Parent > Page"
+    pub fn breadcrumb(&self) -> String {
+        self.hierarchy.iter().chain(std::iter::once(&self.title)).cloned().collect::<Vec<_>>().join(" > ")
+    }
+}
+
+/// Outcome of an `import` run
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub indexed: Vec<String>,
+    /// Pages whose text hasn't changed since they were last imported
+    pub unchanged: usize,
+    pub failed: Vec<(String, String)>,
+}
+
+/// Index every page of the export at `path`
+pub async fn import(app: &mut ChunkyMonkeyApp, format: ExportFormat, path: &Path) -> Result<ImportReport> {
+    let pages = match format {
+        ExportFormat::Notion => notion::read_export(path)?,
+        ExportFormat::Confluence => confluence::read_export(path)?,
+    };
+    if pages.is_empty() {
+        anyhow::bail!("No pages found in {}", path.display());
+    }
+
+    let mut report = ImportReport::default();
+    for page in pages {
+        let breadcrumb = page.breadcrumb();
+        println!("📄 {}", breadcrumb);
+        match app.add_imported_page(&page).await {
+            Ok(0) => report.unchanged += 1,
+            Ok(_) => report.indexed.push(breadcrumb),
+            Err(e) => report.failed.push((breadcrumb, e.to_string())),
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,50 @@
+// Notion "Markdown & CSV" exports: a page is a `Title <id>.md` file whose subpages sit in a
+// `Title <id>` directory beside it, and a database is a `Title <id>.csv` file
+use anyhow::Result;
+use std::path::Path;
+use walkdir::WalkDir;
+use super::ImportedPage;
+
+/// Length of the hex page id Notion appends to exported file names
+const PAGE_ID_LENGTH: usize = 32;
+
+pub fn read_export(root: &Path) -> Result<Vec<ImportedPage>> {
+    if !root.is_dir() {
+        anyhow::bail!("{} is not a directory; unzip the Notion export first", root.display());
+    }
+    let mut pages = Vec::new();
+    for entry in WalkDir::new(root).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_lowercase);
+        if !entry.file_type().is_file() || !matches!(extension.as_deref(), Some("md" | "csv")) {
+            continue;
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        // Databases come as `Title <id>.csv` and, with every row including filtered ones, `Title <id>_all.csv`
+        if extension.as_deref() == Some("csv") && path.with_file_name(format!("{}_all.csv", stem)).exists() {
+            continue;
+        }
+
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let hierarchy = relative.parent()
+            .map(|parent| parent.components().map(|c| page_title(&c.as_os_str().to_string_lossy())).collect())
+            .unwrap_or_default();
+        pages.push(ImportedPage {
+            path: path.to_string_lossy().to_string(),
+            title: page_title(stem.trim_end_matches("_all")),
+            hierarchy,
+            text: std::fs::read_to_string(path)?,
+            from_html: false,
+        });
+    }
+    Ok(pages)
+}
+
+/// Name of an exported page or database without its id ("Roadmap 1a2b…" → "Roadmap")
+fn page_title(name: &str) -> String {
+    match name.rsplit_once(' ') {
+        Some((title, id)) if id.len() == PAGE_ID_LENGTH && id.chars().all(|c| c.is_ascii_hexdigit()) => title.to_string(),
+        _ => name.to_string(),
+    }
+}
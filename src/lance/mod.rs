@@ -0,0 +1,207 @@
+// Disk-backed local vector store on top of LanceDB (requires the `lancedb` cargo feature)
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use crate::embeddings::DistanceMetric;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanceDbConfig {
+    /// Directory holding the LanceDB dataset
+    pub uri: String,
+    pub table_name: String,
+}
+
+impl Default for LanceDbConfig {
+    fn default() -> Self {
+        Self {
+            uri: "chunkymonkey.lance".to_string(),
+            table_name: "chunks".to_string(),
+        }
+    }
+}
+
+/// Local vector store that keeps vectors on disk and answers queries with LanceDB's ANN search
+#[cfg_attr(not(feature = "lancedb"), allow(dead_code))]
+pub struct LanceStore {
+    config: LanceDbConfig,
+    dimension: usize,
+    metric: DistanceMetric,
+    #[cfg(feature = "lancedb")]
+    table: tokio::sync::OnceCell<lancedb::Table>,
+}
+
+#[cfg(feature = "lancedb")]
+impl LanceStore {
+    pub fn new(config: LanceDbConfig, dimension: usize, metric: DistanceMetric) -> Result<Self> {
+        Ok(Self {
+            config,
+            dimension,
+            metric,
+            table: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    fn schema(&self) -> std::sync::Arc<lancedb::arrow::arrow_schema::Schema> {
+        use lancedb::arrow::arrow_schema::{DataType, Field, Schema};
+        use std::sync::Arc;
+
+        Arc::new(Schema::new(vec![
+            Field::new("chunk_id", DataType::UInt32, false),
+            Field::new("document_path", DataType::Utf8, false),
+            Field::new("chunk_text", DataType::Utf8, false),
+            Field::new(
+                "vector",
+                DataType::FixedSizeList(
+                    Arc::new(Field::new("item", DataType::Float32, true)),
+                    self.dimension as i32,
+                ),
+                true,
+            ),
+        ]))
+    }
+
+    /// Open the table, creating the dataset on first use
+    async fn table(&self) -> Result<&lancedb::Table> {
+        self.table.get_or_try_init(|| async {
+            let db = lancedb::connect(&self.config.uri).execute().await?;
+            let table_names = db.table_names().execute().await?;
+
+            let table = if table_names.contains(&self.config.table_name) {
+                db.open_table(&self.config.table_name).execute().await?
+            } else {
+                db.create_empty_table(&self.config.table_name, self.schema()).execute().await?
+            };
+
+            Ok::<_, anyhow::Error>(table)
+        }).await
+    }
+
+    pub async fn add_chunks(&self, chunks: &[(u32, String, String, Vec<f32>)]) -> Result<()> {
+        use lancedb::arrow::arrow_array::{
+            types::Float32Type, FixedSizeListArray, RecordBatch, StringArray, UInt32Array,
+        };
+        use std::sync::Arc;
+
+        if chunks.is_empty() {
+            return Ok(());
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema(),
+            vec![
+                Arc::new(UInt32Array::from_iter_values(chunks.iter().map(|c| c.0))),
+                Arc::new(StringArray::from_iter_values(chunks.iter().map(|c| c.1.as_str()))),
+                Arc::new(StringArray::from_iter_values(chunks.iter().map(|c| c.2.as_str()))),
+                Arc::new(FixedSizeListArray::from_iter_primitive::<Float32Type, _, _>(
+                    chunks.iter().map(|c| Some(c.3.iter().copied().map(Some).collect::<Vec<_>>())),
+                    self.dimension as i32,
+                )),
+            ],
+        )?;
+
+        self.table().await?.add(batch).execute().await?;
+        Ok(())
+    }
+
+    pub async fn search(&self, query_vector: &[f32], k: usize) -> Result<Vec<(u32, f32, String, String)>> {
+        use futures::TryStreamExt;
+        use lancedb::arrow::arrow_array::{Array, Float32Array, RecordBatch, StringArray, UInt32Array};
+        use lancedb::query::{ExecutableQuery, QueryBase};
+
+        let batches: Vec<RecordBatch> = self
+            .table()
+            .await?
+            .query()
+            .nearest_to(query_vector)?
+            .distance_type(match self.metric {
+                DistanceMetric::Cosine => lancedb::DistanceType::Cosine,
+                DistanceMetric::DotProduct => lancedb::DistanceType::Dot,
+                DistanceMetric::Euclidean => lancedb::DistanceType::L2,
+            })
+            .limit(k)
+            .execute()
+            .await?
+            .try_collect()
+            .await?;
+
+        let mut results = Vec::new();
+        for batch in batches {
+            let column = |name: &str| {
+                batch.column_by_name(name)
+                    .ok_or_else(|| anyhow::anyhow!("LanceDB result is missing column {}", name))
+            };
+            let chunk_ids = column("chunk_id")?.as_any().downcast_ref::<UInt32Array>()
+                .ok_or_else(|| anyhow::anyhow!("Unexpected chunk_id column type"))?;
+            let paths = column("document_path")?.as_any().downcast_ref::<StringArray>()
+                .ok_or_else(|| anyhow::anyhow!("Unexpected document_path column type"))?;
+            let texts = column("chunk_text")?.as_any().downcast_ref::<StringArray>()
+                .ok_or_else(|| anyhow::anyhow!("Unexpected chunk_text column type"))?;
+            let distances = column("_distance")?.as_any().downcast_ref::<Float32Array>()
+                .ok_or_else(|| anyhow::anyhow!("Unexpected _distance column type"))?;
+
+            for row in 0..batch.num_rows() {
+                if distances.is_null(row) {
+                    continue;
+                }
+                // Cosine and dot distances are 1 - similarity; L2 distance is squared
+                let distance = distances.value(row);
+                let similarity = match self.metric {
+                    DistanceMetric::Euclidean => 1.0 / (1.0 + distance.max(0.0).sqrt()),
+                    _ => 1.0 - distance,
+                };
+                results.push((
+                    chunk_ids.value(row),
+                    similarity,
+                    paths.value(row).to_string(),
+                    texts.value(row).to_string(),
+                ));
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn count(&self) -> Result<usize> {
+        Ok(self.table().await?.count_rows(None).await?)
+    }
+
+    pub async fn delete_chunks(&self, chunk_ids: &[u32]) -> Result<()> {
+        if chunk_ids.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<String> = chunk_ids.iter().map(|id| id.to_string()).collect();
+        self.table().await?.delete(&format!("chunk_id IN ({})", ids.join(", "))).await?;
+        Ok(())
+    }
+
+    pub async fn clear(&self) -> Result<()> {
+        self.table().await?.delete("true").await?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "lancedb"))]
+impl LanceStore {
+    pub fn new(_config: LanceDbConfig, _dimension: usize, _metric: DistanceMetric) -> Result<Self> {
+        anyhow::bail!("ChunkyMonkey was built without LanceDB support; rebuild with `--features lancedb`")
+    }
+
+    pub async fn add_chunks(&self, _chunks: &[(u32, String, String, Vec<f32>)]) -> Result<()> {
+        anyhow::bail!("LanceDB support is not compiled in")
+    }
+
+    pub async fn search(&self, _query_vector: &[f32], _k: usize) -> Result<Vec<(u32, f32, String, String)>> {
+        anyhow::bail!("LanceDB support is not compiled in")
+    }
+
+    pub async fn count(&self) -> Result<usize> {
+        anyhow::bail!("LanceDB support is not compiled in")
+    }
+
+    pub async fn delete_chunks(&self, _chunk_ids: &[u32]) -> Result<()> {
+        anyhow::bail!("LanceDB support is not compiled in")
+    }
+
+    pub async fn clear(&self) -> Result<()> {
+        anyhow::bail!("LanceDB support is not compiled in")
+    }
+}
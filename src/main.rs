@@ -1,8 +1,10 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use colored::*;
 use anyhow::Result;
 use crate::core::app::ChunkyMonkeyApp;
 use crate::search::Indexer;
+use crate::core::types::{AnswerLength, AnswerStyle, SearchFilter};
+use crate::core::config::{ChunkStrategy, ChunkUnit};
 
 mod core;
 mod db;
@@ -12,6 +14,34 @@ mod cli;
 mod ui;
 mod vector_search;
 mod pinecone;
+mod qdrant;
+mod lance;
+mod serve;
+mod migrate;
+mod chaos;
+mod sync;
+mod rpc;
+mod ranking;
+mod dedup;
+mod chunking;
+mod docs;
+mod setup;
+mod answers;
+mod citations;
+mod context;
+mod stemming;
+mod replicate;
+mod glossary;
+mod web;
+mod telemetry;
+mod workspace;
+mod epub;
+mod ocr;
+mod hooks;
+mod front_matter;
+mod import;
+mod calibrate;
+mod result_cache;
 
 #[derive(Parser)]
 #[command(name = "chunkymonkey")]
@@ -20,6 +50,22 @@ mod pinecone;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    
+    /// Reproducible mode for CI: temperature 0, fixed seeds, cached query embeddings
+    #[arg(long, global = true)]
+    deterministic: bool,
+    
+    /// Search and answer afresh instead of reusing results cached for the same query
+    #[arg(long, global = true)]
+    no_cache: bool,
+    
+    /// Project to work in; each project has its own documents and Pinecone namespace
+    #[arg(long, global = true, value_name = "NAME")]
+    project: Option<String>,
+    
+    /// Log each HTTP request to stderr (service, method, path, status, timing); also enabled by RUST_LOG=debug
+    #[arg(long, global = true, visible_alias = "verbose")]
+    debug_http: bool,
 }
 
 #[derive(Subcommand)]
@@ -27,30 +73,184 @@ enum Commands {
     /// Start ChunkyMonkey (interactive mode)
     Start,
     
-    /// Index a directory of files
+    /// Create config.toml interactively: detect Ollama, pick models and a vector store, test connections
+    Init {
+        /// Replace an existing config.toml without asking
+        #[arg(long)]
+        force: bool,
+    },
+    
+    /// Index directories of files
     Index {
-        /// Directory path to index
-        #[arg(value_name = "DIRECTORY")]
-        directory: String,
+        /// Directories to index, or buckets as s3://bucket/prefix or gs://bucket/prefix
+        #[arg(value_name = "DIRECTORY", required_unless_present = "manifest")]
+        directories: Vec<String>,
+        
+        /// Index the roots an index manifest lists, each with its patterns, excludes, tags and chunking strategy (default: chunkymonkey.toml)
+        #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = search::manifest::MANIFEST_PATH,
+              conflicts_with_all = ["directories", "patterns"])]
+        manifest: Option<std::path::PathBuf>,
         
-        /// File patterns to include (e.g., "*.txt,*.md,*.py")
+        /// File patterns to include, relative to each directory (or bucket prefix) (e.g., "*.{txt,md},src/**/*.py")
         #[arg(short, long, value_name = "PATTERNS")]
         patterns: Option<String>,
+        
+        /// Leave out files and directories matching this glob (e.g. 'target', '**/*.min.js'); repeatable
+        #[arg(long, value_name = "PATTERN")]
+        exclude: Vec<String>,
+        
+        /// Also index files that .gitignore and .ignore files exclude, and VCS directories
+        #[arg(long)]
+        no_ignore: bool,
+        
+        /// Skip files larger than this, in bytes or with a K, M or G suffix (e.g. 20M); overrides indexing.max_file_size
+        #[arg(long, value_name = "SIZE", value_parser = search::parse_size)]
+        max_file_size: Option<u64>,
+        
+        /// List the files that would be indexed or skipped, with estimated chunk counts, without embedding or storing anything
+        #[arg(long)]
+        dry_run: bool,
+        
+        /// Write each file's outcome (status, chunks, bytes, duration, error) as JSON or NDJSON
+        #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "dry_run")]
+        report: Option<search::report::ReportFormat>,
+        
+        /// Write the report to this file instead of stdout
+        #[arg(long, value_name = "PATH", requires = "report")]
+        report_file: Option<std::path::PathBuf>,
+        
+        /// Tag the documents this run indexes (e.g. team:backend), for `search --tag`; repeatable
+        #[arg(long, value_name = "TAG")]
+        tag: Vec<String>,
+    },
+    
+    /// Index again the files whose last indexing attempt failed (listed in `stats`)
+    RetryFailed,
+    
+    /// Index a web page, or every page of a site's sitemap, with the URL as the document path
+    IndexUrl {
+        /// Page URL, or with --sitemap a sitemap URL or site root
+        #[arg(value_name = "URL")]
+        url: String,
+        
+        /// Index every page the sitemap lists (a site root means its /sitemap.xml)
+        #[arg(long)]
+        sitemap: bool,
+        
+        /// Index at most this many pages of the sitemap
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+    },
+    
+    /// Index a Notion or Confluence export page by page, keeping each page's place in the page tree
+    Import {
+        /// Export format
+        #[arg(value_enum)]
+        format: import::ExportFormat,
+        
+        /// Unzipped export directory, or a Confluence XML export's entities.xml
+        #[arg(value_name = "PATH")]
+        path: std::path::PathBuf,
     },
     
     /// Search for content
     Search {
-        /// Search query
-        #[arg(value_name = "QUERY")]
+        /// Search query; +term or +"a phrase" requires chunks to contain it and -term rules them out
+        #[arg(value_name = "QUERY", allow_hyphen_values = true)]
         query: String,
         
         /// Maximum number of results
         #[arg(short, long, default_value = "10")]
         limit: usize,
         
-        /// Similarity threshold (0.0 to 1.0)
+        /// Unused: results are cut at the relevance threshold `calibrate` saves for the embedding model
         #[arg(short, long, default_value = "0.7")]
         threshold: f32,
+        
+        /// Leave out documents matching this glob (e.g. 'tests/**'); repeatable
+        #[arg(long, value_name = "PATTERN")]
+        exclude: Vec<String>,
+        
+        /// Only search documents whose path starts with this (e.g. 'docs/api/')
+        #[arg(long, value_name = "PREFIX")]
+        path_prefix: Option<String>,
+        
+        /// Only search this indexed document, ranking its chunks: a semantic "find in file"
+        #[arg(long = "in", value_name = "PATH")]
+        in_document: Option<String>,
+        
+        /// Only search documents with this extension (e.g. 'md' or '.rs'); repeatable or comma-separated
+        #[arg(long, value_name = "EXT", value_delimiter = ',')]
+        ext: Vec<String>,
+        
+        /// Only search documents with this tag; repeatable, and documents must have all of them
+        #[arg(long, value_name = "TAG")]
+        tag: Vec<String>,
+        
+        /// Only search chunks in this language: a programming language (rust) or a natural language code (de); repeatable
+        #[arg(long, value_name = "LANGUAGE")]
+        lang: Vec<String>,
+        
+        /// Only search documents whose front matter author contains this, ignoring case
+        #[arg(long, value_name = "NAME")]
+        author: Option<String>,
+        
+        /// Only search documents whose front matter title contains this, ignoring case
+        #[arg(long, value_name = "TEXT")]
+        title: Option<String>,
+        
+        /// Only search documents dated on or after this (e.g. 2024-03-01, or 2024-03 for the month)
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+        
+        /// Only search documents dated on or before this (2024-03 takes in the whole month)
+        #[arg(long, value_name = "DATE")]
+        until: Option<String>,
+        
+        /// Output format; json, ndjson, csv and md give each result's path, chunk id, score, lines and text
+        #[arg(short, long, value_enum, default_value = "text")]
+        output: ui::ResultFormat,
+        
+        /// Skip this many results, to page through them (see also --page)
+        #[arg(long, default_value = "0", conflicts_with = "page")]
+        offset: usize,
+        
+        /// Show this page of results, each --limit long, counting from 1
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        page: Option<u32>,
+        
+        /// List the documents the matching chunks are in, each with its best score, number of
+        /// matching chunks and best snippet, instead of the chunks
+        #[arg(long)]
+        group_by_doc: bool,
+        
+        /// How to match: by meaning with embeddings, or by words with the full-text index
+        /// (FTS5 syntax: "exact phrase", a OR b, prefix*), which needs no embedding backend
+        #[arg(long, value_enum, default_value = "semantic")]
+        mode: SearchMode,
+    },
+    
+    /// Find content similar to an indexed document, or to one of its chunks as numbered by `chunk`
+    Similar {
+        /// Indexed document path, optionally with a chunk number (e.g. 'src/db/mod.rs:3')
+        #[arg(value_name = "PATH[:CHUNK]")]
+        target: String,
+        
+        /// Maximum number of results
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        
+        /// Leave out documents matching this glob (e.g. 'tests/**'); repeatable
+        #[arg(long, value_name = "PATTERN")]
+        exclude: Vec<String>,
+        
+        /// Only search documents with this extension (e.g. 'md' or '.rs'); repeatable or comma-separated
+        #[arg(long, value_name = "EXT", value_delimiter = ',')]
+        ext: Vec<String>,
+        
+        /// Output format; json, ndjson, csv and md give each result's path, chunk id, score, lines and text
+        #[arg(short, long, value_enum, default_value = "text")]
+        output: ui::ResultFormat,
     },
     
     /// Ask a question using RAG
@@ -62,47 +262,719 @@ enum Commands {
         /// Number of context chunks to use
         #[arg(short, long, default_value = "5")]
         context: usize,
+        
+        /// Output format for the answer
+        #[arg(short, long, value_enum, default_value = "text")]
+        output: AnswerFormat,
+        
+        /// Show the source excerpts used as context (text output)
+        #[arg(long)]
+        sources: bool,
+        
+        /// Answer length; also sets the LLM token budget
+        #[arg(long, value_enum, default_value = "medium")]
+        length: AnswerLength,
+        
+        /// Reading level to write the answer for (e.g. "beginner", "expert")
+        #[arg(long, value_name = "LEVEL")]
+        reading_level: Option<String>,
+        
+        /// Keep documents matching this glob (e.g. 'tests/**') out of the context; repeatable
+        #[arg(long, value_name = "PATTERN")]
+        exclude: Vec<String>,
+        
+        /// Answer within this time (e.g. 10s, 500ms, 2m): past it, the ranked sources and a summary
+        /// extracted from them are returned instead of a generated answer
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        deadline: Option<std::time::Duration>,
+        
+        /// Also write the answer to this file or named pipe token by token as it is generated, for
+        /// other programs to consume (a pipe waits until a reader opens it)
+        #[arg(long, value_name = "PATH")]
+        stream_to: Option<std::path::PathBuf>,
     },
     
     /// Show database statistics
-    Stats,
+    Stats {
+        /// Print DB, RAG pipeline, per-project and performance stats as one JSON document
+        #[arg(long)]
+        json: bool,
+        
+        /// Show how each command has been used, from the opt-in usage log ([telemetry] in config.toml)
+        #[arg(long)]
+        usage: bool,
+    },
     
     /// Show RAG pipeline statistics
     RagStats,
     
+    /// List the indexed documents with their chunk count, size, content hash and when they were indexed
+    List {
+        /// Order by size, chunk count or indexing date, largest or newest first (default: by path)
+        #[arg(long, value_enum)]
+        sort: Option<DocumentSort>,
+        
+        /// Print the documents as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Remove documents from the index, with their chunks and vectors
+    Remove {
+        /// Document path, a directory whose documents to remove, or a glob (e.g. 'notes/**/*.md')
+        #[arg(value_name = "PATH")]
+        target: String,
+    },
+    
     /// Clear all indexed data
     Clear,
+    
+    /// Serve search, ask and asynchronous indexing jobs over HTTP
+    Serve {
+        /// Address to bind
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        
+        /// Port to listen on
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+        
+        /// Number of /search responses to cache per index generation (0 disables caching)
+        #[arg(long, default_value = "256")]
+        cache_size: usize,
+    },
+    
+    /// Run a JSON-RPC daemon over stdio for editor integrations (search, ask, retrieve, index_file)
+    #[command(alias = "lsp")]
+    Daemon,
+    
+    /// Record whether a search result was relevant to a query (used by tune-ranking)
+    Feedback {
+        /// The query the result was returned for
+        #[arg(value_name = "QUERY")]
+        query: String,
+        
+        /// Chunk id shown next to the search result
+        #[arg(long)]
+        chunk: u32,
+        
+        /// Mark the result as not relevant (default: relevant)
+        #[arg(long)]
+        not_relevant: bool,
+    },
+    
+    /// Fit ranking weights (vector, keyword, rerank, recency) for this project from recorded feedback
+    TuneRanking {
+        /// Show the fitted weights without saving them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// Re-embed chunks whose embeddings came from a different model version
+    Reembed,
+    
+    /// Fit the relevance threshold to the embedding model: sample chunks, score a sentence held out
+    /// of each against the rest of its chunk and against other documents' chunks, and save a
+    /// threshold between the two. Searches of the local index, LanceDB, Pinecone and Qdrant all
+    /// drop results below it
+    Calibrate {
+        /// Number of chunks to sample
+        #[arg(short, long, default_value_t = calibrate::DEFAULT_SAMPLES)]
+        samples: usize,
+        
+        /// Sample the same chunks again by reusing a seed
+        #[arg(long)]
+        seed: Option<u64>,
+        
+        /// Report the distribution and suggested threshold without saving it
+        #[arg(long)]
+        dry_run: bool,
+        
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Manage this project's blocklist of documents that are never retrieved
+    Blocklist {
+        #[command(subcommand)]
+        action: BlocklistAction,
+    },
+    
+    /// Save curated answers that are given instead of generated ones for matching questions
+    Answers {
+        #[command(subcommand)]
+        action: AnswersAction,
+    },
+    
+    /// Manage this project's glossary: terms are footnoted in answers and discouraged variants flagged
+    Glossary {
+        #[command(subcommand)]
+        action: GlossaryAction,
+    },
+    
+    /// Show the chunks a file would be split into, without indexing it
+    Chunk {
+        /// File to chunk
+        #[arg(value_name = "FILE")]
+        file: std::path::PathBuf,
+        
+        /// Chunk with this strategy instead of the one configured for the file
+        #[arg(long, value_enum)]
+        strategy: Option<ChunkStrategy>,
+        
+        /// Print the chunks as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Print random indexed chunks with their metadata, to spot-check chunking and text extraction
+    Sample {
+        /// Number of chunks
+        #[arg(short, long, default_value = "20")]
+        n: usize,
+        
+        /// Pick the same chunks again by reusing a seed
+        #[arg(long)]
+        seed: Option<u64>,
+        
+        /// Print the chunks as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Compare two index databases: documents added, removed and changed, and chunk and embedding totals
+    Diff {
+        /// The earlier index database (e.g. a copy of chunkymonkey.db taken before re-indexing)
+        #[arg(value_name = "INDEX_A")]
+        before: String,
+        
+        /// The index database to compare it with
+        #[arg(value_name = "INDEX_B")]
+        after: String,
+        
+        /// Print the differences as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Inspect the index database directly
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    
+    /// Edit indexed documents in bulk
+    Docs {
+        #[command(subcommand)]
+        action: DocsAction,
+    },
+    
+    /// Reconcile a remote vector store with the local index
+    Sync {
+        #[command(subcommand)]
+        target: SyncTarget,
+    },
+    
+    /// Ship new and changed documents with their embeddings to a mirror, removing ones deleted here
+    Replicate {
+        /// Mirror to update: an index database path, or the URL of a `serve` instance
+        #[arg(long, value_name = "PATH|URL")]
+        to: String,
+        
+        /// Only report what would be sent and removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// Re-embed everything with a model of a different dimension and move to a new Pinecone index
+    MigrateEmbeddings {
+        /// Embedding dimension of the target model
+        #[arg(short, long)]
+        dimension: usize,
+        
+        /// Target embedding model (defaults to the configured model)
+        #[arg(short, long)]
+        model: Option<String>,
+        
+        /// Name for the new Pinecone index (defaults to "<current index>-<dimension>")
+        #[arg(long)]
+        index_name: Option<String>,
+        
+        /// Number of chunks to re-embed per batch
+        #[arg(long, default_value = "32")]
+        batch_size: usize,
+        
+        /// Keep the old Pinecone index instead of deleting it after the swap
+        #[arg(long)]
+        keep_old_index: bool,
+        
+        /// Cloud provider for the new serverless index
+        #[arg(long, default_value = "aws")]
+        cloud: String,
+        
+        /// Region for the new serverless index
+        #[arg(long, default_value = "us-east-1")]
+        region: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BlocklistAction {
+    /// Exclude documents matching a glob (e.g. 'fixtures/**', '*.snap') from all retrieval
+    Add {
+        #[arg(value_name = "PATTERN")]
+        pattern: String,
+    },
+    /// Stop excluding a pattern
+    Remove {
+        #[arg(value_name = "PATTERN")]
+        pattern: String,
+    },
+    /// Show the blocklisted patterns
+    List,
+}
+
+#[derive(Subcommand)]
+enum AnswersAction {
+    /// Save an answer for a question
+    Add {
+        #[arg(value_name = "QUESTION")]
+        question: String,
+        
+        /// The answer text
+        #[arg(long, value_name = "TEXT", conflicts_with = "from_feedback", required_unless_present = "from_feedback")]
+        answer: Option<String>,
+        
+        /// Build the answer from the chunks recorded as relevant to this question with `feedback`
+        #[arg(long)]
+        from_feedback: bool,
+    },
+    /// Show the saved answers
+    List,
+    /// Delete a saved answer
+    Remove {
+        #[arg(value_name = "ID")]
+        id: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum GlossaryAction {
+    /// Define a term, replacing any existing definition
+    Add {
+        #[arg(value_name = "TERM")]
+        term: String,
+        
+        /// What the term means
+        #[arg(long, value_name = "TEXT")]
+        definition: String,
+        
+        /// A discouraged variant of the term, flagged when an answer uses it (repeatable)
+        #[arg(long = "avoid", value_name = "WORD")]
+        avoid: Vec<String>,
+    },
+    /// Show the glossary
+    List,
+    /// Delete a term
+    Remove {
+        #[arg(value_name = "TERM")]
+        term: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Run a read-only SQL query over the documents, chunks and tags tables (see `db tables`)
+    Query {
+        /// A single SELECT statement, e.g. "SELECT project, COUNT(*) FROM documents GROUP BY project"
+        #[arg(value_name = "SQL")]
+        sql: String,
+        
+        /// Print rows as a JSON array of objects
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the tables and columns `db query` can use
+    Tables,
+}
+
+#[derive(Subcommand)]
+enum DocsAction {
+    /// Set tags and project of every document matching a glob, locally and in the remote vector store
+    SetMeta {
+        /// Documents to edit (e.g. 'docs/**', '*.md')
+        #[arg(long, value_name = "PATTERN")]
+        path: String,
+        
+        /// Add a tag (repeatable)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        
+        /// Remove a tag (repeatable)
+        #[arg(long = "remove-tag", value_name = "TAG")]
+        remove_tags: Vec<String>,
+        
+        /// Remove all existing tags before adding new ones
+        #[arg(long)]
+        clear_tags: bool,
+        
+        /// Move the documents to this project (the global --project selects where they are now)
+        #[arg(long, value_name = "NAME", conflicts_with = "to_default_project")]
+        to_project: Option<String>,
+        
+        /// Move the documents to the default project
+        #[arg(long)]
+        to_default_project: bool,
+        
+        /// Only list the documents that would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncTarget {
+    /// Re-upsert chunks missing from Pinecone and delete orphaned vectors
+    Pinecone {
+        /// Only report drift, don't change anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum SearchMode {
+    Semantic,
+    Keyword,
+}
+
+/// The slice of search results `--offset`/`--page` and `--limit` select
+struct ResultPage {
+    offset: usize,
+    limit: usize,
+    /// Given as --page rather than --offset
+    numbered: bool,
+}
+
+impl ResultPage {
+    fn new(offset: usize, page: Option<u32>, limit: usize) -> Self {
+        match page {
+            Some(page) => Self { offset: (page as usize - 1) * limit, limit, numbered: true },
+            None => Self { offset, limit, numbered: false },
+        }
+    }
+
+    /// Results to ask for: those before and on the page, and one more to tell whether a next page exists
+    fn fetch(&self) -> usize {
+        self.offset + self.limit + 1
+    }
+
+    /// The results on the page, and whether there are more after it
+    fn take<T>(&self, results: Vec<T>) -> (Vec<T>, bool) {
+        let more = results.len() > self.offset + self.limit;
+        (results.into_iter().skip(self.offset).take(self.limit).collect(), more)
+    }
+
+    /// How to ask for the next page, as the page was asked for
+    fn next(&self) -> String {
+        if self.numbered {
+            format!("--page {}", self.offset / self.limit.max(1) + 2)
+        } else {
+            format!("--offset {}", self.offset + self.limit)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DocumentSort {
+    Size,
+    Chunks,
+    Date,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum AnswerFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+/// Parse "500ms", "10s", "1.5s" or "2m"; a bare number is seconds
+fn parse_duration(value: &str) -> std::result::Result<std::time::Duration, String> {
+    let value = value.trim();
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = value.strip_suffix('s') {
+        (s, 1.0)
+    } else if let Some(m) = value.strip_suffix('m') {
+        (m, 60.0)
+    } else {
+        (value, 1.0)
+    };
+    match number.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() && n > 0.0 => Ok(std::time::Duration::from_secs_f64(n * scale)),
+        _ => Err(format!("'{}' is not a duration like 500ms, 10s or 2m", value)),
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    core::debug::init(cli.debug_http);
+    
+    if let Commands::Init { force } = cli.command {
+        return setup::run_wizard(force).await;
+    }
+    if let Commands::Chunk { ref file, strategy, json } = cli.command {
+        return inspect_chunks(file, strategy, json);
+    }
+    if let Commands::Diff { ref before, ref after, json } = cli.command {
+        let diff = db::diff::diff_indexes(before, after, cli.project.as_deref())?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        } else {
+            display_corpus_diff(&diff, before, after);
+        }
+        return Ok(());
+    }
+    if setup::needs_setup() && matches!(cli.command, Commands::Start | Commands::Index { .. } | Commands::IndexUrl { .. } | Commands::Import { .. } | Commands::Search { .. } | Commands::Ask { .. }) {
+        setup::offer_first_run_setup().await?;
+    }
     
     // Initialize the app
     let mut app = ChunkyMonkeyApp::new()?;
+    app.set_deterministic(cli.deterministic);
+    app.set_result_cache(!cli.no_cache);
+    app.set_project(cli.project.clone())?;
+    if cli.project.is_none() {
+        workspace::apply(&mut app, matches!(cli.command, Commands::Start | Commands::Search { .. } | Commands::Ask { .. })).await?;
+    }
+    let mut usage = telemetry::UsageRecorder::start(&app.config.telemetry, matches.subcommand_name().unwrap_or("start"));
+    
+    // Vectors from different model versions rank against each other poorly, so flag them before querying
+    if matches!(cli.command, Commands::Start | Commands::Search { mode: SearchMode::Semantic, .. } | Commands::Ask { .. } | Commands::Serve { .. }) {
+        if let Some(drift) = app.check_embedding_drift().await? {
+            warn_embedding_drift(&drift);
+            if matches!(cli.command, Commands::Start) && confirm_reembed()? {
+                let count = app.reembed_stale_chunks().await?;
+                println!("{}", format!("✅ Re-embedded {} chunks", count).green());
+            }
+        }
+    }
     
     match cli.command {
         Commands::Start => {
             cli::interactive::run_interactive(&mut app).await?;
         }
         
-        Commands::Index { directory, patterns } => {
-            let indexer = Indexer::new();
-            indexer.index_directory(&directory, patterns.as_deref(), &mut app).await?;
+        Commands::Init { .. } | Commands::Chunk { .. } | Commands::Diff { .. } => unreachable!("handled before the app is created"),
+        
+        Commands::Index { directories, manifest, patterns, exclude, no_ignore, max_file_size, dry_run, report, report_file, tag } => {
+            let documents_before = app.db.get_documents_by_project(app.project.as_deref())?.len();
+            app.set_index_tags(tag);
+            let mut indexing = app.config.indexing.clone();
+            indexing.exclude.extend(exclude);
+            indexing.respect_ignore_files &= !no_ignore;
+            if let Some(max_file_size) = max_file_size {
+                indexing.max_file_size = max_file_size;
+            }
+            let mut indexer = Indexer::new(indexing);
+            let reporter = match report {
+                Some(format) => {
+                    let out: Box<dyn std::io::Write + Send> = match report_file {
+                        Some(ref path) => Box::new(std::fs::File::create(path)
+                            .map_err(|e| anyhow::anyhow!("Cannot write report {}: {}", path.display(), e))?),
+                        None => Box::new(std::io::stdout()),
+                    };
+                    let (events, reporter) = search::report::spawn(format, out);
+                    indexer = indexer.with_events(events);
+                    Some(reporter)
+                }
+                None => None,
+            };
+            let roots = match manifest {
+                Some(ref path) => search::manifest::load(path)?.roots,
+                None => directories.iter().map(|directory| search::manifest::IndexRoot::new(directory, patterns.as_deref())).collect(),
+            };
+            // Local directories are indexed in one run, then each bucket in a run of its own
+            let (buckets, local): (Vec<_>, Vec<_>) = roots.into_iter()
+                .partition(|root| search::bucket::BucketUrl::parse(&root.path).is_some());
+            if dry_run {
+                if !buckets.is_empty() {
+                    anyhow::bail!("--dry-run works on local directories, not buckets");
+                }
+                for root in &local {
+                    let scope = search::RootScope::enter(root, &mut app);
+                    indexer.for_root(root).dry_run(&root.path, root.patterns.as_deref(), &app)?;
+                    scope.leave(&mut app);
+                }
+            } else {
+                if !local.is_empty() {
+                    indexer.index_roots(&local, &mut app).await?;
+                }
+                for root in &buckets {
+                    let bucket = search::bucket::BucketUrl::parse(&root.path).expect("partitioned as a bucket");
+                    let scope = search::RootScope::enter(root, &mut app);
+                    indexer.for_root(root).index_bucket(&bucket, root.patterns.as_deref(), &mut app).await?;
+                    scope.leave(&mut app);
+                }
+            }
+            // The report is complete once the indexer's channel closes
+            drop(indexer);
+            if let Some(reporter) = reporter {
+                reporter.await??;
+            }
+            let documents_after = app.db.get_documents_by_project(app.project.as_deref())?.len();
+            usage.set_result_count(documents_after.saturating_sub(documents_before));
+            // Suggestions would land in a report written to stdout
+            let report_on_stdout = report.is_some() && report_file.is_none();
+            if documents_before == 0 && documents_after > 0 && !report_on_stdout {
+                ui::show_question_suggestions(&app).await;
+            }
+        }
+        
+        Commands::RetryFailed => {
+            let summary = Indexer::new(app.config.indexing.clone()).retry_failed(&mut app).await?;
+            usage.set_result_count(summary.indexed);
+        }
+        
+        Commands::IndexUrl { url, sitemap, limit } => {
+            let report = web::index_url(&mut app, &url, sitemap, limit).await?;
+            usage.set_result_count(report.indexed.len());
+            display_web_index_report(&report);
+        }
+        
+        Commands::Import { format, path } => {
+            let report = import::import(&mut app, format, &path).await?;
+            usage.set_result_count(report.indexed.len());
+            display_import_report(&report);
+        }
+        
+        Commands::Sample { n, seed, json } => {
+            let sample = app.db.sample_chunks(app.project.as_deref(), n, seed)?;
+            usage.set_result_count(sample.len());
+            display_chunk_sample(&sample, &app.config, json)?;
         }
         
-        Commands::Search { query, limit, threshold } => {
-            let results = app.search(&query, limit, threshold).await?;
-            display_search_results(&results);
+        Commands::Search { query, limit, threshold, exclude, path_prefix, in_document, ext, tag, lang, author, title, since, until, output, offset, page, group_by_doc, mode } => {
+            app.set_exclude_patterns(exclude)?;
+            let document = match in_document {
+                Some(ref path) => Some(app.find_document(path)?.file_path),
+                None => None,
+            };
+            // The global --project has already scoped the search to that project's documents
+            let filter = SearchFilter { extensions: ext, path_prefix, document, tags: tag, languages: lang, author, title, since, until, ..Default::default() };
+            let highlighter = ui::Highlighter::new(&query, &app.config.search.keyword_language);
+            // Each page is cut from the results up to it, which are ordered the same way every time
+            let page = ResultPage::new(offset, page, limit);
+            let more = if group_by_doc {
+                let (documents, more) = page.take(search_documents(&app, &query, page.fetch(), threshold, &filter, mode).await?);
+                usage.set_result_count(documents.len());
+                match output {
+                    ui::ResultFormat::Text => display_document_hits(&documents, &highlighter, page.offset),
+                    format => println!("{}", ui::render_documents(&documents, format)?),
+                }
+                more
+            } else {
+                match mode {
+                    SearchMode::Semantic => {
+                        let (results, more) = page.take(app.search_filtered(&query, page.fetch(), threshold, &filter).await?);
+                        usage.set_result_count(results.len());
+                        match output {
+                            ui::ResultFormat::Text => display_search_results(&results, Some(&highlighter), page.offset),
+                            format => println!("{}", ui::render_results(&ui::ResultRow::all(&results, Some(&highlighter)), format)?),
+                        }
+                        more
+                    }
+                    SearchMode::Keyword => {
+                        let (matches, more) = page.take(app.keyword_search(&query, page.fetch(), &filter)?);
+                        usage.set_result_count(matches.len());
+                        match output {
+                            ui::ResultFormat::Text => display_keyword_matches(&matches, page.offset),
+                            format => println!("{}", ui::render_results(&ui::ResultRow::all(&matches, Some(&highlighter)), format)?),
+                        }
+                        more
+                    }
+                }
+            };
+            if more && output == ui::ResultFormat::Text {
+                println!("{}", format!("More results: {}", page.next()).dimmed());
+            }
         }
         
-        Commands::Ask { question, context } => {
-            println!("🤔 Processing your question with LLM...");
-            let answer = app.ask_question(&question, Some(context)).await?;
-            display_rag_answer(&answer);
+        Commands::Similar { target, limit, exclude, ext, output } => {
+            app.set_exclude_patterns(exclude)?;
+            // "path:3" is chunk #3 of path, unless path itself is indexed with that name
+            let (path, chunk) = match target.rsplit_once(':') {
+                Some((path, chunk)) if app.db.get_document_id_by_path(app.project.as_deref(), &target)?.is_none() => match chunk.parse::<usize>() {
+                    Ok(chunk) => (path, Some(chunk)),
+                    Err(_) => (target.as_str(), None),
+                },
+                _ => (target.as_str(), None),
+            };
+            let filter = SearchFilter { extensions: ext, ..Default::default() };
+            let results = app.search_similar(path, chunk, limit, &filter).await?;
+            usage.set_result_count(results.len());
+            match output {
+                ui::ResultFormat::Text => display_search_results(&results, None, 0),
+                format => println!("{}", ui::render_results(&ui::ResultRow::all(&results, None), format)?),
+            }
         }
         
-        Commands::Stats => {
+        Commands::Ask { question, context, output, sources, length, reading_level, exclude, deadline, stream_to } => {
+            app.set_answer_style(AnswerStyle { length, reading_level });
+            app.set_exclude_patterns(exclude)?;
+            app.set_answer_deadline(deadline);
+            app.set_answer_stream(stream_to);
+            if output == AnswerFormat::Text {
+                println!("🤔 Processing your question with LLM...");
+                app.set_answer_echo(Some(LLM_ANSWER_HEADING));
+            }
+            // Ctrl-C stops retrieval or generation, leaving what was printed so far
+            let cancel = core::cancel::CancellationToken::new();
+            app.set_cancellation(cancel.clone());
+            let interrupt = tokio::spawn({
+                let cancel = cancel.clone();
+                async move {
+                    if tokio::signal::ctrl_c().await.is_ok() {
+                        cancel.cancel();
+                    }
+                }
+            });
+            let result = app.ask_question(&question, Some(context)).await;
+            interrupt.abort();
+            match result {
+                Ok(answer) => {
+                    usage.set_result_count(answer.sources.len());
+                    match output {
+                        // Past the deadline the sources are the answer's substance, so they are always shown
+                        AnswerFormat::Text => display_rag_answer(&answer, sources || answer.deadline_exceeded),
+                        AnswerFormat::Markdown => println!("{}", ui::render_answer_markdown(&answer)),
+                        AnswerFormat::Json => println!("{}", ui::render_answer_json(&answer)?),
+                    }
+                }
+                Err(e) if core::cancel::is_cancelled(&e) => eprintln!("{}", "⏹️  Stopped".yellow()),
+                Err(e) => return Err(e),
+            }
+        }
+        
+        Commands::Stats { json, usage: true } => {
+            let summary = app.db.get_usage_summary()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                display_usage_summary(&summary, app.config.telemetry.enabled);
+            }
+        }
+        
+        Commands::Stats { json: true, .. } => {
+            let snapshot = app.get_stats_snapshot().await?;
+            println!("{}", serde_json::to_string_pretty(&snapshot)?);
+        }
+        
+        Commands::Stats { json: false, .. } => {
             let stats = app.get_stats().await?;
             display_stats(&stats);
         }
@@ -112,16 +984,331 @@ async fn main() -> Result<()> {
             display_rag_stats(&rag_stats);
         }
         
+        Commands::List { sort, json } => {
+            let mut documents = app.db.get_documents_by_project(app.project.as_deref())?;
+            match sort {
+                Some(DocumentSort::Size) => documents.sort_by_key(|document| std::cmp::Reverse(document.size)),
+                Some(DocumentSort::Chunks) => documents.sort_by_key(|document| std::cmp::Reverse(document.chunk_count)),
+                Some(DocumentSort::Date) => documents.sort_by_key(|document| std::cmp::Reverse(document.indexed_at)),
+                None => {}
+            }
+            usage.set_result_count(documents.len());
+            display_document_list(&documents, json)?;
+        }
+        
+        Commands::Remove { target } => {
+            let removed = app.remove_documents(&target).await?;
+            usage.set_result_count(removed.len());
+            if removed.is_empty() {
+                println!("⚠️  No indexed documents match {}", target);
+            } else {
+                for document in &removed {
+                    println!("   🗑️  {} ({} chunks)", document.file_path, document.chunk_count);
+                }
+                let chunks: u32 = removed.iter().map(|document| document.chunk_count).sum();
+                println!("{}", format!("✅ Removed {} document(s) and {} chunks", removed.len(), chunks).green());
+            }
+        }
+        
         Commands::Clear => {
             app.clear_database().await?;
-            println!("{}", "✅ Database cleared successfully!".green());
+            match cli.project {
+                Some(ref project) => println!("{}", format!("✅ Project '{}' cleared successfully!", project).green()),
+                None => println!("{}", "✅ Database cleared successfully!".green()),
+            }
+        }
+        
+        Commands::Serve { host, port, cache_size } => {
+            let replica_token = app.config.replication.token();
+            serve::run_server(&mut app, &host, port, cache_size, replica_token).await?;
+        }
+        
+        Commands::Daemon => {
+            rpc::run_stdio_daemon(&mut app).await?;
+        }
+        
+        Commands::Feedback { query, chunk, not_relevant } => {
+            if app.db.get_chunk(chunk)?.is_none() {
+                anyhow::bail!("Chunk {} not found", chunk);
+            }
+            app.db.record_feedback(app.project.as_deref(), &query, chunk, !not_relevant)?;
+            let label = if not_relevant { "not relevant" } else { "relevant" };
+            println!("{}", format!("✅ Recorded chunk {} as {} for \"{}\"", chunk, label, query).green());
+        }
+        
+        Commands::TuneRanking { dry_run } => {
+            let report = ranking::tune_ranking(&mut app, dry_run).await?;
+            display_tune_report(&report);
+        }
+        
+        Commands::Calibrate { samples, seed, dry_run, json } => {
+            let report = calibrate::calibrate(&mut app, samples, seed, dry_run).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                display_calibration_report(&report);
+            }
+        }
+        
+        Commands::Reembed => {
+            println!("🔄 Re-embedding chunks from other model versions...");
+            let count = app.reembed_stale_chunks().await?;
+            if count == 0 {
+                println!("{}", "✅ All embeddings match the configured model".green());
+            } else {
+                println!("{}", format!("✅ Re-embedded {} chunks", count).green());
+            }
+        }
+        
+        Commands::Blocklist { action: BlocklistAction::Add { pattern } } => {
+            if app.add_blocklist_pattern(&pattern)? {
+                println!("{}", format!("✅ Documents matching '{}' will no longer be retrieved", pattern).green());
+            } else {
+                println!("{}", format!("'{}' is already blocklisted", pattern).yellow());
+            }
+        }
+        
+        Commands::Blocklist { action: BlocklistAction::Remove { pattern } } => {
+            if app.remove_blocklist_pattern(&pattern)? {
+                println!("{}", format!("✅ Removed '{}' from the blocklist", pattern).green());
+            } else {
+                println!("{}", format!("'{}' is not blocklisted", pattern).yellow());
+            }
+        }
+        
+        Commands::Blocklist { action: BlocklistAction::List } => {
+            if app.blocklist.is_empty() {
+                println!("No blocklisted patterns");
+            }
+            for pattern in &app.blocklist {
+                println!("{}", pattern);
+            }
+        }
+        
+        Commands::Answers { action: AnswersAction::Add { question, answer, from_feedback } } => {
+            let answer = match answer {
+                Some(answer) if !from_feedback => answer,
+                _ => answers::answer_from_feedback(&app, &question)?,
+            };
+            let id = answers::learn(&app, &question, &answer).await?;
+            println!("{}", format!("✅ Saved answer #{} for \"{}\"", id, question.trim()).green());
+        }
+        
+        Commands::Answers { action: AnswersAction::List } => {
+            let saved = app.db.get_learned_answers(app.project.as_deref())?;
+            if saved.is_empty() {
+                println!("No saved answers");
+            }
+            for (learned, _, _) in &saved {
+                println!("{} {}", format!("#{}", learned.id).bright_cyan(), learned.question.bold());
+                println!("   {}", learned.answer.lines().next().unwrap_or("").dimmed());
+            }
+        }
+        
+        Commands::Answers { action: AnswersAction::Remove { id } } => {
+            if app.db.remove_learned_answer(app.project.as_deref(), id)? {
+                println!("{}", format!("✅ Removed saved answer #{}", id).green());
+            } else {
+                println!("{}", format!("No saved answer #{}", id).yellow());
+            }
+        }
+        
+        Commands::Glossary { action: GlossaryAction::Add { term, definition, avoid } } => {
+            let (term, definition) = (term.trim(), definition.trim());
+            if term.is_empty() || definition.is_empty() {
+                anyhow::bail!("Both a term and a definition are needed");
+            }
+            let avoid: Vec<String> = avoid.iter().map(|word| word.trim().to_string()).filter(|word| !word.is_empty()).collect();
+            app.db.set_glossary_term(app.project.as_deref(), term, definition, &avoid)?;
+            println!("{}", format!("✅ Defined \"{}\"", term).green());
+        }
+        
+        Commands::Glossary { action: GlossaryAction::List } => {
+            let glossary = app.db.get_glossary(app.project.as_deref())?;
+            if glossary.is_empty() {
+                println!("No glossary terms");
+            }
+            for entry in &glossary {
+                println!("{}: {}", entry.term.bold(), entry.definition);
+                if !entry.avoid.is_empty() {
+                    println!("   {}", format!("avoid: {}", entry.avoid.join(", ")).dimmed());
+                }
+            }
+        }
+        
+        Commands::Glossary { action: GlossaryAction::Remove { term } } => {
+            if app.db.remove_glossary_term(app.project.as_deref(), &term)? {
+                println!("{}", format!("✅ Removed \"{}\"", term).green());
+            } else {
+                println!("{}", format!("No glossary term \"{}\"", term).yellow());
+            }
+        }
+        
+        Commands::Db { action: DbAction::Query { sql, json } } => {
+            let result = db::query::run_query(db::DATABASE_PATH, &sql)?;
+            if json {
+                display_query_json(&result)?;
+            } else {
+                display_query_table(&result);
+            }
+        }
+        
+        Commands::Db { action: DbAction::Tables } => {
+            for (table, columns) in db::query::TABLES {
+                println!("{} {}", table.bright_cyan(), format!("({})", columns).dimmed());
+            }
+        }
+        
+        Commands::Docs { action: DocsAction::SetMeta { path, tags, remove_tags, clear_tags, to_project, to_default_project, dry_run } } => {
+            let project = if to_default_project { Some(None) } else { to_project.map(Some) };
+            let update = docs::MetadataUpdate {
+                path,
+                add_tags: tags,
+                remove_tags,
+                clear_tags,
+                project,
+                dry_run,
+            };
+            let report = docs::set_metadata(&mut app, &update).await?;
+            display_metadata_report(&report);
+        }
+        
+        Commands::Sync { target: SyncTarget::Pinecone { dry_run } } => {
+            let report = sync::sync_pinecone(&mut app, dry_run).await?;
+            display_sync_report(&report);
+        }
+        
+        Commands::Replicate { to, dry_run } => {
+            let report = replicate::replicate(&app.db, app.project.as_deref(), &to, app.config.replication.token(), dry_run).await?;
+            display_replication_report(&report);
+        }
+        
+        Commands::MigrateEmbeddings { dimension, model, index_name, batch_size, keep_old_index, cloud, region } => {
+            let options = migrate::MigrationOptions {
+                dimension,
+                model,
+                index_name,
+                batch_size,
+                keep_old_index,
+                cloud,
+                region,
+            };
+            migrate::migrate_embeddings(&mut app, options).await?;
         }
     }
     
+    usage.finish();
     Ok(())
 }
 
-fn display_search_results(results: &[crate::core::types::SearchResult]) {
+fn display_tune_report(report: &ranking::TuneReport) {
+    println!("\n🎛️  Ranking weights fitted from {} judgements ({} relevant)", report.samples, report.relevant);
+    println!("   {:<10} {:>8} {:>8}", "signal", "before", "after");
+    let rows = [
+        ("vector", report.previous.vector, report.weights.vector),
+        ("keyword", report.previous.keyword, report.weights.keyword),
+        ("rerank", report.previous.rerank, report.weights.rerank),
+        ("recency", report.previous.recency, report.weights.recency),
+    ];
+    for (name, before, after) in rows {
+        println!("   {:<10} {:>8.3} {:>8.3}", name, before, after);
+    }
+    println!("   Pairwise accuracy: {:.1}% → {:.1}%", report.previous_accuracy * 100.0, report.accuracy * 100.0);
+    
+    if report.applied {
+        println!("\n{}", "✅ Weights saved; search now re-ranks with them".green());
+    } else {
+        println!("\n{}", "Dry run: weights not saved".yellow());
+    }
+}
+
+/// Widest histogram bar
+const HISTOGRAM_WIDTH: usize = 30;
+
+fn display_calibration_report(report: &calibrate::CalibrationReport) {
+    println!("\n📏 Similarity scores of {} with {} sampled chunks", report.model.bright_cyan(), report.samples);
+    println!("   {:<12} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7}", "pairs", "min", "p10", "p50", "p90", "p95", "max");
+    for (name, d) in [("matching", &report.matching), ("unrelated", &report.background)] {
+        println!("   {:<12} {:>7.3} {:>7.3} {:>7.3} {:>7.3} {:>7.3} {:>7.3}", name, d.min, d.p10, d.p50, d.p90, d.p95, d.max);
+    }
+    
+    // Each distribution scaled to its own largest bucket, since unrelated pairs far outnumber matching ones
+    let most_matching = report.histogram.iter().map(|(_, matching, _)| *matching).max().unwrap_or(0).max(1);
+    let most_background = report.histogram.iter().map(|(_, _, background)| *background).max().unwrap_or(0).max(1);
+    println!();
+    for (low, matching, background) in &report.histogram {
+        let bar = |count: usize, most: usize| "█".repeat((count * HISTOGRAM_WIDTH).div_ceil(most));
+        println!("   {:>7.3}  {:<w$} {}", low, bar(*matching, most_matching).green(), bar(*background, most_background).dimmed(), w = HISTOGRAM_WIDTH);
+    }
+    println!("   {:>7}  {:<w$} {}", "", "matching".green(), "unrelated".dimmed(), w = HISTOGRAM_WIDTH);
+    
+    println!();
+    if report.overlapping {
+        println!("{}", "⚠️  Matching and unrelated scores overlap; the threshold keeps out 95% of unrelated pairs".yellow());
+    }
+    let previous = report.previous.map_or("none".to_string(), |previous| format!("{:.3}", previous));
+    println!("   Threshold: {} → {:.3}", previous, report.threshold);
+    if report.applied {
+        println!("\n{}", format!("✅ Threshold saved for {}; search drops local results below it", report.model).green());
+    } else {
+        println!("\n{}", "Dry run: threshold not saved".yellow());
+    }
+}
+
+fn warn_embedding_drift(drift: &crate::core::types::EmbeddingDrift) {
+    eprintln!("{}", format!("⚠️  {} of {} chunks were embedded with a different model version than {}",
+        drift.stale_chunks, drift.total_chunks, drift.current_version).yellow());
+    for (version, count) in &drift.stored_versions {
+        eprintln!("   {} chunks: {}", count, version.as_deref().unwrap_or("unknown (indexed before version tracking)"));
+    }
+    eprintln!("   Mixed model versions degrade ranking; run {} to refresh them.", "chunkymonkey reembed".bright_cyan());
+}
+
+fn confirm_reembed() -> Result<bool> {
+    let term = console::Term::stdout();
+    if !term.is_term() {
+        return Ok(false);
+    }
+    term.write_str("Re-embed them now? (y/N): ")?;
+    let answer = term.read_line()?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn display_sync_report(report: &sync::SyncReport) {
+    let namespace = report.namespace.as_deref().unwrap_or("default");
+    println!("\n🔄 Pinecone sync (namespace: {})", namespace.bright_cyan());
+    println!("   📚 Local chunks: {}", report.local_vectors);
+    println!("   ☁️  Remote chunk vectors: {} ({} vectors in namespace)", report.remote_vectors, report.namespace_vector_count);
+    println!("   ➕ Missing remotely: {}", report.missing);
+    println!("   ➖ Orphaned remotely: {}", report.orphaned);
+    
+    if report.dry_run {
+        println!("\n{}", "Dry run: no changes made".yellow());
+    } else if report.upserted == 0 && report.deleted == 0 {
+        println!("\n{}", "✅ Pinecone is in sync".green());
+    } else {
+        println!("\n{}", format!("✅ Re-upserted {} vectors, deleted {} orphans", report.upserted, report.deleted).green());
+    }
+}
+
+fn display_replication_report(report: &replicate::ReplicationReport) {
+    println!("\n🪞 Replicating to {}", report.target.bright_cyan());
+    println!("   📤 New or changed: {}", report.sent);
+    println!("   ➖ Removed: {}", report.removed);
+    println!("   ✔️  Unchanged: {}", report.unchanged);
+    
+    if report.dry_run {
+        println!("\n{}", "Dry run: mirror not changed".yellow());
+    } else if report.sent == 0 && report.removed == 0 {
+        println!("\n{}", "✅ Mirror is up to date".green());
+    } else {
+        println!("\n{}", format!("✅ Sent {} documents ({} chunks), removed {}", report.sent, report.chunks, report.removed).green());
+    }
+}
+
+/// Print search results numbered from `offset` + 1, with the query's terms highlighted in each
+/// preview when there is a query
+fn display_search_results(results: &[crate::core::types::SearchResult], highlighter: Option<&ui::Highlighter>, offset: usize) {
     if results.is_empty() {
         println!("{}", "❌ No results found".red());
         return;
@@ -130,14 +1317,24 @@ fn display_search_results(results: &[crate::core::types::SearchResult]) {
     println!("\n🔍 Search Results ({} found):\n", results.len());
     
     for (i, result) in results.iter().enumerate() {
-        println!("{}. 📄 {} (Similarity: {:.3})", 
-            i + 1, 
-            result.document_path.bright_green(), 
-            result.similarity
+        println!("{}. 📄 {} (Similarity: {:.3}) {}", 
+            offset + i + 1, 
+            result.location().bright_green(), 
+            result.similarity,
+            format!("[chunk {}]", result.chunk_id).dimmed()
         );
         
+        if result.metadata_only {
+            println!("   {}", "(metadata only - chunk text not available locally)".dimmed());
+            println!();
+            continue;
+        }
+        
         // Show a cleaner preview of the content
-        let preview = result.chunk_text.chars().take(60).collect::<String>();
+        let preview = match highlighter {
+            Some(highlighter) => highlighter.highlight_start(&result.chunk_text, 60),
+            None => result.chunk_text.chars().take(60).collect::<String>(),
+        };
         if !preview.is_empty() {
             println!("   {}", preview);
         }
@@ -149,16 +1346,356 @@ fn display_search_results(results: &[crate::core::types::SearchResult]) {
     }
 }
 
-fn display_rag_answer(answer: &crate::core::types::RAGAnswer) {
-    println!("🤖 LLM Answer:");
-    println!("{}", answer.answer);
+fn display_keyword_matches(matches: &[crate::core::types::KeywordMatch], offset: usize) {
+    if matches.is_empty() {
+        println!("{}", "❌ No results found".red());
+        return;
+    }
+    
+    println!("\n🔍 Keyword Matches ({} found):\n", matches.len());
+    
+    for (i, m) in matches.iter().enumerate() {
+        println!("{}. 📄 {} (Score: {:.3}) {}",
+            offset + i + 1,
+            m.location().bright_green(),
+            m.score,
+            format!("[chunk {}]", m.chunk_id).dimmed()
+        );
+        
+        // Matched terms stand out in the snippet; the marks never span lines
+        for line in m.snippet.lines().filter(|line| !line.trim().is_empty()) {
+            let mut highlighted = String::new();
+            for (j, part) in line.split(crate::db::KEYWORD_MATCH_START).enumerate() {
+                match part.split_once(crate::db::KEYWORD_MATCH_END) {
+                    Some((term, rest)) if j > 0 => {
+                        highlighted.push_str(&term.bold().yellow().to_string());
+                        highlighted.push_str(rest);
+                    }
+                    _ => highlighted.push_str(part),
+                }
+            }
+            println!("   {}", highlighted);
+        }
+        println!();
+    }
+}
+
+/// Chunks first fetched per document `search --group-by-doc` lists; the pool grows by this
+/// factor while documents with many matching chunks crowd out the others
+const DOCUMENT_GROUP_OVERSAMPLE: usize = 5;
+
+/// Most chunks `search --group-by-doc` fetches to find its documents
+const MAX_GROUPED_CHUNKS: usize = 2000;
+
+/// The `limit` best documents among the matching chunks, or as many as there are
+async fn search_documents(app: &ChunkyMonkeyApp, query: &str, limit: usize, threshold: f32, filter: &SearchFilter, mode: SearchMode) -> Result<Vec<ui::DocumentRow>> {
+    let mut chunk_limit = (limit * DOCUMENT_GROUP_OVERSAMPLE).min(MAX_GROUPED_CHUNKS);
+    loop {
+        let rows: Vec<ui::ResultRow> = match mode {
+            SearchMode::Semantic => ui::ResultRow::all(&app.search_filtered(query, chunk_limit, threshold, filter).await?, None),
+            SearchMode::Keyword => ui::ResultRow::all(&app.keyword_search(query, chunk_limit, filter)?, None),
+        };
+        let exhausted = rows.len() < chunk_limit || chunk_limit == MAX_GROUPED_CHUNKS;
+        let documents = ui::group_by_document(&rows, limit);
+        if documents.len() == limit || exhausted {
+            return Ok(documents);
+        }
+        chunk_limit = (chunk_limit * DOCUMENT_GROUP_OVERSAMPLE).min(MAX_GROUPED_CHUNKS);
+    }
+}
+
+/// Lines of its best snippet shown under each document
+const DOCUMENT_SNIPPET_LINES: usize = 3;
+
+fn display_document_hits(documents: &[ui::DocumentRow], highlighter: &ui::Highlighter, offset: usize) {
+    if documents.is_empty() {
+        println!("{}", "❌ No results found".red());
+        return;
+    }
+    
+    println!("\n🔍 Matching Documents ({} found):\n", documents.len());
+    
+    for (i, document) in documents.iter().enumerate() {
+        let hits = if document.hits == 1 { "1 chunk".to_string() } else { format!("{} chunks", document.hits) };
+        println!("{}. 📄 {} (Score: {:.3}, {})", offset + i + 1, document.path.bright_green(), document.score, hits.dimmed());
+        // Highlighted as a whole, so the language is told from the whole snippet
+        let snippet = highlighter.highlight(&document.snippet);
+        for line in snippet.lines().filter(|line| !line.trim().is_empty()).take(DOCUMENT_SNIPPET_LINES) {
+            println!("   {}", line);
+        }
+        println!();
+    }
+}
+
+/// Heading of `ask` answers, printed before the first token when the answer is streamed
+const LLM_ANSWER_HEADING: &str = "🤖 LLM Answer:";
+
+fn display_rag_answer(answer: &crate::core::types::RAGAnswer, show_sources: bool) {
+    // A streamed answer is on screen already, save for what validation added
+    let rest = answer.answer[answer.printed..].trim_start();
+    if answer.printed > 0 {
+        if !rest.is_empty() {
+            println!("\n{}", rest);
+        }
+    } else if let Some(ref learned) = answer.learned_from {
+        println!("📌 Saved Answer #{} (to \"{}\"):", learned.id, learned.question);
+        println!("{}", answer.answer);
+    } else {
+        println!("{}", LLM_ANSWER_HEADING);
+        println!("{}", answer.answer);
+    }
+    if answer.deadline_exceeded {
+        println!("{}", "⏱️  The deadline passed before the LLM finished; this summary is extracted from the sources.".yellow());
+    }
+    
+    for misuse in &answer.term_misuses {
+        println!("{}", format!("⚠️  Terminology: \"{}\" is discouraged; the glossary term is \"{}\"", misuse.used, misuse.preferred).yellow());
+    }
+    if !answer.glossary_terms.is_empty() {
+        println!("\n📖 Glossary:");
+        for term in &answer.glossary_terms {
+            println!("   {}: {}", term.term.bold(), term.definition);
+        }
+    }
+    
+    if !answer.citations.is_empty() {
+        println!("\n📎 Citations:");
+        for (i, citation) in answer.citations.iter().enumerate() {
+            println!("[{}] {} (chunk {}): \"{}\"", i + 1, citation.document_path.bright_green(), citation.chunk_id, citation.quote.italic());
+        }
+    }
+    
+    if show_sources && !answer.sources.is_empty() {
+        println!("\n📚 Sources:");
+        for (i, source) in answer.sources.iter().enumerate() {
+            println!("{}. 📄 {} (Similarity: {:.3})", i + 1, source.location().bright_green(), source.similarity);
+            print!("{}", ui::excerpt_terminal(source));
+        }
+    }
+}
+
+/// Lines of each chunk's text shown by `chunk`
+const CHUNK_PREVIEW_LINES: usize = 3;
+
+/// Chunk a file as indexing would (partition settings, strategy, chunk cap) and print the result
+fn inspect_chunks(file: &std::path::Path, strategy: Option<ChunkStrategy>, json: bool) -> Result<()> {
+    let config = core::config::AppConfig::load()?;
+    let text = std::fs::read_to_string(file)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", file.display(), e))?;
+    let path = file.to_string_lossy();
+    let chunking = config.chunking_for(&path);
+    let strategy = strategy.unwrap_or_else(|| chunking::strategy_for(&path, &chunking));
+    let max_chunks = match chunking.max_chunks_per_document {
+        0 => usize::MAX,
+        cap => cap,
+    };
+    let mut chunks = chunking::chunk_file(&path, strategy, &text, &chunking, max_chunks);
+    chunks.extend(chunking::schema_chunk(&path, &text, strategy, chunks.len()));
+    let children = if chunking.child_chunk_size > 0 {
+        chunking::child_chunks(&text, &chunks, strategy, &chunking)
+    } else {
+        Vec::new()
+    };
+
+    // Overlap with the previous chunk, in characters of the shared text
+    let overlaps: Vec<usize> = chunks.iter().enumerate().map(|(i, chunk)| {
+        let previous_end = i.checked_sub(1).and_then(|p| chunks[p].byte_range).map_or(0, |(_, end)| end);
+        match chunk.byte_range {
+            Some((start, end)) if start < previous_end => text[start..previous_end.min(end)].chars().count(),
+            _ => 0,
+        }
+    }).collect();
+    let low_quality = |chunk: &core::types::Chunk| {
+        chunking::is_low_quality(&chunk.text, config.search.min_chunk_chars, config.search.min_chunk_alphanumeric)
+    };
+    let child_counts: Vec<usize> = (0..chunks.len())
+        .map(|i| children.iter().filter(|child| child.parent_id == Some(i as u32)).count())
+        .collect();
+
+    if json {
+        let rows: Vec<serde_json::Value> = chunks.iter().enumerate().map(|(i, chunk)| serde_json::json!({
+            "index": chunk.chunk_index,
+            "byte_start": chunk.byte_range.map(|r| r.0),
+            "byte_end": chunk.byte_range.map(|r| r.1),
+            "lines": chunk.line_range.map(|(first, last)| serde_json::json!({ "first": first, "last": last })),
+            "chars": chunk.text.chars().count(),
+            "tokens": chunking::estimate_tokens(&chunk.text),
+            "overlap_chars": overlaps[i],
+            "section": chunk.section,
+            "children": child_counts[i],
+            "low_quality": low_quality(chunk),
+            "text": chunk.text,
+        })).collect();
+        let output = serde_json::json!({
+            "path": path,
+            "strategy": strategy.name(),
+            "unit": chunking.unit,
+            "max_chunk_size": chunking.max_chunk_size,
+            "overlap_size": chunking.overlap_size,
+            "chunks": rows,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let unit = match chunking.unit {
+        ChunkUnit::Chars => "chars",
+        ChunkUnit::Tokens => "tokens",
+    };
+    println!("📄 {} — {} chunk(s), strategy {}, max {} {} with {} overlap",
+        path.bright_green(), chunks.len(), strategy.name().bright_cyan(),
+        chunking.max_chunk_size, unit, chunking.overlap_size);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut header = format!("#{}", chunk.chunk_index);
+        if let Some((start, end)) = chunk.byte_range {
+            header.push_str(&format!("  bytes {}-{}", start, end));
+        }
+        if let Some((first, last)) = chunk.line_range {
+            header.push_str(&format!("  lines {}-{}", first, last));
+        }
+        header.push_str(&format!("  {} chars / ~{} tokens", chunk.text.chars().count(), chunking::estimate_tokens(&chunk.text)));
+        if overlaps[i] > 0 {
+            header.push_str(&format!("  overlap {} chars", overlaps[i]));
+        }
+        if child_counts[i] > 0 {
+            header.push_str(&format!("  {} children", child_counts[i]));
+        }
+        println!("\n{}", header.bold());
+        if low_quality(chunk) {
+            println!("   {}", "left out when indexing (search.min_chunk_chars / min_chunk_alphanumeric)".yellow());
+        }
+        if let Some(ref section) = chunk.section {
+            println!("   {}", section.dimmed());
+        }
+        for line in chunk.text.lines().take(CHUNK_PREVIEW_LINES) {
+            println!("   │ {}", line);
+        }
+        if chunk.text.lines().count() > CHUNK_PREVIEW_LINES {
+            println!("   │ {}", "...".dimmed());
+        }
+    }
+
+    let sizes: Vec<usize> = chunks.iter().map(|chunk| chunk.text.chars().count()).collect();
+    if let (Some(min), Some(max)) = (sizes.iter().min(), sizes.iter().max()) {
+        println!("\nSizes: min {}, mean {}, max {} chars", min, sizes.iter().sum::<usize>() / sizes.len(), max);
+    }
+    Ok(())
+}
+
+/// Longest cell shown in `db query` tables, in characters
+const QUERY_CELL_WIDTH: usize = 60;
+
+fn display_query_table(result: &db::query::QueryResult) {
+    let cells: Vec<Vec<String>> = result.rows.iter()
+        .map(|row| row.iter().map(|value| {
+            let text = match value {
+                serde_json::Value::Null => "NULL".to_string(),
+                serde_json::Value::String(text) => text.replace(['\n', '\t'], " "),
+                other => other.to_string(),
+            };
+            if text.chars().count() > QUERY_CELL_WIDTH {
+                format!("{}…", text.chars().take(QUERY_CELL_WIDTH - 1).collect::<String>())
+            } else {
+                text
+            }
+        }).collect())
+        .collect();
+    
+    let widths: Vec<usize> = result.columns.iter().enumerate()
+        .map(|(i, column)| cells.iter().map(|row| row[i].chars().count()).chain(std::iter::once(column.chars().count())).max().unwrap_or(0))
+        .collect();
+    let line = |values: &[String]| values.iter().zip(&widths)
+        .map(|(value, width)| format!("{:<width$}", value, width = *width))
+        .collect::<Vec<_>>()
+        .join(" │ ");
+    
+    println!("{}", line(&result.columns).bold());
+    println!("{}", widths.iter().map(|width| "─".repeat(*width)).collect::<Vec<_>>().join("─┼─"));
+    for row in &cells {
+        println!("{}", line(row));
+    }
+    println!("{}", format!("({} row{})", cells.len(), if cells.len() == 1 { "" } else { "s" }).dimmed());
+}
+
+fn display_query_json(result: &db::query::QueryResult) -> Result<()> {
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = result.rows.iter()
+        .map(|row| result.columns.iter().cloned().zip(row.iter().cloned()).collect())
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&rows)?);
+    Ok(())
+}
+
+/// Documents listed per kind of change before the rest are summarized
+const DIFF_LIST_LIMIT: usize = 50;
+
+fn display_corpus_diff(diff: &db::diff::CorpusDiff, before: &str, after: &str) {
+    let delta = |from: usize, to: usize| {
+        let change = to as i64 - from as i64;
+        let text = format!("{:+}", change);
+        if change > 0 { text.green() } else if change < 0 { text.red() } else { text.dimmed() }
+    };
+    
+    println!("\n🔀 {} → {}", before.bright_cyan(), after.bright_cyan());
+    println!("   📄 Documents: {} → {} ({})", diff.before.documents, diff.after.documents, delta(diff.before.documents, diff.after.documents));
+    println!("   📝 Chunks: {} → {} ({})", diff.before.chunks, diff.after.chunks, delta(diff.before.chunks, diff.after.chunks));
+    println!("   🧮 Embeddings: {} → {} ({})", diff.before.embeddings, diff.after.embeddings, delta(diff.before.embeddings, diff.after.embeddings));
+    println!("   ✂️  Chunk texts: {} new, {} dropped", diff.new_chunk_texts, diff.dropped_chunk_texts);
+    if diff.before.models != diff.after.models {
+        let models = |totals: &db::diff::IndexTotals| totals.models.iter()
+            .map(|(model, count)| format!("{} ({})", model, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("   🧠 Embedding models: {} → {}", models(&diff.before), models(&diff.after));
+    }
+    
+    let sections = [
+        ("➕ Added", &diff.added),
+        ("➖ Removed", &diff.removed),
+        ("✏️  Changed", &diff.changed),
+    ];
+    for (title, documents) in sections {
+        if documents.is_empty() {
+            continue;
+        }
+        println!("\n{} ({}):", title, documents.len());
+        for document in documents.iter().take(DIFF_LIST_LIMIT) {
+            let project = document.project.as_deref().map(|p| format!(" [{}]", p)).unwrap_or_default();
+            println!("   {}{} {}", document.path.bright_green(), project.dimmed(),
+                format!("({} → {} chunks)", document.chunks_before, document.chunks_after).dimmed());
+        }
+        if documents.len() > DIFF_LIST_LIMIT {
+            println!("   {}", format!("... and {} more", documents.len() - DIFF_LIST_LIMIT).dimmed());
+        }
+    }
+    
+    if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+        println!("\n{}", format!("✅ No document changes ({} unchanged)", diff.unchanged).green());
+    } else {
+        println!("\n   {} unchanged", diff.unchanged);
+    }
 }
 
+/// Failed files `stats` lists by name
+const MAX_LISTED_FAILURES: usize = 10;
+
 fn display_stats(stats: &crate::core::types::DatabaseStats) {
     println!("\n📊 Database Statistics:");
     println!("   📄 Documents: {}", stats.document_count);
     println!("   📝 Chunks: {}", stats.chunk_count);
     println!("   💾 Database size: {:.2} MB", stats.database_size_mb);
+    if !stats.tags.is_empty() {
+        let tags: Vec<String> = stats.tags.iter().map(|tag| format!("{} ({})", tag.tag, tag.document_count)).collect();
+        println!("   🏷️  Tags: {}", tags.join(", "));
+    }
+    if !stats.failed_files.is_empty() {
+        println!("   {}", format!("❗ Failed files: {} (retry them with `chunkymonkey retry-failed`)", stats.failed_files.len()).yellow());
+        for failure in stats.failed_files.iter().take(MAX_LISTED_FAILURES) {
+            println!("      {} ({} attempt(s)): {}", failure.file_path, failure.attempts, failure.error.red());
+        }
+        if stats.failed_files.len() > MAX_LISTED_FAILURES {
+            println!("      ... and {} more", stats.failed_files.len() - MAX_LISTED_FAILURES);
+        }
+    }
 }
 
 fn display_rag_stats(stats: &crate::core::types::RAGPipelineStats) {
@@ -173,4 +1710,187 @@ fn display_rag_stats(stats: &crate::core::types::RAGPipelineStats) {
     println!("   🌲 Pinecone: {}", if stats.pinecone_available { "✅ Available".bright_green() } else { "❌ Unavailable".red() });
     println!("   🧠 Ollama: {}", if stats.ollama_available { "✅ Available".bright_green() } else { "❌ Unavailable".red() });
     println!("   📐 Embedding Dimension: {}", stats.embedding_dimension);
-} 
\ No newline at end of file
+    
+    println!("\n🩺 Degradation:");
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    if stats.service_errors.is_empty() {
+        println!("   {}", "No service errors recorded".bright_green());
+    }
+    for errors in &stats.service_errors {
+        println!("   ❗ {}: {} errors, last {} ago: {}", errors.service.bright_cyan(), errors.count,
+            format_age(now - errors.last_error_at), errors.last_error.as_deref().unwrap_or("unknown error").red());
+    }
+    println!("   🪂 Fallbacks: {} simple embeddings, {} local searches instead of remote, {} answers without the LLM",
+        stats.embedding_fallbacks, stats.local_search_fallbacks, stats.answer_fallbacks);
+    if stats.fallback_embedded_chunks > 0 {
+        println!("   {}", format!("⚠️  {} chunks are embedded with the fallback model and rank poorly; run `chunkymonkey reembed` once Ollama is back",
+            stats.fallback_embedded_chunks).yellow());
+    }
+    for (name, cache) in [("Query embedding cache", stats.query_cache), ("Search response cache", stats.search_cache),
+                        ("Result cache", stats.result_cache)] {
+        match cache.hit_rate() {
+            Some(rate) => println!("   💾 {}: {:.0}% hits ({} of {})", name, rate * 100.0, cache.hits, cache.hits + cache.misses),
+            None => println!("   💾 {}: unused", name),
+        }
+    }
+}
+
+/// Print sampled chunks with their place in the document and any quality warnings
+fn display_chunk_sample(sample: &[(core::types::Chunk, String, Option<String>)], config: &core::config::AppConfig, json: bool) -> Result<()> {
+    let warnings = |chunk: &core::types::Chunk| {
+        let mut warnings = chunking::encoding_issues(&chunk.text);
+        if chunking::is_low_quality(&chunk.text, config.search.min_chunk_chars, config.search.min_chunk_alphanumeric) {
+            warnings.push("below the quality floor");
+        }
+        warnings
+    };
+    
+    if json {
+        let rows: Vec<serde_json::Value> = sample.iter().map(|(chunk, path, model_version)| serde_json::json!({
+            "chunk_id": chunk.id,
+            "path": path,
+            "index": chunk.chunk_index,
+            "byte_start": chunk.byte_range.map(|r| r.0),
+            "byte_end": chunk.byte_range.map(|r| r.1),
+            "lines": chunk.line_range.map(|(first, last)| serde_json::json!({ "first": first, "last": last })),
+            "section": chunk.section,
+            "chars": chunk.text.chars().count(),
+            "tokens": chunking::estimate_tokens(&chunk.text),
+            "parent_id": chunk.parent_id,
+            "model_version": model_version,
+            "warnings": warnings(chunk),
+            "text": chunk.text,
+        })).collect();
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+    
+    if sample.is_empty() {
+        println!("{}", "No chunks indexed".yellow());
+    }
+    for (chunk, path, model_version) in sample {
+        let mut header = format!("{} #{}", path, chunk.chunk_index);
+        if let Some((first, last)) = chunk.line_range {
+            header.push_str(&format!("  lines {}-{}", first, last));
+        }
+        header.push_str(&format!("  {} chars / ~{} tokens", chunk.text.chars().count(), chunking::estimate_tokens(&chunk.text)));
+        println!("\n{}", header.bold());
+        let mut details = vec![format!("chunk {}", chunk.id)];
+        if let Some(ref section) = chunk.section {
+            details.push(section.clone());
+        }
+        details.push(match model_version {
+            Some(version) => format!("embedded with {}", version),
+            None if chunk.parent_id.is_none() => "not embedded (parent chunk)".to_string(),
+            None => "not embedded".to_string(),
+        });
+        println!("   {}", details.join(" · ").dimmed());
+        for warning in warnings(chunk) {
+            println!("   {}", format!("⚠️  {}", warning).yellow());
+        }
+        for line in chunk.text.lines() {
+            println!("   │ {}", line);
+        }
+    }
+    Ok(())
+}
+
+fn display_web_index_report(report: &web::WebIndexReport) {
+    println!("{}", format!("✅ Indexed {} page(s), {} unchanged", report.indexed.len(), report.unchanged).green());
+    for (url, error) in &report.failed {
+        println!("{}", format!("❌ {}: {}", url, error).red());
+    }
+}
+
+fn display_import_report(report: &import::ImportReport) {
+    println!("{}", format!("✅ Imported {} page(s), {} unchanged", report.indexed.len(), report.unchanged).green());
+    for (page, error) in &report.failed {
+        println!("{}", format!("❌ {}: {}", page, error).red());
+    }
+}
+
+fn display_usage_summary(summary: &[crate::core::types::CommandUsage], enabled: bool) {
+    println!("\n📈 Command Usage:");
+    if summary.is_empty() {
+        println!("   {}", "No usage recorded".yellow());
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    for usage in summary {
+        let results = usage.avg_results.map(|n| format!(", {:.1} results", n)).unwrap_or_default();
+        let failures = if usage.failures > 0 { format!(", {} failed", usage.failures).red().to_string() } else { String::new() };
+        println!("   {} {} runs{}, avg {:.0}ms (max {}ms){}, last {} ago", usage.command.bright_cyan(), usage.runs,
+            failures, usage.avg_duration_ms, usage.max_duration_ms, results, format_age(now - usage.last_used_at));
+    }
+    if !enabled {
+        println!("   {}", "Usage logging is off; set `enabled = true` under [telemetry] in config.toml to record it".yellow());
+    }
+}
+
+fn display_document_list(documents: &[core::types::Document], json: bool) -> Result<()> {
+    if json {
+        let rows: Vec<serde_json::Value> = documents.iter().map(|document| serde_json::json!({
+            "path": document.file_path,
+            "chunks": document.chunk_count,
+            "size": document.size,
+            "hash": document.file_hash,
+            "indexed_at": document.indexed_at,
+        })).collect();
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+    
+    if documents.is_empty() {
+        println!("{}", "No documents indexed".yellow());
+        return Ok(());
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    println!("{}", format!("📚 {} document(s)", documents.len()).bright_blue());
+    for document in documents {
+        let indexed = document.indexed_at.map_or("unknown".to_string(), |at| format!("{} ago", format_age(now - at)));
+        let hash = document.file_hash.get(..12).unwrap_or(&document.file_hash);
+        println!("   {}  {} chunks, {}, {}, indexed {}", document.file_path.bright_green(), document.chunk_count,
+            search::format_size(document.size as u64), hash.dimmed(), indexed);
+    }
+    Ok(())
+}
+
+fn format_age(seconds: i64) -> String {
+    match seconds.max(0) {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+
+fn display_metadata_report(report: &docs::MetadataReport) {
+    if report.documents.is_empty() {
+        println!("{}", "No documents match that pattern".yellow());
+        return;
+    }
+    for (path, tags) in &report.documents {
+        if tags.is_empty() {
+            println!("   {}", path);
+        } else {
+            println!("   {} [{}]", path, tags.join(", "));
+        }
+    }
+    
+    if report.dry_run {
+        println!("\n{}", format!("Dry run: {} of {} documents would change", report.changed, report.documents.len()).yellow());
+    } else if report.changed == 0 {
+        println!("\n{}", "✅ Metadata already up to date".green());
+    } else {
+        println!("\n{}", format!("✅ Updated {} documents ({} remote vectors rewritten)", report.changed, report.vectors_updated).green());
+    }
+}
@@ -0,0 +1,167 @@
+// Guided migration of stored embeddings to a model with a different dimension
+use anyhow::Result;
+use colored::*;
+use std::time::Duration;
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::types::ChunkMetadata;
+use crate::embeddings::EmbeddingModel;
+use crate::pinecone::{PineconeAdmin, PineconeClient, PineconeConfig, Vector};
+
+const INDEX_READY_TIMEOUT: Duration = Duration::from_secs(300);
+
+pub struct MigrationOptions {
+    pub dimension: usize,
+    /// Embedding model to migrate to; defaults to the configured model
+    pub model: Option<String>,
+    /// Name of the new Pinecone index; defaults to `<old index>-<dimension>`
+    pub index_name: Option<String>,
+    pub batch_size: usize,
+    pub keep_old_index: bool,
+    pub cloud: String,
+    pub region: String,
+}
+
+/// Re-embed every chunk with the target model, build a new Pinecone index of the right dimension,
+/// swap the local embeddings in one transaction and point the config at the new index.
+///
+/// Nothing in use is touched until all chunks have been re-embedded and uploaded, so a failure
+/// part-way leaves the existing index and embeddings intact.
+pub async fn migrate_embeddings(app: &mut ChunkyMonkeyApp, options: MigrationOptions) -> Result<()> {
+    let mut ollama_config = app.config.ollama.clone();
+    if let Some(ref model) = options.model {
+        ollama_config.model = model.clone();
+    }
+    ollama_config.embedding_dimension = options.dimension;
+
+    println!("🔬 Checking {} produces {}-dim embeddings...", ollama_config.model.bright_cyan(), options.dimension);
+    let model = EmbeddingModel::from_config(ollama_config.clone())?;
+    model.embed_texts_strict(&["dimension probe".to_string()]).await?;
+
+    let chunks = app.db.get_all_chunks_with_paths()?;
+    if chunks.is_empty() {
+        println!("{}", "No indexed chunks; updating configuration only".yellow());
+    }
+
+    // Re-embed into the staging table
+    app.db.discard_staged_embeddings()?;
+    let batch_size = options.batch_size.max(1);
+    let mut embedded = 0;
+    for batch in chunks.chunks(batch_size) {
+        let texts = batch.iter()
+            .map(|(chunk, path, _)| app.chunk_embedding_text(chunk, path))
+            .collect::<Result<Vec<String>>>()?;
+        let vectors = match model.embed_texts_strict(&texts).await {
+            Ok(vectors) => vectors,
+            Err(e) => {
+                app.db.discard_staged_embeddings()?;
+                return Err(e);
+            }
+        };
+        let staged: Vec<(u32, Vec<f32>)> = batch.iter().map(|(chunk, _, _)| chunk.id).zip(vectors).collect();
+        app.db.stage_embeddings(&staged)?;
+        embedded += batch.len();
+        println!("   Re-embedded {}/{} chunks", embedded, chunks.len());
+    }
+
+    // Build the new Pinecone index alongside the old one
+    let old_pinecone = app.config.pinecone.clone();
+    let mut new_pinecone = None;
+    if app.config.vector_store.backend == "qdrant" {
+        println!("{}", "Note: Qdrant collections are not migrated; recreate the collection with the new dimension and re-index.".yellow());
+    } else if !old_pinecone.api_key.is_empty() {
+        let admin = PineconeAdmin::new(old_pinecone.api_key.clone());
+        let new_index = options.index_name.clone()
+            .unwrap_or_else(|| format!("{}-{}", old_pinecone.index_name, options.dimension));
+        if new_index == old_pinecone.index_name {
+            app.db.discard_staged_embeddings()?;
+            anyhow::bail!("New index name must differ from the current index {}", old_pinecone.index_name);
+        }
+
+        println!("📦 Creating Pinecone index {} ({} dims)...", new_index.bright_cyan(), options.dimension);
+        let description = match create_and_fill_index(app, &admin, &new_index, &old_pinecone, &chunks, &options).await {
+            Ok(description) => description,
+            Err(e) => {
+                app.db.discard_staged_embeddings()?;
+                return Err(e);
+            }
+        };
+        new_pinecone = Some(PineconeConfig {
+            index_name: new_index,
+            host: Some(format!("https://{}", description.host.trim_start_matches("https://"))),
+            ..old_pinecone.clone()
+        });
+    }
+
+    // Swap: local embeddings first (single transaction), then configuration
+    app.db.swap_staged_embeddings(&model.version().await)?;
+    app.config.ollama = ollama_config;
+    if let Some(ref pinecone) = new_pinecone {
+        app.config.pinecone = pinecone.clone();
+    }
+    app.config.save_to_file("config.toml")?;
+    println!("{}", "✅ Embeddings swapped and config.toml updated".green());
+
+    if let Some(pinecone) = new_pinecone {
+        if options.keep_old_index {
+            println!("Keeping old index {}", old_pinecone.index_name);
+        } else {
+            println!("🧹 Deleting old index {}...", old_pinecone.index_name);
+            let admin = PineconeAdmin::new(pinecone.api_key);
+            if let Err(e) = admin.delete_index(&old_pinecone.index_name).await {
+                eprintln!("Warning: Failed to delete old index {}: {}", old_pinecone.index_name, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn create_and_fill_index(
+    app: &ChunkyMonkeyApp,
+    admin: &PineconeAdmin,
+    index_name: &str,
+    old_config: &PineconeConfig,
+    chunks: &[(crate::core::types::Chunk, String, Option<String>)],
+    options: &MigrationOptions,
+) -> Result<crate::pinecone::IndexDescription> {
+    admin.create_index(index_name, options.dimension, app.config.search.metric.pinecone_name(), &options.cloud, &options.region).await?;
+    let description = admin.wait_until_ready(index_name, INDEX_READY_TIMEOUT).await?;
+
+    let mut client = PineconeClient::new(PineconeConfig {
+        index_name: index_name.to_string(),
+        host: Some(format!("https://{}", description.host.trim_start_matches("https://"))),
+        ..old_config.clone()
+    })?;
+
+    // Each project lives in its own namespace
+    let projects: std::collections::BTreeSet<Option<String>> = chunks.iter().map(|(_, _, project)| project.clone()).collect();
+    for project in projects {
+        client.set_namespace(project.clone());
+        let project_chunks: Vec<_> = chunks.iter().filter(|(_, _, p)| *p == project).collect();
+        for batch in project_chunks.chunks(crate::pinecone::UPSERT_BATCH_SIZE) {
+            upsert_batch(app, &client, batch).await?;
+        }
+    }
+
+    Ok(description)
+}
+
+async fn upsert_batch(
+    app: &ChunkyMonkeyApp,
+    client: &PineconeClient,
+    batch: &[&(crate::core::types::Chunk, String, Option<String>)],
+) -> Result<()> {
+    let mut vectors = Vec::new();
+    for (chunk, path, project) in batch.iter().copied() {
+        let values = app.db.get_staged_embedding(chunk.id)?
+            .ok_or_else(|| anyhow::anyhow!("Missing staged embedding for chunk {}", chunk.id))?;
+        let tags = app.db.get_document_metadata(project.as_deref(), path)?.tags;
+        let metadata = ChunkMetadata::for_stored_chunk(path, project.as_deref(), &tags, &chunk.text, chunk.language.as_deref());
+        vectors.push(Vector {
+            id: format!("chunk_{}", chunk.id),
+            values,
+            metadata: crate::pinecone::chunk_metadata(path, &chunk.text, chunk.id, chunk.document_id, &metadata),
+        });
+    }
+    client.upsert_vectors(vectors).await
+}
@@ -0,0 +1,195 @@
+// Text of images and scanned PDFs through Tesseract OCR (requires the `ocr` cargo feature)
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Image formats Leptonica reads
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "tif", "tiff", "bmp", "gif", "webp"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OcrConfig {
+    /// Index images and PDFs by the text OCR finds in them
+    pub enabled: bool,
+    /// Tesseract languages, joined with '+' ("eng", "eng+deu")
+    pub languages: String,
+    /// Directory of Tesseract's `.traineddata` files; Tesseract's own default when unset
+    pub data_path: Option<String>,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            languages: "eng".to_string(),
+            data_path: None,
+        }
+    }
+}
+
+/// Whether the file at `path` is an image or PDF that is read through OCR
+pub fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf") || IMAGE_EXTENSIONS.iter().any(|e| extension.eq_ignore_ascii_case(e)))
+}
+
+/// Text read from a file by `extract_text`
+pub struct Extracted {
+    pub text: String,
+    /// Some of it was recognized by OCR rather than read from a PDF's text layer
+    pub ocr: bool,
+}
+
+/// Text recognized in the image at `path`, or the text of the PDF at `path` with its pages
+/// separated by blank lines. Pages with a text layer are read from it; only pages without one
+/// (scans) are recognized from their images.
+#[cfg(feature = "ocr")]
+pub fn extract_text(path: &Path, config: &OcrConfig) -> Result<Extracted> {
+    let is_pdf = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+    if is_pdf {
+        return pdf_text(path, config);
+    }
+    let mut tesseract = tesseract(config)?;
+    tesseract.set_image(path).map_err(|e| anyhow::anyhow!("Cannot read image {}: {:?}", path.display(), e))?;
+    let text = tesseract.get_utf8_text().map_err(|e| anyhow::anyhow!("OCR failed on {}: {:?}", path.display(), e))?;
+    Ok(Extracted { text, ocr: true })
+}
+
+#[cfg(not(feature = "ocr"))]
+pub fn extract_text(_path: &Path, _config: &OcrConfig) -> Result<Extracted> {
+    anyhow::bail!("ChunkyMonkey was built without OCR support; rebuild with `--features ocr` or set ocr.enabled = false")
+}
+
+#[cfg(feature = "ocr")]
+fn tesseract(config: &OcrConfig) -> Result<leptess::LepTess> {
+    leptess::LepTess::new(config.data_path.as_deref(), &config.languages)
+        .map_err(|e| anyhow::anyhow!("Tesseract could not load languages '{}': {:?}", config.languages, e))
+}
+
+/// Most a page's content or one image is decompressed to, which keeps a PDF bomb from
+/// exhausting memory; a 600 dpi letter-size RGB scan is about 100 MiB
+#[cfg(feature = "ocr")]
+const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+#[cfg(feature = "ocr")]
+fn pdf_text(path: &Path, config: &OcrConfig) -> Result<Extracted> {
+    let document = lopdf::Document::load_mem(&std::fs::read(path)?)
+        .map_err(|e| anyhow::anyhow!("Cannot read PDF {}: {}", path.display(), e))?;
+    // Tesseract is only loaded for the first page without a text layer
+    let mut tesseract = None;
+    let mut pages = Vec::new();
+    let mut ocr = false;
+    for (number, page_id) in document.get_pages() {
+        let text = document.extract_text_with_limit(&[number], MAX_DECOMPRESSED_SIZE).unwrap_or_default();
+        if !text.trim().is_empty() {
+            pages.push(text);
+            continue;
+        }
+        for image in page_images(&document, page_id) {
+            let tesseract = match tesseract {
+                Some(ref mut tesseract) => tesseract,
+                None => tesseract.insert(self::tesseract(config)?),
+            };
+            tesseract.set_image_from_mem(&image)
+                .map_err(|e| anyhow::anyhow!("Cannot read page {} of {}: {:?}", number, path.display(), e))?;
+            pages.push(tesseract.get_utf8_text()
+                .map_err(|e| anyhow::anyhow!("OCR failed on page {} of {}: {:?}", number, path.display(), e))?);
+            ocr = true;
+        }
+    }
+    Ok(Extracted { text: pages.join("\n\n"), ocr })
+}
+
+/// Images of a page in a form Leptonica reads: JPEG (`/DCTDecode`) as stored, CCITT fax data
+/// (`/CCITTFaxDecode`) in a TIFF, and Flate (or otherwise) compressed pixels as PNM. Images in
+/// other encodings (JPEG 2000, JBIG2) or color spaces are skipped.
+#[cfg(feature = "ocr")]
+fn page_images(document: &lopdf::Document, page_id: lopdf::ObjectId) -> Vec<Vec<u8>> {
+    let Ok(images) = document.get_page_images(page_id) else {
+        return Vec::new();
+    };
+    images.iter().filter_map(|image| {
+        let (width, height) = (u32::try_from(image.width).ok()?, u32::try_from(image.height).ok()?);
+        match image.filters.as_deref().unwrap_or_default() {
+            [filter] if filter == "DCTDecode" => Some(image.content.to_vec()),
+            [filter] if filter == "CCITTFaxDecode" => Some(ccitt_tiff(image.content, width, height, image.origin_dict)),
+            _ => {
+                let stream = document.get_object(image.id).ok()?.as_stream().ok()?;
+                let pixels = stream.decompressed_content_with_limit(MAX_DECOMPRESSED_SIZE).ok()?;
+                pnm(&pixels, width as usize, height as usize, image.color_space.as_deref()?, image.bits_per_component?)
+            }
+        }
+    }).collect()
+}
+
+/// Uncompressed pixels as a PNM image: 8-bit gray (PGM) or RGB (PPM), or 1-bit gray (PBM)
+#[cfg(feature = "ocr")]
+fn pnm(pixels: &[u8], width: usize, height: usize, color_space: &str, bits_per_component: i64) -> Option<Vec<u8>> {
+    let (header, row_size) = match (color_space, bits_per_component) {
+        ("DeviceGray" | "CalGray", 8) => (format!("P5\n{} {}\n255\n", width, height), width),
+        ("DeviceRGB" | "CalRGB", 8) => (format!("P6\n{} {}\n255\n", width, height), width * 3),
+        ("DeviceGray" | "CalGray", 1) => (format!("P4\n{} {}\n", width, height), width.div_ceil(8)),
+        _ => return None,
+    };
+    let pixels = pixels.get(..row_size.checked_mul(height)?)?;
+    let mut image = header.into_bytes();
+    if bits_per_component == 1 {
+        // A PDF bitmap has 0 for black, a PBM 1
+        image.extend(pixels.iter().map(|byte| !byte));
+    } else {
+        image.extend_from_slice(pixels);
+    }
+    Some(image)
+}
+
+/// CCITT fax data of an image as a single-strip TIFF, with the coding its `/DecodeParms` give
+#[cfg(feature = "ocr")]
+fn ccitt_tiff(data: &[u8], width: u32, height: u32, dictionary: &lopdf::Dictionary) -> Vec<u8> {
+    let params = dictionary.get(b"DecodeParms").and_then(lopdf::Object::as_dict).ok();
+    let param = |key: &[u8]| params.and_then(|params| params.get(key).ok());
+    let number = |key: &[u8]| param(key).and_then(|value| value.as_i64().ok());
+    let flag = |key: &[u8]| param(key).and_then(|value| value.as_bool().ok()).unwrap_or(false);
+
+    // K < 0 is Group 4, K = 0 one-dimensional Group 3 and K > 0 two-dimensional Group 3
+    let k = number(b"K").unwrap_or(0);
+    let columns = number(b"Columns").and_then(|columns| u32::try_from(columns).ok()).unwrap_or(width);
+    let rows = number(b"Rows").and_then(|rows| u32::try_from(rows).ok()).filter(|&rows| rows > 0).unwrap_or(height);
+    // TIFF's photometric interpretation: 0 is white when BlackIs1, otherwise black
+    let photometric = if flag(b"BlackIs1") { 0 } else { 1 };
+
+    // (tag, type: 3 short or 4 long, value)
+    let mut entries: Vec<(u16, u16, u32)> = vec![
+        (256, 4, columns),
+        (257, 4, rows),
+        (258, 3, 1),
+        (259, 3, if k < 0 { 4 } else { 3 }),
+        (262, 3, photometric),
+        (273, 4, 0),
+        (277, 3, 1),
+        (278, 4, rows),
+        (279, 4, data.len() as u32),
+    ];
+    if k >= 0 {
+        // T4Options: two-dimensional coding, and rows padded to whole bytes
+        entries.push((292, 4, u32::from(k > 0) | if flag(b"EncodedByteAlign") { 4 } else { 0 }));
+    }
+    let data_offset = 8 + 2 + entries.len() * 12 + 4;
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend(8u32.to_le_bytes());
+    tiff.extend((entries.len() as u16).to_le_bytes());
+    for (tag, kind, value) in entries {
+        let value = if tag == 273 { data_offset as u32 } else { value };
+        tiff.extend(tag.to_le_bytes());
+        tiff.extend(kind.to_le_bytes());
+        tiff.extend(1u32.to_le_bytes());
+        match kind {
+            // A short sits in the first two bytes of the value field
+            3 => tiff.extend([(value as u16).to_le_bytes(), [0, 0]].concat()),
+            _ => tiff.extend(value.to_le_bytes()),
+        }
+    }
+    tiff.extend(0u32.to_le_bytes());
+    tiff.extend_from_slice(data);
+    tiff
+}
@@ -1,6 +1,15 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::chaos::{ChaosInjector, ChaosTarget};
+use crate::core::debug::SendLogged;
+use crate::core::types::{ChunkMetadata, SearchFilter};
+
+/// Maximum vectors per upsert request, as recommended by Pinecone
+pub const UPSERT_BATCH_SIZE: usize = 100;
+
+/// Maximum ids per delete request
+pub const DELETE_BATCH_SIZE: usize = 1000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PineconeConfig {
@@ -29,6 +38,8 @@ pub struct QueryRequest {
     pub top_k: Option<u32>,
     pub include_metadata: Option<bool>,
     pub namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,6 +56,117 @@ pub struct Match {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+/// Metadata stored with every chunk vector (Pinecone metadata / Qdrant payload)
+pub fn chunk_metadata(source: &str, text: &str, chunk_id: u32, document_id: u32, chunk: &ChunkMetadata) -> HashMap<String, serde_json::Value> {
+    let mut metadata = HashMap::new();
+    metadata.insert("source".to_string(), serde_json::Value::String(source.to_string()));
+    metadata.insert("text".to_string(), serde_json::Value::String(text.to_string()));
+    metadata.insert("chunk_id".to_string(), serde_json::Value::Number(chunk_id.into()));
+    metadata.insert("document_id".to_string(), serde_json::Value::Number(document_id.into()));
+    
+    // Pinecone rejects null metadata values, so unset fields are left out
+    let optional = [("extension", &chunk.extension), ("project", &chunk.project), ("language", &chunk.language),
+        ("natural_language", &chunk.natural_language)];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            metadata.insert(key.to_string(), serde_json::Value::String(value.clone()));
+        }
+    }
+    if !chunk.tags.is_empty() {
+        metadata.insert("tags".to_string(), serde_json::json!(chunk.tags));
+    }
+    metadata
+}
+
+/// Read the structured chunk metadata back out of a match, deriving what is missing from the
+/// source path (vectors written before these fields were stored carry only `source`)
+pub fn parse_chunk_metadata(metadata: &HashMap<String, serde_json::Value>, source: &str) -> ChunkMetadata {
+    let string = |key: &str| metadata.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+    let tags: Vec<String> = metadata.get("tags")
+        .and_then(|v| v.as_array())
+        .map(|tags| tags.iter().filter_map(|t| t.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default();
+    
+    let text = string("text").unwrap_or_default();
+    let derived = match string("natural_language") {
+        Some(natural_language) => ChunkMetadata::for_stored_chunk(source, None, &tags, &text, Some(&natural_language)),
+        None => ChunkMetadata::for_chunk(source, None, &tags, &text),
+    };
+    ChunkMetadata {
+        extension: string("extension").or(derived.extension),
+        project: string("project"),
+        tags,
+        language: string("language").or(derived.language),
+        natural_language: derived.natural_language,
+    }
+}
+
+/// Translate a search filter into Pinecone's metadata filter syntax.
+///
+/// Pinecone has no prefix operator, so `path_prefix` is not pushed down and must be checked on the
+/// returned matches. Returns None when nothing can be pushed down.
+pub fn metadata_filter(filter: &SearchFilter) -> Option<serde_json::Value> {
+    let mut clauses = Vec::new();
+    if let Some(ref document) = filter.document {
+        clauses.push(serde_json::json!({ "source": { "$eq": document } }));
+    }
+    if let Some(ref documents) = filter.documents {
+        clauses.push(serde_json::json!({ "source": { "$in": documents } }));
+    }
+    if !filter.extensions.is_empty() {
+        clauses.push(serde_json::json!({ "extension": { "$in": filter.normalized_extensions() } }));
+    }
+    if let Some(ref project) = filter.project {
+        clauses.push(serde_json::json!({ "project": { "$eq": project } }));
+    }
+    // A list field matches `$in` when any element is listed, so each required tag is its own clause
+    for tag in &filter.tags {
+        clauses.push(serde_json::json!({ "tags": { "$in": [tag] } }));
+    }
+    if !filter.languages.is_empty() {
+        let languages: Vec<String> = filter.languages.iter().map(|l| l.to_lowercase()).collect();
+        clauses.push(serde_json::json!({ "$or": [
+            { "language": { "$in": languages } },
+            { "natural_language": { "$in": languages } },
+        ] }));
+    }
+    
+    match clauses.len() {
+        0 => None,
+        1 => clauses.pop(),
+        _ => Some(serde_json::json!({ "$and": clauses })),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NamespaceStats {
+    #[serde(default, rename = "vectorCount")]
+    pub vector_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IndexStats {
+    #[serde(default)]
+    pub namespaces: HashMap<String, NamespaceStats>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedVector {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListPagination {
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListResponse {
+    #[serde(default)]
+    vectors: Vec<ListedVector>,
+    pagination: Option<ListPagination>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Usage {
     #[serde(default)]
@@ -55,6 +177,9 @@ pub struct PineconeClient {
     client: reqwest::Client,
     pub config: PineconeConfig,
     base_url: String,
+    chaos: ChaosInjector,
+    /// Namespace all requests are scoped to (one per ChunkyMonkey project)
+    namespace: Option<String>,
 }
 
 impl PineconeClient {
@@ -75,6 +200,8 @@ impl PineconeClient {
             client,
             config,
             base_url,
+            chaos: ChaosInjector::default(),
+            namespace: None,
         })
     }
 
@@ -91,13 +218,25 @@ impl PineconeClient {
             client: reqwest::Client::new(),
             config,
             base_url: String::new(),
+            chaos: ChaosInjector::default(),
+            namespace: None,
         })
     }
 
+    pub fn set_chaos(&mut self, chaos: ChaosInjector) {
+        self.chaos = chaos;
+    }
+
+    pub fn set_namespace(&mut self, namespace: Option<String>) {
+        self.namespace = namespace;
+    }
+
     pub async fn upsert_vectors(&self, vectors: Vec<Vector>) -> Result<()> {
+        self.chaos.inject(ChaosTarget::Pinecone).await?;
+        
         let request = UpsertRequest {
             vectors,
-            namespace: None,
+            namespace: self.namespace.clone(),
         };
 
         let response = self
@@ -106,7 +245,7 @@ impl PineconeClient {
             .header("Api-Key", &self.config.api_key)
             .header("Content-Type", "application/json")
             .json(&request)
-            .send()
+            .send_logged("pinecone")
             .await?;
 
         if !response.status().is_success() {
@@ -121,12 +260,16 @@ impl PineconeClient {
         &self,
         vector: Vec<f32>,
         top_k: u32,
+        filter: Option<serde_json::Value>,
     ) -> Result<Vec<Match>> {
+        self.chaos.inject(ChaosTarget::Pinecone).await?;
+        
         let request = QueryRequest {
             vector: vector.clone(),
             top_k: Some(top_k),
             include_metadata: Some(true),
-            namespace: None,
+            namespace: self.namespace.clone(),
+            filter,
         };
 
         let response = self
@@ -135,7 +278,7 @@ impl PineconeClient {
             .header("Api-Key", &self.config.api_key)
             .header("Content-Type", "application/json")
             .json(&request)
-            .send()
+            .send_logged("pinecone")
             .await?;
 
         if !response.status().is_success() {
@@ -157,10 +300,20 @@ impl PineconeClient {
         }
     }
 
+    /// Delete vectors by id, split into requests of at most `DELETE_BATCH_SIZE` ids
     pub async fn delete_vectors(&self, ids: Vec<String>) -> Result<()> {
+        for batch in ids.chunks(DELETE_BATCH_SIZE) {
+            self.delete_vector_batch(batch).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_vector_batch(&self, ids: &[String]) -> Result<()> {
+        self.chaos.inject(ChaosTarget::Pinecone).await?;
+        
         let request = serde_json::json!({
             "ids": ids,
-            "namespace": null
+            "namespace": self.namespace
         });
 
         let response = self
@@ -169,7 +322,7 @@ impl PineconeClient {
             .header("Api-Key", &self.config.api_key)
             .header("Content-Type", "application/json")
             .json(&request)
-            .send()
+            .send_logged("pinecone")
             .await?;
 
         if !response.status().is_success() {
@@ -179,4 +332,214 @@ impl PineconeClient {
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    pub async fn describe_index_stats(&self) -> Result<IndexStats> {
+        self.chaos.inject(ChaosTarget::Pinecone).await?;
+        
+        let response = self
+            .client
+            .post(format!("{}/describe_index_stats", self.base_url))
+            .header("Api-Key", &self.config.api_key)
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({}))
+            .send_logged("pinecone")
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Pinecone describe_index_stats failed: {}", error_text);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// List the ids of all vectors in the client's namespace starting with `prefix` (serverless indexes)
+    pub async fn list_vector_ids(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        let mut pagination_token: Option<String> = None;
+
+        loop {
+            self.chaos.inject(ChaosTarget::Pinecone).await?;
+            
+            let mut query = vec![("prefix", prefix.to_string()), ("limit", "100".to_string())];
+            if let Some(ref namespace) = self.namespace {
+                query.push(("namespace", namespace.clone()));
+            }
+            if let Some(ref token) = pagination_token {
+                query.push(("paginationToken", token.clone()));
+            }
+
+            let response = self
+                .client
+                .get(format!("{}/vectors/list", self.base_url))
+                .header("Api-Key", &self.config.api_key)
+                .query(&query)
+                .send_logged("pinecone")
+                .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                anyhow::bail!("Pinecone list vectors failed: {}", error_text);
+            }
+
+            let page: ListResponse = response.json().await?;
+            ids.extend(page.vectors.into_iter().map(|v| v.id));
+
+            match page.pagination.and_then(|p| p.next) {
+                Some(next) => pagination_token = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// Delete every vector in the client's namespace
+    pub async fn delete_namespace(&self) -> Result<()> {
+        self.delete_all_in(self.namespace.as_deref().unwrap_or_default()).await
+    }
+
+    /// Delete every vector in every namespace of the index, whichever project wrote it
+    pub async fn delete_all_namespaces(&self) -> Result<()> {
+        for namespace in self.describe_index_stats().await?.namespaces.keys() {
+            self.delete_all_in(namespace).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_all_in(&self, namespace: &str) -> Result<()> {
+        self.chaos.inject(ChaosTarget::Pinecone).await?;
+        
+        let request = serde_json::json!({
+            "deleteAll": true,
+            "namespace": namespace
+        });
+
+        let response = self
+            .client
+            .post(&format!("{}/vectors/delete", self.base_url))
+            .header("Api-Key", &self.config.api_key)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send_logged("pinecone")
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Pinecone namespace delete failed: {}", error_text);
+        }
+
+        Ok(())
+    }
+}
+
+const CONTROL_PLANE_URL: &str = "https://api.pinecone.io";
+const API_VERSION: &str = "2024-07";
+
+#[derive(Debug, Deserialize)]
+pub struct IndexStatus {
+    pub ready: bool,
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IndexDescription {
+    pub host: String,
+    pub status: IndexStatus,
+    #[serde(default)]
+    pub dimension: Option<usize>,
+}
+
+/// Control-plane client for creating, describing and deleting Pinecone indexes
+pub struct PineconeAdmin {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl PineconeAdmin {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+        }
+    }
+
+    pub async fn create_index(&self, name: &str, dimension: usize, metric: &str, cloud: &str, region: &str) -> Result<()> {
+        let request = serde_json::json!({
+            "name": name,
+            "dimension": dimension,
+            "metric": metric,
+            "spec": {
+                "serverless": {
+                    "cloud": cloud,
+                    "region": region
+                }
+            }
+        });
+
+        let response = self
+            .client
+            .post(format!("{}/indexes", CONTROL_PLANE_URL))
+            .header("Api-Key", &self.api_key)
+            .header("X-Pinecone-API-Version", API_VERSION)
+            .json(&request)
+            .send_logged("pinecone")
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Pinecone index creation failed: {}", error_text);
+        }
+
+        Ok(())
+    }
+
+    pub async fn describe_index(&self, name: &str) -> Result<IndexDescription> {
+        let response = self
+            .client
+            .get(format!("{}/indexes/{}", CONTROL_PLANE_URL, name))
+            .header("Api-Key", &self.api_key)
+            .header("X-Pinecone-API-Version", API_VERSION)
+            .send_logged("pinecone")
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Pinecone describe index failed: {}", error_text);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Poll the index until it reports ready, giving up after `timeout`
+    pub async fn wait_until_ready(&self, name: &str, timeout: std::time::Duration) -> Result<IndexDescription> {
+        let start = std::time::Instant::now();
+        loop {
+            let description = self.describe_index(name).await?;
+            if description.status.ready {
+                return Ok(description);
+            }
+            if start.elapsed() > timeout {
+                anyhow::bail!("Pinecone index {} not ready after {:?} (state: {})", name, timeout, description.status.state);
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        }
+    }
+
+    pub async fn delete_index(&self, name: &str) -> Result<()> {
+        let response = self
+            .client
+            .delete(format!("{}/indexes/{}", CONTROL_PLANE_URL, name))
+            .header("Api-Key", &self.api_key)
+            .header("X-Pinecone-API-Version", API_VERSION)
+            .send_logged("pinecone")
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Pinecone index deletion failed: {}", error_text);
+        }
+
+        Ok(())
+    }
+}
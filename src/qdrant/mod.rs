@@ -0,0 +1,223 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::chaos::{ChaosInjector, ChaosTarget};
+use crate::core::debug::SendLogged;
+use crate::embeddings::DistanceMetric;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QdrantConfig {
+    pub url: String,
+    pub api_key: Option<String>,
+    pub collection_name: String,
+}
+
+impl Default for QdrantConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            api_key: None,
+            collection_name: "chunkymonkey".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Point {
+    pub id: u64,
+    pub vector: Vec<f32>,
+    pub payload: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpsertRequest {
+    pub points: Vec<Point>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchRequest {
+    pub vector: Vec<f32>,
+    pub limit: u32,
+    pub with_payload: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub result: Vec<ScoredPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScoredPoint {
+    pub id: serde_json::Value,
+    pub score: f32,
+    #[serde(default)]
+    pub payload: Option<HashMap<String, serde_json::Value>>,
+}
+
+pub struct QdrantClient {
+    client: reqwest::Client,
+    pub config: QdrantConfig,
+    base_url: String,
+    collection_ready: AtomicBool,
+    chaos: ChaosInjector,
+}
+
+impl QdrantClient {
+    pub fn new(config: QdrantConfig) -> Result<Self> {
+        if config.url.is_empty() {
+            anyhow::bail!("Qdrant URL is not configured");
+        }
+
+        let base_url = config.url.trim_end_matches('/').to_string();
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            config,
+            base_url,
+            collection_ready: AtomicBool::new(false),
+            chaos: ChaosInjector::default(),
+        })
+    }
+
+    pub fn set_chaos(&mut self, chaos: ChaosInjector) {
+        self.chaos = chaos;
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut builder = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path))
+            .header("Content-Type", "application/json");
+
+        if let Some(ref api_key) = self.config.api_key {
+            builder = builder.header("api-key", api_key);
+        }
+
+        builder
+    }
+
+    /// Vector size of the configured collection, or None when it doesn't exist yet
+    pub async fn collection_dimension(&self) -> Result<Option<usize>> {
+        let path = format!("/collections/{}", self.config.collection_name);
+        let response = self.request(reqwest::Method::GET, &path).send_logged("qdrant").await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Qdrant collection lookup failed: {}", error_text);
+        }
+
+        let info: serde_json::Value = response.json().await?;
+        Ok(info["result"]["config"]["params"]["vectors"]["size"].as_u64().map(|size| size as usize))
+    }
+
+    /// Create the collection with the given vector dimension if it doesn't exist yet
+    pub async fn ensure_collection(&self, dimension: usize, metric: DistanceMetric) -> Result<()> {
+        if self.collection_ready.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let path = format!("/collections/{}", self.config.collection_name);
+        let response = self.request(reqwest::Method::GET, &path).send_logged("qdrant").await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            let request = serde_json::json!({
+                "vectors": {
+                    "size": dimension,
+                    "distance": metric.qdrant_name()
+                }
+            });
+
+            let response = self
+                .request(reqwest::Method::PUT, &path)
+                .json(&request)
+                .send_logged("qdrant")
+                .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                anyhow::bail!("Qdrant collection creation failed: {}", error_text);
+            }
+        } else if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Qdrant collection lookup failed: {}", error_text);
+        }
+
+        self.collection_ready.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub async fn upsert_points(&self, points: Vec<Point>) -> Result<()> {
+        self.chaos.inject(ChaosTarget::Qdrant).await?;
+        
+        let request = UpsertRequest { points };
+
+        let response = self
+            .request(
+                reqwest::Method::PUT,
+                &format!("/collections/{}/points?wait=true", self.config.collection_name),
+            )
+            .json(&request)
+            .send_logged("qdrant")
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Qdrant upsert failed: {}", error_text);
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_points(&self, ids: Vec<u64>) -> Result<()> {
+        self.chaos.inject(ChaosTarget::Qdrant).await?;
+        
+        let request = serde_json::json!({ "points": ids });
+
+        let response = self
+            .request(
+                reqwest::Method::POST,
+                &format!("/collections/{}/points/delete?wait=true", self.config.collection_name),
+            )
+            .json(&request)
+            .send_logged("qdrant")
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Qdrant delete failed: {}", error_text);
+        }
+
+        Ok(())
+    }
+
+    pub async fn search_points(&self, vector: Vec<f32>, limit: u32) -> Result<Vec<ScoredPoint>> {
+        self.chaos.inject(ChaosTarget::Qdrant).await?;
+        
+        let request = SearchRequest {
+            vector,
+            limit,
+            with_payload: true,
+        };
+
+        let response = self
+            .request(
+                reqwest::Method::POST,
+                &format!("/collections/{}/points/search", self.config.collection_name),
+            )
+            .json(&request)
+            .send_logged("qdrant")
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Qdrant search failed: {}", error_text);
+        }
+
+        let search_response: SearchResponse = response.json().await?;
+        Ok(search_response.result)
+    }
+}
@@ -0,0 +1,207 @@
+// Weighted re-ranking of search results, and fitting the weights from relevance feedback
+use anyhow::Result;
+use std::collections::HashMap;
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::types::{RankingWeights, SearchResult};
+
+/// Feedback examples needed before weights are fitted
+const MIN_FEEDBACK: usize = 10;
+/// Age at which the recency signal has decayed to 1/e
+const RECENCY_DAYS: f64 = 30.0;
+const FIT_ITERATIONS: usize = 2000;
+const LEARNING_RATE: f32 = 0.5;
+
+/// Ranking signals for one (query, chunk) pair, each in roughly [0, 1]
+#[derive(Debug, Clone, Copy)]
+pub struct Signals {
+    pub vector: f32,
+    pub keyword: f32,
+    pub rerank: f32,
+    pub recency: f32,
+}
+
+impl Signals {
+    fn as_array(&self) -> [f32; 4] {
+        [self.vector, self.keyword, self.rerank, self.recency]
+    }
+
+    pub fn score(&self, weights: &RankingWeights) -> f32 {
+        weights.vector * self.vector
+            + weights.keyword * self.keyword
+            + weights.rerank * self.rerank
+            + weights.recency * self.recency
+    }
+}
+
+/// Fraction of the query's terms (longer than 2 characters) that appear in the text, comparing
+/// stems in the text's language (see `search.keyword_language`)
+pub fn keyword_overlap(query: &str, text: &str, language: &str) -> f32 {
+    let analyzer = crate::stemming::Analyzer::for_text(language, text);
+    let text_words: std::collections::HashSet<String> = analyzer.terms(text).into_iter().collect();
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 2)
+        .flat_map(|w| analyzer.terms(w))
+        .collect();
+
+    if terms.is_empty() {
+        return 0.0;
+    }
+    terms.iter().filter(|t| text_words.contains(*t)).count() as f32 / terms.len() as f32
+}
+
+/// Exponential decay on the time since the document was indexed; 0 when unknown
+pub fn recency(indexed_at: Option<i64>) -> f32 {
+    let Some(indexed_at) = indexed_at else { return 0.0 };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let age_days = (now - indexed_at).max(0) as f64 / 86_400.0;
+    (-age_days / RECENCY_DAYS).exp() as f32
+}
+
+fn signals_for(app: &ChunkyMonkeyApp, query: &str, result: &SearchResult) -> Result<Signals> {
+    Ok(Signals {
+        vector: result.similarity,
+        keyword: keyword_overlap(query, &result.chunk_text, &app.config.search.keyword_language),
+        rerank: app.score_chunk_relevance(&result.chunk_text, query),
+        recency: recency(app.db.get_document_indexed_at(app.project.as_deref(), &result.document_path)?),
+    })
+}
+
+/// Re-score results with the given weights and sort best first. The combined score replaces `similarity`.
+pub fn rerank(app: &ChunkyMonkeyApp, query: &str, results: &mut [SearchResult], weights: &RankingWeights) -> Result<()> {
+    for result in results.iter_mut() {
+        result.similarity = signals_for(app, query, result)?.score(weights);
+    }
+    results.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then(a.chunk_id.cmp(&b.chunk_id)));
+    Ok(())
+}
+
+/// Outcome of `tune-ranking`
+#[derive(Debug)]
+pub struct TuneReport {
+    pub samples: usize,
+    pub relevant: usize,
+    pub previous: RankingWeights,
+    pub weights: RankingWeights,
+    /// Fraction of (relevant, not relevant) pairs for the same query ordered correctly
+    pub previous_accuracy: f32,
+    pub accuracy: f32,
+    pub applied: bool,
+}
+
+/// Fit ranking weights for the current project from recorded relevance feedback and persist them
+/// (unless `dry_run`). The fit is a logistic regression on the ranking signals with non-negative
+/// weights, normalised to sum to 1 so scores stay on the familiar 0-1 scale.
+pub async fn tune_ranking(app: &mut ChunkyMonkeyApp, dry_run: bool) -> Result<TuneReport> {
+    let feedback = app.db.get_feedback(app.project.as_deref())?;
+    let relevant = feedback.iter().filter(|(_, _, r)| *r).count();
+    if feedback.len() < MIN_FEEDBACK || relevant == 0 || relevant == feedback.len() {
+        anyhow::bail!(
+            "Need at least {} feedback judgements including both relevant and not relevant results (have {}, {} relevant); record some with `chunkymonkey feedback`",
+            MIN_FEEDBACK, feedback.len(), relevant
+        );
+    }
+
+    // Each query is embedded once
+    let mut query_vectors: HashMap<String, Vec<f32>> = HashMap::new();
+    let mut examples = Vec::new();
+    for (query, chunk_id, is_relevant) in &feedback {
+        if !query_vectors.contains_key(query) {
+            query_vectors.insert(query.clone(), app.embedding_model.embed_text(query).await?);
+        }
+        let Some(chunk) = app.db.get_chunk(*chunk_id)? else { continue };
+        let Some(embedding) = app.db.get_embedding(*chunk_id)? else { continue };
+        let document_path = app.db.get_document(chunk.document_id)?.map(|d| d.file_path).unwrap_or_default();
+
+        let result = SearchResult {
+            chunk_id: *chunk_id,
+            similarity: app.config.search.metric.similarity(&query_vectors[query], &embedding.vector),
+            document_path,
+            chunk_text: chunk.text,
+            metadata_only: false,
+            metadata: Default::default(),
+            lines: chunk.line_range,
+        };
+        examples.push((query.as_str(), signals_for(app, query, &result)?, *is_relevant));
+    }
+
+    let previous = app.db.get_ranking_weights(app.project.as_deref())?.unwrap_or_default();
+    let weights = fit_weights(&examples);
+    let report = TuneReport {
+        samples: examples.len(),
+        relevant,
+        previous,
+        weights,
+        previous_accuracy: pairwise_accuracy(&examples, &previous),
+        accuracy: pairwise_accuracy(&examples, &weights),
+        applied: !dry_run,
+    };
+
+    if !dry_run {
+        app.db.save_ranking_weights(app.project.as_deref(), &weights, examples.len())?;
+        app.ranking_weights = weights;
+    }
+    Ok(report)
+}
+
+fn fit_weights(examples: &[(&str, Signals, bool)]) -> RankingWeights {
+    let mut w = [0.25f32; 4];
+    let mut bias = 0.0f32;
+    let n = examples.len() as f32;
+
+    for _ in 0..FIT_ITERATIONS {
+        let mut grad = [0.0f32; 4];
+        let mut grad_bias = 0.0f32;
+        for (_, signals, relevant) in examples {
+            let x = signals.as_array();
+            let z: f32 = bias + w.iter().zip(x.iter()).map(|(wi, xi)| wi * xi).sum::<f32>();
+            let error = 1.0 / (1.0 + (-z).exp()) - if *relevant { 1.0 } else { 0.0 };
+            for i in 0..4 {
+                grad[i] += error * x[i];
+            }
+            grad_bias += error;
+        }
+        for i in 0..4 {
+            // Projected gradient step keeps every signal's weight non-negative
+            w[i] = (w[i] - LEARNING_RATE * grad[i] / n).max(0.0);
+        }
+        bias -= LEARNING_RATE * grad_bias / n;
+    }
+
+    let total: f32 = w.iter().sum();
+    if total <= f32::EPSILON {
+        return RankingWeights::default();
+    }
+    RankingWeights {
+        vector: w[0] / total,
+        keyword: w[1] / total,
+        rerank: w[2] / total,
+        recency: w[3] / total,
+    }
+}
+
+fn pairwise_accuracy(examples: &[(&str, Signals, bool)], weights: &RankingWeights) -> f32 {
+    let mut correct = 0.0;
+    let mut pairs = 0;
+    for (query, positive, relevant) in examples {
+        if !relevant {
+            continue;
+        }
+        for (other_query, negative, other_relevant) in examples {
+            if *other_relevant || other_query != query {
+                continue;
+            }
+            pairs += 1;
+            let (p, n) = (positive.score(weights), negative.score(weights));
+            if p > n {
+                correct += 1.0;
+            } else if p == n {
+                correct += 0.5;
+            }
+        }
+    }
+    if pairs == 0 { 0.0 } else { correct / pairs as f32 }
+}
@@ -0,0 +1,189 @@
+// Incremental replication of a project's documents, chunks and embeddings to a mirror: another
+// index database on disk, or a `serve` instance
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::core::types::ReplicaDocument;
+use crate::db::Database;
+
+/// Documents sent per request to a remote mirror
+const BATCH_SIZE: usize = 16;
+
+/// Read when `replication.token` is empty
+const TOKEN_ENV: &str = "CHUNKYMONKEY_REPLICA_TOKEN";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplicationConfig {
+    /// Shared secret a `serve` instance requires on its `/replica` endpoints and `replicate` sends
+    /// as a bearer token; the CHUNKYMONKEY_REPLICA_TOKEN environment variable when empty. Without
+    /// one, `serve` does not accept replicated documents at all.
+    pub token: String,
+}
+
+impl ReplicationConfig {
+    pub fn token(&self) -> Option<String> {
+        Some(self.token.clone())
+            .filter(|token| !token.is_empty())
+            .or_else(|| std::env::var(TOKEN_ENV).ok().filter(|token| !token.is_empty()))
+    }
+}
+
+/// Outcome of a replication run
+#[derive(Debug, Default)]
+pub struct ReplicationReport {
+    pub target: String,
+    /// New or changed documents shipped to the mirror
+    pub sent: usize,
+    /// Chunks of the shipped documents
+    pub chunks: usize,
+    /// Documents removed from the mirror because the source no longer has them
+    pub removed: usize,
+    pub unchanged: usize,
+    pub dry_run: bool,
+}
+
+/// Body of `GET /replica/manifest`
+#[derive(Serialize, Deserialize)]
+pub struct ManifestResponse {
+    pub revisions: BTreeMap<String, String>,
+}
+
+/// Body of `POST /replica/documents`
+#[derive(Serialize, Deserialize)]
+pub struct ImportBody {
+    pub documents: Vec<ReplicaDocument>,
+}
+
+/// Body of `POST /replica/remove`
+#[derive(Serialize, Deserialize)]
+pub struct RemoveBody {
+    pub paths: Vec<String>,
+}
+
+/// Where documents are replicated to
+enum Mirror {
+    /// An index database file, written directly; documents keep their project
+    Database(Database),
+    /// A `serve` instance, which stores documents in the project it serves and adds them to its
+    /// vector stores
+    Remote { client: reqwest::Client, url: String, token: String },
+}
+
+impl Mirror {
+    fn open(to: &str, token: Option<String>) -> Result<Self> {
+        if to.starts_with("http://") || to.starts_with("https://") {
+            let Some(token) = token else {
+                anyhow::bail!("Replicating to a serve instance needs the token it was started with (replication.token or {})", TOKEN_ENV);
+            };
+            return Ok(Self::Remote { client: reqwest::Client::new(), url: to.trim_end_matches('/').to_string(), token });
+        }
+        let source = std::fs::canonicalize(crate::db::DATABASE_PATH).ok();
+        if source.is_some() && std::fs::canonicalize(to).ok() == source {
+            anyhow::bail!("{} is this index's own database", to);
+        }
+        Ok(Self::Database(Database::open(to)?))
+    }
+
+    async fn revisions(&self, project: Option<&str>) -> Result<BTreeMap<String, String>> {
+        match self {
+            Self::Database(db) => db.document_revisions(project),
+            Self::Remote { client, url, token } => {
+                let response = client.get(format!("{}/replica/manifest", url)).bearer_auth(token).send().await?;
+                Ok(check(response).await?.json::<ManifestResponse>().await?.revisions)
+            }
+        }
+    }
+
+    async fn import(&mut self, documents: Vec<ReplicaDocument>, project: Option<&str>) -> Result<()> {
+        match self {
+            Self::Database(db) => {
+                for document in &documents {
+                    db.import_document(document, project)?;
+                }
+                Ok(())
+            }
+            Self::Remote { client, url, token } => {
+                let response = client.post(format!("{}/replica/documents", url))
+                    .bearer_auth(token)
+                    .json(&ImportBody { documents })
+                    .send().await?;
+                check(response).await.map(|_| ())
+            }
+        }
+    }
+
+    async fn remove(&mut self, paths: Vec<String>, project: Option<&str>) -> Result<()> {
+        match self {
+            Self::Database(db) => {
+                for path in &paths {
+                    if let Some(document_id) = db.get_document_id_by_path(project, path)? {
+                        db.delete_document(document_id)?;
+                    }
+                }
+                Ok(())
+            }
+            Self::Remote { client, url, token } => {
+                let response = client.post(format!("{}/replica/remove", url))
+                    .bearer_auth(token)
+                    .json(&RemoveBody { paths })
+                    .send().await?;
+                check(response).await.map(|_| ())
+            }
+        }
+    }
+}
+
+/// Turn an error status from a mirror into an error carrying its message
+async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    let message = body.get("error").and_then(|e| e.as_str()).unwrap_or("no details");
+    anyhow::bail!("Mirror returned {}: {}", status, message)
+}
+
+/// Bring the mirror at `to` (a database path or a `serve` URL) up to date with `project`: ship
+/// documents it lacks or holds at another revision, and remove those the source no longer has.
+/// Unchanged documents are not sent, so repeated runs only cost what changed. A `serve` mirror is
+/// sent `token`.
+pub async fn replicate(db: &Database, project: Option<&str>, to: &str, token: Option<String>, dry_run: bool) -> Result<ReplicationReport> {
+    let mut mirror = Mirror::open(to, token)?;
+    let local = db.document_revisions(project)?;
+    let remote = mirror.revisions(project).await?;
+
+    let changed: Vec<&String> = local.iter()
+        .filter(|(path, revision)| remote.get(*path) != Some(*revision))
+        .map(|(path, _)| path)
+        .collect();
+    let removed: Vec<String> = remote.keys().filter(|path| !local.contains_key(*path)).cloned().collect();
+
+    let mut report = ReplicationReport {
+        target: to.to_string(),
+        sent: changed.len(),
+        removed: removed.len(),
+        unchanged: local.len() - changed.len(),
+        dry_run,
+        ..Default::default()
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    for batch in changed.chunks(BATCH_SIZE) {
+        let mut documents = Vec::with_capacity(batch.len());
+        for path in batch {
+            if let Some(document) = db.export_document(project, path)? {
+                report.chunks += document.chunks.len();
+                documents.push(document);
+            }
+        }
+        mirror.import(documents, project).await?;
+    }
+    if !removed.is_empty() {
+        mirror.remove(removed, project).await?;
+    }
+    Ok(report)
+}
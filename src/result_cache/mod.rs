@@ -0,0 +1,51 @@
+// Result cache: search results and answers kept in the database by query and settings, so the same
+// search or question over an unchanged corpus comes back at once instead of embedding, searching
+// and generating again. Entries belong to one index generation and expire after `ttl_secs`.
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResultCacheConfig {
+    /// Seconds a cached search result or answer is reused; 0 disables the cache
+    pub ttl_secs: u64,
+    /// Entries kept at most; the least recently cached are dropped beyond this
+    pub max_entries: usize,
+}
+
+impl Default for ResultCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 3600,
+            max_entries: 1000,
+        }
+    }
+}
+
+impl ResultCacheConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.ttl_secs > 0 && self.max_entries > 0
+    }
+}
+
+/// What an entry holds; part of the key, so a search and a question with the same text never collide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultKind {
+    Search,
+    Answer,
+}
+
+impl ResultKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResultKind::Search => "search",
+            ResultKind::Answer => "answer",
+        }
+    }
+}
+
+/// Stable key for a request: the same kind and inputs always hash the same
+pub fn cache_key(kind: ResultKind, request: &serde_json::Value) -> String {
+    let digest = Sha256::digest(format!("{}:{}", kind.as_str(), request).as_bytes());
+    format!("{:x}", digest)
+}
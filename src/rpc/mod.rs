@@ -0,0 +1,261 @@
+// JSON-RPC 2.0 daemon over stdio for editor integrations, framed like LSP (Content-Length headers)
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::types::{AnswerStyle, SearchFilter};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Largest message body read; a longer one is skipped and answered with a parse error
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+const METHODS: &[&str] = &["initialize", "search", "snippets", "ask", "retrieve", "index_file", "feedback", "shutdown", "exit"];
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: Option<String>,
+    /// Absent for notifications, which get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        RpcError { code: INTERNAL_ERROR, message: e.to_string() }
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    query: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default = "default_threshold")]
+    threshold: f32,
+    #[serde(default)]
+    filter: SearchFilter,
+}
+
+#[derive(Deserialize)]
+struct SnippetParams {
+    query: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+#[derive(Deserialize)]
+struct AskParams {
+    question: String,
+    context: Option<usize>,
+    /// Answer length and reading level, as for `ask --length/--reading-level`
+    #[serde(flatten)]
+    style: AnswerStyle,
+    /// As for `ask --deadline`, in milliseconds
+    deadline_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct RetrieveParams {
+    question: String,
+    context: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct FeedbackParams {
+    query: String,
+    chunk_id: u32,
+    relevant: bool,
+}
+
+#[derive(Deserialize)]
+struct IndexFileParams {
+    path: String,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+fn default_threshold() -> f32 {
+    0.7
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError { code: INVALID_PARAMS, message: e.to_string() })
+}
+
+/// Serve JSON-RPC requests from stdin until `exit` or EOF.
+///
+/// Requests are handled one at a time on this task, since the app owns the SQLite connection.
+/// Stdout carries only protocol messages; progress output goes to stderr.
+pub async fn run_stdio_daemon(app: &mut ChunkyMonkeyApp) -> Result<()> {
+    let mut reader = BufReader::new(tokio::io::stdin());
+    let mut stdout = tokio::io::stdout();
+    eprintln!("🐒 ChunkyMonkey JSON-RPC daemon listening on stdio");
+
+    while let Some(message) = read_message(&mut reader).await? {
+        let body = match message {
+            Message::Body(body) => body,
+            Message::Malformed(reason) => {
+                write_message(&mut stdout, &error_response(Value::Null, PARSE_ERROR, &reason)).await?;
+                continue;
+            }
+        };
+        let request: Request = match serde_json::from_slice(&body) {
+            Ok(request) => request,
+            Err(e) => {
+                write_message(&mut stdout, &error_response(Value::Null, PARSE_ERROR, &e.to_string())).await?;
+                continue;
+            }
+        };
+
+        if request.method == "exit" {
+            break;
+        }
+
+        let id = request.id.clone();
+        let outcome = if request.jsonrpc.as_deref() != Some("2.0") {
+            Err(RpcError { code: INVALID_REQUEST, message: "jsonrpc must be \"2.0\"".to_string() })
+        } else {
+            dispatch(app, &request.method, request.params).await
+        };
+        let _ = app.flush_health();
+
+        // Notifications are processed but never answered
+        let Some(id) = id else { continue };
+        let response = match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e.code, &e.message),
+        };
+        write_message(&mut stdout, &response).await?;
+    }
+
+    Ok(())
+}
+
+async fn dispatch(app: &mut ChunkyMonkeyApp, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "initialize" => Ok(json!({
+            "name": "chunkymonkey",
+            "version": env!("CARGO_PKG_VERSION"),
+            "methods": METHODS,
+        })),
+        "search" => {
+            let params: SearchParams = parse_params(params)?;
+            let results = app.search_filtered(&params.query, params.limit, params.threshold, &params.filter).await?;
+            Ok(json!(results))
+        }
+        "snippets" => {
+            let params: SnippetParams = parse_params(params)?;
+            let snippets = app.retrieve_snippets(&params.query, params.limit).await?;
+            Ok(json!(snippets))
+        }
+        "ask" => {
+            let params: AskParams = parse_params(params)?;
+            app.set_answer_style(params.style);
+            app.set_answer_deadline(params.deadline_ms.map(std::time::Duration::from_millis));
+            let answer = app.ask_question(&params.question, params.context).await?;
+            Ok(json!(answer))
+        }
+        "retrieve" => {
+            let params: RetrieveParams = parse_params(params)?;
+            let sources = app.retrieve(&params.question, params.context).await?;
+            Ok(json!(sources))
+        }
+        "index_file" => {
+            let params: IndexFileParams = parse_params(params)?;
+            let path = PathBuf::from(&params.path);
+            if !path.is_file() {
+                return Err(RpcError { code: INVALID_PARAMS, message: format!("{} is not a file", params.path) });
+            }
+            // add_document returns 0 when the file is already indexed with the same content
+            let document_id = app.add_document(&path).await?;
+            Ok(json!({
+                "path": params.path,
+                "status": if document_id == 0 { "unchanged" } else { "indexed" },
+                "document_id": (document_id != 0).then_some(document_id),
+            }))
+        }
+        "feedback" => {
+            let params: FeedbackParams = parse_params(params)?;
+            if app.db.get_chunk(params.chunk_id)?.is_none() {
+                return Err(RpcError { code: INVALID_PARAMS, message: format!("Chunk {} not found", params.chunk_id) });
+            }
+            app.db.record_feedback(app.project.as_deref(), &params.query, params.chunk_id, params.relevant)?;
+            Ok(Value::Null)
+        }
+        "shutdown" => Ok(Value::Null),
+        _ => Err(RpcError { code: METHOD_NOT_FOUND, message: format!("Unknown method: {}", method) }),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// A message read from the client
+enum Message {
+    Body(Vec<u8>),
+    /// Why a message's framing could not be used; its body is skipped when its length is known
+    Malformed(String),
+}
+
+/// Read one `Content-Length`-framed message, or None at end of input
+async fn read_message<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Message>> {
+    let mut content_length = None;
+    let content_length = loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if let Some(content_length) = content_length.take() {
+                break content_length;
+            }
+            // Tolerate blank lines between messages
+            continue;
+        }
+        // The header is found anywhere in the line: after a malformed length the body is left
+        // unread, and runs into the next message's header when it has no trailing line break
+        if let Some(at) = line.to_ascii_lowercase().find("content-length:") {
+            let value = line[at + "content-length:".len()..].trim();
+            content_length = Some(value.parse::<usize>().map_err(|e| format!("Invalid Content-Length '{}': {}", value, e)));
+        }
+    };
+
+    match content_length {
+        Ok(length) if length > MAX_MESSAGE_SIZE => {
+            tokio::io::copy(&mut (&mut *reader).take(length as u64), &mut tokio::io::sink()).await?;
+            Ok(Some(Message::Malformed(format!("Message of {} bytes is over the {} byte limit", length, MAX_MESSAGE_SIZE))))
+        }
+        Ok(length) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            Ok(Some(Message::Body(body)))
+        }
+        Err(reason) => Ok(Some(Message::Malformed(reason))),
+    }
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    writer.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes()).await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
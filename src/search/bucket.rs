@@ -0,0 +1,165 @@
+// Indexing the objects of an S3 bucket, or of a GCS bucket through its S3-compatible API, with
+// the same pattern filters and chunkers as local files (requires the `s3` cargo feature).
+// Credentials come from the AWS credential chain: environment, shared profile, SSO, instance role.
+use anyhow::Result;
+use crate::core::app::ChunkyMonkeyApp;
+use super::Indexer;
+use super::progress::IndexSummary;
+
+/// A bucket and key prefix given to `index` as `s3://bucket/prefix` or `gs://bucket/prefix`
+#[cfg_attr(not(feature = "s3"), allow(dead_code))]
+pub struct BucketUrl {
+    /// "s3" or "gs"
+    scheme: &'static str,
+    bucket: String,
+    prefix: String,
+}
+
+impl BucketUrl {
+    /// The bucket `url` names, or None when it is not an s3:// or gs:// URL
+    pub fn parse(url: &str) -> Option<Self> {
+        let (scheme, rest) = url.split_once("://")?;
+        let scheme = match scheme {
+            "s3" => "s3",
+            "gs" => "gs",
+            _ => return None,
+        };
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return None;
+        }
+        Some(Self { scheme, bucket: bucket.to_string(), prefix: prefix.to_string() })
+    }
+}
+
+#[cfg(feature = "s3")]
+impl Indexer {
+    /// Index the objects under the URL's prefix that the patterns select, matched against the key
+    /// below the prefix as paths below a directory are. Each object is downloaded in blocks to a
+    /// temporary file, indexed under its URL and removed; objects whose ETag and size are those
+    /// they were last indexed with are not downloaded again.
+    pub async fn index_bucket(&self, url: &BucketUrl, patterns: Option<&str>, app: &mut ChunkyMonkeyApp) -> Result<IndexSummary> {
+        let (include, exclude) = self.globs(patterns)?;
+        let client = s3::client(url).await;
+        let (objects, oversized): (Vec<_>, Vec<_>) = s3::list_objects(&client, url).await?
+            .into_iter()
+            .filter(|object| {
+                let key = object.key.as_str();
+                let relative = key.strip_prefix(url.prefix.as_str()).unwrap_or(key).trim_start_matches('/');
+                // Keys have no directories to prune, so excludes also apply to each parent "directory"
+                let excluded = relative.match_indices('/').map(|(i, _)| &relative[..i]).chain([relative])
+                    .any(|path| exclude.iter().any(|glob| glob.matches(path)));
+                let included = include.is_empty() || include.iter().any(|glob| glob.matches(relative));
+                !excluded && included
+            })
+            .partition(|object| object.size <= self.config.max_file_size);
+        if objects.is_empty() && oversized.is_empty() {
+            eprintln!("⚠️  No objects found under {}://{}/{}", url.scheme, url.bucket, url.prefix);
+            return Ok(IndexSummary::default());
+        }
+
+        // Removed, with anything left in it, when dropped
+        let directory = tempfile::Builder::new().prefix(&format!("chunkymonkey-{}-", url.scheme)).tempdir()?;
+        let mut run = self.start_run(objects.len());
+        for object in &oversized {
+            run.skip(&format!("{}://{}/{}", url.scheme, url.bucket, object.key), object.size,
+                format!("larger than {} (indexing.max_file_size)", super::format_size(self.config.max_file_size)));
+        }
+        for object in &objects {
+            if app.cancellation().is_cancelled() {
+                run.summary.cancelled = true;
+                break;
+            }
+            let path = format!("{}://{}/{}", url.scheme, url.bucket, object.key);
+            run.send(super::progress::IndexEvent::File { path: path.clone() });
+            let started = std::time::Instant::now();
+            let unchanged = match (&object.etag, app.db.get_object_version(app.project.as_deref(), &path)?) {
+                (Some(etag), Some((indexed_etag, indexed_size))) => *etag == indexed_etag && object.size == indexed_size,
+                _ => false,
+            };
+            if unchanged {
+                run.record(&path, Ok(0), object.size, started, app);
+                continue;
+            }
+            let result = match s3::download(&client, url, &object.key, directory.path()).await {
+                Ok(file) => {
+                    let result = self.index_file_as(&file, &path, app).await;
+                    let _ = std::fs::remove_file(&file);
+                    result
+                }
+                Err(e) => Err(e),
+            };
+            if let (Ok(_), Some(etag)) = (&result, &object.etag) {
+                app.db.set_object_version(app.project.as_deref(), &path, etag, object.size)?;
+            }
+            run.record(&path, result, object.size, started, app);
+        }
+        let summary = run.finish().await;
+        self.report_oversized(oversized.len());
+        super::report_left_out_chunks(app);
+        Ok(summary)
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+impl Indexer {
+    pub async fn index_bucket(&self, _url: &BucketUrl, _patterns: Option<&str>, _app: &mut ChunkyMonkeyApp) -> Result<IndexSummary> {
+        anyhow::bail!("ChunkyMonkey was built without bucket support; rebuild with `--features s3`")
+    }
+}
+
+#[cfg(feature = "s3")]
+mod s3 {
+    use anyhow::Result;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use super::BucketUrl;
+
+    /// Google Cloud Storage's S3-compatible endpoint, used with HMAC keys as AWS credentials
+    const GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+
+    pub async fn client(url: &BucketUrl) -> aws_sdk_s3::Client {
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+        if url.scheme == "gs" {
+            loader = loader.endpoint_url(GCS_ENDPOINT).region(aws_config::Region::new("auto"));
+        }
+        aws_sdk_s3::Client::new(&loader.load().await)
+    }
+
+    /// An object listed under a bucket prefix
+    pub struct ListedObject {
+        pub key: String,
+        pub size: u64,
+        pub etag: Option<String>,
+    }
+
+    /// The objects under the URL's prefix, leaving out folder markers
+    pub async fn list_objects(client: &aws_sdk_s3::Client, url: &BucketUrl) -> Result<Vec<ListedObject>> {
+        let mut objects = Vec::new();
+        let mut pages = client.list_objects_v2().bucket(&url.bucket).prefix(&url.prefix).into_paginator().send();
+        while let Some(page) = pages.next().await {
+            for object in page?.contents() {
+                if let Some(key) = object.key().filter(|key| !key.ends_with('/')) {
+                    objects.push(ListedObject {
+                        key: key.to_string(),
+                        size: object.size().unwrap_or(0).max(0) as u64,
+                        etag: object.e_tag().map(str::to_string),
+                    });
+                }
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Download an object into `directory` under its file name, so its extension still picks the
+    /// chunker, and return the file's path
+    pub async fn download(client: &aws_sdk_s3::Client, url: &BucketUrl, key: &str, directory: &Path) -> Result<PathBuf> {
+        let path = directory.join(key.rsplit('/').next().unwrap_or(key));
+        let mut body = client.get_object().bucket(&url.bucket).key(key).send().await?.body;
+        let mut file = std::fs::File::create(&path)?;
+        while let Some(bytes) = body.try_next().await? {
+            file.write_all(&bytes)?;
+        }
+        Ok(path)
+    }
+}
@@ -0,0 +1,143 @@
+// .gitignore and .ignore support for directory walks, so build output, dependencies and VCS
+// metadata stay out of the index
+use glob::{MatchOptions, Pattern};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Files read in each directory, later ones taking precedence
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// Directories never walked while ignore files are respected
+const VCS_DIRECTORIES: &[&str] = &[".git", ".hg", ".svn"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// One line of an ignore file
+struct Rule {
+    pattern: Pattern,
+    /// "!pattern": re-include what an earlier rule excluded
+    negated: bool,
+    /// "pattern/": matches directories only
+    directory_only: bool,
+    /// Patterns with a slash match the path from the ignore file's directory; others match a name
+    /// at any depth
+    anchored: bool,
+}
+
+/// Decides which paths of a walk the ignore files along the way exclude. Rules of a directory's
+/// ignore files apply below it; deeper and later rules override earlier ones, as with git.
+pub struct IgnoreMatcher {
+    root: PathBuf,
+    /// The root's absolute path, where rules of `inherited` are matched
+    canonical_root: Option<PathBuf>,
+    /// Rules of ignore files above the walk's root, up to the repository root, outermost first
+    inherited: Vec<(PathBuf, Vec<Rule>)>,
+    /// Rules of each directory in the walk, read when first needed
+    rules: HashMap<PathBuf, Vec<Rule>>,
+}
+
+impl IgnoreMatcher {
+    pub fn new(root: &Path) -> Self {
+        let canonical_root = root.canonicalize().ok();
+        // A walk inside a repository still honours the ignore files above it in the repository
+        let mut inherited = Vec::new();
+        if let Some(canonical) = canonical_root.as_ref().filter(|dir| !dir.join(".git").exists()) {
+            for ancestor in canonical.ancestors().skip(1) {
+                inherited.push((ancestor.to_path_buf(), read_rules(ancestor)));
+                if ancestor.join(".git").exists() {
+                    break;
+                }
+            }
+            if !inherited.last().is_some_and(|(dir, _)| dir.join(".git").exists()) {
+                inherited.clear();
+            }
+            inherited.reverse();
+        }
+        Self { root: root.to_path_buf(), canonical_root, inherited, rules: HashMap::new() }
+    }
+
+    /// Whether `path`, found by walking the root, is excluded
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        if path == self.root {
+            return false;
+        }
+        if is_dir && path.file_name().is_some_and(|name| VCS_DIRECTORIES.iter().any(|vcs| name == *vcs)) {
+            return true;
+        }
+        let Ok(relative) = path.strip_prefix(&self.root) else { return false };
+
+        let mut ignored = false;
+        if let Some(canonical_root) = self.canonical_root.as_ref().filter(|_| !self.inherited.is_empty()) {
+            let absolute = canonical_root.join(relative);
+            for (directory, rules) in &self.inherited {
+                apply(rules, directory, &absolute, is_dir, &mut ignored);
+            }
+        }
+
+        let mut directory = self.root.clone();
+        let mut components = relative.components().peekable();
+        loop {
+            let rules = self.rules.entry(directory.clone()).or_insert_with(|| read_rules(&directory));
+            apply(rules, &directory, path, is_dir, &mut ignored);
+            match components.next() {
+                Some(component) if components.peek().is_some() => directory.push(component),
+                _ => break,
+            }
+        }
+        ignored
+    }
+}
+
+/// Let the rules of the ignore files in `directory` decide on `path`, the last match winning
+fn apply(rules: &[Rule], directory: &Path, path: &Path, is_dir: bool, ignored: &mut bool) {
+    let Ok(relative) = path.strip_prefix(directory) else { return };
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    for rule in rules {
+        if rule.directory_only && !is_dir {
+            continue;
+        }
+        let matched = if rule.anchored {
+            rule.pattern.matches_with(&relative, MATCH_OPTIONS)
+        } else {
+            rule.pattern.matches_with(&name, MATCH_OPTIONS)
+        };
+        if matched {
+            *ignored = !rule.negated;
+        }
+    }
+}
+
+/// Rules of the ignore files in `directory`, in file order
+fn read_rules(directory: &Path) -> Vec<Rule> {
+    IGNORE_FILES.iter()
+        .filter_map(|file| std::fs::read_to_string(directory.join(file)).ok())
+        .flat_map(|content| content.lines().filter_map(parse_rule).collect::<Vec<_>>())
+        .collect()
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (directory_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    if line.is_empty() {
+        return None;
+    }
+    let pattern = Pattern::new(line).ok()?;
+    Some(Rule { pattern, negated, directory_only, anchored })
+}
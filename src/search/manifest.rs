@@ -0,0 +1,69 @@
+// Index manifests: the roots of a knowledge base with the patterns, tags and chunking strategy of
+// each, so `index --manifest` rebuilds the whole index the same way every time
+//
+//     [[roots]]
+//     path = "docs"
+//     patterns = "**/*.md"
+//     tags = ["docs"]
+//     strategy = "markdown"
+//
+//     [[roots]]
+//     path = "src"
+//     exclude = ["generated"]
+//     strategy = "code"
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::Path;
+use crate::core::config::ChunkStrategy;
+
+/// Manifest `index --manifest` reads when given no path
+pub const MANIFEST_PATH: &str = "chunkymonkey.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IndexManifest {
+    pub roots: Vec<IndexRoot>,
+}
+
+/// A directory (or bucket URL) to index, with how its files are picked and indexed
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexRoot {
+    pub path: String,
+    /// Comma-separated include patterns, as `index --patterns` takes them
+    pub patterns: Option<String>,
+    /// Left out in addition to `indexing.exclude`
+    pub exclude: Vec<String>,
+    /// Added to the documents indexed from this root
+    pub tags: Vec<String>,
+    /// Chunker for every file under the root, in place of `chunking.strategies`
+    pub strategy: Option<ChunkStrategy>,
+}
+
+impl IndexRoot {
+    pub fn new(path: &str, patterns: Option<&str>) -> Self {
+        Self { path: path.to_string(), patterns: patterns.map(str::to_string), ..Default::default() }
+    }
+}
+
+/// Read a manifest, resolving relative root paths against the manifest's directory
+pub fn load(path: &Path) -> Result<IndexManifest> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read index manifest {}: {}", path.display(), e))?;
+    let mut manifest: IndexManifest = toml::from_str(&text)
+        .map_err(|e| anyhow::anyhow!("Invalid index manifest {}: {}", path.display(), e))?;
+    if manifest.roots.is_empty() {
+        anyhow::bail!("Index manifest {} lists no [[roots]]", path.display());
+    }
+
+    let base = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+    for root in &mut manifest.roots {
+        if root.path.is_empty() {
+            anyhow::bail!("A root in {} has no path", path.display());
+        }
+        if let Some(base) = base.filter(|_| super::bucket::BucketUrl::parse(&root.path).is_none() && Path::new(&root.path).is_relative()) {
+            root.path = base.join(&root.path).to_string_lossy().to_string();
+        }
+    }
+    Ok(manifest)
+}
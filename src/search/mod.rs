@@ -1,122 +1,466 @@
+pub mod bucket;
+pub mod ignore;
+pub mod manifest;
+pub mod progress;
+pub mod report;
+
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::time::Instant;
 use walkdir::WalkDir;
-use glob::Pattern;
-use crate::core::app::ChunkyMonkeyApp;
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::core::app::{ChunkyMonkeyApp, STREAM_WINDOW_SIZE};
+use crate::core::config::ChunkStrategy;
+use crate::core::types::{split_patterns, PathGlob, PlannedDocument};
+use manifest::IndexRoot;
+use progress::{IndexEvent, IndexSummary};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Default for `indexing.max_file_size`
+const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Time allowed to index a file, per window of `STREAM_WINDOW_SIZE`, besides waiting for
+/// embedding rate limits
+const FILE_TIMEOUT_SECS: u64 = 30;
+
+/// Which files of a directory `index` picks up. Patterns are globs matched against the path
+/// below the indexed directory (see `path_matches_glob`): `*.rs`, `src/**/*.rs`, `*.{md,txt}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexingConfig {
+    /// Files to index when `index --patterns` is not given (empty indexes every file)
+    pub include: Vec<String>,
+    /// Files and directories never indexed; `index --exclude` adds to these
+    pub exclude: Vec<String>,
+    /// Leave out what .gitignore and .ignore files exclude, and VCS directories
+    /// (`index --no-ignore` overrides it for one run)
+    pub respect_ignore_files: bool,
+    /// Files larger than this many bytes are skipped (`index --max-file-size`); those over
+    /// `STREAM_WINDOW_SIZE` are streamed
+    pub max_file_size: u64,
+}
+
+impl Default for IndexingConfig {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_ignore_files: true,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+        }
+    }
+}
 
-pub struct Indexer;
+pub struct Indexer {
+    config: IndexingConfig,
+    /// Where progress events go; without one, runs draw the `index` progress bar
+    events: Option<UnboundedSender<IndexEvent>>,
+}
 
 impl Indexer {
-    pub fn new() -> Self {
-        Self
+    pub fn new(config: IndexingConfig) -> Self {
+        Self { config, events: None }
     }
 
-    pub async fn index_directory(&self, directory: &str, patterns: Option<&str>, app: &mut ChunkyMonkeyApp) -> Result<()> {
-        let directory_path = Path::new(directory);
-        if !directory_path.exists() {
-            anyhow::bail!("Directory does not exist: {}", directory);
+    /// Send the progress of runs to `events` for the caller to render, instead of drawing the
+    /// progress bar
+    pub fn with_events(mut self, events: UnboundedSender<IndexEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub async fn index_directory(&self, directory: &str, patterns: Option<&str>, app: &mut ChunkyMonkeyApp) -> Result<IndexSummary> {
+        self.index_roots(&[IndexRoot::new(directory, patterns)], app).await
+    }
+
+    /// The indexer for one root: its excludes added to the configured ones
+    pub fn for_root(&self, root: &IndexRoot) -> Self {
+        let mut config = self.config.clone();
+        config.exclude.extend(root.exclude.iter().cloned());
+        Self { config, events: self.events.clone() }
+    }
+
+    /// Index local directories in one run, each with its own patterns, excludes, tags and
+    /// chunking strategy
+    pub async fn index_roots(&self, roots: &[IndexRoot], app: &mut ChunkyMonkeyApp) -> Result<IndexSummary> {
+        let mut planned = Vec::new();
+        for root in roots {
+            let indexer = self.for_root(root);
+            let (files, oversized) = indexer.collect_matching_files(&root.path, root.patterns.as_deref())?;
+            if files.is_empty() && oversized.is_empty() {
+                let include = root.patterns.clone().unwrap_or_else(|| indexer.config.include.join(","));
+                eprintln!("⚠️  No files found in {} matching patterns: {}", root.path, if include.is_empty() { "*" } else { &include });
+            }
+            planned.push((root, files, oversized));
         }
-        if !directory_path.is_dir() {
-            anyhow::bail!("Path is not a directory: {}", directory);
+        if planned.iter().all(|(_, files, oversized)| files.is_empty() && oversized.is_empty()) {
+            return Ok(IndexSummary::default());
         }
 
-        // Parse file patterns
-        let patterns = if let Some(pat) = patterns {
-            pat.split(',').map(|s| s.trim()).collect::<Vec<_>>()
-        } else {
-            vec!["*"]
-        };
+        let mut run = self.start_run(planned.iter().map(|(_, files, _)| files.len()).sum());
+        let mut oversized_count = 0;
+        for file in planned.iter().flat_map(|(_, _, oversized)| oversized) {
+            oversized_count += 1;
+            run.skip(&file.to_string_lossy(), std::fs::metadata(file).map_or(0, |metadata| metadata.len()),
+                format!("larger than {} (indexing.max_file_size)", format_size(self.config.max_file_size)));
+        }
+        for (root, files, _) in &planned {
+            let files: Vec<(&Path, String)> = files.iter().map(|file| (file.as_path(), file.to_string_lossy().to_string())).collect();
+            let scope = RootScope::enter(root, app);
+            self.index_into(&mut run, &files, app).await;
+            scope.leave(app);
+            if run.summary.cancelled {
+                break;
+            }
+        }
+        let summary = run.finish().await;
+        self.report_oversized(oversized_count);
+        report_left_out_chunks(app);
+        Ok(summary)
+    }
 
-        // Collect files
-        let files = self.collect_files(directory_path, &patterns)?;
+    /// Index files one by one as part of `run`, each as the document at its path, until they are
+    /// done or the run is cancelled. A file that fails is counted and the run goes on.
+    async fn index_into(&self, run: &mut Run, files: &[(&Path, String)], app: &mut ChunkyMonkeyApp) {
+        for (file_path, path) in files {
+            if app.cancellation().is_cancelled() {
+                run.summary.cancelled = true;
+                break;
+            }
+            run.send(IndexEvent::File { path: path.clone() });
+            let (started, bytes) = (Instant::now(), std::fs::metadata(file_path).map_or(0, |metadata| metadata.len()));
+            let result = self.index_file_as(file_path, path, app).await;
+            run.record(path, result, bytes, started, app);
+            
+            // Small delay to prevent overwhelming the system
+            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        }
+    }
+
+    /// Index again the files of the current project whose last attempt failed. Files that no
+    /// longer exist are forgotten; bucket objects are retried by indexing the bucket again.
+    pub async fn retry_failed(&self, app: &mut ChunkyMonkeyApp) -> Result<IndexSummary> {
+        let failures = app.db.get_index_failures(Some(app.project.as_deref()))?;
+        let mut files = Vec::new();
+        for failure in failures {
+            if bucket::BucketUrl::parse(&failure.file_path).is_some() {
+                eprintln!("ℹ️  {} is a bucket object; index its bucket again to retry it", failure.file_path);
+            } else if Path::new(&failure.file_path).is_file() {
+                files.push(failure.file_path);
+            } else {
+                eprintln!("ℹ️  {} no longer exists and is no longer retried", failure.file_path);
+                app.db.clear_index_failure(&failure.file_path)?;
+            }
+        }
         if files.is_empty() {
-            println!("⚠️  No files found matching patterns: {}", patterns.join(", "));
-            return Ok(());
+            println!("✅ No failed files to retry");
+            return Ok(IndexSummary::default());
         }
 
-        // Create progress bar with better styling
-        let pb = ProgressBar::new(files.len() as u64);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("🐒 [{spinner:.green}] [{bar:40.cyan/blue}] {pos}/{len} files [{elapsed_precise}] {msg}")
-            .unwrap()
-            .progress_chars("█░"));
+        let files: Vec<(&Path, String)> = files.iter().map(|file| (Path::new(file.as_str()), file.clone())).collect();
+        let mut run = self.start_run(files.len());
+        self.index_into(&mut run, &files, app).await;
+        let summary = run.finish().await;
+        report_left_out_chunks(app);
+        Ok(summary)
+    }
 
-        let mut _success_count = 0;
-        let mut _error_count = 0;
+    /// Start a run over `files` files, reporting to the caller's channel or to a progress bar
+    fn start_run(&self, files: usize) -> Run {
+        let (events, renderer) = match &self.events {
+            Some(events) => (events.clone(), None),
+            None => {
+                let (events, receiver) = tokio::sync::mpsc::unbounded_channel();
+                (events, Some(tokio::spawn(progress::render_progress_bar(receiver))))
+            }
+        };
+        let run = Run { events, renderer, summary: IndexSummary { files, ..Default::default() } };
+        run.send(IndexEvent::Started { files });
+        run
+    }
 
-        // Process files one by one
-        for file_path in files.iter() {
-            let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
-            pb.set_message(format!("Processing: {}", file_name));
-            
-            match self.index_file(file_path, app).await {
-                Ok(_) => {
-                    _success_count += 1;
+    /// Report what indexing the directory would do, file by file, without embedding or storing
+    /// anything: files to index with their estimated chunks, unchanged files and skipped ones
+    pub fn dry_run(&self, directory: &str, patterns: Option<&str>, app: &ChunkyMonkeyApp) -> Result<()> {
+        let (files, oversized) = self.collect_matching_files(directory, patterns)?;
+        println!("🔍 Dry run of indexing {}: nothing is embedded or stored", directory);
+        let (mut indexed, mut unchanged, mut skipped, mut chunks) = (0, 0, oversized.len(), 0);
+        let mut estimate_known = true;
+        for file_path in &files {
+            let path = file_path.display();
+            match app.plan_document(file_path) {
+                Ok(PlannedDocument::Unchanged) => {
+                    unchanged += 1;
+                    println!("   = {}  unchanged", path);
+                }
+                Ok(PlannedDocument::Index { chunks: estimate, changed }) => {
+                    indexed += 1;
+                    let status = if changed { "changed" } else { "new" };
+                    match estimate {
+                        Some(estimate) => {
+                            chunks += estimate;
+                            println!("   + {}  {}, ~{} chunks", path, status, estimate);
+                        }
+                        None => {
+                            estimate_known = false;
+                            println!("   + {}  {}, chunks known once its text is extracted", path, status);
+                        }
+                    }
                 }
                 Err(e) => {
-                    _error_count += 1;
-                    // Only show errors, not successful completions
-                    pb.set_message(format!("❌ Error: {}", e));
+                    skipped += 1;
+                    println!("   - {}  skipped: {}", path, e);
                 }
             }
-            
-            pb.inc(1);
-            
-            // Small delay to prevent overwhelming the system
-            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
         }
+        for file_path in &oversized {
+            println!("   - {}  skipped: larger than {} (indexing.max_file_size)", file_path.display(), format_size(self.config.max_file_size));
+        }
+        println!("📊 {} file(s) would be indexed ({}{} chunks), {} unchanged, {} skipped",
+            indexed, if estimate_known { "~" } else { "at least ~" }, chunks, unchanged, skipped);
+        Ok(())
+    }
 
-        pb.finish_with_message("Indexing complete! 🎉");
-        
-        // Don't show error summary - let the CLI handle the user experience
-        // Errors are logged internally but not displayed to users
+    /// Validate the directory and collect the files matching the comma-separated patterns, along
+    /// with the matching files skipped for being over `max_file_size`
+    pub fn collect_matching_files(&self, directory: &str, patterns: Option<&str>) -> Result<(Vec<std::path::PathBuf>, Vec<std::path::PathBuf>)> {
+        let directory_path = Path::new(directory);
+        if !directory_path.exists() {
+            anyhow::bail!("Directory does not exist: {}", directory);
+        }
+        if !directory_path.is_dir() {
+            anyhow::bail!("Path is not a directory: {}", directory);
+        }
 
-        Ok(())
+        let (include, exclude) = self.globs(patterns)?;
+        self.collect_files(directory_path, &include, &exclude)
+    }
+
+    /// Include and exclude globs. Comma-separated patterns from the command line replace the
+    /// configured includes.
+    fn globs(&self, patterns: Option<&str>) -> Result<(Vec<PathGlob>, Vec<PathGlob>)> {
+        let include = match patterns {
+            Some(patterns) => split_patterns(patterns),
+            None => self.config.include.clone(),
+        };
+        let include = include.iter().map(|pattern| PathGlob::new(pattern)).collect::<Result<Vec<_>>>()?;
+        let exclude = self.config.exclude.iter().map(|pattern| PathGlob::new(pattern)).collect::<Result<Vec<_>>>()?;
+        Ok((include, exclude))
     }
 
-    fn collect_files(&self, directory: &Path, patterns: &[&str]) -> Result<Vec<std::path::PathBuf>> {
+    fn collect_files(&self, directory: &Path, include: &[PathGlob], exclude: &[PathGlob]) -> Result<(Vec<std::path::PathBuf>, Vec<std::path::PathBuf>)> {
         let mut files = Vec::new();
+        let mut oversized = Vec::new();
+        let mut ignore = self.config.respect_ignore_files.then(|| ignore::IgnoreMatcher::new(directory));
+        let relative = |path: &Path| path.strip_prefix(directory).unwrap_or(path).to_string_lossy().replace('\\', "/");
         
         for entry in WalkDir::new(directory)
             .follow_links(true)
             .into_iter()
+            .filter_entry(|entry| {
+                // Excluded directories are not walked at all
+                let path = relative(entry.path());
+                if !path.is_empty() && exclude.iter().any(|glob| glob.matches(&path)) {
+                    return false;
+                }
+                match ignore.as_mut() {
+                    Some(ignore) => !ignore.is_ignored(entry.path(), entry.file_type().is_dir()),
+                    None => true,
+                }
+            })
             .filter_map(|e| e.ok())
         {
             let path = entry.path();
             
             if path.is_file() {
                 // Check if file matches any pattern
-                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-                let matches_pattern = patterns.iter().any(|pattern| {
-                    if let Ok(pat) = Pattern::new(pattern) {
-                        pat.matches(&file_name)
-                    } else {
-                        false
-                    }
-                });
+                let matches_pattern = include.is_empty() || include.iter().any(|glob| glob.matches(&relative(path)));
                 
                 if matches_pattern {
-                    // Filter by file size (skip files larger than 10MB)
+                    // Filter by file size
                     if let Ok(metadata) = std::fs::metadata(path) {
-                        if metadata.len() <= 10 * 1024 * 1024 { // 10MB
+                        if metadata.len() <= self.config.max_file_size {
                             files.push(path.to_path_buf());
+                        } else {
+                            oversized.push(path.to_path_buf());
                         }
                     }
                 }
             }
         }
         
-        Ok(files)
+        Ok((files, oversized))
+    }
+
+    fn report_oversized(&self, oversized: usize) {
+        if oversized > 0 {
+            eprintln!("ℹ️  Skipped {} file(s) larger than {} (indexing.max_file_size, index --max-file-size)",
+                oversized, format_size(self.config.max_file_size));
+        }
+    }
+
+    /// Index one file, returning its document id (0 when its content was indexed already)
+    pub async fn index_file(&self, file_path: &Path, app: &mut ChunkyMonkeyApp) -> Result<u32> {
+        self.index_file_as(file_path, file_path.to_str().unwrap(), app).await
     }
 
-    async fn index_file(&self, file_path: &Path, app: &mut ChunkyMonkeyApp) -> Result<()> {
-        // Add timeout to prevent hanging on problematic files
-        let timeout_duration = tokio::time::Duration::from_secs(30);
+    /// Index the file at `file_path` as the document at `path` (see `ChunkyMonkeyApp::add_document_as`)
+    pub async fn index_file_as(&self, file_path: &Path, path: &str, app: &mut ChunkyMonkeyApp) -> Result<u32> {
+        // Add timeout to prevent hanging on problematic files; streamed files get it per window
+        let windows = std::fs::metadata(file_path)
+            .map_or(1, |metadata| (metadata.len() as usize).div_ceil(STREAM_WINDOW_SIZE).max(1));
+        let timeout_duration = tokio::time::Duration::from_secs(FILE_TIMEOUT_SECS * windows as u64);
         
-        match tokio::time::timeout(timeout_duration, app.add_document(file_path)).await {
-            Ok(result) => result.map(|_| ()), // Convert Result<u32> to Result<()>
-            Err(_) => anyhow::bail!("Timeout while processing file: {}", file_path.display()),
+        // Time spent waiting on rate limits is added to the deadline: a file with many chunks
+        // under a tight quota is slow, not stuck
+        let started = tokio::time::Instant::now();
+        let waited_before = crate::embeddings::rate_limit::time_waited();
+        let mut deadline = started + timeout_duration;
+        let work = app.add_document_as(file_path, path);
+        tokio::pin!(work);
+        loop {
+            tokio::select! {
+                result = &mut work => return result,
+                _ = tokio::time::sleep_until(deadline) => {
+                    let now = tokio::time::Instant::now();
+                    let extended = started + timeout_duration + (crate::embeddings::rate_limit::time_waited() - waited_before);
+                    if extended > now {
+                        deadline = extended;
+                    } else if crate::embeddings::rate_limit::is_waiting() {
+                        // A wait still running is only counted once it ends
+                        deadline = now + tokio::time::Duration::from_secs(1);
+                    } else {
+                        anyhow::bail!("Timeout while processing file: {}", file_path.display());
+                    }
+                }
+            }
         }
     }
-} 
\ No newline at end of file
+} 
+
+/// The app's index tags and chunking strategies from before a root's were put in place
+pub struct RootScope {
+    tags: Vec<String>,
+    strategies: BTreeMap<String, ChunkStrategy>,
+}
+
+impl RootScope {
+    /// Add the root's tags to those documents are indexed with and, when it sets one, make its
+    /// strategy the chunker of every file until `leave`
+    pub fn enter(root: &IndexRoot, app: &mut ChunkyMonkeyApp) -> Self {
+        let scope = Self { tags: app.index_tags().to_vec(), strategies: app.config.chunking.strategies.clone() };
+        let mut tags = scope.tags.clone();
+        tags.extend(root.tags.iter().filter(|tag| !scope.tags.contains(tag)).cloned());
+        app.set_index_tags(tags);
+        if let Some(strategy) = root.strategy {
+            app.config.chunking.strategies = BTreeMap::from([("*".to_string(), strategy)]);
+        }
+        scope
+    }
+
+    pub fn leave(self, app: &mut ChunkyMonkeyApp) {
+        app.set_index_tags(self.tags);
+        app.config.chunking.strategies = self.strategies;
+    }
+}
+
+/// An indexing run in progress: its counts, and where its events go
+struct Run {
+    events: UnboundedSender<IndexEvent>,
+    /// The progress bar drawing the events, when the caller is not rendering them
+    renderer: Option<tokio::task::JoinHandle<()>>,
+    summary: IndexSummary,
+}
+
+impl Run {
+    fn send(&self, event: IndexEvent) {
+        // A renderer that has gone away only means nobody is watching
+        let _ = self.events.send(event);
+    }
+
+    /// Count the outcome of indexing the document at `path`, `bytes` long, started at `started`:
+    /// its id, 0 when it was unchanged. Failures are kept in the database for `retry-failed`.
+    fn record(&mut self, path: &str, result: Result<u32>, bytes: u64, started: Instant, app: &ChunkyMonkeyApp) {
+        let recorded = match &result {
+            Err(e) if crate::core::cancel::is_cancelled(e) => Ok(()),
+            Err(e) => app.db.record_index_failure(app.project.as_deref(), path, &e.to_string()),
+            Ok(_) => app.db.clear_index_failure(path),
+        };
+        if let Err(e) = recorded {
+            eprintln!("Warning: could not record the outcome of {}: {}", path, e);
+        }
+        let (path, duration) = (path.to_string(), started.elapsed());
+        let event = match result {
+            Ok(0) => {
+                self.summary.unchanged += 1;
+                IndexEvent::Indexed { path, chunks: 0, unchanged: true, bytes, duration }
+            }
+            Ok(document_id) => {
+                let chunks = app.db.get_document(document_id).ok().flatten().map_or(0, |document| document.chunk_count as usize);
+                self.summary.indexed += 1;
+                self.summary.chunks += chunks;
+                IndexEvent::Indexed { path, chunks, unchanged: false, bytes, duration }
+            }
+            Err(e) => {
+                self.summary.failed += 1;
+                IndexEvent::Failed { path, error: e.to_string(), bytes, duration }
+            }
+        };
+        self.send(event);
+    }
+
+    fn skip(&mut self, path: &str, bytes: u64, reason: String) {
+        self.summary.skipped += 1;
+        self.send(IndexEvent::Skipped { path: path.to_string(), bytes, reason });
+    }
+
+    /// Report the end of the run and wait for the progress bar to draw it
+    async fn finish(self) -> IndexSummary {
+        self.send(IndexEvent::Finished(self.summary.clone()));
+        drop(self.events);
+        if let Some(renderer) = self.renderer {
+            let _ = renderer.await;
+        }
+        self.summary
+    }
+}
+
+/// Report the documents cut off at `chunking.max_chunks_per_document` and the low-quality chunks
+/// left out since the counts were last taken
+fn report_left_out_chunks(app: &mut ChunkyMonkeyApp) {
+    let truncated = app.take_truncated_document_count();
+    if truncated > 0 {
+        eprintln!("ℹ️  Cut off {} document(s) at their chunk limit; the rest of their text is not indexed (chunking.max_chunks_per_document)",
+            truncated);
+    }
+    let skipped = app.take_skipped_chunk_count();
+    if skipped > 0 {
+        eprintln!("ℹ️  Left out {} low-quality chunk(s) (search.min_chunk_chars = {}, search.min_chunk_alphanumeric = {})",
+            skipped, app.config.search.min_chunk_chars, app.config.search.min_chunk_alphanumeric);
+    }
+}
+
+/// Parse a size given as bytes or with a K, KB, M, MB, G or GB suffix (powers of 1024): "500KB", "20M"
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(format!("unknown size unit '{}' (use K, M or G)", unit.trim())),
+    };
+    let number: f64 = number.parse().map_err(|_| format!("invalid size '{}'", text))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [(&str, u64); 3] = [("GB", 1024 * 1024 * 1024), ("MB", 1024 * 1024), ("KB", 1024)];
+    UNITS.iter()
+        .find(|(_, size)| bytes >= *size)
+        .map(|(unit, size)| format!("{:.1}{}", bytes as f64 / *size as f64, unit).replace(".0", ""))
+        .unwrap_or_else(|| format!("{} bytes", bytes))
+}
@@ -0,0 +1,157 @@
+// Progress of an indexing run as events, so what is shown comes from the work actually done:
+// the indexer sends them over a channel and a renderer (the `index` progress bar, the interactive
+// spinner) draws them
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+#[derive(Debug, Clone)]
+pub enum IndexEvent {
+    /// Indexing of `files` files is starting
+    Started { files: usize },
+    /// A file is being indexed
+    File { path: String },
+    /// A file of `bytes` bytes was indexed in `duration` with `chunks` chunks embedded;
+    /// `unchanged` when its content was indexed already and nothing was done
+    Indexed { path: String, chunks: usize, unchanged: bool, bytes: u64, duration: Duration },
+    Failed { path: String, error: String, bytes: u64, duration: Duration },
+    /// A file left out of the run before indexing, such as one over `indexing.max_file_size`
+    Skipped { path: String, bytes: u64, reason: String },
+    Finished(IndexSummary),
+}
+
+/// What an indexing run did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexSummary {
+    /// Files the run set out to index, not counting skipped ones
+    pub files: usize,
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Chunks embedded across the indexed files
+    pub chunks: usize,
+    pub cancelled: bool,
+}
+
+impl IndexSummary {
+    pub fn done(&self) -> usize {
+        self.indexed + self.unchanged + self.failed
+    }
+
+    /// Add the counts of another run to these
+    pub fn add(&mut self, other: &IndexSummary) {
+        self.files += other.files;
+        self.indexed += other.indexed;
+        self.unchanged += other.unchanged;
+        self.failed += other.failed;
+        self.skipped += other.skipped;
+        self.chunks += other.chunks;
+        self.cancelled |= other.cancelled;
+    }
+}
+
+/// A run's counts as events arrive, with the rate they arrive at
+pub struct Progress {
+    pub summary: IndexSummary,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self { summary: IndexSummary::default(), started: Instant::now() }
+    }
+
+    pub fn apply(&mut self, event: &IndexEvent) {
+        match event {
+            IndexEvent::Started { files } => {
+                self.summary.files = *files;
+                self.started = Instant::now();
+            }
+            IndexEvent::File { .. } => {}
+            IndexEvent::Indexed { unchanged: true, .. } => self.summary.unchanged += 1,
+            IndexEvent::Indexed { chunks, .. } => {
+                self.summary.indexed += 1;
+                self.summary.chunks += chunks;
+            }
+            IndexEvent::Failed { .. } => self.summary.failed += 1,
+            IndexEvent::Skipped { .. } => self.summary.skipped += 1,
+            IndexEvent::Finished(summary) => self.summary = summary.clone(),
+        }
+    }
+
+    /// Time left at the rate files have been done so far; None before the first file is done
+    pub fn eta(&self) -> Option<Duration> {
+        let done = self.summary.done();
+        if done == 0 {
+            return None;
+        }
+        let remaining = self.summary.files.saturating_sub(done) as u32;
+        Some(self.started.elapsed() / done as u32 * remaining)
+    }
+
+    /// "57 chunks, ETA 12s"
+    pub fn status(&self) -> String {
+        match self.eta() {
+            Some(eta) => format!("{} chunks, ETA {}", self.summary.chunks, format_duration(eta)),
+            None => format!("{} chunks", self.summary.chunks),
+        }
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Draw the events of a run as the `index` progress bar until the run finishes
+pub async fn render_progress_bar(mut events: UnboundedReceiver<IndexEvent>) {
+    let pb = ProgressBar::new(0);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("🐒 [{spinner:.green}] [{bar:40.cyan/blue}] {pos}/{len} files [{elapsed_precise}] {msg}")
+        .unwrap()
+        .progress_chars("█░"));
+    pb.enable_steady_tick(Duration::from_millis(100));
+
+    let mut progress = Progress::new();
+    while let Some(event) = events.recv().await {
+        progress.apply(&event);
+        match &event {
+            IndexEvent::Started { files } => pb.set_length(*files as u64),
+            IndexEvent::File { path } => pb.set_message(format!("{} · Processing: {}", progress.status(), file_name(path))),
+            IndexEvent::Indexed { path, chunks, unchanged, .. } => {
+                pb.set_position(progress.summary.done() as u64);
+                if !unchanged {
+                    pb.set_message(format!("{} · Indexed: {} ({} chunks)", progress.status(), file_name(path), chunks));
+                }
+            }
+            IndexEvent::Failed { path, error, .. } => {
+                pb.set_position(progress.summary.done() as u64);
+                pb.set_message(format!("❌ Error in {}: {}", file_name(path), error));
+            }
+            IndexEvent::Skipped { .. } => {}
+            IndexEvent::Finished(summary) if summary.cancelled => {
+                pb.abandon_with_message(format!("Indexing cancelled after {} of {} files", summary.done(), summary.files));
+            }
+            IndexEvent::Finished(summary) => {
+                pb.finish_with_message(format!("Indexing complete! 🎉 {} indexed, {} unchanged, {} failed, {} chunks",
+                    summary.indexed, summary.unchanged, summary.failed, summary.chunks));
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// Machine-readable indexing reports (`index --report`): each file's outcome, for CI pipelines that
+// check an indexing run succeeded and track the corpus as it grows
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use super::progress::{self, IndexEvent, IndexSummary};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// One JSON document with the run's totals and every file, written when the run ends
+    Json,
+    /// One JSON object per line for each file, written as the file is done
+    Ndjson,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileReport {
+    pub path: String,
+    /// "indexed", "unchanged", "failed" or "skipped"
+    pub status: &'static str,
+    /// Chunks embedded; 0 unless indexed
+    pub chunks: usize,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FileReport {
+    /// The outcome an event reports, for events that end a file
+    fn from_event(event: &IndexEvent) -> Option<Self> {
+        let report = match event {
+            IndexEvent::Indexed { path, chunks, unchanged, bytes, duration } => Self {
+                path: path.clone(),
+                status: if *unchanged { "unchanged" } else { "indexed" },
+                chunks: *chunks,
+                bytes: *bytes,
+                duration_ms: duration.as_millis() as u64,
+                error: None,
+            },
+            IndexEvent::Failed { path, error, bytes, duration } => Self {
+                path: path.clone(),
+                status: "failed",
+                chunks: 0,
+                bytes: *bytes,
+                duration_ms: duration.as_millis() as u64,
+                error: Some(error.clone()),
+            },
+            IndexEvent::Skipped { path, bytes, reason } => Self {
+                path: path.clone(),
+                status: "skipped",
+                chunks: 0,
+                bytes: *bytes,
+                duration_ms: 0,
+                error: Some(reason.clone()),
+            },
+            _ => return None,
+        };
+        Some(report)
+    }
+}
+
+/// Start writing the report of a run to `out` while the progress bar draws it. Returns the
+/// channel to give the indexer (`Indexer::with_events`) and the task to await once the indexer
+/// is dropped, which finishes the report.
+pub fn spawn(format: ReportFormat, out: Box<dyn Write + Send>) -> (UnboundedSender<IndexEvent>, JoinHandle<Result<()>>) {
+    let (events, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let (bar, bar_receiver) = tokio::sync::mpsc::unbounded_channel();
+    let renderer = tokio::spawn(progress::render_progress_bar(bar_receiver));
+    let writer = tokio::spawn(async move {
+        let result = write_report(receiver, format, out, bar).await;
+        let _ = renderer.await;
+        result
+    });
+    (events, writer)
+}
+
+/// Write a file's outcome as each event ending one arrives, passing the events on to `forward`.
+/// `index` may make several runs (local directories, then each bucket); the report and progress
+/// bar cover them together, with files counted across runs and one summary of their totals.
+async fn write_report(mut events: UnboundedReceiver<IndexEvent>, format: ReportFormat, mut out: Box<dyn Write + Send>,
+                      forward: UnboundedSender<IndexEvent>) -> Result<()> {
+    let mut files = Vec::new();
+    let mut summary = IndexSummary::default();
+    let mut started = 0;
+    while let Some(event) = events.recv().await {
+        if let Some(file) = FileReport::from_event(&event) {
+            match format {
+                ReportFormat::Ndjson => {
+                    writeln!(out, "{}", serde_json::to_string(&file)?)?;
+                    out.flush()?;
+                }
+                ReportFormat::Json => files.push(file),
+            }
+        }
+        let event = match event {
+            IndexEvent::Started { files } => {
+                started += files;
+                IndexEvent::Started { files: started }
+            }
+            IndexEvent::Finished(ref finished) => {
+                summary.add(finished);
+                continue;
+            }
+            event => event,
+        };
+        let _ = forward.send(event);
+    }
+    let _ = forward.send(IndexEvent::Finished(summary.clone()));
+
+    if format == ReportFormat::Json {
+        serde_json::to_writer_pretty(&mut out, &serde_json::json!({ "summary": summary, "files": files }))?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+// Response cache for `/search`, invalidated whenever the index generation moves
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// Serialized search responses for a single index generation, evicting the oldest entry when full
+pub struct SearchCache {
+    capacity: usize,
+    generation: i64,
+    entries: HashMap<String, Arc<String>>,
+    order: VecDeque<String>,
+}
+
+impl SearchCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            generation: 0,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn get(&mut self, key: &str, generation: i64) -> Option<Arc<String>> {
+        self.sync_generation(generation);
+        self.entries.get(key).cloned()
+    }
+
+    pub fn insert(&mut self, key: String, generation: i64, body: Arc<String>) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.sync_generation(generation);
+        if self.generation != generation {
+            // Computed against an older index than entries already cached
+            return;
+        }
+        if self.entries.insert(key.clone(), body).is_none() {
+            self.order.push_back(key);
+        }
+        while self.entries.len() > self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Drop everything cached for an older generation
+    fn sync_generation(&mut self, generation: i64) {
+        if generation > self.generation {
+            self.generation = generation;
+            self.entries.clear();
+            self.order.clear();
+        }
+    }
+}
+
+/// Stable key for a search request: the same query and filters always hash the same
+pub fn cache_key(request: &serde_json::Value) -> String {
+    let digest = Sha256::digest(request.to_string().as_bytes());
+    digest.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Strong ETag tying a response to its request and the index generation it was computed from
+pub fn etag(key: &str, generation: i64) -> String {
+    format!("\"{}-{}\"", key, generation)
+}
+
+/// Whether an `If-None-Match` header value names the given ETag
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',')
+        .map(|candidate| candidate.trim().trim_start_matches("W/"))
+        .any(|candidate| candidate == "*" || candidate == etag)
+}
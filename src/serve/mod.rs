@@ -0,0 +1,581 @@
+// HTTP serve mode: search/ask endpoints plus an asynchronous indexing job API
+use anyhow::Result;
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::cancel::CancellationToken;
+use crate::core::health;
+use crate::core::types::{AnswerStyle, RAGAnswer, ReplicaDocument, SearchFilter, SearchResult, Snippet};
+use crate::db::Database;
+use crate::replicate::{ImportBody, ManifestResponse, RemoveBody};
+use crate::search::Indexer;
+
+mod cache;
+
+use cache::SearchCache;
+
+/// Largest `/replica/documents` request accepted; a batch carries every chunk's embedding
+const REPLICA_BODY_LIMIT: usize = 256 * 1024 * 1024;
+
+/// Lifecycle of an indexing job
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Progress and outcome of an indexing job, as returned by `GET /jobs/{id}`
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub directory: String,
+    pub patterns: Option<String>,
+    pub state: JobState,
+    pub files_total: usize,
+    pub files_done: usize,
+    pub files_failed: usize,
+    pub errors: Vec<String>,
+    /// Cancelled by `POST /jobs/{id}/cancel`
+    #[serde(skip)]
+    cancel: CancellationToken,
+}
+
+type JobTable = Arc<Mutex<HashMap<u64, JobStatus>>>;
+
+/// Reads sent from HTTP handlers to the reader task. Searches and answers carry a token their
+/// handler cancels when it is dropped, i.e. when the client disconnects, so the reader stops (or
+/// never starts) work nobody is waiting for.
+enum ReadRequest {
+    Search {
+        query: String,
+        limit: usize,
+        threshold: f32,
+        filter: Box<SearchFilter>,
+        cancel: CancellationToken,
+        reply: oneshot::Sender<Result<Vec<SearchResult>>>,
+    },
+    Snippets {
+        query: String,
+        limit: usize,
+        cancel: CancellationToken,
+        reply: oneshot::Sender<Result<Vec<Snippet>>>,
+    },
+    Ask {
+        question: String,
+        context: Option<usize>,
+        style: AnswerStyle,
+        deadline: Option<Duration>,
+        cancel: CancellationToken,
+        reply: oneshot::Sender<Result<RAGAnswer>>,
+    },
+    ReplicaManifest {
+        reply: oneshot::Sender<Result<std::collections::BTreeMap<String, String>>>,
+    },
+}
+
+/// Writes sent from HTTP handlers to the worker task, which owns the app
+enum WorkerRequest {
+    Index {
+        job_id: u64,
+    },
+    ReplicaImport {
+        documents: Vec<ReplicaDocument>,
+        reply: oneshot::Sender<Result<usize>>,
+    },
+    ReplicaRemove {
+        paths: Vec<String>,
+        reply: oneshot::Sender<Result<usize>>,
+    },
+}
+
+#[derive(Clone)]
+struct ServeState {
+    worker: mpsc::Sender<WorkerRequest>,
+    reader: mpsc::Sender<ReadRequest>,
+    jobs: JobTable,
+    next_job_id: Arc<AtomicU64>,
+    /// Separate read-only connection so handlers can check the index generation without queuing
+    /// behind the worker
+    db: Arc<Mutex<Database>>,
+    search_cache: Arc<Mutex<SearchCache>>,
+    /// Bearer token the `/replica` endpoints require
+    replica_token: Option<Arc<str>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SearchBody {
+    query: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default = "default_threshold")]
+    threshold: f32,
+    /// Restrict results by extension, path prefix, project, tags or language
+    #[serde(default)]
+    filter: SearchFilter,
+}
+
+#[derive(Deserialize)]
+struct SnippetBody {
+    query: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+#[derive(Deserialize)]
+struct AskBody {
+    question: String,
+    context: Option<usize>,
+    /// Answer length and reading level, as for `ask --length/--reading-level`
+    #[serde(flatten)]
+    style: AnswerStyle,
+    /// As for `ask --deadline`, in milliseconds
+    deadline_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct IndexBody {
+    directory: String,
+    patterns: Option<String>,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+fn default_threshold() -> f32 {
+    0.7
+}
+
+/// Error response carrying a status code and a JSON `{"error": ...}` body
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+fn worker_unavailable() -> ApiError {
+    ApiError(StatusCode::SERVICE_UNAVAILABLE, "Worker is not running".to_string())
+}
+
+/// Reject a `/replica` request that does not carry the configured token
+fn authorize_replica(state: &ServeState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let sent = headers.get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match (state.replica_token.as_deref(), sent) {
+        (Some(token), Some(sent)) if constant_time_eq(token.as_bytes(), sent.as_bytes()) => Ok(()),
+        _ => Err(ApiError(StatusCode::UNAUTHORIZED, "Missing or wrong replication token".to_string())),
+    }
+}
+
+/// Compare without returning early, so response times do not reveal how much of a token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Run the HTTP server until Ctrl-C.
+///
+/// The app is not `Sync` (it owns the SQLite connection), so a worker loop on this task owns it
+/// and processes writes in order, while a reader loop owns a second app (see
+/// `ChunkyMonkeyApp::reader`) and answers searches, snippets and questions, so they do not wait
+/// for an index job. Handlers only talk to the two over channels. Job status lives in a shared
+/// table so `GET /jobs/{id}` stays responsive while an index job runs.
+///
+/// `/search` responses are cached per index generation (up to `cache_size` entries, 0 disables
+/// caching) and carry an ETag, so clients and proxies can revalidate with `If-None-Match`. Any
+/// write to the index, including jobs run through `/index`, moves the generation and invalidates
+/// the cache.
+///
+/// The `/replica` endpoints write and delete documents, so they are only mounted with a
+/// `replica_token`, which every request to them must send as a bearer token.
+pub async fn run_server(app: &mut ChunkyMonkeyApp, host: &str, port: u16, cache_size: usize, replica_token: Option<String>) -> Result<()> {
+    let (tx, rx) = mpsc::channel(64);
+    let (read_tx, read_rx) = mpsc::channel(64);
+    let reader = app.reader()?;
+    let state = ServeState {
+        worker: tx,
+        reader: read_tx,
+        jobs: Arc::new(Mutex::new(HashMap::new())),
+        next_job_id: Arc::new(AtomicU64::new(1)),
+        db: Arc::new(Mutex::new(Database::new()?)),
+        search_cache: Arc::new(Mutex::new(SearchCache::new(cache_size))),
+        replica_token: replica_token.map(Arc::from),
+    };
+    let jobs = state.jobs.clone();
+
+    let router = Router::new()
+        .route("/health", get(health))
+        .route("/search", post(search))
+        .route("/snippets", post(snippets))
+        .route("/ask", post(ask))
+        .route("/index", post(start_index_job))
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/{id}", get(get_job))
+        .route("/jobs/{id}/cancel", post(cancel_job));
+    let router = if state.replica_token.is_some() {
+        router
+            .route("/replica/manifest", get(replica_manifest))
+            .route("/replica/documents", post(replica_import).layer(DefaultBodyLimit::max(REPLICA_BODY_LIMIT)))
+            .route("/replica/remove", post(replica_remove))
+    } else {
+        router
+    };
+    let replication = state.replica_token.is_some();
+    let router = router.with_state(state);
+
+    let listener = tokio::net::TcpListener::bind((host, port)).await?;
+    println!("🐒 ChunkyMonkey serving on http://{}", listener.local_addr()?);
+    if !replication {
+        println!("   Not accepting replicated documents: set replication.token to enable /replica");
+    }
+
+    let server = axum::serve(listener, router).with_graceful_shutdown(async {
+        let _ = tokio::signal::ctrl_c().await;
+    });
+
+    tokio::select! {
+        result = server => result?,
+        _ = run_worker(app, rx, jobs) => {}
+        _ = run_reader(reader, read_rx) => {}
+    }
+
+    Ok(())
+}
+
+/// Answer reads with the reader app, reloading its in-memory index first whenever the index
+/// generation has moved since it was loaded, e.g. by an index job on the worker
+async fn run_reader(mut app: ChunkyMonkeyApp, mut rx: mpsc::Receiver<ReadRequest>) {
+    let mut loaded = app.db.get_index_generation().ok();
+    while let Some(request) = rx.recv().await {
+        let generation = app.db.get_index_generation().ok();
+        if generation != loaded {
+            match app.reload_local_index() {
+                Ok(()) => loaded = generation,
+                Err(e) => eprintln!("Warning: Failed to reload the index: {}", e),
+            }
+        }
+        match request {
+            ReadRequest::Search { query, limit, threshold, filter, cancel, reply } => {
+                app.set_cancellation(cancel);
+                let _ = reply.send(app.search_filtered(&query, limit, threshold, &filter).await);
+            }
+            ReadRequest::Snippets { query, limit, cancel, reply } => {
+                app.set_cancellation(cancel);
+                let _ = reply.send(app.retrieve_snippets(&query, limit).await);
+            }
+            ReadRequest::Ask { question, context, style, deadline, cancel, reply } => {
+                app.set_cancellation(cancel);
+                app.set_answer_style(style);
+                app.set_answer_deadline(deadline);
+                let _ = reply.send(app.ask_question(&question, context).await);
+            }
+            ReadRequest::ReplicaManifest { reply } => {
+                let _ = reply.send(app.db.document_revisions(app.project.as_deref()));
+            }
+        }
+        app.set_cancellation(CancellationToken::new());
+        let _ = app.flush_health();
+    }
+}
+
+async fn run_worker(app: &mut ChunkyMonkeyApp, mut rx: mpsc::Receiver<WorkerRequest>, jobs: JobTable) {
+    while let Some(request) = rx.recv().await {
+        match request {
+            WorkerRequest::Index { job_id } => {
+                run_index_job(app, &jobs, job_id).await;
+            }
+            WorkerRequest::ReplicaImport { documents, reply } => {
+                let mut result = Ok(documents.len());
+                for document in &documents {
+                    if let Err(e) = app.import_replica(document).await {
+                        result = Err(e);
+                        break;
+                    }
+                }
+                let _ = reply.send(result);
+            }
+            WorkerRequest::ReplicaRemove { paths, reply } => {
+                let mut result = Ok(0);
+                for path in &paths {
+                    match app.remove_replica(path).await {
+                        Ok(removed) => result = result.map(|count| count + removed as usize),
+                        Err(e) => {
+                            result = Err(e);
+                            break;
+                        }
+                    }
+                }
+                let _ = reply.send(result);
+            }
+        }
+        // Keep `rag-stats` current while the server runs
+        let _ = app.flush_health();
+    }
+}
+
+async fn run_index_job(app: &mut ChunkyMonkeyApp, jobs: &JobTable, job_id: u64) {
+    let update = |f: &dyn Fn(&mut JobStatus)| {
+        if let Some(job) = jobs.lock().unwrap().get_mut(&job_id) {
+            f(job);
+        }
+    };
+
+    let (directory, patterns, token) = match jobs.lock().unwrap().get(&job_id) {
+        Some(job) => (job.directory.clone(), job.patterns.clone(), job.cancel.clone()),
+        None => return,
+    };
+    if token.is_cancelled() {
+        return;
+    }
+
+    // The token is checked between files rather than handed to the app, so a cancelled job
+    // finishes the file it is on instead of leaving it half stored
+    let indexer = Indexer::new(app.config.indexing.clone());
+    let files = match indexer.collect_matching_files(&directory, patterns.as_deref()) {
+        Ok((files, _)) => files,
+        Err(e) => {
+            update(&|job| {
+                job.state = JobState::Failed;
+                job.errors.push(e.to_string());
+            });
+            return;
+        }
+    };
+
+    update(&|job| {
+        job.state = JobState::Running;
+        job.files_total = files.len();
+    });
+
+    for file_path in &files {
+        if token.is_cancelled() {
+            break;
+        }
+        let result = indexer.index_file(file_path, app).await;
+        update(&|job| {
+            job.files_done += 1;
+            if let Err(ref e) = result {
+                job.files_failed += 1;
+                job.errors.push(format!("{}: {}", file_path.display(), e));
+            }
+        });
+    }
+
+    let state = if token.is_cancelled() { JobState::Cancelled } else { JobState::Completed };
+    update(&|job| job.state = state);
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn search(State(state): State<ServeState>, headers: HeaderMap, Json(body): Json<SearchBody>) -> Result<Response, ApiError> {
+    if !state.search_cache.lock().unwrap().is_enabled() {
+        let results = run_search(&state, body).await?;
+        return Ok(Json(results).into_response());
+    }
+
+    let key = cache::cache_key(&serde_json::to_value(&body).map_err(anyhow::Error::from)?);
+    let generation = state.db.lock().unwrap().get_index_generation()?;
+    let etag = cache::etag(&key, generation);
+
+    let not_modified = headers.get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| cache::etag_matches(value, &etag));
+    if not_modified {
+        return Ok(cached_response(StatusCode::NOT_MODIFIED, &etag, None, "HIT"));
+    }
+
+    let cached = state.search_cache.lock().unwrap().get(&key, generation);
+    health::record_cache(cached.is_some(), health::SEARCH_CACHE_HIT, health::SEARCH_CACHE_MISS);
+    if let Some(json) = cached {
+        return Ok(cached_response(StatusCode::OK, &etag, Some(json.as_str()), "HIT"));
+    }
+
+    let results = run_search(&state, body).await?;
+    let json = Arc::new(serde_json::to_string(&results).map_err(anyhow::Error::from)?);
+    state.search_cache.lock().unwrap().insert(key, generation, json.clone());
+    Ok(cached_response(StatusCode::OK, &etag, Some(json.as_str()), "MISS"))
+}
+
+async fn run_search(state: &ServeState, body: SearchBody) -> Result<Vec<SearchResult>, ApiError> {
+    let cancel = CancellationToken::new();
+    let _cancel_on_drop = cancel.clone().drop_guard();
+    let (reply, response) = oneshot::channel();
+    state.reader
+        .send(ReadRequest::Search {
+            query: body.query,
+            limit: body.limit,
+            threshold: body.threshold,
+            filter: Box::new(body.filter),
+            cancel,
+            reply,
+        })
+        .await
+        .map_err(|_| worker_unavailable())?;
+
+    Ok(response.await.map_err(|_| worker_unavailable())??)
+}
+
+/// Search response with validators: clients may store it but must revalidate before reuse
+fn cached_response(status: StatusCode, etag: &str, json: Option<&str>, cache_status: &'static str) -> Response {
+    let mut response = match json {
+        Some(json) => (status, [(header::CONTENT_TYPE, "application/json")], json.to_string()).into_response(),
+        None => status.into_response(),
+    };
+    let headers = response.headers_mut();
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, value);
+    }
+    headers.insert("x-cache", HeaderValue::from_static(cache_status));
+    response
+}
+
+async fn snippets(State(state): State<ServeState>, Json(body): Json<SnippetBody>) -> Result<Json<Vec<Snippet>>, ApiError> {
+    let cancel = CancellationToken::new();
+    let _cancel_on_drop = cancel.clone().drop_guard();
+    let (reply, response) = oneshot::channel();
+    state.reader
+        .send(ReadRequest::Snippets {
+            query: body.query,
+            limit: body.limit,
+            cancel,
+            reply,
+        })
+        .await
+        .map_err(|_| worker_unavailable())?;
+
+    let snippets = response.await.map_err(|_| worker_unavailable())??;
+    Ok(Json(snippets))
+}
+
+async fn ask(State(state): State<ServeState>, Json(body): Json<AskBody>) -> Result<Json<RAGAnswer>, ApiError> {
+    let cancel = CancellationToken::new();
+    let _cancel_on_drop = cancel.clone().drop_guard();
+    let (reply, response) = oneshot::channel();
+    state.reader
+        .send(ReadRequest::Ask {
+            question: body.question,
+            context: body.context,
+            style: body.style,
+            deadline: body.deadline_ms.map(Duration::from_millis),
+            cancel,
+            reply,
+        })
+        .await
+        .map_err(|_| worker_unavailable())?;
+
+    let answer = response.await.map_err(|_| worker_unavailable())??;
+    Ok(Json(answer))
+}
+
+async fn start_index_job(State(state): State<ServeState>, Json(body): Json<IndexBody>) -> Result<(StatusCode, Json<JobStatus>), ApiError> {
+    let job_id = state.next_job_id.fetch_add(1, Ordering::Relaxed);
+    let job = JobStatus {
+        id: job_id,
+        directory: body.directory,
+        patterns: body.patterns,
+        state: JobState::Queued,
+        files_total: 0,
+        files_done: 0,
+        files_failed: 0,
+        errors: Vec::new(),
+        cancel: CancellationToken::new(),
+    };
+    state.jobs.lock().unwrap().insert(job_id, job.clone());
+
+    state.worker
+        .send(WorkerRequest::Index { job_id })
+        .await
+        .map_err(|_| worker_unavailable())?;
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+async fn list_jobs(State(state): State<ServeState>) -> Json<Vec<JobStatus>> {
+    let mut jobs: Vec<JobStatus> = state.jobs.lock().unwrap().values().cloned().collect();
+    jobs.sort_by_key(|job| job.id);
+    Json(jobs)
+}
+
+/// Revision of each document this server holds, for `replicate` to work out what to send
+async fn replica_manifest(State(state): State<ServeState>, headers: HeaderMap) -> Result<Json<ManifestResponse>, ApiError> {
+    authorize_replica(&state, &headers)?;
+    let (reply, response) = oneshot::channel();
+    state.reader
+        .send(ReadRequest::ReplicaManifest { reply })
+        .await
+        .map_err(|_| worker_unavailable())?;
+
+    let revisions = response.await.map_err(|_| worker_unavailable())??;
+    Ok(Json(ManifestResponse { revisions }))
+}
+
+/// Store documents replicated from another index, in the project this server serves
+async fn replica_import(State(state): State<ServeState>, headers: HeaderMap, Json(body): Json<ImportBody>) -> Result<Json<serde_json::Value>, ApiError> {
+    authorize_replica(&state, &headers)?;
+    let (reply, response) = oneshot::channel();
+    state.worker
+        .send(WorkerRequest::ReplicaImport { documents: body.documents, reply })
+        .await
+        .map_err(|_| worker_unavailable())?;
+
+    let imported = response.await.map_err(|_| worker_unavailable())??;
+    Ok(Json(serde_json::json!({ "imported": imported })))
+}
+
+async fn replica_remove(State(state): State<ServeState>, headers: HeaderMap, Json(body): Json<RemoveBody>) -> Result<Json<serde_json::Value>, ApiError> {
+    authorize_replica(&state, &headers)?;
+    let (reply, response) = oneshot::channel();
+    state.worker
+        .send(WorkerRequest::ReplicaRemove { paths: body.paths, reply })
+        .await
+        .map_err(|_| worker_unavailable())?;
+
+    let removed = response.await.map_err(|_| worker_unavailable())??;
+    Ok(Json(serde_json::json!({ "removed": removed })))
+}
+
+/// Stop a queued or running indexing job after the file it is working on
+async fn cancel_job(State(state): State<ServeState>, Path(id): Path<u64>) -> Result<Json<JobStatus>, ApiError> {
+    let mut jobs = state.jobs.lock().unwrap();
+    let job = jobs.get_mut(&id)
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Job {} not found", id)))?;
+    if matches!(job.state, JobState::Queued | JobState::Running) {
+        job.cancel.cancel();
+        if job.state == JobState::Queued {
+            job.state = JobState::Cancelled;
+        }
+    }
+    Ok(Json(job.clone()))
+}
+
+async fn get_job(State(state): State<ServeState>, Path(id): Path<u64>) -> Result<Json<JobStatus>, ApiError> {
+    state.jobs.lock().unwrap()
+        .get(&id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Job {} not found", id)))
+}
@@ -0,0 +1,255 @@
+// First-run configuration wizard (`chunkymonkey init`): detects Ollama, picks models and a vector
+// store, checks that each service answers and writes a config.toml that loads back cleanly
+use anyhow::Result;
+use colored::*;
+use console::Term;
+use crate::core::config::{AppConfig, OllamaConfig};
+use crate::embeddings::ollama::OllamaEmbeddings;
+use crate::pinecone::PineconeAdmin;
+use crate::qdrant::QdrantClient;
+
+pub const CONFIG_PATH: &str = "config.toml";
+
+const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+const DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+const DEFAULT_LLM_MODEL: &str = "llama3";
+
+/// Whether there is nothing to configure from: no config.toml and no Ollama or Pinecone environment
+pub fn needs_setup() -> bool {
+    !std::path::Path::new(CONFIG_PATH).exists()
+        && std::env::var_os("OLLAMA_BASE_URL").is_none()
+        && std::env::var_os("PINECONE_API_KEY").is_none()
+}
+
+/// On a first run in a terminal, offer the wizard; otherwise say that defaults are in use
+pub async fn offer_first_run_setup() -> Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        eprintln!("{}", format!(
+            "⚠️  No {} found: using defaults, which embed with a low-quality fallback unless Ollama runs at {}. Run `chunkymonkey init` to configure.",
+            CONFIG_PATH, DEFAULT_OLLAMA_URL
+        ).yellow());
+        return Ok(());
+    }
+
+    println!("{}", format!("👋 No {} found.", CONFIG_PATH).bright_cyan());
+    if confirm(&term, "Set up ChunkyMonkey now?", true)? {
+        run_wizard(false).await?;
+    } else {
+        println!("{}", "Using defaults for now; run `chunkymonkey init` any time.".dimmed());
+    }
+    Ok(())
+}
+
+/// Walk through the settings and write config.toml. An existing file is only replaced with `force`.
+pub async fn run_wizard(force: bool) -> Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        anyhow::bail!("`init` needs an interactive terminal; copy config.toml.example to {} instead", CONFIG_PATH);
+    }
+    if std::path::Path::new(CONFIG_PATH).exists() && !force
+        && !confirm(&term, &format!("{} already exists. Replace it?", CONFIG_PATH), false)? {
+        println!("Left {} unchanged.", CONFIG_PATH);
+        return Ok(());
+    }
+
+    let mut config = AppConfig::default();
+
+    println!("\n{}", "🦙 Ollama (embeddings and answers)".bold());
+    let default_url = std::env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| DEFAULT_OLLAMA_URL.to_string());
+    config.ollama.base_url = prompt(&term, "Ollama URL", &default_url)?;
+
+    let installed = OllamaEmbeddings::new_with_config(config.ollama.clone())?.list_models().await;
+    let models = match installed {
+        Ok(models) => {
+            println!("{}", format!("   ✅ Found Ollama with {} model(s)", models.len()).green());
+            if !models.is_empty() {
+                println!("   {}", models.join(", ").dimmed());
+            }
+            models
+        }
+        Err(e) => {
+            println!("{}", format!("   ⚠️  Ollama is not reachable at {}: {}", config.ollama.base_url, e).yellow());
+            println!("{}", "   Until it runs, chunks are embedded with a low-quality fallback and answers are extracted, not generated.".yellow());
+            Vec::new()
+        }
+    };
+
+    let embedding_default = models.iter()
+        .find(|name| name.contains("embed"))
+        .map_or(DEFAULT_EMBEDDING_MODEL, |name| name.as_str());
+    config.ollama.model = prompt(&term, "Embedding model", embedding_default)?;
+    let llm_default = models.iter()
+        .find(|name| !name.contains("embed"))
+        .map_or(DEFAULT_LLM_MODEL, |name| name.as_str());
+    config.ollama.llm_model = prompt(&term, "Answer (LLM) model", llm_default)?;
+
+    if !models.is_empty() {
+        for model in [&config.ollama.model, &config.ollama.llm_model] {
+            if !is_installed(&models, model) {
+                println!("{}", format!("   ⚠️  {} is not installed; run `ollama pull {}`", model, model).yellow());
+            }
+        }
+    }
+
+    config.ollama.embedding_dimension = match probe_embedding_dimension(&config.ollama).await {
+        Ok(dimension) => {
+            println!("{}", format!("   ✅ {} embeds to {} dimensions", config.ollama.model, dimension).green());
+            dimension
+        }
+        Err(e) => {
+            println!("{}", format!("   ⚠️  Could not embed with {}: {}", config.ollama.model, e).yellow());
+            prompt(&term, "Embedding dimension", &config.ollama.embedding_dimension.to_string())?
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Embedding dimension must be a number"))?
+        }
+    };
+
+    println!("\n{}", "🗄️  Vector store".bold());
+    let mut backends = vec![
+        ("local", "SQLite and an in-memory index, nothing to set up"),
+        ("pinecone", "Pinecone (hosted)"),
+        ("qdrant", "Qdrant (self-hosted or cloud)"),
+    ];
+    if cfg!(feature = "lancedb") {
+        backends.push(("lancedb", "LanceDB on disk"));
+    }
+    match backends[choose(&term, "Where should vectors live?", &backends)?].0 {
+        "pinecone" => configure_pinecone(&term, &mut config).await?,
+        "qdrant" => configure_qdrant(&term, &mut config).await?,
+        "lancedb" => config.vector_store.backend = "lancedb".to_string(),
+        _ => {}
+    }
+
+    write_config(&config)?;
+    println!("\n{}", format!("✅ Wrote {}", CONFIG_PATH).green().bold());
+    println!("   Next: {}", "chunkymonkey index <directory>".bright_cyan());
+    Ok(())
+}
+
+async fn configure_pinecone(term: &Term, config: &mut AppConfig) -> Result<()> {
+    let api_key = secret_prompt(term, "Pinecone API key (blank to skip)")?;
+    if api_key.is_empty() {
+        println!("{}", "   Skipping Pinecone; vectors stay local.".dimmed());
+        return Ok(());
+    }
+    config.pinecone.api_key = api_key.clone();
+    config.pinecone.index_name = prompt(term, "Index name", "chunkymonkey")?;
+
+    match PineconeAdmin::new(api_key).describe_index(&config.pinecone.index_name).await {
+        Ok(index) => {
+            println!("{}", format!("   ✅ Connected to index {}", config.pinecone.index_name).green());
+            let host = if index.host.starts_with("http") { index.host } else { format!("https://{}", index.host) };
+            config.pinecone.host = Some(host);
+            if let Some(dimension) = index.dimension.filter(|d| *d != config.ollama.embedding_dimension) {
+                println!("{}", format!(
+                    "   ⚠️  The index holds {}-dimensional vectors but {} produces {}; use a matching index or `migrate-embeddings`",
+                    dimension, config.ollama.model, config.ollama.embedding_dimension
+                ).yellow());
+            }
+        }
+        Err(e) => {
+            println!("{}", format!("   ⚠️  Could not reach index {}: {}", config.pinecone.index_name, e).yellow());
+            let host = prompt(term, "Index host URL (blank to keep trying at startup)", "")?;
+            config.pinecone.host = (!host.is_empty()).then_some(host);
+        }
+    }
+    Ok(())
+}
+
+async fn configure_qdrant(term: &Term, config: &mut AppConfig) -> Result<()> {
+    config.vector_store.backend = "qdrant".to_string();
+    config.qdrant.url = prompt(term, "Qdrant URL", "http://localhost:6333")?;
+    let api_key = secret_prompt(term, "Qdrant API key (blank for none)")?;
+    config.qdrant.api_key = (!api_key.is_empty()).then_some(api_key);
+    config.qdrant.collection_name = prompt(term, "Collection", &config.qdrant.collection_name)?;
+
+    match QdrantClient::new(config.qdrant.clone())?.collection_dimension().await {
+        Ok(Some(dimension)) if dimension != config.ollama.embedding_dimension => {
+            println!("{}", format!(
+                "   ⚠️  Collection {} holds {}-dimensional vectors but {} produces {}",
+                config.qdrant.collection_name, dimension, config.ollama.model, config.ollama.embedding_dimension
+            ).yellow());
+        }
+        Ok(Some(_)) => println!("{}", format!("   ✅ Connected to collection {}", config.qdrant.collection_name).green()),
+        Ok(None) => println!("{}", format!("   ✅ Connected; collection {} is created on first index", config.qdrant.collection_name).green()),
+        Err(e) => println!("{}", format!("   ⚠️  Could not reach Qdrant: {}", e).yellow()),
+    }
+    Ok(())
+}
+
+/// Embed a probe sentence to learn the model's output dimension
+async fn probe_embedding_dimension(ollama: &OllamaConfig) -> Result<usize> {
+    let embedding = OllamaEmbeddings::new_with_config(ollama.clone())?.embed_text("ChunkyMonkey setup check").await?;
+    if embedding.is_empty() {
+        anyhow::bail!("the model returned an empty embedding");
+    }
+    Ok(embedding.len())
+}
+
+/// Ollama lists untagged models as `<name>:latest`
+fn is_installed(models: &[String], model: &str) -> bool {
+    models.iter().any(|name| name == model || *name == format!("{}:latest", model))
+}
+
+/// Write the config only if it parses back, so a bad value can't leave an unloadable file behind.
+/// It can hold API keys, so on Unix only its owner may read it.
+fn write_config(config: &AppConfig) -> Result<()> {
+    let content = toml::to_string(config)?;
+    toml::from_str::<AppConfig>(&content)
+        .map_err(|e| anyhow::anyhow!("Generated configuration does not validate: {}", e))?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to a new file; an existing one (`init --force`) is narrowed first
+        if std::path::Path::new(CONFIG_PATH).exists() {
+            std::fs::set_permissions(CONFIG_PATH, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    std::io::Write::write_all(&mut options.open(CONFIG_PATH)?, content.as_bytes())?;
+    Ok(())
+}
+
+fn prompt(term: &Term, label: &str, default: &str) -> Result<String> {
+    if default.is_empty() {
+        term.write_str(&format!("{}: ", label))?;
+    } else {
+        term.write_str(&format!("{} [{}]: ", label, default.dimmed()))?;
+    }
+    let answer = term.read_line()?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.to_string() } else { answer.to_string() })
+}
+
+/// Like `prompt` with no default, without echoing what is typed
+fn secret_prompt(term: &Term, label: &str) -> Result<String> {
+    term.write_str(&format!("{}: ", label))?;
+    Ok(term.read_secure_line()?.trim().to_string())
+}
+
+pub(crate) fn confirm(term: &Term, label: &str, default_yes: bool) -> Result<bool> {
+    term.write_str(&format!("{} ({}): ", label, if default_yes { "Y/n" } else { "y/N" }))?;
+    let answer = term.read_line()?;
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "" => default_yes,
+        answer => matches!(answer, "y" | "yes"),
+    })
+}
+
+/// Numbered menu; returns the index of the chosen option (the first by default)
+fn choose(term: &Term, label: &str, options: &[(&str, &str)]) -> Result<usize> {
+    println!("{}", label);
+    for (i, (name, description)) in options.iter().enumerate() {
+        println!("   {}. {} {}", i + 1, name.bright_cyan(), format!("- {}", description).dimmed());
+    }
+    loop {
+        let answer = prompt(term, "Choice", "1")?;
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+            _ => println!("{}", format!("Enter a number from 1 to {}", options.len()).yellow()),
+        }
+    }
+}
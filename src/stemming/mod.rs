@@ -0,0 +1,173 @@
+// Language-aware normalization of words for keyword matching: lowercase, reduce each word to its
+// stem with the Snowball stemmer for the text's language, then drop diacritics. The language is
+// detected with whatlang's trigram and script models.
+use rust_stemmers::{Algorithm, Stemmer};
+use whatlang::Lang;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Languages with a stemmer, their ISO 639-1 code and name, and some of their most frequent
+/// words, which are left out of highlighting
+const LANGUAGES: &[(Algorithm, &str, &str, &[&str])] = &[
+    (Algorithm::English, "en", "English", &["the", "and", "of", "to", "is", "in", "that", "it", "with", "for"]),
+    (Algorithm::German, "de", "German", &["der", "die", "und", "das", "ist", "nicht", "mit", "ein", "ich", "zu"]),
+    (Algorithm::French, "fr", "French", &["le", "la", "les", "et", "est", "des", "une", "dans", "pour", "que"]),
+    (Algorithm::Spanish, "es", "Spanish", &["el", "los", "las", "y", "es", "del", "una", "por", "para", "que"]),
+    (Algorithm::Italian, "it", "Italian", &["il", "di", "che", "è", "della", "per", "non", "una", "gli", "sono"]),
+    (Algorithm::Portuguese, "pt", "Portuguese", &["o", "os", "da", "do", "não", "uma", "para", "com", "que", "em"]),
+    (Algorithm::Dutch, "nl", "Dutch", &["de", "het", "een", "en", "van", "niet", "dat", "met", "voor", "zijn"]),
+    (Algorithm::Swedish, "sv", "Swedish", &["och", "att", "det", "som", "är", "på", "för", "med", "inte", "jag"]),
+    (Algorithm::Norwegian, "no", "Norwegian", &["og", "det", "er", "som", "på", "ikke", "til", "med", "av", "jeg"]),
+    (Algorithm::Danish, "da", "Danish", &["og", "det", "er", "som", "på", "ikke", "til", "med", "af", "jeg"]),
+    (Algorithm::Finnish, "fi", "Finnish", &["ja", "on", "ei", "että", "se", "oli", "kun", "mutta", "tai", "myös"]),
+    (Algorithm::Russian, "ru", "Russian", &["и", "в", "не", "на", "что", "с", "он", "как", "это", "по"]),
+];
+
+/// ISO 639-1 codes of the languages whatlang detects (Bokmål is "no", as for the stemmer)
+const ISO_639_1: &[(Lang, &str)] = &[
+    (Lang::Epo, "eo"), (Lang::Eng, "en"), (Lang::Rus, "ru"), (Lang::Cmn, "zh"), (Lang::Spa, "es"),
+    (Lang::Por, "pt"), (Lang::Ita, "it"), (Lang::Ben, "bn"), (Lang::Fra, "fr"), (Lang::Deu, "de"),
+    (Lang::Ukr, "uk"), (Lang::Kat, "ka"), (Lang::Ara, "ar"), (Lang::Hin, "hi"), (Lang::Jpn, "ja"),
+    (Lang::Heb, "he"), (Lang::Yid, "yi"), (Lang::Pol, "pl"), (Lang::Amh, "am"), (Lang::Jav, "jv"),
+    (Lang::Kor, "ko"), (Lang::Nob, "no"), (Lang::Dan, "da"), (Lang::Swe, "sv"), (Lang::Fin, "fi"),
+    (Lang::Tur, "tr"), (Lang::Nld, "nl"), (Lang::Hun, "hu"), (Lang::Ces, "cs"), (Lang::Ell, "el"),
+    (Lang::Bul, "bg"), (Lang::Bel, "be"), (Lang::Mar, "mr"), (Lang::Kan, "kn"), (Lang::Ron, "ro"),
+    (Lang::Slv, "sl"), (Lang::Hrv, "hr"), (Lang::Srp, "sr"), (Lang::Mkd, "mk"), (Lang::Lit, "lt"),
+    (Lang::Lav, "lv"), (Lang::Est, "et"), (Lang::Tam, "ta"), (Lang::Vie, "vi"), (Lang::Urd, "ur"),
+    (Lang::Tha, "th"), (Lang::Guj, "gu"), (Lang::Uzb, "uz"), (Lang::Pan, "pa"), (Lang::Aze, "az"),
+    (Lang::Ind, "id"), (Lang::Tel, "te"), (Lang::Pes, "fa"), (Lang::Mal, "ml"), (Lang::Ori, "or"),
+    (Lang::Mya, "my"), (Lang::Nep, "ne"), (Lang::Sin, "si"), (Lang::Khm, "km"), (Lang::Tuk, "tk"),
+    (Lang::Aka, "ak"), (Lang::Zul, "zu"), (Lang::Sna, "sn"), (Lang::Afr, "af"), (Lang::Lat, "la"),
+    (Lang::Slk, "sk"), (Lang::Cat, "ca"), (Lang::Tgl, "tl"), (Lang::Hye, "hy"), (Lang::Cym, "cy"),
+];
+
+/// Confidence whatlang must have in a language of Latin script to assume it; short phrases and
+/// code fall below it. Other scripts (Hangul, Hebrew, Arabic, Cyrillic) narrow the language down
+/// to a few, so their best guess is taken.
+const MIN_LATIN_CONFIDENCE: f64 = 0.5;
+
+/// Splits text into normalized terms, stemmed for one language when it is known
+#[derive(Clone, Copy)]
+pub struct Analyzer {
+    language: Option<Algorithm>,
+}
+
+impl Analyzer {
+    /// Analyzer for `text` per the `search.keyword_language` setting: "auto" detects the language,
+    /// "none" only lowercases and strips diacritics, anything else names a language of `LANGUAGES`
+    /// (checked when the config is loaded, see `check_setting`)
+    pub fn for_text(setting: &str, text: &str) -> Self {
+        let language = match setting {
+            "none" => None,
+            "auto" => detect_language(text),
+            name => stemmer_language(name),
+        };
+        Self { language }
+    }
+
+    /// Analyzer for text in the language with this ISO 639-1 code, stemming only if it has a stemmer
+    pub fn for_language(code: Option<&str>) -> Self {
+        Self { language: code.and_then(stemmer_language) }
+    }
+
+    /// Lowercased, stemmed words of `text` without diacritics ("Häuser" gives "haus" in German)
+    pub fn terms(&self, text: &str) -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let word = word.to_lowercase();
+                match self.language {
+                    Some(language) => fold_diacritics(&Stemmer::create(language).stem(&word)),
+                    None => fold_diacritics(&word),
+                }
+            })
+            .collect()
+    }
+
+    /// Byte ranges of the words of `text` whose terms are among `terms`
+    pub fn find_terms(&self, text: &str, terms: &std::collections::HashSet<String>) -> Vec<(usize, usize)> {
+        word_spans(text).into_iter()
+            .filter(|&(start, end)| self.terms(&text[start..end]).iter().any(|term| terms.contains(term)))
+            .collect()
+    }
+}
+
+/// Byte ranges of the words of `text`, the runs of letters and digits terms are made from
+pub fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let start = word.as_ptr() as usize - text.as_ptr() as usize;
+            (start, start + word.len())
+        })
+        .collect()
+}
+
+/// Terms of `text` as the keyword index holds them, space-separated: stemmed in its language (an
+/// ISO 639-1 code) when that has a stemmer
+pub fn index_terms(text: &str, language: Option<&str>) -> String {
+    Analyzer::for_language(language).terms(text).join(" ")
+}
+
+/// Terms a query word may be indexed as: its stem in each language the `search.keyword_language`
+/// setting allows ("auto" allows all of them) and, for text in other languages, the word itself
+pub fn query_terms(setting: &str, word: &str) -> Vec<String> {
+    let languages: Vec<Algorithm> = match setting {
+        "none" => Vec::new(),
+        "auto" => LANGUAGES.iter().map(|(algorithm, _, _, _)| *algorithm).collect(),
+        name => stemmer_language(name).into_iter().collect(),
+    };
+    let mut terms = Analyzer { language: None }.terms(word);
+    for language in languages {
+        terms.extend(Analyzer { language: Some(language) }.terms(word));
+    }
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+/// Check a `search.keyword_language` setting: "auto", "none", or the name or ISO 639-1 code of
+/// a language with a stemmer ("german", "de")
+pub fn check_setting(setting: &str) -> Result<(), String> {
+    if matches!(setting, "auto" | "none") || stemmer_language(setting).is_some() {
+        return Ok(());
+    }
+    let names: Vec<String> = LANGUAGES.iter().map(|(_, _, name, _)| name.to_lowercase()).collect();
+    Err(format!("unknown keyword_language '{}': expected \"auto\", \"none\" or one of {}", setting, names.join(", ")))
+}
+
+/// The stemmer for a language named ("German") or given by its ISO 639-1 code ("de")
+fn stemmer_language(name: &str) -> Option<Algorithm> {
+    LANGUAGES.iter()
+        .find(|(_, code, known, _)| known.eq_ignore_ascii_case(name) || code.eq_ignore_ascii_case(name))
+        .map(|(algorithm, _, _, _)| *algorithm)
+}
+
+/// Whether `word` is one of the most frequent words of a known language ("the", "und")
+pub fn is_frequent_word(word: &str) -> bool {
+    let word = word.to_lowercase();
+    LANGUAGES.iter().any(|(_, _, _, frequent)| frequent.contains(&word.as_str()))
+}
+
+/// The stemmer for the language `text` is written in, if it has one
+pub fn detect_language(text: &str) -> Option<Algorithm> {
+    detect_language_code(text).and_then(stemmer_language)
+}
+
+/// ISO 639-1 code of the natural language `text` is written in ("de"), when it can be told
+pub fn detect_language_code(text: &str) -> Option<&'static str> {
+    let info = whatlang::detect(text)?;
+    if info.script() == whatlang::Script::Latin && info.confidence() < MIN_LATIN_CONFIDENCE {
+        return None;
+    }
+    ISO_639_1.iter().find(|(lang, _)| *lang == info.lang()).map(|(_, code)| *code)
+}
+
+/// English name of the language with this ISO 639-1 code ("German")
+pub fn language_name(code: &str) -> Option<&'static str> {
+    ISO_639_1.iter().find(|(_, known)| known.eq_ignore_ascii_case(code)).map(|(lang, _)| lang.eng_name())
+}
+
+fn fold_diacritics(word: &str) -> String {
+    word.nfkd().filter(|c| !is_combining_mark(*c)).collect()
+}
@@ -0,0 +1,101 @@
+// Reconcile the remote vector store with the chunks in the local database
+use anyhow::Result;
+use std::collections::HashSet;
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::types::ChunkMetadata;
+use crate::pinecone::{chunk_metadata, Vector, UPSERT_BATCH_SIZE};
+
+/// Outcome of a Pinecone reconcile run
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub namespace: Option<String>,
+    pub local_vectors: usize,
+    pub remote_vectors: usize,
+    /// All vectors in the namespace according to describe_index_stats, including non-chunk ids
+    pub namespace_vector_count: u64,
+    /// Local chunks with no vector in Pinecone
+    pub missing: usize,
+    /// Pinecone vectors with no local chunk
+    pub orphaned: usize,
+    pub upserted: usize,
+    pub deleted: usize,
+    pub dry_run: bool,
+}
+
+/// Compare the vector ids in the app's Pinecone namespace against the local chunks of the current
+/// project, re-upsert chunks that are missing remotely and delete remote vectors with no local chunk.
+///
+/// Only ids with the `chunk_` prefix are considered, so vectors written by other tools are left alone.
+pub async fn sync_pinecone(app: &mut ChunkyMonkeyApp, dry_run: bool) -> Result<SyncReport> {
+    let pinecone = app.pinecone_client.as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Pinecone is not configured"))?;
+
+    let local = app.db.get_project_chunk_vectors(app.project.as_deref())?;
+    let remote: HashSet<String> = pinecone.list_vector_ids("chunk_").await?.into_iter().collect();
+    let stats = pinecone.describe_index_stats().await?;
+    let namespace_vector_count = stats.namespaces
+        .get(app.project.as_deref().unwrap_or(""))
+        .map(|ns| ns.vector_count)
+        .unwrap_or(0);
+
+    let expected_id = |chunk_id: u32, vector_id: &Option<String>| {
+        vector_id.clone().unwrap_or_else(|| format!("chunk_{}", chunk_id))
+    };
+    let local_ids: HashSet<String> = local.iter()
+        .map(|(chunk, _, vector_id, _)| expected_id(chunk.id, vector_id))
+        .collect();
+
+    let missing: Vec<_> = local.iter()
+        .filter(|(chunk, _, vector_id, _)| !remote.contains(&expected_id(chunk.id, vector_id)))
+        .collect();
+    let mut orphaned: Vec<String> = remote.difference(&local_ids).cloned().collect();
+    orphaned.sort();
+
+    let mut report = SyncReport {
+        namespace: app.project.clone(),
+        local_vectors: local.len(),
+        remote_vectors: remote.len(),
+        namespace_vector_count,
+        missing: missing.len(),
+        orphaned: orphaned.len(),
+        dry_run,
+        ..Default::default()
+    };
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    let mut uploaded = Vec::new();
+    for batch in missing.chunks(UPSERT_BATCH_SIZE) {
+        let mut vectors = Vec::new();
+        for (chunk, path, vector_id, embedding) in batch.iter().filter(|(_, _, _, embedding)| !embedding.is_empty()) {
+            let tags = app.db.get_document_metadata(app.project.as_deref(), path)?.tags;
+            let metadata = ChunkMetadata::for_stored_chunk(path, app.project.as_deref(), &tags, &chunk.text, chunk.language.as_deref());
+            vectors.push(Vector {
+                id: expected_id(chunk.id, vector_id),
+                values: embedding.clone(),
+                metadata: chunk_metadata(path, &chunk.text, chunk.id, chunk.document_id, &metadata),
+            });
+        }
+        if vectors.is_empty() {
+            continue;
+        }
+
+        let ids: Vec<(u32, String)> = batch.iter()
+            .filter(|(_, _, _, embedding)| !embedding.is_empty())
+            .map(|(chunk, _, vector_id, _)| (chunk.id, expected_id(chunk.id, vector_id)))
+            .collect();
+        pinecone.upsert_vectors(vectors).await?;
+        report.upserted += ids.len();
+        uploaded.extend(ids);
+    }
+
+    if !orphaned.is_empty() {
+        pinecone.delete_vectors(orphaned).await?;
+        report.deleted = report.orphaned;
+    }
+
+    app.db.set_chunk_vector_ids(&uploaded)?;
+    Ok(report)
+}
@@ -0,0 +1,73 @@
+// Opt-in usage log: each command's name, duration, result count and outcome, written to the local
+// database. Queries, paths and content are never recorded, and nothing leaves the machine.
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use crate::db::Database;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// Days to keep log entries (0 keeps them forever)
+    pub retention_days: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: 90,
+        }
+    }
+}
+
+/// Times one command and logs it when dropped. A run that never reaches `finish` is logged as a
+/// failure, so commands that bail out with `?` are still counted. Disabled telemetry is a no-op.
+pub struct UsageRecorder {
+    db: Option<Database>,
+    command: String,
+    retention_days: u64,
+    started: Instant,
+    started_at: i64,
+    result_count: Option<usize>,
+    succeeded: bool,
+}
+
+impl UsageRecorder {
+    pub fn start(config: &TelemetryConfig, command: &str) -> Self {
+        // Logging must never stop a command, so an unopenable database just disables it
+        let db = if config.enabled { Database::new().ok() } else { None };
+        Self {
+            db,
+            command: command.to_string(),
+            retention_days: config.retention_days,
+            started: Instant::now(),
+            started_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+            result_count: None,
+            succeeded: false,
+        }
+    }
+
+    /// Record how many results the command produced (search hits, answer sources, documents indexed)
+    pub fn set_result_count(&mut self, count: usize) {
+        self.result_count = Some(count);
+    }
+
+    /// Mark the command as completed successfully
+    pub fn finish(mut self) {
+        self.succeeded = true;
+    }
+}
+
+impl Drop for UsageRecorder {
+    fn drop(&mut self) {
+        if let Some(db) = &self.db {
+            let duration_ms = self.started.elapsed().as_millis() as u64;
+            let _ = db.record_usage(&self.command, self.started_at, duration_ms, self.result_count,
+                self.succeeded, self.retention_days);
+        }
+    }
+}
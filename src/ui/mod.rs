@@ -1,2 +1,448 @@
-// UI module for future TUI enhancements
-// This could include ratatui-based interfaces, progress bars, etc. 
\ No newline at end of file
+// UI module for rendering answers and sources outside the interactive TUI
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+use serde::Serialize;
+use crate::core::types::{location, KeywordMatch, RAGAnswer, SearchResult};
+
+/// Finds a query's terms in result text, in any form with the same stem ("index" also finds
+/// "indexing"), for highlighting them and reporting where they are
+pub struct Highlighter {
+    query: String,
+    /// `search.keyword_language`
+    language: String,
+}
+
+/// Where a query term is in a result's text, in characters
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MatchOffset {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Highlighter {
+    pub fn new(query: &str, language: &str) -> Self {
+        Self { query: query.to_string(), language: language.to_string() }
+    }
+
+    /// Byte ranges of the words of `text` matching a query term. Terms are compared as
+    /// `keyword_overlap` compares them: longer than 2 characters, stemmed in the text's language.
+    fn spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let analyzer = crate::stemming::Analyzer::for_text(&self.language, text);
+        let terms: std::collections::HashSet<String> = self.query
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() > 2 && !crate::stemming::is_frequent_word(word))
+            .flat_map(|word| analyzer.terms(word))
+            .collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        analyzer.find_terms(text, &terms)
+    }
+
+    /// `text` with the query's terms in bold
+    pub fn highlight(&self, text: &str) -> String {
+        self.highlight_start(text, usize::MAX)
+    }
+
+    /// The first `chars` characters of `text` with the query's terms in bold. The language to
+    /// stem in is told from the whole text, which a short preview may not be enough for.
+    pub fn highlight_start(&self, text: &str, chars: usize) -> String {
+        let cut = text.char_indices().nth(chars).map_or(text.len(), |(i, _)| i);
+        let mut output = String::new();
+        let mut end = 0;
+        for (start, stop) in self.spans(text).into_iter().take_while(|(start, _)| *start < cut) {
+            let stop = stop.min(cut);
+            output.push_str(&text[end..start]);
+            output.push_str(&text[start..stop].bold().yellow().to_string());
+            end = stop;
+        }
+        output.push_str(&text[end..cut]);
+        output
+    }
+
+    /// Character offsets of the query's terms in `text`
+    pub fn offsets(&self, text: &str) -> Vec<MatchOffset> {
+        let mut offsets = Vec::new();
+        let (mut byte, mut char) = (0, 0);
+        for (start, end) in self.spans(text) {
+            char += text[byte..start].chars().count();
+            let length = text[start..end].chars().count();
+            offsets.push(MatchOffset { start: char, end: char + length });
+            char += length;
+            byte = end;
+        }
+        offsets
+    }
+}
+
+/// How `search` prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ResultFormat {
+    /// Colored listing with a preview of each result
+    Text,
+    /// One JSON array of results
+    Json,
+    /// One JSON object per line for each result
+    Ndjson,
+    /// A header row, then one row per result
+    Csv,
+    /// A Markdown section per result with its excerpt
+    Md,
+}
+
+/// A search result as the machine-readable formats write it, whichever mode found it
+#[derive(Debug, Serialize)]
+pub struct ResultRow {
+    pub path: String,
+    pub chunk_id: u32,
+    /// Similarity for semantic search, BM25 relevance for keyword search
+    pub score: f32,
+    pub line_start: Option<usize>,
+    pub line_end: Option<usize>,
+    pub text: String,
+    /// Where the query's terms are in `text`
+    pub matches: Vec<MatchOffset>,
+    /// The part of the text around the matched terms, for keyword matches
+    #[serde(skip)]
+    pub snippet: Option<String>,
+}
+
+impl ResultRow {
+    /// Rows for results, with where the query's terms are in each when there is a query
+    pub fn all<'a, T: 'a>(results: &'a [T], highlighter: Option<&Highlighter>) -> Vec<Self> where &'a T: Into<Self> {
+        results.iter()
+            .map(|result| {
+                let mut row: Self = result.into();
+                if let Some(highlighter) = highlighter {
+                    row.matches = highlighter.offsets(&row.text);
+                }
+                row
+            })
+            .collect()
+    }
+
+    fn location(&self) -> String {
+        location(&self.path, self.line_start.zip(self.line_end))
+    }
+
+    /// The matched part of the chunk with keyword marks removed, or its start
+    fn preview(&self) -> String {
+        match self.snippet {
+            Some(ref snippet) => snippet.replace(crate::db::KEYWORD_MATCH_START, "").replace(crate::db::KEYWORD_MATCH_END, ""),
+            None if self.text.chars().count() > PREVIEW_CHARS => format!("{}…", self.text.chars().take(PREVIEW_CHARS).collect::<String>()),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// Characters of a semantic result's text shown as its document's best snippet
+const PREVIEW_CHARS: usize = 200;
+
+/// The documents among search results, each scored by its best chunk
+#[derive(Debug, Serialize)]
+pub struct DocumentRow {
+    pub path: String,
+    /// Score of the best chunk
+    pub score: f32,
+    /// Chunks of the document among the results
+    pub hits: usize,
+    pub chunk_id: u32,
+    pub line_start: Option<usize>,
+    pub line_end: Option<usize>,
+    /// Text of the best chunk, or the part of it around the matched terms
+    pub snippet: String,
+}
+
+/// Aggregate chunk results per document, best document first, keeping at most `limit`
+pub fn group_by_document(rows: &[ResultRow], limit: usize) -> Vec<DocumentRow> {
+    let mut documents: Vec<DocumentRow> = Vec::new();
+    for row in rows {
+        match documents.iter_mut().find(|document| document.path == row.path) {
+            Some(document) => {
+                document.hits += 1;
+                if row.score > document.score {
+                    *document = DocumentRow { hits: document.hits, ..DocumentRow::best(row) };
+                }
+            }
+            None => documents.push(DocumentRow::best(row)),
+        }
+    }
+    documents.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    documents.truncate(limit);
+    documents
+}
+
+impl DocumentRow {
+    fn best(row: &ResultRow) -> Self {
+        Self {
+            path: row.path.clone(),
+            score: row.score,
+            hits: 1,
+            chunk_id: row.chunk_id,
+            line_start: row.line_start,
+            line_end: row.line_end,
+            snippet: row.preview(),
+        }
+    }
+}
+
+impl From<&SearchResult> for ResultRow {
+    fn from(result: &SearchResult) -> Self {
+        Self {
+            path: result.document_path.clone(),
+            chunk_id: result.chunk_id,
+            score: result.similarity,
+            line_start: result.lines.map(|(first, _)| first),
+            line_end: result.lines.map(|(_, last)| last),
+            text: result.chunk_text.clone(),
+            matches: Vec::new(),
+            snippet: None,
+        }
+    }
+}
+
+impl From<&KeywordMatch> for ResultRow {
+    fn from(m: &KeywordMatch) -> Self {
+        Self {
+            path: m.document_path.clone(),
+            chunk_id: m.chunk_id,
+            score: m.score,
+            line_start: m.lines.map(|(first, _)| first),
+            line_end: m.lines.map(|(_, last)| last),
+            text: m.chunk_text.clone(),
+            matches: Vec::new(),
+            snippet: Some(m.snippet.clone()),
+        }
+    }
+}
+
+/// Render search results in one of the machine-readable formats (not `Text`, which main prints)
+pub fn render_results(rows: &[ResultRow], format: ResultFormat) -> Result<String> {
+    let mut output = String::new();
+    match format {
+        ResultFormat::Text => anyhow::bail!("text results are printed, not rendered"),
+        ResultFormat::Json | ResultFormat::Ndjson => return render_json(rows, format == ResultFormat::Ndjson),
+        ResultFormat::Csv => {
+            output.push_str("path,chunk_id,score,line_start,line_end,text");
+            for row in rows {
+                let line = |value: Option<usize>| value.map(|v| v.to_string()).unwrap_or_default();
+                output.push_str(&format!("\n{},{},{:.4},{},{},{}", csv_field(&row.path), row.chunk_id, row.score,
+                    line(row.line_start), line(row.line_end), csv_field(&row.text)));
+            }
+        }
+        ResultFormat::Md => {
+            for (i, row) in rows.iter().enumerate() {
+                output.push_str(&format!("### {}. `{}` (Score: {:.3})\n\n{}\n\n", i + 1, row.location(), row.score,
+                    markdown_excerpt(&row.path, &row.text)));
+            }
+        }
+    }
+    Ok(output.trim_end().to_string())
+}
+
+/// Render documents grouped from search results in one of the machine-readable formats
+pub fn render_documents(documents: &[DocumentRow], format: ResultFormat) -> Result<String> {
+    let mut output = String::new();
+    match format {
+        ResultFormat::Text => anyhow::bail!("text results are printed, not rendered"),
+        ResultFormat::Json | ResultFormat::Ndjson => return render_json(documents, format == ResultFormat::Ndjson),
+        ResultFormat::Csv => {
+            output.push_str("path,score,hits,chunk_id,line_start,line_end,snippet");
+            for document in documents {
+                let line = |value: Option<usize>| value.map(|v| v.to_string()).unwrap_or_default();
+                output.push_str(&format!("\n{},{:.4},{},{},{},{},{}", csv_field(&document.path), document.score, document.hits,
+                    document.chunk_id, line(document.line_start), line(document.line_end), csv_field(&document.snippet)));
+            }
+        }
+        ResultFormat::Md => {
+            for (i, document) in documents.iter().enumerate() {
+                output.push_str(&format!("### {}. `{}` (Score: {:.3}, hits: {})\n\n{}\n\n", i + 1, document.path, document.score,
+                    document.hits, markdown_excerpt(&document.path, &document.snippet)));
+            }
+        }
+    }
+    Ok(output.trim_end().to_string())
+}
+
+/// One JSON array, or one object per line
+fn render_json<T: Serialize>(rows: &[T], lines: bool) -> Result<String> {
+    if !lines {
+        return Ok(serde_json::to_string_pretty(rows)?);
+    }
+    let lines = rows.iter().map(serde_json::to_string).collect::<serde_json::Result<Vec<_>>>()?;
+    Ok(lines.join("\n"))
+}
+
+/// A CSV field, quoted when it holds a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Map a source path to the Markdown code fence language for its extension
+pub fn code_language(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "tsx" => "tsx",
+        "jsx" => "jsx",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "scala" => "scala",
+        "sh" | "bash" | "zsh" => "bash",
+        "sql" => "sql",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "json" => "json",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "lua" => "lua",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Render a source excerpt as Markdown: a fenced block for code, a blockquote for prose
+pub fn excerpt_markdown(result: &SearchResult) -> String {
+    markdown_excerpt(&result.document_path, &result.chunk_text)
+}
+
+fn markdown_excerpt(path: &str, text: &str) -> String {
+    match code_language(path) {
+        Some(language) => {
+            // The fence must be longer than any run of backticks in the excerpt, which could otherwise close it
+            let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+            let fence = "`".repeat(longest_run.max(2) + 1);
+            format!("{}{}\n{}\n{}", fence, language, text.trim_end(), fence)
+        }
+        None => text
+            .lines()
+            .map(|line| format!("> {}", line))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Render an answer and its sources as a Markdown document
+pub fn render_answer_markdown(answer: &RAGAnswer) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("## Question\n\n{}\n\n", answer.question));
+    output.push_str(&format!("## Answer\n\n{}\n", crate::glossary::footnote_markdown(&answer.answer, &answer.glossary_terms).trim_end()));
+    if let Some(ref learned) = answer.learned_from {
+        output.push_str(&format!("\n_Saved answer #{} to \"{}\"_\n", learned.id, learned.question));
+    }
+    if answer.deadline_exceeded {
+        output.push_str("\n_The deadline passed before the LLM finished; this summary is extracted from the sources._\n");
+    }
+
+    if !answer.term_misuses.is_empty() {
+        output.push('\n');
+        for misuse in &answer.term_misuses {
+            output.push_str(&format!("> **Terminology:** \"{}\" is discouraged; the glossary term is \"{}\".\n", misuse.used, misuse.preferred));
+        }
+    }
+    if !answer.glossary_terms.is_empty() {
+        output.push('\n');
+        for (i, term) in answer.glossary_terms.iter().enumerate() {
+            output.push_str(&format!("[^{}]: **{}**: {}\n", i + 1, term.term, term.definition));
+        }
+    }
+
+    if !answer.citations.is_empty() {
+        output.push_str("\n## Citations\n\n");
+        for (i, citation) in answer.citations.iter().enumerate() {
+            output.push_str(&format!("{}. `{}` (chunk {})\n\n   > {}\n\n", i + 1, citation.document_path, citation.chunk_id, citation.quote.replace('\n', "\n   > ")));
+        }
+    }
+
+    if !answer.sources.is_empty() {
+        output.push_str("\n## Sources\n");
+        for (i, source) in answer.sources.iter().enumerate() {
+            output.push_str(&format!(
+                "\n### {}. `{}` (Similarity: {:.3})\n\n{}\n",
+                i + 1,
+                source.location(),
+                source.similarity,
+                excerpt_markdown(source)
+            ));
+        }
+    }
+
+    output
+}
+
+/// Render an answer and its sources as JSON, including fenced excerpts for each source
+pub fn render_answer_json(answer: &RAGAnswer) -> Result<String> {
+    let sources: Vec<serde_json::Value> = answer.sources.iter().map(|source| {
+        serde_json::json!({
+            "chunk_id": source.chunk_id,
+            "document_path": source.document_path,
+            "lines": source.lines.map(|(first, last)| serde_json::json!({ "first": first, "last": last })),
+            "similarity": source.similarity,
+            "metadata_only": source.metadata_only,
+            "language": code_language(&source.document_path),
+            "text": source.chunk_text,
+            "excerpt_markdown": excerpt_markdown(source),
+        })
+    }).collect();
+
+    let output = serde_json::json!({
+        "question": answer.question,
+        "answer": answer.answer,
+        "sources": sources,
+        "learned_answer_id": answer.learned_from.as_ref().map(|learned| learned.id),
+        "citations": answer.citations,
+        "deadline_exceeded": answer.deadline_exceeded,
+        "glossary_terms": answer.glossary_terms,
+        "term_misuses": answer.term_misuses,
+    });
+
+    Ok(serde_json::to_string_pretty(&output)?)
+}
+
+/// Render a source excerpt for the terminal, highlighting code sources
+pub fn excerpt_terminal(result: &SearchResult) -> String {
+    match code_language(&result.document_path) {
+        Some(language) => {
+            let mut output = format!("   {}\n", format!("─── {} ───", language).dimmed());
+            for line in result.chunk_text.lines() {
+                output.push_str(&format!("   {}\n", line.bright_cyan()));
+            }
+            output
+        }
+        None => result
+            .chunk_text
+            .lines()
+            .map(|line| format!("   │ {}\n", line))
+            .collect(),
+    }
+}
+
+/// After a first indexing run, print example questions the corpus can answer so the first prompt isn't blank.
+/// Prints nothing when no LLM is configured or generation fails.
+pub async fn show_question_suggestions(app: &crate::core::app::ChunkyMonkeyApp) {
+    println!("\n💭 Finding questions your documents can answer...");
+    let questions = match app.suggest_questions(5).await {
+        Ok(questions) if !questions.is_empty() => questions,
+        _ => return,
+    };
+
+    println!("\n{}", "💡 Try asking:".bright_purple().bold());
+    for question in questions {
+        println!("   • {}", question.bright_cyan());
+    }
+}
@@ -1,57 +1,239 @@
 use anyhow::Result;
-use std::collections::HashMap;
-use crate::embeddings::cosine_similarity;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::core::types::{ChunkMetadata, DocumentMetadata, SearchFilter, SearchResult};
+use crate::embeddings::DistanceMetric;
+
+/// File format marker and version of local index snapshots
+const SNAPSHOT_MAGIC: &[u8; 8] = b"CMVIDX01";
+
+/// Below this many vectors a single thread beats the cost of splitting the scan across the pool
+const PARALLEL_SEARCH_THRESHOLD: usize = 4096;
+
+/// Where the in-memory index is persisted between runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalIndexConfig {
+    /// Save the index after rebuilding it and load it from disk on startup
+    pub persist: bool,
+    /// Directory holding one snapshot file per project
+    pub dir: String,
+}
+
+impl Default for LocalIndexConfig {
+    fn default() -> Self {
+        Self {
+            persist: true,
+            dir: "chunkymonkey.index".to_string(),
+        }
+    }
+}
+
+impl LocalIndexConfig {
+    /// Snapshot file for a project; names are hashed so any project name makes a valid file name
+    pub fn snapshot_path(&self, project: Option<&str>) -> PathBuf {
+        use sha2::{Digest, Sha256};
+        let file_name = match project {
+            None => "default.idx".to_string(),
+            Some(project) => {
+                let digest = format!("{:x}", Sha256::digest(project.as_bytes()));
+                format!("project-{}.idx", &digest[..16])
+            }
+        };
+        Path::new(&self.dir).join(file_name)
+    }
+}
+
+/// A scored chunk in the top-k heap. Ordered so that weaker results compare greater,
+/// which keeps the weakest of the current top k at the top of the (max-)heap.
+#[derive(PartialEq)]
+struct Candidate {
+    similarity: f32,
+    chunk_id: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Highest similarity first, ties by chunk id for stable output
+        other.similarity.total_cmp(&self.similarity)
+            .then(self.chunk_id.cmp(&other.chunk_id))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Per-worker state of a top-k scan
+struct ScanState<'a> {
+    k: usize,
+    heap: BinaryHeap<Candidate>,
+    document_matches: HashMap<&'a str, bool>,
+}
+
+impl ScanState<'_> {
+    fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+            document_matches: HashMap::new(),
+        }
+    }
+}
+
+/// Add a candidate to a heap holding the best k, evicting the weakest when it is better
+fn push_top_k(heap: &mut BinaryHeap<Candidate>, candidate: Candidate, k: usize) {
+    if heap.len() < k {
+        heap.push(candidate);
+    } else if heap.peek().is_some_and(|weakest| candidate < *weakest) {
+        heap.pop();
+        heap.push(candidate);
+    }
+}
+
+/// What the index keeps about a chunk besides its vector
+pub struct IndexedChunk {
+    pub document_path: String,
+    pub chunk_text: String,
+    pub metadata: ChunkMetadata,
+}
 
 pub struct VectorIndex {
     vectors: HashMap<u32, Vec<f32>>,
-    metadata: HashMap<u32, (String, String)>, // chunk_id -> (document_path, chunk_text)
+    metadata: HashMap<u32, IndexedChunk>,
     dimension: usize,
+    metric: DistanceMetric,
 }
 
 impl VectorIndex {
-    pub fn new(dimension: usize) -> Self {
+    pub fn new(dimension: usize, metric: DistanceMetric) -> Self {
         Self {
             vectors: HashMap::new(),
             metadata: HashMap::new(),
             dimension,
+            metric,
         }
     }
 
-    pub fn add_vector(&mut self, chunk_id: u32, vector: &[f32], document_path: &str, chunk_text: &str) -> Result<()> {
+    pub fn add_vector(&mut self, chunk_id: u32, vector: &[f32], document_path: &str, chunk_text: &str, metadata: ChunkMetadata) -> Result<()> {
         if vector.len() != self.dimension {
             anyhow::bail!("Vector dimension mismatch: expected {}, got {}", self.dimension, vector.len());
         }
         
         // Store vector and metadata
         self.vectors.insert(chunk_id, vector.to_vec());
-        self.metadata.insert(chunk_id, (document_path.to_string(), chunk_text.to_string()));
+        self.metadata.insert(chunk_id, IndexedChunk {
+            document_path: document_path.to_string(),
+            chunk_text: chunk_text.to_string(),
+            metadata,
+        });
         
         Ok(())
     }
 
-    pub fn search_similar(&self, query_vector: &[f32], k: usize) -> Result<Vec<(u32, f32, String, String)>> {
+    /// The k most similar chunks matching `filter`. The filter is checked before scoring,
+    /// so restrictive filters still return up to k results.
+    pub fn search_similar(&self, query_vector: &[f32], k: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+        self.search_similar_where(query_vector, k, filter, None)
+    }
+
+    /// The k most similar chunks matching `filter` whose text passes `keep`, however dissimilar
+    pub fn search_similar_where(&self, query_vector: &[f32], k: usize, filter: &SearchFilter, keep: Option<&(dyn Fn(&str) -> bool + Sync)>) -> Result<Vec<SearchResult>> {
         if query_vector.len() != self.dimension {
             anyhow::bail!("Query vector dimension mismatch: expected {}, got {}", self.dimension, query_vector.len());
         }
         
-        let mut results = Vec::new();
+        if k == 0 {
+            return Ok(Vec::new());
+        }
         
-        // Calculate similarity with all vectors
-        for (chunk_id, vector) in &self.vectors {
-            if let Some((document_path, chunk_text)) = self.metadata.get(chunk_id) {
-                let similarity = cosine_similarity(query_vector, vector);
-                results.push((*chunk_id, similarity, document_path.clone(), chunk_text.clone()));
+        // Keep only the best k candidates; metadata is cloned for the winners alone.
+        // Large indexes are scanned in parallel, each worker keeping its own top k, then merged.
+        let heap = if self.vectors.len() >= PARALLEL_SEARCH_THRESHOLD {
+            self.vectors.par_iter()
+                .fold(
+                    || ScanState::new(k),
+                    |mut state, (chunk_id, vector)| {
+                        self.scan(&mut state, *chunk_id, vector, query_vector, filter, keep);
+                        state
+                    },
+                )
+                .map(|state| state.heap)
+                .reduce(
+                    || BinaryHeap::with_capacity(k + 1),
+                    |mut merged, other| {
+                        for candidate in other {
+                            push_top_k(&mut merged, candidate, k);
+                        }
+                        merged
+                    },
+                )
+        } else {
+            let mut state = ScanState::new(k);
+            for (chunk_id, vector) in &self.vectors {
+                self.scan(&mut state, *chunk_id, vector, query_vector, filter, keep);
             }
-        }
+            state.heap
+        };
         
-        // Sort by similarity (highest first) and take top k
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        results.truncate(k);
+        // Ascending order is best first
+        let results = heap.into_sorted_vec().into_iter()
+            .filter_map(|candidate| {
+                self.metadata.get(&candidate.chunk_id).map(|chunk| SearchResult {
+                    chunk_id: candidate.chunk_id,
+                    document_path: chunk.document_path.clone(),
+                    chunk_text: chunk.chunk_text.clone(),
+                    similarity: candidate.similarity,
+                    metadata_only: false,
+                    metadata: chunk.metadata.clone(),
+                    lines: None,
+                })
+            })
+            .collect();
         
         Ok(results)
     }
 
-    pub fn get_chunk_info(&self, chunk_id: u32) -> Option<&(String, String)> {
+    /// Score one vector into a worker's top k, skipping chunks the filter rejects
+    fn scan<'a>(&'a self, state: &mut ScanState<'a>, chunk_id: u32, vector: &[f32], query_vector: &[f32], filter: &SearchFilter, keep: Option<&(dyn Fn(&str) -> bool + Sync)>) {
+        if let Some(keep) = keep {
+            if !self.metadata.get(&chunk_id).is_some_and(|chunk| keep(&chunk.chunk_text)) {
+                return;
+            }
+        }
+        if !filter.is_empty() {
+            let Some(chunk) = self.metadata.get(&chunk_id) else { return };
+            if !filter.matches_text(&chunk.chunk_text) {
+                return;
+            }
+            // Path, extension, project and tags are per document, so they are checked once per worker;
+            // the language is detected per chunk
+            let matches = *state.document_matches.entry(chunk.document_path.as_str())
+                .or_insert_with(|| filter.matches_source(&chunk.document_path, &chunk.metadata));
+            if !matches || !filter.matches_language(&chunk.metadata) {
+                return;
+            }
+        }
+        let candidate = Candidate {
+            similarity: self.metric.similarity(query_vector, vector),
+            chunk_id,
+        };
+        push_top_k(&mut state.heap, candidate, state.k);
+    }
+
+    pub fn remove_vector(&mut self, chunk_id: u32) {
+        self.vectors.remove(&chunk_id);
+        self.metadata.remove(&chunk_id);
+    }
+
+    pub fn get_chunk_info(&self, chunk_id: u32) -> Option<&IndexedChunk> {
         self.metadata.get(&chunk_id)
     }
 
@@ -67,47 +249,223 @@ impl VectorIndex {
         self.vectors.clear();
         self.metadata.clear();
     }
+
+    /// Write the index to `path` tagged with the database generation it reflects.
+    /// The file is written beside the target and renamed over it, so readers never see a partial snapshot.
+    pub fn write_snapshot(&self, path: &Path, generation: i64) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("idx.tmp");
+        let mut out = BufWriter::new(std::fs::File::create(&tmp_path)?);
+        
+        out.write_all(SNAPSHOT_MAGIC)?;
+        out.write_all(&generation.to_le_bytes())?;
+        out.write_all(&(self.dimension as u32).to_le_bytes())?;
+        out.write_all(&(self.vectors.len() as u32).to_le_bytes())?;
+        
+        for (chunk_id, vector) in &self.vectors {
+            let Some(chunk) = self.metadata.get(chunk_id) else { continue };
+            out.write_all(&chunk_id.to_le_bytes())?;
+            for value in vector {
+                out.write_all(&value.to_le_bytes())?;
+            }
+            write_bytes(&mut out, chunk.document_path.as_bytes())?;
+            write_bytes(&mut out, chunk.chunk_text.as_bytes())?;
+            write_bytes(&mut out, &serde_json::to_vec(&chunk.metadata)?)?;
+        }
+        
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Stream a snapshot back in. Returns None when there is no snapshot, or it was taken at another
+    /// database generation or for another dimension; only the header is read in that case.
+    pub fn read_snapshot(path: &Path, generation: i64, dimension: usize, metric: DistanceMetric) -> Result<Option<Self>> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // No length read from the file can be more than the file holds
+        let file_size = file.metadata()?.len();
+        let mut input = BufReader::new(file);
+        
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC
+            || i64::from_le_bytes(read_array(&mut input)?) != generation
+            || u32::from_le_bytes(read_array(&mut input)?) as usize != dimension
+        {
+            return Ok(None);
+        }
+        
+        let count = u32::from_le_bytes(read_array(&mut input)?) as usize;
+        // Each chunk takes at least its id, its vector and three length prefixes
+        if count as u64 * (16 + dimension as u64 * 4) > file_size {
+            anyhow::bail!("the snapshot claims {} chunks, more than its {} bytes hold", count, file_size);
+        }
+        let mut index = Self::new(dimension, metric);
+        index.vectors.reserve(count);
+        index.metadata.reserve(count);
+        
+        let mut vector_bytes = vec![0u8; dimension * 4];
+        for _ in 0..count {
+            let chunk_id = u32::from_le_bytes(read_array(&mut input)?);
+            input.read_exact(&mut vector_bytes)?;
+            let vector = vector_bytes.chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            let document_path = String::from_utf8(read_bytes(&mut input, file_size)?)?;
+            let chunk_text = String::from_utf8(read_bytes(&mut input, file_size)?)?;
+            let metadata = serde_json::from_slice(&read_bytes(&mut input, file_size)?)?;
+            
+            index.vectors.insert(chunk_id, vector);
+            index.metadata.insert(chunk_id, IndexedChunk { document_path, chunk_text, metadata });
+        }
+        
+        Ok(Some(index))
+    }
+}
+
+fn write_bytes(out: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(bytes)
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    input.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Bytes after a length prefix, which is rejected when it is over `max_len`
+fn read_bytes(input: &mut impl Read, max_len: u64) -> std::io::Result<Vec<u8>> {
+    let len = u32::from_le_bytes(read_array(input)?);
+    if u64::from(len) > max_len {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("length prefix {} is past the end of the file", len)));
+    }
+    let mut buf = vec![0u8; len as usize];
+    input.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Merge ranked result lists with Reciprocal Rank Fusion: each list contributes 1 / (k + rank)
+/// for every chunk it returns. Only ranks are used, so lists whose scores are on different scales
+/// (e.g. a remote index and the local index) can be combined.
+///
+/// The fused score replaces `similarity`, normalised so that a chunk ranked first in every list scores 1.0.
+pub fn reciprocal_rank_fusion(lists: Vec<Vec<SearchResult>>, k: f32, limit: usize) -> Vec<SearchResult> {
+    let list_count = lists.len().max(1) as f32;
+    let mut fused: HashMap<u32, (f32, SearchResult)> = HashMap::new();
+    
+    for list in lists {
+        for (rank, result) in list.into_iter().enumerate() {
+            let contribution = 1.0 / (k + rank as f32 + 1.0);
+            fused.entry(result.chunk_id)
+                .and_modify(|(score, existing)| {
+                    *score += contribution;
+                    // Prefer a copy that has the chunk text
+                    if existing.metadata_only && !result.metadata_only {
+                        *existing = result.clone();
+                    }
+                })
+                .or_insert((contribution, result));
+        }
+    }
+    
+    let max_score = list_count / (k + 1.0);
+    let mut results: Vec<SearchResult> = fused.into_values()
+        .map(|(score, mut result)| {
+            result.similarity = score / max_score;
+            result
+        })
+        .collect();
+    results.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then(a.chunk_id.cmp(&b.chunk_id)));
+    results.truncate(limit);
+    results
 }
 
 // Enhanced RAG search with relevance scoring
 pub struct RAGSearchEngine {
     vector_index: VectorIndex,
     relevance_threshold: f32,
+    candidate_multiplier: usize,
 }
 
 impl RAGSearchEngine {
-    pub fn new(dimension: usize, relevance_threshold: f32) -> Self {
+    pub fn new(dimension: usize, relevance_threshold: f32, metric: DistanceMetric) -> Self {
         Self {
-            vector_index: VectorIndex::new(dimension),
+            vector_index: VectorIndex::new(dimension, metric),
             relevance_threshold,
+            candidate_multiplier: 2,
         }
     }
 
-    pub fn add_chunk(&mut self, chunk_id: u32, vector: &[f32], document_path: &str, chunk_text: &str) -> Result<()> {
-        self.vector_index.add_vector(chunk_id, vector, document_path, chunk_text)
+    pub fn add_chunk(&mut self, chunk_id: u32, vector: &[f32], document_path: &str, chunk_text: &str, metadata: ChunkMetadata) -> Result<()> {
+        self.vector_index.add_vector(chunk_id, vector, document_path, chunk_text, metadata)
+    }
+
+    pub fn remove_chunk(&mut self, chunk_id: u32) {
+        self.vector_index.remove_vector(chunk_id);
+    }
+
+    /// Load the vectors of one project from its snapshot when it matches the database, otherwise
+    /// rebuild from the database and write a fresh snapshot. Without a snapshot path this is a plain rebuild.
+    pub fn load_vectors(&mut self, db: &crate::db::Database, project: Option<&str>, snapshot: Option<&Path>) -> Result<()> {
+        let Some(path) = snapshot else {
+            return self.load_vectors_from_database(db, project);
+        };
+        
+        // Read the generation before the rows: a write in between leaves the snapshot stale, never wrong
+        let generation = db.get_index_generation()?;
+        let dimension = self.vector_index.dimension;
+        let metric = self.vector_index.metric;
+        match VectorIndex::read_snapshot(path, generation, dimension, metric) {
+            Ok(Some(index)) => {
+                self.vector_index = index;
+                return Ok(());
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Warning: Ignoring unreadable index snapshot {}: {}", path.display(), e),
+        }
+        
+        self.load_vectors_from_database(db, project)?;
+        if let Err(e) = self.vector_index.write_snapshot(path, generation) {
+            eprintln!("Warning: Failed to save index snapshot {}: {}", path.display(), e);
+        }
+        Ok(())
     }
 
-    /// Load all vectors from the database into the in-memory index
-    pub fn load_vectors_from_database(&mut self, db: &crate::db::Database) -> Result<()> {
+    /// Load the vectors of one project (`None` is the default project) into the in-memory index
+    pub fn load_vectors_from_database(&mut self, db: &crate::db::Database, project: Option<&str>) -> Result<()> {
         // Get all chunks with their embeddings from the database
         let mut stmt = db.get_connection().prepare(
-            "SELECT c.id as chunk_id, c.text, d.file_path, e.vector
+            "SELECT c.id as chunk_id, c.text, d.file_path, e.vector, d.metadata, c.language
              FROM chunks c
              JOIN documents d ON c.document_id = d.id
              JOIN embeddings e ON c.id = e.chunk_id
+             WHERE d.project IS ?
              ORDER BY c.id"
         )?;
         
-        let rows = stmt.query_map([], |row| {
+        let rows = stmt.query_map([project], |row| {
             let chunk_id: u32 = row.get(0)?;
             let text: String = row.get(1)?;
             let file_path: String = row.get(2)?;
             let vector_json: String = row.get(3)?;
             
+            let metadata_json: Option<String> = row.get(4)?;
+            let language: Option<String> = row.get(5)?;
+            
             let vector: Vec<f32> = serde_json::from_str(&vector_json)
                 .unwrap_or_default();
+            let document_metadata: DocumentMetadata = metadata_json
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
             
-            Ok((chunk_id, text, file_path, vector))
+            Ok((chunk_id, text, file_path, vector, document_metadata, language))
         })?;
         
         // Clear existing vectors and load from database
@@ -115,9 +473,10 @@ impl RAGSearchEngine {
         
         let mut loaded_count = 0;
         for row in rows {
-            let (chunk_id, text, file_path, vector) = row?;
+            let (chunk_id, text, file_path, vector, document_metadata, language) = row?;
             if !vector.is_empty() {
-                self.vector_index.add_vector(chunk_id, &vector, &file_path, &text)?;
+                let metadata = ChunkMetadata::for_stored_chunk(&file_path, project, &document_metadata.tags, &text, language.as_deref());
+                self.vector_index.add_vector(chunk_id, &vector, &file_path, &text, metadata)?;
                 loaded_count += 1;
             }
         }
@@ -125,12 +484,12 @@ impl RAGSearchEngine {
         Ok(())
     }
 
-    pub fn search_relevant_chunks(&self, _query: &str, query_vector: &[f32], k: usize) -> Result<Vec<(u32, f32, String, String)>> {
+    pub fn search_relevant_chunks(&self, _query: &str, query_vector: &[f32], k: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
         // Get initial vector search results
-        let mut results = self.vector_index.search_similar(query_vector, k * 2)?;
+        let mut results = self.vector_index.search_similar(query_vector, k * self.candidate_multiplier, filter)?;
         
         // Filter by relevance threshold
-        results.retain(|(_, similarity, _, _)| *similarity >= self.relevance_threshold);
+        results.retain(|result| result.similarity >= self.relevance_threshold);
         
         // Take top k results
         results.truncate(k);
@@ -138,14 +497,20 @@ impl RAGSearchEngine {
         Ok(results)
     }
 
+    /// Chunks containing any of `terms` verbatim, most similar first, ignoring the relevance threshold
+    pub fn search_exact_chunks(&self, query_vector: &[f32], terms: &[String], k: usize, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+        let keep = |text: &str| crate::context::contains_exact(text, terms);
+        self.vector_index.search_similar_where(query_vector, k, filter, Some(&keep))
+    }
+
     pub fn get_context_for_question(&self, question: &str, question_vector: &[f32], context_size: usize) -> Result<String> {
-        let relevant_chunks = self.search_relevant_chunks(question, question_vector, context_size)?;
+        let relevant_chunks = self.search_relevant_chunks(question, question_vector, context_size, &SearchFilter::default())?;
         
         let mut context = String::new();
-        for (i, (_, similarity, document_path, chunk_text)) in relevant_chunks.iter().enumerate() {
-            context.push_str(&format!("--- Chunk {} (Similarity: {:.3}) ---\n", i + 1, similarity));
-            context.push_str(&format!("Source: {}\n", document_path));
-            context.push_str(&format!("Content: {}\n\n", chunk_text));
+        for (i, result) in relevant_chunks.iter().enumerate() {
+            context.push_str(&format!("--- Chunk {} (Similarity: {:.3}) ---\n", i + 1, result.similarity));
+            context.push_str(&format!("Source: {}\n", result.document_path));
+            context.push_str(&format!("Content: {}\n\n", result.chunk_text));
         }
         
         Ok(context)
@@ -159,6 +524,11 @@ impl RAGSearchEngine {
         self.relevance_threshold
     }
 
+    /// Fetch `multiplier` nearest neighbours per requested result before thresholding
+    pub fn set_candidate_multiplier(&mut self, multiplier: usize) {
+        self.candidate_multiplier = multiplier.max(1);
+    }
+
     pub fn clear(&mut self) {
         self.vector_index.clear();
     }
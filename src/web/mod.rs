@@ -0,0 +1,204 @@
+// Indexing web pages: fetch a URL (or every page a sitemap lists), extract its readable text and
+// index it with the URL as the document path
+use anyhow::Result;
+use regex::Regex;
+use std::time::Duration;
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::debug::SendLogged;
+
+/// Time allowed to fetch one page or sitemap
+const FETCH_TIMEOUT_SECS: u64 = 30;
+
+/// Sitemap indexes nest other sitemaps; deeper nesting than this is not followed
+const MAX_SITEMAP_DEPTH: usize = 3;
+
+/// Elements that never hold readable page text
+const SKIPPED_ELEMENTS: &[&str] = &["head", "script", "style", "noscript", "template", "svg", "iframe", "nav", "header", "footer", "form", "aside"];
+
+/// Outcome of an `index-url` run
+#[derive(Debug, Default)]
+pub struct WebIndexReport {
+    pub indexed: Vec<String>,
+    /// Pages whose text hasn't changed since they were last indexed
+    pub unchanged: usize,
+    pub failed: Vec<(String, String)>,
+}
+
+/// Readable content of a fetched page
+pub struct Page {
+    pub title: Option<String>,
+    pub text: String,
+}
+
+/// Index the page at `url`, or with `sitemap` every page listed by the sitemap at `url` (a site
+/// root is taken to mean its /sitemap.xml), up to `limit` pages
+pub async fn index_url(app: &mut ChunkyMonkeyApp, url: &str, sitemap: bool, limit: Option<usize>) -> Result<WebIndexReport> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("chunkymonkey/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(FETCH_TIMEOUT_SECS))
+        .build()?;
+
+    let mut urls = if sitemap {
+        let sitemap_url = if url.trim_end_matches('/').ends_with(".xml") {
+            url.to_string()
+        } else {
+            format!("{}/sitemap.xml", url.trim_end_matches('/'))
+        };
+        sitemap_urls(&client, &sitemap_url).await?
+    } else {
+        vec![url.to_string()]
+    };
+    if let Some(limit) = limit {
+        urls.truncate(limit);
+    }
+    if urls.is_empty() {
+        anyhow::bail!("The sitemap at {} lists no pages", url);
+    }
+
+    let mut report = WebIndexReport::default();
+    for page_url in urls {
+        println!("🌐 {}", page_url);
+        let indexed = match fetch_page(&client, &page_url).await {
+            Ok(page) => app.add_web_page(&page_url, page.title.as_deref(), &page.text).await,
+            Err(e) => Err(e),
+        };
+        match indexed {
+            Ok(0) => report.unchanged += 1,
+            Ok(_) => report.indexed.push(page_url),
+            Err(e) => report.failed.push((page_url, e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// Fetch a page: HTML is reduced to its readable text, plain text and markdown are kept as is
+pub async fn fetch_page(client: &reqwest::Client, url: &str) -> Result<Page> {
+    let response = client.get(url).send_logged("web").await?;
+    if !response.status().is_success() {
+        anyhow::bail!("Server returned {}", response.status());
+    }
+    let content_type = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("text/html")
+        .to_lowercase();
+    let body = response.text().await?;
+
+    let page = if content_type.contains("html") {
+        html_to_text(&body)
+    } else if content_type.starts_with("text/") {
+        Page { title: None, text: body }
+    } else {
+        anyhow::bail!("{} is not a text or HTML page", content_type);
+    };
+    if page.text.trim().is_empty() {
+        anyhow::bail!("No readable text on the page");
+    }
+    Ok(page)
+}
+
+/// Page URLs listed by a sitemap, following nested sitemap indexes
+pub async fn sitemap_urls(client: &reqwest::Client, url: &str) -> Result<Vec<String>> {
+    let mut pages = Vec::new();
+    let mut pending = vec![(url.to_string(), 0)];
+    let loc = Regex::new(r"(?is)<loc>\s*(.*?)\s*</loc>").unwrap();
+    while let Some((sitemap_url, depth)) = pending.pop() {
+        let response = client.get(&sitemap_url).send_logged("web").await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Sitemap {} returned {}", sitemap_url, response.status());
+        }
+        let body = response.text().await?;
+        let locations = loc.captures_iter(&body).map(|captures| decode_entities(&captures[1]));
+        if body.contains("<sitemapindex") {
+            if depth < MAX_SITEMAP_DEPTH {
+                pending.extend(locations.map(|location| (location, depth + 1)));
+            }
+        } else {
+            pages.extend(locations);
+        }
+    }
+    pages.dedup();
+    Ok(pages)
+}
+
+/// Readable text of an HTML page as markdown-like text: the `<main>` or `<article>` element when
+/// there is one, without scripts, navigation and other chrome, headings kept as `#` lines
+pub fn html_to_text(html: &str) -> Page {
+    let title = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap()
+        .captures(html)
+        .map(|captures| collapse_whitespace(&decode_entities(&strip_tags(&captures[1]))))
+        .filter(|title| !title.is_empty());
+
+    let mut body = Regex::new(r"(?s)<!--.*?-->").unwrap().replace_all(html, "").into_owned();
+    for element in ["main", "article"] {
+        let content = Regex::new(&format!(r"(?is)<{0}\b[^>]*>(.*)</{0}>", element)).unwrap()
+            .captures(&body)
+            .map(|captures| captures[1].to_string());
+        if let Some(content) = content {
+            body = content;
+            break;
+        }
+    }
+    for element in SKIPPED_ELEMENTS {
+        body = Regex::new(&format!(r"(?is)<{0}\b[^>]*>.*?</{0}>", element)).unwrap().replace_all(&body, "\n").into_owned();
+    }
+
+    let body = Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]>").unwrap().replace_all(&body, |captures: &regex::Captures| {
+        let level: usize = captures[1].parse().unwrap_or(1);
+        format!("\n\n{} {}\n\n", "#".repeat(level), collapse_whitespace(&strip_tags(&captures[2])))
+    });
+    let body = Regex::new(r"(?i)<li\b[^>]*>").unwrap().replace_all(&body, "\n- ");
+    let body = Regex::new(r"(?i)<br\s*/?>").unwrap().replace_all(&body, "\n");
+    let body = Regex::new(r"(?i)</?(p|div|section|table|tr|ul|ol|dl|dt|dd|blockquote|pre|figure|hr)\b[^>]*>").unwrap().replace_all(&body, "\n\n");
+    let body = Regex::new(r"(?i)</t[dh]>").unwrap().replace_all(&body, " ");
+    let text = decode_entities(&strip_tags(&body));
+
+    // One space between words, at most one blank line between blocks
+    let mut output = String::new();
+    let mut blank = true;
+    for line in text.lines().map(collapse_whitespace) {
+        if line.is_empty() {
+            if !blank {
+                output.push('\n');
+            }
+            blank = true;
+        } else {
+            output.push_str(&line);
+            output.push('\n');
+            blank = false;
+        }
+    }
+    Page { title, text: output.trim().to_string() }
+}
+
+fn strip_tags(html: &str) -> String {
+    Regex::new(r"(?s)<[^>]*>").unwrap().replace_all(html, "").into_owned()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decode named entities common in page text and numeric character references
+fn decode_entities(text: &str) -> String {
+    Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap().replace_all(text, |captures: &regex::Captures| {
+        let entity = &captures[1];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "hellip" => Some('…'),
+            "copy" => Some('©'),
+            _ => entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        decoded.map_or_else(|| captures[0].to_string(), |c| c.to_string())
+    }).into_owned()
+}
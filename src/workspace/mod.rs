@@ -0,0 +1,88 @@
+// Zero-config use inside a git repository: the repository is remembered as a project of its own,
+// set up and indexed the first time a command needs documents there
+use anyhow::Result;
+use colored::*;
+use console::Term;
+use std::path::{Path, PathBuf};
+use crate::core::app::ChunkyMonkeyApp;
+use crate::core::config::ChunkStrategy;
+use crate::search::Indexer;
+
+/// The git repository around the working directory
+pub struct Workspace {
+    pub root: PathBuf,
+    /// Project name suggested for it: the repository directory's name
+    pub name: String,
+}
+
+/// The nearest enclosing directory with a `.git` (a directory, or a file in worktrees and submodules)
+pub fn detect() -> Option<Workspace> {
+    let cwd = std::env::current_dir().ok()?;
+    let root = cwd.ancestors().find(|dir| dir.join(".git").exists())?.to_path_buf();
+    let name = root.file_name()?.to_string_lossy().to_string();
+    Some(Workspace { root, name })
+}
+
+/// Work in the project remembered for the repository around the working directory. Inside a
+/// repository without one, and with nothing indexed in the default project, `offer_setup` asks
+/// (in a terminal) to create a project named after the repository and index it.
+pub async fn apply(app: &mut ChunkyMonkeyApp, offer_setup: bool) -> Result<()> {
+    let Some(workspace) = detect() else {
+        return Ok(());
+    };
+    let root = workspace.root.to_string_lossy().to_string();
+    if let Some(project) = app.db.get_workspace_project(&root)? {
+        return app.set_project(project);
+    }
+
+    let term = Term::stdout();
+    // Documents in the default project mean this index is already in use; leave it as it is
+    if !offer_setup || !term.is_term() || !app.db.get_documents_by_project(None)?.is_empty() {
+        return Ok(());
+    }
+
+    let name = unused_project_name(app, &workspace.name)?;
+    println!("{}", format!("📁 {} is a git repository without a ChunkyMonkey project.", root).bright_cyan());
+    if !crate::setup::confirm(&term, &format!("Create project '{}' and index the repository now?", name), true)? {
+        app.db.set_workspace_project(&root, None)?;
+        println!("{}", "Using the default project in this repository; pass --project to pick another.".dimmed());
+        return Ok(());
+    }
+
+    app.set_project(Some(name.clone()))?;
+    app.db.set_workspace_project(&root, Some(&name))?;
+    index_workspace(app, &workspace.root).await?;
+    println!("✅ Project '{}' is set up; commands run in this repository use it from now on.", name);
+    Ok(())
+}
+
+/// `name`, or `name-2`, `name-3`... when another repository's project already has it
+fn unused_project_name(app: &ChunkyMonkeyApp, name: &str) -> Result<String> {
+    let taken: Vec<String> = app.db.get_projects()?.into_iter().filter_map(|(project, _, _)| project).collect();
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    while taken.contains(&candidate) {
+        n += 1;
+        candidate = format!("{}-{}", name, n);
+    }
+    Ok(candidate)
+}
+
+/// Index the whole repository with defaults suited to code: ignore files respected, ChunkyMonkey's
+/// own files left out and source files split between definitions unless strategies are configured
+async fn index_workspace(app: &mut ChunkyMonkeyApp, root: &Path) -> Result<()> {
+    let mut indexing = app.config.indexing.clone();
+    indexing.respect_ignore_files = true;
+    indexing.exclude.extend([
+        format!("{}*", crate::db::DATABASE_PATH),
+        app.config.local_index.dir.clone(),
+        crate::setup::CONFIG_PATH.to_string(),
+    ]);
+    if app.config.chunking.strategies.is_empty() {
+        for extension in crate::chunking::CODE_EXTENSIONS {
+            app.config.chunking.strategies.insert(extension.to_string(), ChunkStrategy::Code);
+        }
+    }
+    Indexer::new(indexing).index_directory(&root.to_string_lossy(), None, app).await?;
+    Ok(())
+}